@@ -0,0 +1,458 @@
+//! Terminal debugger. Runs a ROM without video or sound and reads commands from stdin, one per
+//! line. Addresses are in hex or are labels from a symbol file, and an empty line repeats the
+//! last command that ran the console:
+//!
+//! ```text
+//! step [3]                    Step into instructions
+//! next                        Step over a JSR
+//! finish                      Run until the current subroutine or interrupt handler returns
+//! continue [frames]           Run until something stops it, for up to a minute by default
+//! frame [2]                   Advance to the end of the frame, or more frames
+//! scanline 240                Run to a scanline
+//! break 8000[-80FF] [rwx]     Stop on executing (the default), reading or writing addresses
+//!       ... bank 3[/4000]     only while bank 3 is mapped there, counting 8KB banks by default
+//! watch 2000[-23FF] [rw]      Stop on accessing PPU addresses through PPUDATA, writes by default
+//! on nmi|irq|brk|jam          Toggle stopping on an event
+//! delete [1], unwatch [1]     Remove a breakpoint or watchpoint, or all of them
+//! list                        Show breakpoints and watchpoints
+//! regs                        Show the registers and upcoming code
+//! dis [C000] [16]             Disassemble
+//! mem 0300 [64], ppu 3F00 [32]
+//!                             Dump CPU or PPU memory
+//! poke 0300 FF                Change RAM or ROM
+//! symbols game.nl             Load labels from an ld65 -Ln, FCEUX .nl or `name = $addr` file
+//! quit
+//! ```
+//!
+//! For example: `nees-debugger game.nes game.labels`.
+
+use std::io::{BufRead, Write};
+
+use nees::{
+    debugger::{BankCondition, Breakpoint, Debugger, Step, Stop, Watchpoint},
+    disassembler::{self, Symbols},
+    nes001::{Access, NES001},
+};
+use nees_std::archive::{self, RomFile};
+
+/// What `continue` runs for without a frame count: a minute.
+const CONTINUE_FRAMES: u32 = 3600;
+
+/// Commands an empty line repeats.
+const RUN_COMMANDS: &[&str] = &[
+    "step", "s", "next", "n", "finish", "continue", "c", "frame", "scanline",
+];
+
+fn parse_number<T: std::str::FromStr>(text: Option<&str>) -> Result<T, String> {
+    let text = text.ok_or("Missing number")?;
+    text.parse().map_err(|_| format!("{} isn't a number", text))
+}
+
+fn parse_hex(text: &str) -> Result<u16, String> {
+    u16::from_str_radix(text.trim_start_matches('$'), 16)
+        .map_err(|_| format!("{} isn't a hex number", text))
+}
+
+fn parse_hex_byte(text: &str) -> Result<u8, String> {
+    u8::from_str_radix(text.trim_start_matches('$'), 16)
+        .map_err(|_| format!("{} isn't a hex byte", text))
+}
+
+fn describe_access(access: Access) -> String {
+    match access {
+        Access::Read(address, value) => format!("read ${:04X} = ${:02X}", address, value),
+        Access::Write(address, value) => format!("write ${:04X} = ${:02X}", address, value),
+        Access::PpuRead(address, value) => format!("PPU read ${:04X} = ${:02X}", address, value),
+        Access::PpuWrite(address, value) => format!("PPU write ${:04X} = ${:02X}", address, value),
+    }
+}
+
+fn flags(status: u8) -> String {
+    b"CZIDB-VN"
+        .iter()
+        .enumerate()
+        .rev()
+        .map(|(bit, &flag)| {
+            if status & (1 << bit) != 0 {
+                flag as char
+            } else {
+                flag.to_ascii_lowercase() as char
+            }
+        })
+        .collect()
+}
+
+struct Session {
+    nes: NES001,
+    debugger: Debugger,
+    symbols: Symbols,
+    framebuffer: Vec<u32>,
+    last_command: String,
+}
+
+impl Session {
+    /// A hex address or a label.
+    fn parse_address(&self, text: Option<&str>) -> Result<u16, String> {
+        let text = text.ok_or("Missing address")?;
+        match self.symbols.address(text) {
+            Some(address) => Ok(address),
+            None => parse_hex(text),
+        }
+    }
+
+    /// An address, or a range like `8000-80FF`.
+    fn parse_range(&self, text: Option<&str>) -> Result<(u16, u16), String> {
+        let text = text.ok_or("Missing address")?;
+        match text.split_once('-') {
+            Some((start, end)) => Ok((
+                self.parse_address(Some(start))?,
+                self.parse_address(Some(end))?,
+            )),
+            None => {
+                let address = self.parse_address(Some(text))?;
+                Ok((address, address))
+            }
+        }
+    }
+
+    fn name(&self, address: u16) -> String {
+        match self.symbols.get(address) {
+            Some(label) => format!("${:04X} ({})", address, label),
+            None => format!("${:04X}", address),
+        }
+    }
+
+    fn show_state(&self) {
+        let registers = self.nes.registers();
+        let (scanline, dot) = self.nes.position();
+        println!(
+            "PC={:04X} A={:02X} X={:02X} Y={:02X} SP={:02X} P={:02X} [{}]  scanline {} dot {}",
+            registers.pc,
+            registers.a,
+            registers.x,
+            registers.y,
+            registers.sp,
+            registers.status,
+            flags(registers.status),
+            scanline,
+            dot
+        );
+        if !self.nes.at_instruction() {
+            println!("Between instructions; step to get to the next one");
+        }
+        let code =
+            disassembler::listing(&self.nes.disassemble(registers.pc, 6), Some(&self.symbols));
+        let current = format!("{:04X} ", registers.pc);
+        for line in code.lines() {
+            let marker = if line.starts_with(&current) { '>' } else { ' ' };
+            println!("{} {}", marker, line);
+        }
+    }
+
+    fn run(&mut self, step: Step) {
+        let stop = self
+            .debugger
+            .run(&mut self.nes, step, &mut |_| {}, &mut self.framebuffer);
+        match stop {
+            Stop::Done => {}
+            Stop::Breakpoint(index, None) => println!("Breakpoint {}", index),
+            Stop::Breakpoint(index, Some(access)) => {
+                println!("Breakpoint {}: {}", index, describe_access(access))
+            }
+            Stop::Watchpoint(index, access) => {
+                println!("Watchpoint {}: {}", index, describe_access(access))
+            }
+            Stop::Nmi => println!("NMI"),
+            Stop::Irq => println!("IRQ"),
+            Stop::Brk => println!("BRK"),
+            Stop::Jam => println!("JAM opcode"),
+            Stop::Timeout => println!("Gave up after {} frames", self.debugger.timeout_frames),
+        }
+        self.show_state();
+    }
+
+    fn add_breakpoint<'a>(
+        &mut self,
+        mut words: impl Iterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        let (start, end) = self.parse_range(words.next())?;
+        let mut breakpoint = Breakpoint {
+            execute: false,
+            ..Breakpoint::execute(start)
+        };
+        breakpoint.end = end;
+        while let Some(word) = words.next() {
+            if word == "bank" {
+                let text = words.next().ok_or("Missing bank")?;
+                let (bank, size) = match text.split_once('/') {
+                    Some((bank, size)) => (bank, parse_hex(size)? as usize),
+                    None => (text, 0x2000),
+                };
+                let memory = self
+                    .nes
+                    .cpu_mapping(start)
+                    .ok_or_else(|| format!("${:04X} isn't banked", start))?
+                    .memory;
+                breakpoint.bank = Some(BankCondition {
+                    memory,
+                    size,
+                    bank: parse_number(Some(bank))?,
+                });
+                continue;
+            }
+            for flag in word.chars() {
+                match flag {
+                    'r' => breakpoint.read = true,
+                    'w' => breakpoint.write = true,
+                    'x' => breakpoint.execute = true,
+                    _ => return Err(format!("{} isn't r, w or x", flag)),
+                }
+            }
+        }
+        if !(breakpoint.read || breakpoint.write) {
+            breakpoint.execute = true;
+        }
+
+        println!(
+            "Breakpoint {} at {}",
+            self.debugger.breakpoints.len(),
+            self.name(start)
+        );
+        self.debugger.breakpoints.push(breakpoint);
+        Ok(())
+    }
+
+    fn add_watchpoint<'a>(
+        &mut self,
+        mut words: impl Iterator<Item = &'a str>,
+    ) -> Result<(), String> {
+        let (start, end) = self.parse_range(words.next())?;
+        let access = words.next().unwrap_or("w");
+        let watchpoint = Watchpoint {
+            start: start & 0x3FFF,
+            end: end & 0x3FFF,
+            read: access.contains('r'),
+            write: access.contains('w'),
+            enabled: true,
+        };
+        println!(
+            "Watchpoint {} at PPU ${:04X}",
+            self.debugger.watchpoints.len(),
+            start
+        );
+        self.debugger.watchpoints.push(watchpoint);
+        Ok(())
+    }
+
+    fn list(&self) {
+        for (index, breakpoint) in self.debugger.breakpoints.iter().enumerate() {
+            let kinds: String = [
+                (breakpoint.read, 'r'),
+                (breakpoint.write, 'w'),
+                (breakpoint.execute, 'x'),
+            ]
+            .iter()
+            .filter_map(|&(set, flag)| set.then_some(flag))
+            .collect();
+            let bank = breakpoint.bank.map_or(String::new(), |bank| {
+                format!(" in {:?} bank {}/{:X}", bank.memory, bank.bank, bank.size)
+            });
+            println!(
+                "Breakpoint {}: {}-${:04X} {}{}",
+                index,
+                self.name(breakpoint.start),
+                breakpoint.end,
+                kinds,
+                bank
+            );
+        }
+        for (index, watchpoint) in self.debugger.watchpoints.iter().enumerate() {
+            println!(
+                "Watchpoint {}: PPU ${:04X}-${:04X} {}{}",
+                index,
+                watchpoint.start,
+                watchpoint.end,
+                if watchpoint.read { "r" } else { "" },
+                if watchpoint.write { "w" } else { "" }
+            );
+        }
+        let break_on = self.debugger.break_on;
+        println!(
+            "Stopping on: nmi {}, irq {}, brk {}, jam {}",
+            break_on.nmi, break_on.irq, break_on.brk, break_on.jam
+        );
+    }
+
+    fn dump(&self, start: u16, length: u16, peek: impl Fn(u16) -> u8) {
+        for row in (0..length).step_by(16) {
+            let address = start.wrapping_add(row);
+            let bytes: Vec<String> = (0..16.min(length - row))
+                .map(|offset| format!("{:02X}", peek(address.wrapping_add(offset))))
+                .collect();
+            println!("{:04X}  {}", address, bytes.join(" "));
+        }
+    }
+
+    fn run_command(&mut self, line: &str) -> Result<(), String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(());
+        };
+
+        match command {
+            "step" | "s" => {
+                let count: u32 = words.next().map_or(Ok(1), |n| parse_number(Some(n)))?;
+                if count == 0 {
+                    return Err("Can't step 0 instructions".to_string());
+                }
+                for _ in 1..count {
+                    if self.debugger.run(
+                        &mut self.nes,
+                        Step::Into,
+                        &mut |_| {},
+                        &mut self.framebuffer,
+                    ) != Stop::Done
+                    {
+                        break;
+                    }
+                }
+                self.run(Step::Into);
+            }
+            "next" | "n" => self.run(Step::Over),
+            "finish" => self.run(Step::Out),
+            "continue" | "c" => {
+                let frames = words
+                    .next()
+                    .map_or(Ok(CONTINUE_FRAMES), |n| parse_number(Some(n)))?;
+                self.run(Step::Frames(frames));
+            }
+            "frame" => {
+                let frames = words.next().map_or(Ok(1), |n| parse_number(Some(n)))?;
+                self.run(Step::Frames(frames));
+            }
+            "scanline" => self.run(Step::Scanline(parse_number(words.next())?)),
+            "break" | "b" => self.add_breakpoint(words)?,
+            "watch" => self.add_watchpoint(words)?,
+            "on" => {
+                let break_on = &mut self.debugger.break_on;
+                let event = match words.next() {
+                    Some("nmi") => &mut break_on.nmi,
+                    Some("irq") => &mut break_on.irq,
+                    Some("brk") => &mut break_on.brk,
+                    Some("jam") => &mut break_on.jam,
+                    _ => return Err("Expected nmi, irq, brk or jam".to_string()),
+                };
+                *event = !*event;
+                self.list();
+            }
+            "delete" | "unwatch" => {
+                let index = words.next().map(|n| parse_number(Some(n))).transpose()?;
+                let count = if command == "delete" {
+                    self.debugger.breakpoints.len()
+                } else {
+                    self.debugger.watchpoints.len()
+                };
+                match index {
+                    Some(index) if index >= count => return Err(format!("No {} to remove", index)),
+                    Some(index) if command == "delete" => {
+                        self.debugger.breakpoints.remove(index);
+                    }
+                    Some(index) => {
+                        self.debugger.watchpoints.remove(index);
+                    }
+                    None if command == "delete" => self.debugger.breakpoints.clear(),
+                    None => self.debugger.watchpoints.clear(),
+                }
+            }
+            "list" => self.list(),
+            "regs" => self.show_state(),
+            "dis" => {
+                let address = match words.next() {
+                    Some(address) => self.parse_address(Some(address))?,
+                    None => self.nes.registers().pc,
+                };
+                let count = words.next().map_or(Ok(16), |n| parse_number(Some(n)))?;
+                print!(
+                    "{}",
+                    disassembler::listing(
+                        &self.nes.disassemble(address, count),
+                        Some(&self.symbols)
+                    )
+                );
+            }
+            "mem" => {
+                let address = self.parse_address(words.next())?;
+                let length = words.next().map_or(Ok(64), |n| parse_number(Some(n)))?;
+                self.dump(address, length, |address| self.nes.peek(address));
+            }
+            "ppu" => {
+                let address = self.parse_address(words.next())?;
+                let length = words.next().map_or(Ok(32), |n| parse_number(Some(n)))?;
+                self.dump(address, length, |address| self.nes.peek_ppu(address));
+            }
+            "poke" => {
+                let address = self.parse_address(words.next())?;
+                let value = parse_hex_byte(words.next().ok_or("Missing value")?)?;
+                self.nes.poke(address, value);
+            }
+            "symbols" => {
+                let path = words.next().ok_or("Missing file")?;
+                self.load_symbols(path)?;
+            }
+            "quit" | "q" => std::process::exit(0),
+            _ => return Err(format!("Unknown command {}", command)),
+        }
+        Ok(())
+    }
+
+    fn load_symbols(&mut self, path: &str) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|error| format!("{}: {}", path, error))?;
+        self.symbols = Symbols::parse(&text).map_err(|error| format!("{}: {}", path, error))?;
+        Ok(())
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.get(1) else {
+        eprintln!("Usage: nees-debugger <rom> [symbols]");
+        std::process::exit(1);
+    };
+
+    let rom = RomFile::read_choosing(path, |names| {
+        archive::choose_with_prompt(names, &mut std::io::stdin().lock(), &mut std::io::stdout())
+    })
+    .unwrap();
+    let mut nes = nees_std::load_rom(&rom).unwrap();
+    nees_std::load_battery(&rom.path, &mut nes);
+    let mut session = Session {
+        nes,
+        debugger: Debugger::new(),
+        symbols: Symbols::new(),
+        framebuffer: vec![0; 256 * 240],
+        last_command: String::new(),
+    };
+    if let Some(path) = args.get(2) {
+        if let Err(error) = session.load_symbols(path) {
+            eprintln!("{}", error);
+        }
+    }
+
+    // Get to the reset handler's first instruction
+    session.run(Step::Into);
+    print!("(nees) ");
+    std::io::stdout().flush().ok();
+    for line in std::io::stdin().lock().lines() {
+        let Ok(mut line) = line else { break };
+        if line.trim().is_empty() {
+            line = session.last_command.clone();
+        } else if RUN_COMMANDS.contains(&line.split_whitespace().next().unwrap_or_default()) {
+            session.last_command = line.clone();
+        }
+        if let Err(error) = session.run_command(&line) {
+            eprintln!("{}", error);
+        }
+        print!("(nees) ");
+        std::io::stdout().flush().ok();
+    }
+}
@@ -1,6 +1,13 @@
 //#![windows_subsystem = "windows"]
 
-use nees::nes001;
+use nees::{
+    nes001,
+    nsf::NSF,
+    nsf_player::NSFPlayer,
+    ram_search::{Filter, RamSearch, ValueType},
+};
+use nees_osd::config_menu::{RamSearchStep, OSD};
+use nees_std::archive::{self, RomFile};
 use nes001::ControllerState;
 use platform::{
     keys,
@@ -10,9 +17,75 @@ use platform::{
 mod gamepad;
 mod platform;
 
+fn cheat_list(nes: &nes001::NES001) -> Vec<(String, bool)> {
+    nes.cheats()
+        .iter()
+        .map(|cheat| (format!("{} {}", cheat.code, cheat.description), cheat.enabled))
+        .collect()
+}
+
+/// Runs a step of the OSD's RAM search, adding any frozen results to the ROM's cheats.
+fn step_ram_search(
+    nes: &mut nes001::NES001,
+    osd: &mut OSD,
+    ram_search: &mut Option<RamSearch>,
+    rom_path: &str,
+    step: RamSearchStep,
+) {
+    if let RamSearchStep::New = step {
+        *ram_search = None;
+    }
+    let search = ram_search.get_or_insert_with(|| RamSearch::new(nes, ValueType::U8));
+    let filter = match step {
+        RamSearchStep::New => None,
+        RamSearchStep::Equal => Some(Filter::Equal),
+        RamSearchStep::Changed => Some(Filter::Changed),
+        RamSearchStep::Increased => Some(Filter::Increased),
+        RamSearchStep::Decreased => Some(Filter::Decreased),
+        RamSearchStep::Freeze { address, value } => {
+            for cheat in search.freeze_cheats(address, value) {
+                nes.cheats_mut().add(cheat);
+            }
+            nees_std::save_cheats(rom_path, nes);
+            osd.set_cheats(cheat_list(nes));
+            return;
+        }
+    };
+    if let Some(filter) = filter {
+        search.filter(nes, filter);
+    }
+    let results = search
+        .results(nes)
+        .iter()
+        .map(|result| (result.address, result.value))
+        .collect();
+    osd.set_ram_search_results(search.len(), results);
+}
+
 fn main() {
-    let rom_path = "roms/tmnt2.nes";
-    let mut nes = nes001::NES001::from_rom(&std::fs::read(rom_path).unwrap());
+    let rom_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "roms/tmnt2.nes".to_string());
+    let rom = RomFile::read_choosing(&rom_path, |names| {
+        archive::choose_with_prompt(names, &mut std::io::stdin().lock(), &mut std::io::stdout())
+    })
+    .unwrap();
+    // Saves are named after the game in the database, else after the ROM, even when it's inside
+    // an archive
+    let rom_path = rom.path.as_str();
+    let is_nsf = rom_path.ends_with(".nsf") || rom_path.ends_with(".nsfe");
+    let mut nsf_player = is_nsf.then(|| NSFPlayer::new(NSF::new(&rom.data).unwrap()));
+    let mut nes = if let Some(player) = &nsf_player {
+        // Unused while playing music; the player runs its own console
+        nes001::NES001::from_nsf(player.nsf(), player.track())
+    } else if rom_path.ends_with(".fds") {
+        let bios = std::fs::read("roms/disksys.rom").expect("FDS games need roms/disksys.rom");
+        nes001::NES001::from_fds(&rom.data, &bios).unwrap()
+    } else {
+        nees_std::load_rom(&rom).unwrap()
+    };
+    nees_std::load_battery(rom_path, &mut nes);
+    nees_std::load_cheats(rom_path, &mut nes).unwrap();
 
     let mut controller_states: [ControllerState; 2] =
         [ControllerState::new(), ControllerState::new()];
@@ -70,6 +143,17 @@ fn main() {
 
     let mut osd = nees_osd::config_menu::OSD::new();
     let mut osd_open = false;
+    osd.set_cheats(cheat_list(&nes));
+    let mut ram_search = None;
+    if let Some(player) = &nsf_player {
+        let nsf = player.nsf();
+        let tracks = (0..nsf.total_songs)
+            .map(|i| nsf.track_name(i).unwrap_or("").to_string())
+            .collect();
+        osd.open_track_list(tracks, player.track());
+        osd.draw_step(&mut framebuffer);
+        osd_open = true;
+    }
 
     let mut player_select_key = [b'K', b'Q'];
     let mut player_start_key = [b'L', b'W'];
@@ -128,6 +212,9 @@ fn main() {
                 Key(F7, true) => {
                     nees_std::load_state(rom_path, &mut nes);
                 }
+                Key(F8, true) => {
+                    nes.flip_disk();
+                }
                 Command { which: 3 } => {
                     nees_std::save_state(rom_path, &nes);
                 }
@@ -183,6 +270,27 @@ fn main() {
                         nees_osd::config_menu::StepResponse::SaveState => todo!(),
                         nees_osd::config_menu::StepResponse::LoadState => todo!(),
                         nees_osd::config_menu::StepResponse::HorizontalAdjustment(_) => todo!(),
+                        nees_osd::config_menu::StepResponse::PlayTrack(track) => {
+                            if let Some(player) = &mut nsf_player {
+                                player.select_track(track);
+                            }
+                        }
+                        nees_osd::config_menu::StepResponse::StopPlayback => {
+                            if let Some(player) = &mut nsf_player {
+                                player.stop();
+                            }
+                        }
+                        nees_osd::config_menu::StepResponse::SetCheatEnabled {
+                            index,
+                            enabled,
+                        } => {
+                            nes.cheats_mut().set_enabled(index as usize, enabled);
+                            nees_std::save_cheats(rom_path, &nes);
+                        }
+                        nees_osd::config_menu::StepResponse::RamSearch(step) => {
+                            step_ram_search(&mut nes, &mut osd, &mut ram_search, rom_path, step);
+                            osd.draw_step(&mut framebuffer);
+                        }
                     }
                 }
             }
@@ -197,7 +305,14 @@ fn main() {
             accum = std::time::Duration::ZERO;
         }
 
-        if !osd_open {
+        if let Some(player) = &mut nsf_player {
+            // Music keeps playing behind the track list
+            accum += delta;
+            while accum >= dt_target {
+                player.tick_frame(&mut waveout_callback);
+                accum -= dt_target;
+            }
+        } else if !osd_open {
             sec_accum += delta;
             accum += delta;
 
@@ -206,7 +321,11 @@ fn main() {
                 nes_frames = 0;
                 sec_accum = std::time::Duration::ZERO;
 
-                wnd.set_title(format!("Nees - FPS: {}", nes_fps).as_str());
+                let game_title = nes
+                    .game()
+                    .map(|game| format!(" - {}", game.title))
+                    .unwrap_or_default();
+                wnd.set_title(format!("Nees{} - FPS: {}", game_title, nes_fps).as_str());
             }
 
             while accum >= dt_target {
@@ -226,4 +345,6 @@ fn main() {
 
         std::thread::sleep(std::time::Duration::from_millis(1));
     }
+
+    nees_std::save_battery(rom_path, &nes);
 }
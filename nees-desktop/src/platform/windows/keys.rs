@@ -4,4 +4,5 @@ pub const ARROW_RIGHT: u8 = 39;
 pub const ARROW_DOWN: u8 = 40;
 pub const F5: u8 = 116;
 pub const F7: u8 = 118;
+pub const F8: u8 = 119;
 pub const ESCAPE: u8 = 27;
\ No newline at end of file
@@ -0,0 +1,93 @@
+//! Command-line NSF player. Lists the tracks of an NSF or NSFe file, then renders one as
+//! 16-bit mono PCM, either into a WAV file or to stdout for piping into a player:
+//!
+//! ```text
+//! nees-nsfplay music.nsf 3 | aplay -f S16_LE -r 15720
+//! nees-nsfplay music.nsf 3 --wav track3.wav
+//! ```
+
+use std::io::{IsTerminal, Write};
+
+use nees::{nsf::NSF, nsf_player::NSFPlayer};
+
+/// One sample per scanline, as the core produces them.
+const SAMPLE_RATE: u32 = 15720;
+
+fn format_ms(ms: u32) -> String {
+    format!("{}:{:02}", ms / 60_000, (ms / 1000) % 60)
+}
+
+fn wav_header(num_samples: u32) -> Vec<u8> {
+    let data_len = num_samples * 2;
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&(36 + data_len).to_le_bytes());
+    header.extend_from_slice(b"WAVEfmt ");
+    header.extend_from_slice(&16_u32.to_le_bytes());
+    header.extend_from_slice(&1_u16.to_le_bytes()); // PCM
+    header.extend_from_slice(&1_u16.to_le_bytes()); // Mono
+    header.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    header.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    header.extend_from_slice(&2_u16.to_le_bytes());
+    header.extend_from_slice(&16_u16.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&data_len.to_le_bytes());
+    header
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.get(1) else {
+        eprintln!("Usage: nees-nsfplay <file.nsf|file.nsfe> [track] [--wav out.wav]");
+        std::process::exit(1);
+    };
+    let track: Option<u8> = args.get(2).and_then(|t| t.parse().ok());
+    let wav_path = args
+        .iter()
+        .position(|arg| arg == "--wav")
+        .and_then(|i| args.get(i + 1));
+
+    let nsf = NSF::new(&std::fs::read(path).unwrap()).unwrap();
+    let mut player = NSFPlayer::new(nsf);
+
+    let nsf = player.nsf();
+    eprintln!("{} - {} ({})", nsf.name, nsf.artist, nsf.copyright);
+    for i in 0..nsf.total_songs {
+        let name = nsf.track_name(i).unwrap_or("");
+        let length = nsf.track_length_ms(i).map(format_ms).unwrap_or_default();
+        eprintln!("{:3} {:40} {}", i + 1, name, length);
+    }
+
+    if wav_path.is_none() && std::io::stdout().is_terminal() {
+        eprintln!("Pipe stdout into a player, or use --wav to write a file");
+        return;
+    }
+
+    if let Some(track) = track {
+        player.select_track(track.saturating_sub(1));
+    }
+    eprintln!(
+        "Playing track {} for {}",
+        player.track() + 1,
+        format_ms(player.track_length_ms() + player.fade_ms())
+    );
+
+    let mut samples = Vec::new();
+    let mut stdout = std::io::stdout().lock();
+    while player.is_playing() {
+        player.tick_frame(&mut |sample: i16| samples.extend_from_slice(&sample.to_le_bytes()));
+        if wav_path.is_none() {
+            if stdout.write_all(&samples).is_err() {
+                // The player on the other end of the pipe quit
+                return;
+            }
+            samples.clear();
+        }
+    }
+
+    if let Some(wav_path) = wav_path {
+        let mut wav = wav_header(samples.len() as u32 / 2);
+        wav.extend_from_slice(&samples);
+        std::fs::write(wav_path, wav).unwrap();
+    }
+}
@@ -13,6 +13,25 @@ pub enum StepResponse {
     SaveState,
     LoadState,
     HorizontalAdjustment(i16),
+    PlayTrack(u8),
+    StopPlayback,
+    SetCheatEnabled { index: u8, enabled: bool },
+    RamSearch(RamSearchStep),
+}
+
+/// What the RAM search screen asks the frontend to do with its search.
+#[derive(Clone, Copy)]
+pub enum RamSearchStep {
+    New,
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    /// Add cheats freezing a result at its current value.
+    Freeze {
+        address: u16,
+        value: i32,
+    },
 }
 
 #[repr(u8)]
@@ -29,18 +48,54 @@ const GRAY: u8 = 1;
 const WHITE: u8 = 2;
 const BLUE: u8 = 3;
 const START_ROW: u8 = 3;
-const END_ROW: u8 = START_ROW + 14;
+const END_ROW: u8 = START_ROW + 16;
+/// How many tracks fit on the track list screen at once.
+const VISIBLE_TRACKS: u8 = 10;
+/// How many cheats fit on the cheats screen at once.
+const VISIBLE_CHEATS: u8 = 10;
+const MAIN_CHEATS: u8 = 5;
+/// The main menu's last item.
+const MAIN_RAM_SEARCH: u8 = 6;
+/// The RAM search screen's filters come first, then up to this many results.
+const RAM_SEARCH_FILTERS: u8 = 5;
+const VISIBLE_RAM_SEARCH_RESULTS: usize = 5;
 
 enum OSDState {
-    Main { current_selection: u8 },
-    RemapPlayer { which_player: u8, current_key: u8 },
-    VideoSettings { current_selection: u8 },
-    VideoSettingsHorizontalAdjustment { value: i16 },
+    Main {
+        current_selection: u8,
+    },
+    RemapPlayer {
+        which_player: u8,
+        current_key: u8,
+    },
+    VideoSettings {
+        current_selection: u8,
+    },
+    VideoSettingsHorizontalAdjustment {
+        value: i16,
+    },
+    TrackList {
+        current_selection: u8,
+        first_visible: u8,
+    },
+    Cheats {
+        current_selection: u8,
+        first_visible: u8,
+    },
+    RamSearch {
+        current_selection: u8,
+    },
 }
 
 #[allow(clippy::upper_case_acronyms)]
 pub struct OSD {
     current_menu: OSDState,
+    tracks: Vec<String>,
+    /// Names of the game's cheats, and whether they're on.
+    cheats: Vec<(String, bool)>,
+    /// The number of RAM search candidates, and the first few with their values.
+    ram_search_count: Option<usize>,
+    ram_search_results: Vec<(u16, i32)>,
 }
 
 impl OSD {
@@ -49,9 +104,40 @@ impl OSD {
             current_menu: OSDState::Main {
                 current_selection: 0,
             },
+            tracks: Vec::new(),
+            cheats: Vec::new(),
+            ram_search_count: None,
+            ram_search_results: Vec::new(),
+        }
+    }
+
+    /// Sets the cheats listed on the cheats screen, which can turn them on and off.
+    pub fn set_cheats(&mut self, cheats: Vec<(String, bool)>) {
+        self.cheats = cheats;
+    }
+
+    /// Shows how a RAM search is going, after the frontend runs a `RamSearchStep`.
+    pub fn set_ram_search_results(&mut self, count: usize, results: Vec<(u16, i32)>) {
+        self.ram_search_count = Some(count);
+        self.ram_search_results = results;
+        self.ram_search_results.truncate(VISIBLE_RAM_SEARCH_RESULTS);
+
+        // Filtering can leave fewer results than there were
+        if let OSDState::RamSearch { current_selection } = &mut self.current_menu {
+            let back = RAM_SEARCH_FILTERS + self.ram_search_results.len() as u8;
+            *current_selection = (*current_selection).min(back);
         }
     }
 
+    /// Switches to the track list screen for an NSF, whose last entry stops playback.
+    pub fn open_track_list(&mut self, tracks: Vec<String>, current_track: u8) {
+        self.tracks = tracks;
+        self.current_menu = OSDState::TrackList {
+            current_selection: current_track,
+            first_visible: current_track.saturating_sub(VISIBLE_TRACKS - 1),
+        };
+    }
+
     fn draw_char(&self, framebuffer: &mut [u32], col: u8, mut row: u8, c: char, fg: u8, bg: u8) {
         row += START_ROW;
         let font = include_bytes!("../menu_font.bin");
@@ -121,6 +207,18 @@ impl OSD {
                 self.draw_menu_item(framebuffer, 9, "  Video settings", current_selection == 2);
                 self.draw_menu_item(framebuffer, 11, "  Save state", current_selection == 3);
                 self.draw_menu_item(framebuffer, 12, "  Load state", current_selection == 4);
+                self.draw_menu_item(
+                    framebuffer,
+                    14,
+                    "  Cheats",
+                    current_selection == MAIN_CHEATS,
+                );
+                self.draw_menu_item(
+                    framebuffer,
+                    15,
+                    "  RAM search",
+                    current_selection == MAIN_RAM_SEARCH,
+                );
             }
             OSDState::RemapPlayer {
                 which_player: _,
@@ -143,7 +241,12 @@ impl OSD {
                     "  Horizontal adjustment",
                     current_selection == 0,
                 );
-                self.draw_menu_item(framebuffer, 7, "  Curve ratio (N/A)", current_selection == 1);
+                self.draw_menu_item(
+                    framebuffer,
+                    7,
+                    "  Curve ratio (N/A)",
+                    current_selection == 1,
+                );
                 self.draw_menu_item(framebuffer, 8, "  Scanlines (N/A)", current_selection == 2);
                 self.draw_menu_item(framebuffer, 10, "  Back", current_selection == 3);
             }
@@ -158,6 +261,92 @@ impl OSD {
                     BACKGROUND,
                 );
             }
+            OSDState::TrackList {
+                current_selection,
+                first_visible,
+            } => {
+                let last_visible =
+                    (first_visible as usize + VISIBLE_TRACKS as usize).min(self.tracks.len());
+                for (i, track) in self.tracks[first_visible as usize..last_visible]
+                    .iter()
+                    .enumerate()
+                {
+                    let index = first_visible + i as u8;
+                    let text: String = format!("  {:2} {}", index + 1, track)
+                        .chars()
+                        .take(29)
+                        .collect();
+                    self.draw_menu_item(
+                        framebuffer,
+                        3 + i as u8,
+                        &text,
+                        current_selection == index,
+                    );
+                }
+                let stop_selected = current_selection as usize == self.tracks.len();
+                self.draw_menu_item(framebuffer, 4 + VISIBLE_TRACKS, "  Stop", stop_selected);
+            }
+            OSDState::Cheats {
+                current_selection,
+                first_visible,
+            } => {
+                if self.cheats.is_empty() {
+                    self.draw_string_centered(
+                        framebuffer,
+                        6,
+                        "No cheats for this game",
+                        GRAY,
+                        BACKGROUND,
+                    );
+                }
+                let last_visible =
+                    (first_visible as usize + VISIBLE_CHEATS as usize).min(self.cheats.len());
+                for (i, (name, enabled)) in self.cheats[first_visible as usize..last_visible]
+                    .iter()
+                    .enumerate()
+                {
+                    let index = first_visible + i as u8;
+                    let text: String = format!("  [{}] {}", if *enabled { 'x' } else { ' ' }, name)
+                        .chars()
+                        .take(29)
+                        .collect();
+                    self.draw_menu_item(
+                        framebuffer,
+                        3 + i as u8,
+                        &text,
+                        current_selection == index,
+                    );
+                }
+                let back_selected = current_selection as usize == self.cheats.len();
+                self.draw_menu_item(framebuffer, 4 + VISIBLE_CHEATS, "  Back", back_selected);
+            }
+            OSDState::RamSearch { current_selection } => {
+                let status = match self.ram_search_count {
+                    Some(count) => format!("{} candidates", count),
+                    None => "Start a new search".to_string(),
+                };
+                self.draw_string_centered(framebuffer, 3, &status, GRAY, BACKGROUND);
+                let filters = ["New search", "Same", "Changed", "Increased", "Decreased"];
+                for (i, filter) in filters.iter().enumerate() {
+                    self.draw_menu_item(
+                        framebuffer,
+                        5 + i as u8,
+                        &format!("  {}", filter),
+                        current_selection == i as u8,
+                    );
+                }
+                for (i, (address, value)) in self.ram_search_results.iter().enumerate() {
+                    let selection = RAM_SEARCH_FILTERS + i as u8;
+                    self.draw_menu_item(
+                        framebuffer,
+                        10 + i as u8,
+                        &format!("  ${:04X} = {} (freeze)", address, value),
+                        current_selection == selection,
+                    );
+                }
+                let back = RAM_SEARCH_FILTERS + self.ram_search_results.len() as u8;
+                self.draw_menu_item(framebuffer, 15, "  Back", current_selection == back);
+            }
         }
     }
 
@@ -167,7 +356,7 @@ impl OSD {
                 OSDAction::Up => {
                     self.current_menu = OSDState::Main {
                         current_selection: if current_selection == 0 {
-                            4
+                            MAIN_RAM_SEARCH
                         } else {
                             current_selection - 1
                         },
@@ -175,7 +364,7 @@ impl OSD {
                 }
                 OSDAction::Down => {
                     self.current_menu = OSDState::Main {
-                        current_selection: if current_selection == 4 {
+                        current_selection: if current_selection == MAIN_RAM_SEARCH {
                             0
                         } else {
                             current_selection + 1
@@ -202,6 +391,17 @@ impl OSD {
                     }
                     3 => return StepResponse::SaveState,
                     4 => return StepResponse::LoadState,
+                    MAIN_CHEATS => {
+                        self.current_menu = OSDState::Cheats {
+                            current_selection: 0,
+                            first_visible: 0,
+                        };
+                    }
+                    MAIN_RAM_SEARCH => {
+                        self.current_menu = OSDState::RamSearch {
+                            current_selection: 0,
+                        };
+                    }
                     _ => {}
                 },
             },
@@ -284,6 +484,99 @@ impl OSD {
                 }
                 return StepResponse::HorizontalAdjustment(value);
             }
+            OSDState::TrackList {
+                current_selection,
+                first_visible,
+            } => {
+                let stop = self.tracks.len() as u8;
+                let current_selection = match action {
+                    OSDAction::Up if current_selection == 0 => stop,
+                    OSDAction::Up => current_selection - 1,
+                    OSDAction::Down if current_selection == stop => 0,
+                    OSDAction::Down => current_selection + 1,
+                    OSDAction::Ok if current_selection == stop => {
+                        return StepResponse::StopPlayback
+                    }
+                    OSDAction::Ok => return StepResponse::PlayTrack(current_selection),
+                };
+
+                // Scroll to keep the selected track in view
+                let track = current_selection.min(stop.saturating_sub(1));
+                let first_visible = first_visible
+                    .min(track)
+                    .max(track.saturating_sub(VISIBLE_TRACKS - 1));
+                self.current_menu = OSDState::TrackList {
+                    current_selection,
+                    first_visible,
+                };
+            }
+            OSDState::Cheats {
+                current_selection,
+                first_visible,
+            } => {
+                let back = self.cheats.len() as u8;
+                let current_selection = match action {
+                    OSDAction::Up if current_selection == 0 => back,
+                    OSDAction::Up => current_selection - 1,
+                    OSDAction::Down if current_selection == back => 0,
+                    OSDAction::Down => current_selection + 1,
+                    OSDAction::Ok if current_selection == back => {
+                        self.current_menu = OSDState::Main {
+                            current_selection: MAIN_CHEATS,
+                        };
+                        return StepResponse::None;
+                    }
+                    OSDAction::Ok => {
+                        let enabled = &mut self.cheats[current_selection as usize].1;
+                        *enabled = !*enabled;
+                        return StepResponse::SetCheatEnabled {
+                            index: current_selection,
+                            enabled: *enabled,
+                        };
+                    }
+                };
+
+                // Scroll to keep the selected cheat in view
+                let cheat = current_selection.min(back.saturating_sub(1));
+                let first_visible = first_visible
+                    .min(cheat)
+                    .max(cheat.saturating_sub(VISIBLE_CHEATS - 1));
+                self.current_menu = OSDState::Cheats {
+                    current_selection,
+                    first_visible,
+                };
+            }
+            OSDState::RamSearch { current_selection } => {
+                let back = RAM_SEARCH_FILTERS + self.ram_search_results.len() as u8;
+                let current_selection = match action {
+                    OSDAction::Up if current_selection == 0 => back,
+                    OSDAction::Up => current_selection - 1,
+                    OSDAction::Down if current_selection == back => 0,
+                    OSDAction::Down => current_selection + 1,
+                    OSDAction::Ok if current_selection == back => {
+                        self.current_menu = OSDState::Main {
+                            current_selection: MAIN_RAM_SEARCH,
+                        };
+                        return StepResponse::None;
+                    }
+                    OSDAction::Ok => {
+                        let step = match current_selection {
+                            0 => RamSearchStep::New,
+                            1 => RamSearchStep::Equal,
+                            2 => RamSearchStep::Changed,
+                            3 => RamSearchStep::Increased,
+                            4 => RamSearchStep::Decreased,
+                            _ => {
+                                let result = (current_selection - RAM_SEARCH_FILTERS) as usize;
+                                let (address, value) = self.ram_search_results[result];
+                                RamSearchStep::Freeze { address, value }
+                            }
+                        };
+                        return StepResponse::RamSearch(step);
+                    }
+                };
+                self.current_menu = OSDState::RamSearch { current_selection };
+            }
         }
 
         StepResponse::None
@@ -0,0 +1,161 @@
+//! Headless RAM search, for finding cheat addresses from a terminal or a script. Runs a ROM
+//! without video or sound and reads commands from stdin, one per line:
+//!
+//! ```text
+//! frames 60                  Run 60 frames
+//! hold start right           Hold player 1's buttons while running; "hold" alone lets go
+//! new [u8|i8|u16|i16]        Start a new search, with every address a candidate
+//! equal, changed,
+//! increased, decreased       Keep the candidates that changed like this since the last search
+//! value 3                    Keep the candidates holding 3
+//! list [20]                  Show the first candidates
+//! freeze 0075 3              Freeze $0075 at 3, adding a cheat to the ROM's cheat file
+//! quit
+//! ```
+//!
+//! For example, to find the lives counter in a game where the player starts with 3 and has
+//! just lost one: `nees-ramsearch game.nes < lives.txt`.
+
+use std::io::{BufRead, Write};
+
+use nees::{
+    nes001::{ControllerState, NES001},
+    ram_search::{Filter, RamSearch, ValueType},
+};
+use nees_std::archive::{self, RomFile};
+
+fn parse_buttons<'a>(names: impl Iterator<Item = &'a str>) -> Result<ControllerState, String> {
+    let mut state = ControllerState::new();
+    for name in names {
+        state = match name {
+            "a" => state.with_a(true),
+            "b" => state.with_b(true),
+            "select" => state.with_select(true),
+            "start" => state.with_start(true),
+            "up" => state.with_up(true),
+            "down" => state.with_down(true),
+            "left" => state.with_left(true),
+            "right" => state.with_right(true),
+            _ => return Err(format!("Unknown button {}", name)),
+        };
+    }
+    Ok(state)
+}
+
+fn parse_value_type(name: Option<&str>) -> Result<ValueType, String> {
+    match name {
+        None | Some("u8") => Ok(ValueType::U8),
+        Some("i8") => Ok(ValueType::I8),
+        Some("u16") => Ok(ValueType::U16),
+        Some("i16") => Ok(ValueType::I16),
+        Some(name) => Err(format!("Unknown value type {}", name)),
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(text: Option<&str>) -> Result<T, String> {
+    let text = text.ok_or("Missing number")?;
+    text.parse().map_err(|_| format!("{} isn't a number", text))
+}
+
+struct Session {
+    nes: NES001,
+    rom_path: String,
+    search: RamSearch,
+    framebuffer: Vec<u32>,
+}
+
+impl Session {
+    fn run_command(&mut self, line: &str) -> Result<(), String> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(());
+        };
+
+        let filter = match command {
+            "frames" => {
+                let frames: u32 = parse_number(words.next())?;
+                for _ in 0..frames {
+                    self.nes.tick_frame(&mut |_| {}, &mut self.framebuffer);
+                }
+                return Ok(());
+            }
+            "hold" => {
+                let state = parse_buttons(words)?;
+                self.nes.set_buttons_down(0, &state);
+                return Ok(());
+            }
+            "new" => {
+                let value_type = parse_value_type(words.next())?;
+                self.search = RamSearch::new(&self.nes, value_type);
+                println!("{} candidates", self.search.len());
+                return Ok(());
+            }
+            "list" => {
+                let count = words.next().map_or(Ok(20), |n| parse_number(Some(n)))?;
+                for result in self.search.results(&self.nes).iter().take(count) {
+                    println!(
+                        "${:04X} = {} (was {})",
+                        result.address, result.value, result.previous
+                    );
+                }
+                return Ok(());
+            }
+            "freeze" => {
+                let address = words.next().ok_or("Missing address")?;
+                let address = u16::from_str_radix(address.trim_start_matches('$'), 16)
+                    .map_err(|_| format!("{} isn't a hex address", address))?;
+                let value = parse_number(words.next())?;
+                let cheats = self.search.freeze_cheats(address, value);
+                if cheats.is_empty() {
+                    return Err(format!("${:04X} isn't in RAM", address));
+                }
+                for cheat in cheats {
+                    println!("Added {}", cheat.code);
+                    self.nes.cheats_mut().add(cheat);
+                }
+                nees_std::save_cheats(&self.rom_path, &self.nes);
+                return Ok(());
+            }
+            "quit" => std::process::exit(0),
+            "equal" => Filter::Equal,
+            "changed" => Filter::Changed,
+            "increased" => Filter::Increased,
+            "decreased" => Filter::Decreased,
+            "value" => Filter::Value(parse_number(words.next())?),
+            _ => return Err(format!("Unknown command {}", command)),
+        };
+        println!("{} candidates", self.search.filter(&self.nes, filter));
+        Ok(())
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(path) = args.get(1) else {
+        eprintln!("Usage: nees-ramsearch <rom> < commands.txt");
+        std::process::exit(1);
+    };
+
+    let rom = RomFile::read_choosing(path, |names| {
+        archive::choose_with_prompt(names, &mut std::io::stdin().lock(), &mut std::io::stdout())
+    })
+    .unwrap();
+    let mut nes = nees_std::load_rom(&rom).unwrap();
+    nees_std::load_battery(&rom.path, &mut nes);
+    nees_std::load_cheats(&rom.path, &mut nes).unwrap();
+    let search = RamSearch::new(&nes, ValueType::U8);
+    let mut session = Session {
+        nes,
+        rom_path: rom.path,
+        search,
+        framebuffer: vec![0; 256 * 240],
+    };
+
+    for line in std::io::stdin().lock().lines() {
+        let Ok(line) = line else { break };
+        if let Err(error) = session.run_command(&line) {
+            eprintln!("{}", error);
+        }
+        std::io::stdout().flush().ok();
+    }
+}
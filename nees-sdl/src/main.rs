@@ -1,6 +1,7 @@
 use std::{collections::VecDeque, os::raw::c_void};
 
-use nees::nes001::{self, ControllerState};
+use nees::nes001::ControllerState;
+use nees_std::archive::{self, RomFile};
 use sdl2::{
     audio::{AudioCallback, AudioSpec, AudioSpecDesired},
     event::Event,
@@ -116,8 +117,16 @@ fn main() {
         device.lock().push_sample(sample);
     };
 
-    let rom_path = "roms/punchout.nes";
-    let mut nes = nes001::NES001::from_rom(&std::fs::read(rom_path).unwrap());
+    let rom_path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "roms/punchout.nes".to_string());
+    let rom = RomFile::read_choosing(&rom_path, |names| {
+        archive::choose_with_prompt(names, &mut std::io::stdin().lock(), &mut std::io::stdout())
+    })
+    .unwrap();
+    let mut nes = nees_std::load_rom(&rom).unwrap();
+    nees_std::load_battery(&rom.path, &mut nes);
+    nees_std::load_cheats(&rom.path, &mut nes).unwrap();
 
     let mut player1_controller_state: ControllerState = ControllerState::new();
     let mut player2_controller_state: ControllerState = ControllerState::new();
@@ -225,7 +234,11 @@ fn main() {
             nes_frames = 0;
             sec_accum = std::time::Duration::ZERO;            
 
-            window.set_title(format!("NES Emulator - FPS: {}", nes_fps).as_str()).unwrap();
+            let game_title = nes
+                .game()
+                .map(|game| format!(" - {}", game.title))
+                .unwrap_or_default();
+            window.set_title(format!("NES Emulator{} - FPS: {}", game_title, nes_fps).as_str()).unwrap();
         }
 
         while accum >= dt_target {
@@ -245,5 +258,7 @@ fn main() {
         std::thread::sleep(std::time::Duration::from_millis(1));
     }
 
+    nees_std::save_battery(&rom.path, &nes);
+
     println!("Hello, world!");
 }
@@ -0,0 +1,349 @@
+#[cfg(feature = "archive")]
+use std::io::{Cursor, Read};
+use std::{
+    io::{BufRead, Write},
+    path::Path,
+};
+
+use anyhow::anyhow;
+
+#[cfg(feature = "archive")]
+use crate::patch::MAX_TARGET_SIZE;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const SEVEN_ZIP_MAGIC: &[u8] = &[b'7', b'z', 0xBC, 0xAF, 0x27, 0x1C];
+
+/// Extensions of the files worth taking out of an archive.
+#[cfg(feature = "archive")]
+const ROM_EXTENSIONS: &[&str] = &["nes", "fds", "nsf", "nsfe", "unf", "unif"];
+
+/// A ROM, read straight from disk or out of a zip, gzip or 7z archive.
+pub struct RomFile {
+    /// Where the ROM would be if it wasn't archived, like roms/Game.nes for Game.nes inside
+    /// roms/games.zip. Saves and patches are kept next to this path.
+    pub path: String,
+    pub data: Vec<u8>,
+}
+
+impl RomFile {
+    /// Reads a ROM, taking the first one from archives holding several.
+    pub fn read(path: &str) -> anyhow::Result<Self> {
+        Self::read_choosing(path, |_| 0)
+    }
+
+    /// Reads a ROM, unpacking it if it's archived. When an archive holds several ROMs, `choose`
+    /// is given their names and returns the index of the one to run.
+    pub fn read_choosing(
+        path: &str,
+        choose: impl FnOnce(&[String]) -> usize,
+    ) -> anyhow::Result<Self> {
+        let data = std::fs::read(path).map_err(|e| anyhow!(e))?;
+        let Some((name, data)) = unpack(path, &data, choose)? else {
+            return Ok(Self {
+                path: path.to_string(),
+                data,
+            });
+        };
+
+        // Archives can have folders, which don't exist next to the archive
+        let file_name = name.rsplit(['/', '\\']).next().unwrap_or(&name);
+        Ok(Self {
+            path: Path::new(path)
+                .with_file_name(file_name)
+                .to_string_lossy()
+                .into_owned(),
+            data,
+        })
+    }
+}
+
+/// Asks which of an archive's ROMs to run, listing them on `output` and reading the number
+/// from `input`. Picks the first when the input runs out.
+pub fn choose_with_prompt(
+    names: &[String],
+    input: &mut impl BufRead,
+    output: &mut impl Write,
+) -> usize {
+    writeln!(output, "The archive holds several ROMs:").ok();
+    for (i, name) in names.iter().enumerate() {
+        writeln!(output, "{:>3}: {}", i + 1, name).ok();
+    }
+    loop {
+        write!(output, "Which one? ").ok();
+        output.flush().ok();
+        let mut line = String::new();
+        match input.read_line(&mut line) {
+            Ok(0) | Err(_) => return 0,
+            Ok(_) => {}
+        }
+        if let Ok(number @ 1..) = line.trim().parse::<usize>() {
+            if number <= names.len() {
+                return number - 1;
+            }
+        }
+    }
+}
+
+/// The name and contents of the ROM in an archive, or None if the data isn't one.
+#[cfg(feature = "archive")]
+fn unpack(
+    path: &str,
+    data: &[u8],
+    choose: impl FnOnce(&[String]) -> usize,
+) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+    Ok(Some(if data.starts_with(ZIP_MAGIC) {
+        read_zip(data, choose)?
+    } else if data.starts_with(GZIP_MAGIC) {
+        read_gzip(path, data)?
+    } else if data.starts_with(SEVEN_ZIP_MAGIC) {
+        read_7z(data, choose)?
+    } else {
+        return Ok(None);
+    }))
+}
+
+#[cfg(not(feature = "archive"))]
+fn unpack(
+    _path: &str,
+    data: &[u8],
+    _choose: impl FnOnce(&[String]) -> usize,
+) -> anyhow::Result<Option<(String, Vec<u8>)>> {
+    if [ZIP_MAGIC, GZIP_MAGIC, SEVEN_ZIP_MAGIC]
+        .iter()
+        .any(|magic| data.starts_with(magic))
+    {
+        return Err(anyhow!("Archives need nees-std's archive feature"));
+    }
+    Ok(None)
+}
+
+#[cfg(feature = "archive")]
+fn is_rom_name(name: &str) -> bool {
+    Path::new(name)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            ROM_EXTENSIONS
+                .iter()
+                .any(|rom_extension| extension.eq_ignore_ascii_case(rom_extension))
+        })
+}
+
+/// The name of the archived ROM to run, out of all the files in an archive.
+#[cfg(feature = "archive")]
+fn choose_rom(names: &[String], choose: impl FnOnce(&[String]) -> usize) -> anyhow::Result<String> {
+    let roms: Vec<String> = names
+        .iter()
+        .filter(|name| is_rom_name(name))
+        .cloned()
+        .collect();
+    let index = match roms.len() {
+        0 => return Err(anyhow!("No ROMs in the archive")),
+        1 => 0,
+        _ => choose(&roms),
+    };
+    roms.get(index)
+        .cloned()
+        .ok_or_else(|| anyhow!("No ROM chosen from the archive"))
+}
+
+#[cfg(feature = "archive")]
+fn read_zip(
+    data: &[u8],
+    choose: impl FnOnce(&[String]) -> usize,
+) -> anyhow::Result<(String, Vec<u8>)> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(data)).map_err(|e| anyhow!(e))?;
+    let names: Vec<String> = (0..archive.len())
+        .filter_map(|i| archive.name_for_index(i).map(str::to_string))
+        .collect();
+    let name = choose_rom(&names, choose)?;
+
+    let file = archive.by_name(&name).map_err(|e| anyhow!(e))?;
+    let rom = read_rom(file)?;
+    Ok((name, rom))
+}
+
+/// Decompresses an archived ROM, stopping at the same size limit as patches so that a small
+/// archive can't fill up memory.
+#[cfg(feature = "archive")]
+fn read_rom(reader: impl Read) -> anyhow::Result<Vec<u8>> {
+    let mut rom = Vec::new();
+    reader
+        .take(MAX_TARGET_SIZE as u64 + 1)
+        .read_to_end(&mut rom)
+        .map_err(|e| anyhow!(e))?;
+    if rom.len() > MAX_TARGET_SIZE {
+        return Err(anyhow!("The archived ROM is too big"));
+    }
+    Ok(rom)
+}
+
+/// Gzip holds a single file, which usually keeps its name in the header. Without one, the ROM is
+/// named after the archive, so game.nes.gz holds game.nes.
+#[cfg(feature = "archive")]
+fn read_gzip(path: &str, data: &[u8]) -> anyhow::Result<(String, Vec<u8>)> {
+    let mut decoder = flate2::read::GzDecoder::new(data);
+    let rom = read_rom(&mut decoder)?;
+
+    let name = decoder
+        .header()
+        .and_then(|header| header.filename())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .or_else(|| {
+            let stem = Path::new(path).file_stem()?;
+            Some(stem.to_string_lossy().into_owned())
+        })
+        .ok_or_else(|| anyhow!("Gzip file without a name"))?;
+    Ok((name, rom))
+}
+
+#[cfg(feature = "archive")]
+fn read_7z(
+    data: &[u8],
+    choose: impl FnOnce(&[String]) -> usize,
+) -> anyhow::Result<(String, Vec<u8>)> {
+    let mut archive = sevenz_rust::SevenZReader::new(
+        Cursor::new(data),
+        data.len() as u64,
+        sevenz_rust::Password::empty(),
+    )
+    .map_err(|e| anyhow!(e))?;
+    let names: Vec<String> = archive
+        .archive()
+        .files
+        .iter()
+        .filter(|entry| !entry.is_directory())
+        .map(|entry| entry.name().to_string())
+        .collect();
+    let name = choose_rom(&names, choose)?;
+
+    // Entries are compressed together, so the ones before the ROM are decompressed too
+    let mut rom = None;
+    archive
+        .for_each_entries(|entry, reader| {
+            if entry.name() != name {
+                return Ok(true);
+            }
+            rom = Some(read_rom(reader));
+            Ok(false)
+        })
+        .map_err(|e| anyhow!(e))?;
+    let rom = rom.ok_or_else(|| anyhow!("{} is missing from the archive", name))??;
+    Ok((name, rom))
+}
+
+#[cfg(all(test, feature = "archive"))]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use super::{choose_with_prompt, RomFile, MAX_TARGET_SIZE};
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    /// Writes an archive to a temporary file, reads it with `read_choosing` and cleans up.
+    fn read_archive(
+        file_name: &str,
+        data: &[u8],
+        choose: impl FnOnce(&[String]) -> usize,
+    ) -> anyhow::Result<RomFile> {
+        let dir = std::env::temp_dir().join(format!("nees-archive-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(file_name);
+        std::fs::write(&path, data).unwrap();
+        let rom = RomFile::read_choosing(path.to_str().unwrap(), choose);
+        std::fs::remove_file(&path).unwrap();
+        rom
+    }
+
+    fn zip(files: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, data) in files {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_zip() {
+        let data = zip(&[
+            ("readme.txt", b"Hi"),
+            ("games/a.nes", b"NES A"),
+            ("games/b.NES", b"NES B"),
+        ]);
+        let rom = read_archive("games.zip", &data, |roms| {
+            assert_eq!(roms, names(&["games/a.nes", "games/b.NES"]));
+            1
+        })
+        .unwrap();
+        assert_eq!(rom.data, b"NES B");
+        // Named as if it were next to the archive
+        assert!(rom.path.ends_with("b.NES"));
+        assert!(!rom.path.contains("games/"));
+
+        let data = zip(&[("readme.txt", b"Hi")]);
+        assert!(read_archive("empty.zip", &data, |_| 0).is_err());
+    }
+
+    #[test]
+    fn test_gzip() {
+        let mut encoder = flate2::GzBuilder::new()
+            .filename("inside.nes")
+            .write(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"NES data").unwrap();
+        let rom = read_archive("game.gz", &encoder.finish().unwrap(), |_| 0).unwrap();
+        assert_eq!(rom.data, b"NES data");
+        assert!(rom.path.ends_with("inside.nes"));
+
+        // Without a name in the header, the ROM is named after the archive
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"NES data").unwrap();
+        let rom = read_archive("other.nes.gz", &encoder.finish().unwrap(), |_| 0).unwrap();
+        assert!(rom.path.ends_with("other.nes"));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(&vec![0; MAX_TARGET_SIZE + 1]).unwrap();
+        let data = encoder.finish().unwrap();
+        assert!(read_archive("bomb.nes.gz", &data, |_| 0).is_err());
+
+        let data = zip(&[("bomb.nes", &vec![0; MAX_TARGET_SIZE + 1])]);
+        assert!(read_archive("bomb.zip", &data, |_| 0).is_err());
+    }
+
+    #[test]
+    fn test_7z() {
+        let mut writer = sevenz_rust::SevenZWriter::new(Cursor::new(Vec::new())).unwrap();
+        for (name, data) in [("a.nes", b"NES A"), ("b.nes", b"NES B")] {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            entry.has_stream = true;
+            writer.push_archive_entry(entry, Some(&data[..])).unwrap();
+        }
+        let data = writer.finish().unwrap().into_inner();
+        let rom = read_archive("games.7z", &data, |_| 1).unwrap();
+        assert_eq!(rom.data, b"NES B");
+        assert!(rom.path.ends_with("b.nes"));
+    }
+
+    #[test]
+    fn test_choose_with_prompt() {
+        let roms = names(&["a.nes", "b.nes"]);
+        let mut output = Vec::new();
+        let choice = choose_with_prompt(&roms, &mut &b"x\n3\n2\n"[..], &mut output);
+        assert_eq!(choice, 1);
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("  2: b.nes"));
+        assert_eq!(output.matches("Which one?").count(), 3);
+
+        // Running out of input picks the first
+        assert_eq!(choose_with_prompt(&roms, &mut &b""[..], &mut Vec::new()), 0);
+    }
+}
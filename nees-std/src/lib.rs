@@ -1,21 +1,111 @@
-use std::io::{Read, Write};
+use std::{
+    io::{Read, Write},
+    path::{Path, PathBuf},
+};
 
 use anyhow::anyhow;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
-use nees::{nes001, EasyReader, EasyWriter};
+use nees::{
+    cheats::Cheats, game_db::GameDatabase, nes001, EasyReader, EasyWriter, MapperRegistry,
+};
+
+pub mod archive;
+pub mod patch;
+
+/// The game database: nes20db.xml from the ROM's directory if there is one, else the built-in
+/// database.
+pub fn game_database(rom_path: &str) -> GameDatabase {
+    let database_path = std::path::Path::new(rom_path).with_file_name("nes20db.xml");
+    match std::fs::read_to_string(database_path) {
+        Ok(xml) => GameDatabase::from_nes20db(&xml),
+        Err(_) => GameDatabase::builtin(),
+    }
+}
+
+/// Loads an iNES or UNIF ROM, applying a patch next to it like game.ips and fixing its header
+/// from the game database.
+pub fn load_rom(rom: &archive::RomFile) -> anyhow::Result<nes001::NES001> {
+    let data = match patch::find_patch(&rom.path) {
+        Some(patch) => patch::apply(&rom.data, &patch)?,
+        None => rom.data.clone(),
+    };
+    nes001::NES001::from_rom_with_database(&data, &MapperRegistry::new(), &game_database(&rom.path))
+}
+
+/// Where a save file with the given extension goes: named after the game's title when it's in
+/// the game database, so that differently named dumps share saves, else after the ROM.
+fn save_path(rom_path: &str, title: Option<&str>, extension: &str) -> PathBuf {
+    match title {
+        Some(title) => {
+            let file_name: String = title
+                .chars()
+                .map(|c| if r#"/\:*?"<>|"#.contains(c) { '_' } else { c })
+                .collect();
+            Path::new(rom_path).with_file_name(format!("{}.{}", file_name, extension))
+        }
+        None => PathBuf::from(format!("{}.{}", rom_path, extension)),
+    }
+}
+
+/// The save file to read: the one named after the game, falling back to one named after the
+/// ROM from before the game was recognised.
+fn existing_save_path(rom_path: &str, title: Option<&str>, extension: &str) -> PathBuf {
+    let path = save_path(rom_path, title, extension);
+    if path.exists() {
+        path
+    } else {
+        save_path(rom_path, None, extension)
+    }
+}
+
+fn title(nes: &nes001::NES001) -> Option<&str> {
+    nes.game().map(|game| game.title.as_str())
+}
 
 pub fn load_state(rom_path: &str, nes: &mut nes001::NES001) {
-    let save_path = format!("{}.sav", rom_path);
+    let save_path = existing_save_path(rom_path, title(nes), "sav");
     let mut buf_reader = MyBufReader::new(std::fs::File::open(save_path).unwrap());
     nes.load(&mut buf_reader).unwrap();
 }
 
 pub fn save_state(rom_path: &str, nes: &nes001::NES001) {
-    let save_path = format!("{}.sav", rom_path);
+    let save_path = save_path(rom_path, title(nes), "sav");
     let mut buf_writer = MyBufWriter::new(std::fs::File::create(save_path).unwrap());
     nes.save(&mut buf_writer).unwrap();
 }
 
+/// Restores battery-backed save data from next to the ROM, if there is any.
+pub fn load_battery(rom_path: &str, nes: &mut nes001::NES001) {
+    let battery_path = existing_save_path(rom_path, title(nes), "srm");
+    if let Ok(data) = std::fs::read(battery_path) {
+        nes.load_battery_data(&data);
+    }
+}
+
+pub fn save_battery(rom_path: &str, nes: &nes001::NES001) {
+    if let Some(data) = nes.battery_data() {
+        let battery_path = save_path(rom_path, title(nes), "srm");
+        std::fs::write(battery_path, data).unwrap();
+    }
+}
+
+/// Loads the ROM's cheat codes from the cheat file next to it, if there is one.
+pub fn load_cheats(rom_path: &str, nes: &mut nes001::NES001) -> anyhow::Result<()> {
+    let cheats_path = existing_save_path(rom_path, title(nes), "cht");
+    if let Ok(text) = std::fs::read_to_string(cheats_path) {
+        *nes.cheats_mut() = Cheats::from_cheat_file(&text)?;
+    }
+    Ok(())
+}
+
+/// Writes the cheat codes back, so the ones turned on or off stay that way.
+pub fn save_cheats(rom_path: &str, nes: &nes001::NES001) {
+    if !nes.cheats().is_empty() {
+        let cheats_path = save_path(rom_path, title(nes), "cht");
+        std::fs::write(cheats_path, nes.cheats().to_cheat_file()).unwrap();
+    }
+}
+
 pub fn save_state_buffer(nes: &nes001::NES001, writer: &mut dyn Write) {
     let mut buf_writer = MyBufWriter::new(writer);
     nes.save(&mut buf_writer).unwrap();
@@ -97,3 +187,35 @@ impl<W: Write> EasyWriter for MyBufWriter<W> {
         self.writer.write_all(buf).map_err(|e| anyhow!(e))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{existing_save_path, save_path};
+
+    #[test]
+    fn test_save_paths() {
+        assert_eq!(
+            save_path("roms/game.nes", None, "srm"),
+            Path::new("roms/game.nes.srm")
+        );
+        assert_eq!(
+            save_path("roms/game.nes", Some("Zelda: A/B? (USA)"), "srm"),
+            Path::new("roms/Zelda_ A_B_ (USA).srm")
+        );
+
+        // Saves from before the game was recognised are still found
+        let dir = std::env::temp_dir().join(format!("nees-saves-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let rom_path = dir.join("game.nes");
+        let rom_path = rom_path.to_str().unwrap();
+        std::fs::write(format!("{}.sav", rom_path), b"old").unwrap();
+        let path = existing_save_path(rom_path, Some("Game (USA)"), "sav");
+        assert_eq!(path, Path::new(&format!("{}.sav", rom_path)));
+        std::fs::write(dir.join("Game (USA).sav"), b"new").unwrap();
+        let path = existing_save_path(rom_path, Some("Game (USA)"), "sav");
+        assert_eq!(path, dir.join("Game (USA).sav"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,342 @@
+use anyhow::anyhow;
+use nees::{crc32::crc32, ips};
+
+/// Patch extensions looked for next to a ROM, in order of preference.
+const PATCH_EXTENSIONS: &[&str] = &["bps", "ups", "ips"];
+/// Far beyond any real ROM, so that a corrupt header can't make us allocate gigabytes.
+pub(crate) const MAX_TARGET_SIZE: usize = 64 * 1024 * 1024;
+
+/// Finds a patch with the same name as the ROM, like game.ips for game.nes.
+pub fn find_patch(rom_path: &str) -> Option<Vec<u8>> {
+    let rom_path = std::path::Path::new(rom_path);
+    PATCH_EXTENSIONS
+        .iter()
+        .find_map(|extension| std::fs::read(rom_path.with_extension(extension)).ok())
+}
+
+/// Applies an IPS, UPS or BPS patch, telling them apart by their magic.
+pub fn apply(rom: &[u8], patch: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if patch.starts_with(b"PATCH") {
+        ips::apply(rom, patch)
+    } else if patch.starts_with(b"UPS1") {
+        apply_ups(rom, patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(rom, patch)
+    } else {
+        Err(anyhow!("Unknown patch format"))
+    }
+}
+
+struct PatchReader<'a> {
+    patch: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> PatchReader<'a> {
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        let value = *self
+            .patch
+            .get(self.pos)
+            .ok_or_else(|| anyhow!("Unexpected end of patch"))?;
+        self.pos += 1;
+        Ok(value)
+    }
+
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .patch
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow!("Unexpected end of patch"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// UPS and BPS numbers: 7 bits per byte, least significant first, with the top bit marking
+    /// the last byte. Each continuation also adds one, so every number has a single encoding.
+    fn number(&mut self) -> anyhow::Result<usize> {
+        let mut value = 0_usize;
+        let mut shift = 1_usize;
+        loop {
+            let byte = self.u8()?;
+            value = value
+                .checked_add((byte & 0x7F) as usize * shift)
+                .ok_or_else(|| anyhow!("Number too large in patch"))?;
+            if (byte & 0x80) != 0 {
+                return Ok(value);
+            }
+            shift = shift
+                .checked_mul(128)
+                .ok_or_else(|| anyhow!("Number too large in patch"))?;
+            value += shift;
+        }
+    }
+}
+
+fn check_target_size(target_size: usize) -> anyhow::Result<()> {
+    if target_size > MAX_TARGET_SIZE {
+        return Err(anyhow!("The patched ROM would be too big"));
+    }
+    Ok(())
+}
+
+/// UPS and BPS end with the CRCs of the source, the target and the rest of the patch.
+fn split_footer(patch: &[u8]) -> anyhow::Result<(&[u8], [u32; 3])> {
+    if patch.len() < 4 + 12 {
+        return Err(anyhow!("Patch too short"));
+    }
+    let (body, footer) = patch.split_at(patch.len() - 12);
+    let crc = |i: usize| u32::from_le_bytes(footer[i * 4..i * 4 + 4].try_into().unwrap());
+    let crcs = [crc(0), crc(1), crc(2)];
+
+    if crc32(&patch[..patch.len() - 4]) != crcs[2] {
+        return Err(anyhow!("The patch is corrupt"));
+    }
+    Ok((body, crcs))
+}
+
+fn check_source(rom: &[u8], crc: u32) -> anyhow::Result<()> {
+    if crc32(rom) != crc {
+        return Err(anyhow!("The patch is for a different ROM"));
+    }
+    Ok(())
+}
+
+fn check_target(output: &[u8], crc: u32) -> anyhow::Result<()> {
+    if crc32(output) != crc {
+        return Err(anyhow!("The patched ROM doesn't match the patch's CRC"));
+    }
+    Ok(())
+}
+
+/// UPS patches XOR runs of bytes in place, with the runs separated by skipped bytes.
+fn apply_ups(rom: &[u8], patch: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (body, [source_crc, target_crc, _]) = split_footer(patch)?;
+    check_source(rom, source_crc)?;
+
+    let mut f = PatchReader {
+        patch: body,
+        pos: 4,
+    };
+    let _source_size = f.number()?;
+    let target_size = f.number()?;
+    check_target_size(target_size)?;
+
+    let mut output = rom.to_vec();
+    output.resize(target_size, 0);
+    let mut pos = 0;
+    while f.pos < body.len() {
+        pos += f.number()?;
+        loop {
+            let xor = f.u8()?;
+            if let Some(byte) = output.get_mut(pos) {
+                *byte ^= xor;
+            }
+            pos += 1;
+            if xor == 0 {
+                break;
+            }
+        }
+    }
+
+    check_target(&output, target_crc)?;
+    Ok(output)
+}
+
+/// BPS patches build the target from copies out of the source, the patch and the target so
+/// far.
+fn apply_bps(rom: &[u8], patch: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let (body, [source_crc, target_crc, _]) = split_footer(patch)?;
+    check_source(rom, source_crc)?;
+
+    let mut f = PatchReader {
+        patch: body,
+        pos: 4,
+    };
+    let _source_size = f.number()?;
+    let target_size = f.number()?;
+    check_target_size(target_size)?;
+    let metadata_size = f.number()?;
+    f.bytes(metadata_size)?;
+
+    let out_of_range = || anyhow!("The patch copies from outside the ROM");
+    // Signed offsets are stored as a magnitude with the sign in bit 0
+    let relative = |offset: usize, data: usize| {
+        if (data & 1) != 0 {
+            offset.checked_sub(data >> 1)
+        } else {
+            offset.checked_add(data >> 1)
+        }
+    };
+
+    // Only trust the header's size as far as the patch and ROM could plausibly produce
+    let mut output = Vec::with_capacity(target_size.min(rom.len() + patch.len()));
+    let mut source_offset = 0;
+    let mut target_offset = 0;
+    while f.pos < body.len() {
+        let data = f.number()?;
+        let len = (data >> 2) + 1;
+        if output.len() + len > target_size {
+            return Err(anyhow!("The patched ROM is the wrong size"));
+        }
+        match data & 3 {
+            // Source read
+            0 => {
+                let pos = output.len();
+                let bytes = rom.get(pos..pos + len).ok_or_else(out_of_range)?;
+                output.extend_from_slice(bytes);
+            }
+            // Target read
+            1 => output.extend_from_slice(f.bytes(len)?),
+            // Source copy
+            2 => {
+                source_offset = relative(source_offset, f.number()?).ok_or_else(out_of_range)?;
+                let bytes = rom
+                    .get(source_offset..source_offset + len)
+                    .ok_or_else(out_of_range)?;
+                output.extend_from_slice(bytes);
+                source_offset += len;
+            }
+            // Target copy, which can overlap what it's writing to repeat a pattern
+            _ => {
+                target_offset = relative(target_offset, f.number()?).ok_or_else(out_of_range)?;
+                for _ in 0..len {
+                    let byte = *output.get(target_offset).ok_or_else(out_of_range)?;
+                    output.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if output.len() != target_size {
+        return Err(anyhow!("The patched ROM is the wrong size"));
+    }
+    check_target(&output, target_crc)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use nees::{crc32::crc32, ips};
+
+    use super::apply;
+
+    /// Encodes a UPS/BPS number, the inverse of `PatchReader::number`.
+    fn number(patch: &mut Vec<u8>, mut value: usize) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                patch.push(byte | 0x80);
+                return;
+            }
+            patch.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn add_footer(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        patch.extend_from_slice(&crc32(&patch).to_le_bytes());
+        patch
+    }
+
+    fn ups(source: &[u8], target: &[u8]) -> Vec<u8> {
+        let mut patch = b"UPS1".to_vec();
+        number(&mut patch, source.len());
+        number(&mut patch, target.len());
+        let xor = |i: usize| source.get(i).copied().unwrap_or(0) ^ target[i];
+        let (mut i, mut last) = (0, 0);
+        while i < target.len() {
+            if xor(i) == 0 {
+                i += 1;
+                continue;
+            }
+            number(&mut patch, i - last);
+            while i < target.len() && xor(i) != 0 {
+                patch.push(xor(i));
+                i += 1;
+            }
+            patch.push(0);
+            i += 1;
+            last = i;
+        }
+        add_footer(patch, source, target)
+    }
+
+    #[test]
+    fn test_ips() {
+        let source = b"Hello, world";
+        let target = b"Jello, there!";
+        let patch = ips::diff(source, target).unwrap();
+        assert_eq!(apply(source, &patch).unwrap(), target);
+        assert!(apply(source, b"PATCH\x00\x00").is_err());
+    }
+
+    #[test]
+    fn test_ups() {
+        let source = b"Hello, world";
+        let target = b"Jello, there!";
+        let patch = ups(source, target);
+        assert_eq!(apply(source, &patch).unwrap(), target);
+
+        // The wrong ROM, and a corrupt patch
+        assert!(apply(b"Hello, World", &patch).is_err());
+        let mut corrupt = patch.clone();
+        corrupt[6] ^= 1;
+        assert!(apply(source, &corrupt).is_err());
+    }
+
+    #[test]
+    fn test_bps() {
+        let source = b"ABCDEFGH";
+        let target = b"ABCDxyxyxyEF";
+        let mut patch = b"BPS1".to_vec();
+        number(&mut patch, source.len());
+        number(&mut patch, target.len());
+        number(&mut patch, 0);
+        // Read ABCD from the source
+        number(&mut patch, 3 << 2);
+        // Read xy from the patch
+        number(&mut patch, (1 << 2) | 1);
+        patch.extend_from_slice(b"xy");
+        // Repeat it twice by copying from 4 bytes on in the target
+        number(&mut patch, (3 << 2) | 3);
+        number(&mut patch, 4 << 1);
+        // Copy EF from 4 bytes on in the source
+        number(&mut patch, (1 << 2) | 2);
+        number(&mut patch, 4 << 1);
+        let patch = add_footer(patch, source, target);
+        assert_eq!(apply(source, &patch).unwrap(), target);
+
+        // A bad target CRC
+        let mut corrupt = patch[..patch.len() - 12].to_vec();
+        corrupt = add_footer(corrupt, source, b"ABCDxyxyxyEG");
+        assert!(apply(source, &corrupt).is_err());
+    }
+
+    #[test]
+    fn test_huge_target_size() {
+        let source = b"ABCD";
+        for magic in [b"UPS1", b"BPS1"] {
+            let mut patch = magic.to_vec();
+            number(&mut patch, source.len());
+            number(&mut patch, 1 << 40);
+            number(&mut patch, 0);
+            let patch = add_footer(patch, source, source);
+            assert!(apply(source, &patch).is_err());
+        }
+
+        // Commands can't run past the size either, like repeating the first byte forever
+        let mut patch = b"BPS1".to_vec();
+        number(&mut patch, source.len());
+        number(&mut patch, 4);
+        number(&mut patch, 0);
+        number(&mut patch, 0);
+        number(&mut patch, (1 << 30) | 3);
+        number(&mut patch, 0);
+        let patch = add_footer(patch, source, source);
+        assert!(apply(source, &patch).is_err());
+    }
+}
@@ -71,7 +71,13 @@ async function start(rom: Uint8Array, rom_name: string) {
 
     const o = await loadwasm();
 
-    const nees_state_ptr = init(rom);
+    let nees_state_ptr;
+    try {
+        nees_state_ptr = init(rom);
+    } catch (e) {
+        alert(`Couldn't load ${rom_name}: ${e}`);
+        return;
+    }
     const fb_ptr = get_framebuffer_ptr();
     const fb_u8 = new Uint8Array(o.memory.buffer, fb_ptr, 256 * 240 * 4);
 
@@ -1,7 +1,11 @@
 use core::panic;
 
-use nees::nes001::{self, ControllerState};
-use nees_osd::config_menu::OSDAction;
+use nees::{
+    cheats::Cheats,
+    nes001::{self, ControllerState},
+    ram_search::{Filter, RamSearch, ValueType},
+};
+use nees_osd::config_menu::{OSDAction, RamSearchStep};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
@@ -20,15 +24,17 @@ pub fn get_framebuffer_ptr() -> *mut u32 {
 pub struct State {
     nes: nes001::NES001,
     osd: nees_osd::config_menu::OSD,
+    ram_search: Option<RamSearch>,
 }
 
 #[wasm_bindgen]
-pub fn init(rom: &[u8]) -> *mut State {
+pub fn init(rom: &[u8]) -> Result<*mut State, String> {
     let state = Box::new(State {
-        nes: nes001::NES001::from_rom(rom),
+        nes: nes001::NES001::from_rom(rom).map_err(|e| e.to_string())?,
         osd: nees_osd::config_menu::OSD::new(),
+        ram_search: None,
     });
-    Box::into_raw(state)
+    Ok(Box::into_raw(state))
 }
 
 #[wasm_bindgen]
@@ -110,7 +116,86 @@ pub unsafe fn step_osd(state: *mut State, action: u8) -> StepResponse {
         nees_osd::config_menu::StepResponse::HorizontalAdjustment(value) => {
             StepResponse { action: 11, value }
         }
+        nees_osd::config_menu::StepResponse::PlayTrack(track) => StepResponse {
+            action: 12,
+            value: track as i16,
+        },
+        nees_osd::config_menu::StepResponse::StopPlayback => StepResponse {
+            action: 13,
+            value: 0,
+        },
+        nees_osd::config_menu::StepResponse::RamSearch(step) => {
+            step_ram_search(state, step);
+            StepResponse {
+                action: 15,
+                value: state.ram_search.as_ref().map_or(0, |search| search.len()) as i16,
+            }
+        }
+        nees_osd::config_menu::StepResponse::SetCheatEnabled { index, enabled } => {
+            state.nes.cheats_mut().set_enabled(index as usize, enabled);
+            StepResponse {
+                action: 14,
+                value: index as i16,
+            }
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub unsafe fn load_cheats(state: *mut State, cheat_file: &str) -> Result<(), String> {
+    let state = unsafe { state.as_mut().unwrap() };
+    let cheats = Cheats::from_cheat_file(cheat_file).map_err(|e| e.to_string())?;
+    *state.nes.cheats_mut() = cheats;
+    state.osd.set_cheats(cheat_list(&state.nes));
+    Ok(())
+}
+
+fn cheat_list(nes: &nes001::NES001) -> Vec<(String, bool)> {
+    nes.cheats()
+        .iter()
+        .map(|cheat| (format!("{} {}", cheat.code, cheat.description), cheat.enabled))
+        .collect()
+}
+
+fn step_ram_search(state: &mut State, step: RamSearchStep) {
+    let State {
+        nes,
+        osd,
+        ram_search,
+    } = state;
+    if let RamSearchStep::New = step {
+        *ram_search = None;
+    }
+    let search = ram_search.get_or_insert_with(|| RamSearch::new(nes, ValueType::U8));
+    let filter = match step {
+        RamSearchStep::New => None,
+        RamSearchStep::Equal => Some(Filter::Equal),
+        RamSearchStep::Changed => Some(Filter::Changed),
+        RamSearchStep::Increased => Some(Filter::Increased),
+        RamSearchStep::Decreased => Some(Filter::Decreased),
+        RamSearchStep::Freeze { address, value } => {
+            for cheat in search.freeze_cheats(address, value) {
+                nes.cheats_mut().add(cheat);
+            }
+            osd.set_cheats(cheat_list(nes));
+            return;
+        }
+    };
+    if let Some(filter) = filter {
+        search.filter(nes, filter);
     }
+    let results = search
+        .results(nes)
+        .iter()
+        .map(|result| (result.address, result.value))
+        .collect();
+    osd.set_ram_search_results(search.len(), results);
+}
+
+#[wasm_bindgen]
+pub unsafe fn save_cheats(state: *const State) -> String {
+    let state = unsafe { state.as_ref().unwrap() };
+    state.nes.cheats().to_cheat_file()
 }
 
 #[wasm_bindgen]
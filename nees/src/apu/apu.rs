@@ -138,8 +138,17 @@ impl APU {
         }
     }
     pub fn read_reg(&mut self, address: u16) -> u8 {
+        let value = self.peek_reg(address);
         if address == 0x4015 {
-            let value = ((self.interrupt_inhibit as u8) << 7)
+            self.frame_interrupt_flag = false;
+        }
+        value
+    }
+
+    /// Reads a register without acknowledging the frame interrupt.
+    pub fn peek_reg(&self, address: u16) -> u8 {
+        if address == 0x4015 {
+            ((self.interrupt_inhibit as u8) << 7)
                 | ((self.frame_interrupt_flag as u8) << 6)
                 | (if self.dmc.sample_bytes_remaining > 0 {
                     16
@@ -149,11 +158,7 @@ impl APU {
                 | ((self.noise.length_counter.value > 0) as u8) << 3
                 | ((self.triangle.length_counter.value > 0) as u8) << 2
                 | ((self.pulse2.length_counter.value > 0) as u8) << 1
-                | ((self.pulse1.length_counter.value > 0) as u8);
-
-            self.frame_interrupt_flag = false;
-
-            value
+                | ((self.pulse1.length_counter.value > 0) as u8)
         } else {
             0
         }
@@ -169,7 +174,12 @@ impl APU {
         }
     }
 
-    pub fn tick<T: FnMut(i16)>(&mut self, scanline: i16, waveout_callback: &mut T) {
+    pub fn tick<T: FnMut(i16)>(
+        &mut self,
+        scanline: i16,
+        cart: &dyn CartridgeWithSaveLoad,
+        waveout_callback: &mut T,
+    ) {
         match self.cycle_counter {
             3278 => {
                 self.clock_envelopes();
@@ -231,8 +241,10 @@ impl APU {
         let tnd_out = self.tnd_volume_lookup_table[3 * (self.triangle.current_output as usize)
             + 2 * (self.noise.current_output as usize) + (self.dmc.output_level as usize)];
 
-        let sample = pulse_out + tnd_out;
-        self.sample_out += (sample - self.sample_out) >> 4;
+        let sample = pulse_out as i32 + tnd_out as i32 + cart.audio_output() as i32;
+        let sample_out = self.sample_out as i32;
+        self.sample_out = (sample_out + ((sample - sample_out) >> 4))
+            .clamp(i16::MIN as i32, i16::MAX as i32) as i16;
 
         if scanline != self.last_scanline {
             self.last_scanline = scanline;
@@ -0,0 +1,319 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// Wave output relative to the maximum gain, by master volume: 2/2, 2/3, 2/4 and 2/5.
+const MASTER_VOLUME_TABLE: [u32; 4] = [36, 24, 17, 14];
+
+/// Scales the 0..63 output to about 2.4 times a full-volume APU pulse.
+const FDS_OUTPUT_SCALE: i16 = 185;
+
+/// Modulation table entries, as steps applied to the mod counter. Entry 4 resets it instead.
+const MOD_STEPS: [i8; 8] = [0, 1, 2, 4, 0, -4, -2, -1];
+const MOD_RESET: u8 = 4;
+
+/// The envelope and frequency shared by the volume and mod units.
+struct Unit {
+    speed: u8,
+    gain: u8,
+    increase: bool,
+    envelope_off: bool,
+    frequency: u16,
+    timer: u32,
+}
+
+impl Unit {
+    fn new() -> Self {
+        Self {
+            speed: 0,
+            gain: 0,
+            increase: false,
+            envelope_off: true,
+            frequency: 0,
+            timer: 0,
+        }
+    }
+
+    fn write_envelope(&mut self, value: u8, master_speed: u8) {
+        self.speed = value & 0x3F;
+        self.increase = (value & 0x40) != 0;
+        self.envelope_off = (value & 0x80) != 0;
+        if self.envelope_off {
+            self.gain = self.speed;
+        }
+        self.reset_timer(master_speed);
+    }
+
+    fn reset_timer(&mut self, master_speed: u8) {
+        self.timer = 8 * (self.speed as u32 + 1) * master_speed as u32;
+    }
+
+    /// Returns true when the envelope steps.
+    fn tick_envelope(&mut self, master_speed: u8) -> bool {
+        if self.envelope_off || master_speed == 0 {
+            return false;
+        }
+
+        self.timer = self.timer.saturating_sub(1);
+        if self.timer > 0 {
+            return false;
+        }
+
+        self.reset_timer(master_speed);
+        if self.increase && self.gain < 32 {
+            self.gain += 1;
+        } else if !self.increase && self.gain > 0 {
+            self.gain -= 1;
+        }
+        true
+    }
+
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.speed)?;
+        writer.write_u8(self.gain)?;
+        writer.write_bool(self.increase)?;
+        writer.write_bool(self.envelope_off)?;
+        writer.write_u16(self.frequency)?;
+        writer.write_u32(self.timer)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.speed = reader.read_u8()?;
+        self.gain = reader.read_u8()?;
+        self.increase = reader.read_bool()?;
+        self.envelope_off = reader.read_bool()?;
+        self.frequency = reader.read_u16()?;
+        self.timer = reader.read_u32()?;
+
+        Ok(())
+    }
+}
+
+/// The Famicom Disk System's sound: a single channel playing a 64-step, 6-bit waveform from
+/// RAM, with a volume envelope and a second wavetable that modulates its pitch.
+pub struct FDSAudio {
+    wave_table: [u8; 64],
+    wave_write_enabled: bool,
+    wave_halted: bool,
+    wave_accumulator: u16,
+    wave_position: u8,
+    envelopes_halted: bool,
+    master_volume: u8,
+    master_speed: u8,
+    volume: Unit,
+    modulator: Unit,
+    mod_table: [u8; 64],
+    mod_position: u8,
+    mod_counter: i8,
+    mod_accumulator: u16,
+    mod_halted: bool,
+    mod_output: i32,
+    output: u8,
+}
+
+impl FDSAudio {
+    pub fn new() -> Self {
+        Self {
+            wave_table: [0; 64],
+            wave_write_enabled: false,
+            wave_halted: true,
+            wave_accumulator: 0,
+            wave_position: 0,
+            envelopes_halted: false,
+            master_volume: 0,
+            master_speed: 0xE8,
+            volume: Unit::new(),
+            modulator: Unit::new(),
+            mod_table: [0; 64],
+            mod_position: 0,
+            mod_counter: 0,
+            mod_accumulator: 0,
+            mod_halted: true,
+            mod_output: 0,
+            output: 0,
+        }
+    }
+
+    pub fn read_reg(&self, address: u16) -> u8 {
+        match address {
+            0x4040..=0x407F => self.wave_table[(address & 0x3F) as usize] | 0x40,
+            0x4090 => self.volume.gain | 0x40,
+            0x4092 => self.modulator.gain | 0x40,
+            _ => 0x40,
+        }
+    }
+
+    pub fn write_reg(&mut self, address: u16, value: u8) {
+        match address {
+            0x4040..=0x407F if self.wave_write_enabled => {
+                self.wave_table[(address & 0x3F) as usize] = value & 0x3F;
+            }
+            0x4080 => self.volume.write_envelope(value, self.master_speed),
+            0x4082 => self.volume.frequency = (self.volume.frequency & 0xF00) | value as u16,
+            0x4083 => {
+                self.volume.frequency =
+                    (self.volume.frequency & 0xFF) | ((value & 0x0F) as u16) << 8;
+                self.envelopes_halted = (value & 0x40) != 0;
+                self.wave_halted = (value & 0x80) != 0;
+                if self.wave_halted {
+                    self.wave_accumulator = 0;
+                    self.wave_position = 0;
+                }
+                if self.envelopes_halted {
+                    self.volume.reset_timer(self.master_speed);
+                    self.modulator.reset_timer(self.master_speed);
+                }
+            }
+            0x4084 => self.modulator.write_envelope(value, self.master_speed),
+            0x4085 => self.set_mod_counter(value as i32 & 0x7F),
+            0x4086 => self.modulator.frequency = (self.modulator.frequency & 0xF00) | value as u16,
+            0x4087 => {
+                self.modulator.frequency =
+                    (self.modulator.frequency & 0xFF) | ((value & 0x0F) as u16) << 8;
+                self.mod_halted = (value & 0x80) != 0;
+                if self.mod_halted {
+                    self.mod_accumulator = 0;
+                }
+            }
+            // Each write fills two steps of the table, which can only be written while halted
+            0x4088 if self.mod_halted => {
+                self.mod_table[self.mod_position as usize] = value & 0b111;
+                self.mod_table[(self.mod_position as usize + 1) & 0x3F] = value & 0b111;
+                self.mod_position = (self.mod_position + 2) & 0x3F;
+            }
+            0x4089 => {
+                self.master_volume = value & 0b11;
+                self.wave_write_enabled = (value & 0x80) != 0;
+            }
+            0x408A => self.master_speed = value,
+            _ => {}
+        }
+    }
+
+    /// The mod counter is 7-bit signed.
+    fn set_mod_counter(&mut self, value: i32) {
+        self.mod_counter = (((value + 64) & 0x7F) - 64) as i8;
+    }
+
+    /// The pitch offset from the mod unit, following the hardware's rounding.
+    fn update_mod_output(&mut self) {
+        let mut temp = self.mod_counter as i32 * self.modulator.gain as i32;
+        let remainder = temp & 0x0F;
+        temp >>= 4;
+        if remainder > 0 && (temp & 0x80) == 0 {
+            temp += if self.mod_counter < 0 { -1 } else { 2 };
+        }
+        if temp >= 192 {
+            temp -= 256;
+        } else if temp < -64 {
+            temp += 256;
+        }
+
+        temp *= self.volume.frequency as i32;
+        let remainder = temp & 0x3F;
+        temp >>= 6;
+        if remainder >= 32 {
+            temp += 1;
+        }
+        self.mod_output = temp;
+    }
+
+    fn tick_modulator(&mut self) -> bool {
+        if self.mod_halted || self.modulator.frequency == 0 {
+            return false;
+        }
+
+        let (accumulator, overflowed) = self
+            .mod_accumulator
+            .overflowing_add(self.modulator.frequency);
+        self.mod_accumulator = accumulator;
+        if overflowed {
+            let entry = self.mod_table[self.mod_position as usize];
+            if entry == MOD_RESET {
+                self.set_mod_counter(0);
+            } else {
+                self.set_mod_counter(self.mod_counter as i32 + MOD_STEPS[entry as usize] as i32);
+            }
+            self.mod_position = (self.mod_position + 1) & 0x3F;
+        }
+        overflowed
+    }
+
+    /// Advances by one CPU cycle.
+    pub fn tick(&mut self) {
+        if !self.wave_halted && !self.envelopes_halted {
+            self.volume.tick_envelope(self.master_speed);
+            if self.modulator.tick_envelope(self.master_speed) {
+                self.update_mod_output();
+            }
+        }
+        if self.tick_modulator() {
+            self.update_mod_output();
+        }
+
+        // The output holds while the wave RAM is being written
+        if !self.wave_write_enabled {
+            let gain = self.volume.gain.min(32) as u32;
+            let level = gain * MASTER_VOLUME_TABLE[self.master_volume as usize];
+            self.output =
+                (self.wave_table[self.wave_position as usize] as u32 * level / 1152) as u8;
+        }
+
+        let pitch = self.volume.frequency as i32 + self.mod_output;
+        if !self.wave_halted && !self.wave_write_enabled && pitch > 0 {
+            let (accumulator, overflowed) = self.wave_accumulator.overflowing_add(pitch as u16);
+            self.wave_accumulator = accumulator;
+            if overflowed {
+                self.wave_position = (self.wave_position + 1) & 0x3F;
+            }
+        }
+    }
+
+    pub fn output(&self) -> i16 {
+        self.output as i16 * FDS_OUTPUT_SCALE
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.wave_table)?;
+        writer.write_bool(self.wave_write_enabled)?;
+        writer.write_bool(self.wave_halted)?;
+        writer.write_u16(self.wave_accumulator)?;
+        writer.write_u8(self.wave_position)?;
+        writer.write_bool(self.envelopes_halted)?;
+        writer.write_u8(self.master_volume)?;
+        writer.write_u8(self.master_speed)?;
+        self.volume.save(writer)?;
+        self.modulator.save(writer)?;
+        writer.write_all(&self.mod_table)?;
+        writer.write_u8(self.mod_position)?;
+        writer.write_u8(self.mod_counter as u8)?;
+        writer.write_u16(self.mod_accumulator)?;
+        writer.write_bool(self.mod_halted)?;
+        writer.write_u32(self.mod_output as u32)?;
+        writer.write_u8(self.output)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.wave_table)?;
+        self.wave_write_enabled = reader.read_bool()?;
+        self.wave_halted = reader.read_bool()?;
+        self.wave_accumulator = reader.read_u16()?;
+        self.wave_position = reader.read_u8()?;
+        self.envelopes_halted = reader.read_bool()?;
+        self.master_volume = reader.read_u8()?;
+        self.master_speed = reader.read_u8()?;
+        self.volume.load(reader)?;
+        self.modulator.load(reader)?;
+        reader.read_exact(&mut self.mod_table)?;
+        self.mod_position = reader.read_u8()?;
+        self.mod_counter = reader.read_u8()? as i8;
+        self.mod_accumulator = reader.read_u16()?;
+        self.mod_halted = reader.read_bool()?;
+        self.mod_output = reader.read_u32()? as i32;
+        self.output = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,152 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+use super::pulse::Pulse;
+
+/// Envelopes and length counters are clocked at a fixed 240Hz, independent of the APU's frame
+/// counter.
+const FRAME_PERIOD: u16 = 7457;
+
+/// Scales a pulse's 0-15 volume so that it roughly matches an APU pulse.
+const PULSE_OUTPUT_SCALE: i16 = 326;
+
+/// Scales the 8-bit PCM level to about the loudness of a full-volume pulse.
+const PCM_OUTPUT_SCALE: i16 = 20;
+
+/// The MMC5's audio: two pulse channels like the APU's, minus the sweep units, and an 8-bit PCM
+/// channel fed either by register writes or by snooping CPU reads from $8000-$BFFF.
+pub struct MMC5Audio {
+    pulse1: Pulse,
+    pulse2: Pulse,
+    frame_counter: u16,
+    odd_cycle: bool,
+    pcm_read_mode: bool,
+    pcm_irq_enabled: bool,
+    pcm_irq_pending: bool,
+    pcm_level: u8,
+}
+
+impl MMC5Audio {
+    pub fn new() -> Self {
+        let mut pulse1 = Pulse::new();
+        let mut pulse2 = Pulse::new();
+        // Without a sweep unit nothing mutes the channel, which a negated, disabled sweep mimics
+        pulse1.write_reg(1, 0x08);
+        pulse2.write_reg(1, 0x08);
+
+        Self {
+            pulse1,
+            pulse2,
+            frame_counter: 0,
+            odd_cycle: false,
+            pcm_read_mode: false,
+            pcm_irq_enabled: false,
+            pcm_irq_pending: false,
+            pcm_level: 0,
+        }
+    }
+
+    pub fn write_reg(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000 | 0x5002 | 0x5003 => self.pulse1.write_reg((address & 3) as u8, value),
+            0x5004 | 0x5006 | 0x5007 => self.pulse2.write_reg((address & 3) as u8, value),
+            0x5010 => {
+                self.pcm_read_mode = (value & 0x01) != 0;
+                self.pcm_irq_enabled = (value & 0x80) != 0;
+            }
+            0x5011 if !self.pcm_read_mode && value != 0 => self.pcm_level = value,
+            0x5015 => {
+                self.pulse1.enabled = (value & 1) != 0;
+                self.pulse2.enabled = (value & 2) != 0;
+                if !self.pulse1.enabled {
+                    self.pulse1.length_counter.value = 0;
+                }
+                if !self.pulse2.enabled {
+                    self.pulse2.length_counter.value = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn read_reg(&mut self, address: u16) -> u8 {
+        match address {
+            0x5010 => {
+                let value = (self.pcm_irq_pending as u8) << 7 | self.pcm_read_mode as u8;
+                self.pcm_irq_pending = false;
+                value
+            }
+            0x5015 => {
+                ((self.pulse2.length_counter.value > 0) as u8) << 1
+                    | (self.pulse1.length_counter.value > 0) as u8
+            }
+            _ => 0,
+        }
+    }
+
+    /// Feeds the PCM channel from a CPU read of $8000-$BFFF when in read mode. A zero byte does
+    /// not play; it raises the PCM IRQ instead.
+    pub fn snoop_read(&mut self, value: u8) {
+        if !self.pcm_read_mode {
+            return;
+        }
+
+        if value == 0 {
+            self.pcm_irq_pending = true;
+        } else {
+            self.pcm_level = value;
+        }
+    }
+
+    pub fn irq(&self) -> bool {
+        self.pcm_irq_enabled && self.pcm_irq_pending
+    }
+
+    pub fn tick(&mut self) {
+        self.frame_counter += 1;
+        if self.frame_counter == FRAME_PERIOD {
+            self.frame_counter = 0;
+            for pulse in [&mut self.pulse1, &mut self.pulse2] {
+                pulse.envelope.clock(pulse.length_counter.halt);
+                pulse.length_counter.clock();
+            }
+        }
+
+        // Like the APU's, the pulse timers run at half the CPU clock
+        self.odd_cycle = !self.odd_cycle;
+        if self.odd_cycle {
+            self.pulse1.tick(false);
+            self.pulse2.tick(false);
+        }
+    }
+
+    pub fn output(&self) -> i16 {
+        (self.pulse1.current_output as i16 + self.pulse2.current_output as i16) * PULSE_OUTPUT_SCALE
+            + self.pcm_level as i16 * PCM_OUTPUT_SCALE
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        self.pulse1.save(writer)?;
+        self.pulse2.save(writer)?;
+        writer.write_u16(self.frame_counter)?;
+        writer.write_bool(self.odd_cycle)?;
+        writer.write_bool(self.pcm_read_mode)?;
+        writer.write_bool(self.pcm_irq_enabled)?;
+        writer.write_bool(self.pcm_irq_pending)?;
+        writer.write_u8(self.pcm_level)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.pulse1.load(reader)?;
+        self.pulse2.load(reader)?;
+        self.frame_counter = reader.read_u16()?;
+        self.odd_cycle = reader.read_bool()?;
+        self.pcm_read_mode = reader.read_bool()?;
+        self.pcm_irq_enabled = reader.read_bool()?;
+        self.pcm_irq_pending = reader.read_bool()?;
+        self.pcm_level = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -1,11 +1,23 @@
 mod apu;
 mod dmc;
 mod envelope;
+mod fds;
 mod length_counter;
+mod mmc5;
+mod n163;
 mod noise;
+mod opll;
 mod pulse;
+mod sunsoft5b;
 mod tables;
 mod timer;
 mod triangle;
+mod vrc6;
 
 pub use apu::*;
+pub use fds::FDSAudio;
+pub use mmc5::MMC5Audio;
+pub use n163::N163Audio;
+pub use opll::OPLL;
+pub use sunsoft5b::Sunsoft5BAudio;
+pub use vrc6::VRC6Audio;
@@ -0,0 +1,182 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// One channel is updated every 15 CPU cycles.
+const CHANNEL_UPDATE_CYCLES: u8 = 15;
+
+/// Scales a channel's -120..105 output into the APU's sample range.
+const N163_OUTPUT_SCALE: i32 = 48;
+
+/// The Namco 163 wavetable chip. Up to eight channels share 128 bytes of sound RAM, which also
+/// holds the 4-bit waveforms. The chip only ever outputs one channel at a time, cycling through
+/// the enabled ones; `multiplexed` selects between that and a mix of all channels.
+pub struct N163Audio {
+    ram: [u8; 128],
+    address: u8,
+    auto_increment: bool,
+    current_channel: u8,
+    cycle_counter: u8,
+    channel_outputs: [i16; 8],
+    multiplexed: bool,
+}
+
+impl N163Audio {
+    pub fn new() -> Self {
+        Self {
+            ram: [0; 128],
+            address: 0,
+            auto_increment: false,
+            current_channel: 7,
+            cycle_counter: 0,
+            channel_outputs: [0; 8],
+            multiplexed: false,
+        }
+    }
+
+    pub fn set_multiplexed(&mut self, multiplexed: bool) {
+        self.multiplexed = multiplexed;
+    }
+
+    pub fn write_address(&mut self, value: u8) {
+        self.address = value & 0x7F;
+        self.auto_increment = (value & 0x80) != 0;
+    }
+
+    pub fn read_data(&mut self) -> u8 {
+        let value = self.ram[self.address as usize];
+        self.advance_address();
+        value
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        self.ram[self.address as usize] = value;
+        self.advance_address();
+    }
+
+    fn advance_address(&mut self) {
+        if self.auto_increment {
+            self.address = (self.address + 1) & 0x7F;
+        }
+    }
+
+    fn enabled_channels(&self) -> u8 {
+        ((self.ram[0x7F] >> 4) & 0b111) + 1
+    }
+
+    fn update_channel(&mut self, channel: u8) {
+        let base = 0x40 + (channel as usize) * 8;
+        let frequency = self.ram[base] as u32
+            | (self.ram[base + 2] as u32) << 8
+            | ((self.ram[base + 4] & 0b11) as u32) << 16;
+        let mut phase = self.ram[base + 1] as u32
+            | (self.ram[base + 3] as u32) << 8
+            | (self.ram[base + 5] as u32) << 16;
+        let length = 256 - (self.ram[base + 4] & 0xFC) as u32;
+        let offset = self.ram[base + 6] as u32;
+        let volume = (self.ram[base + 7] & 0x0F) as i16;
+
+        phase = (phase + frequency) % (length << 16);
+        self.ram[base + 1] = phase as u8;
+        self.ram[base + 3] = (phase >> 8) as u8;
+        self.ram[base + 5] = (phase >> 16) as u8;
+
+        let sample_index = ((phase >> 16) + offset) & 0xFF;
+        let sample = (self.ram[(sample_index >> 1) as usize] >> ((sample_index & 1) * 4)) & 0x0F;
+        self.channel_outputs[channel as usize] = (sample as i16 - 8) * volume;
+    }
+
+    pub fn tick(&mut self) {
+        self.cycle_counter += 1;
+        if self.cycle_counter < CHANNEL_UPDATE_CYCLES {
+            return;
+        }
+        self.cycle_counter = 0;
+
+        // Channels are serviced from 7 downwards
+        let lowest_channel = 8 - self.enabled_channels();
+        if self.current_channel <= lowest_channel {
+            self.current_channel = 7;
+        } else {
+            self.current_channel -= 1;
+        }
+        self.update_channel(self.current_channel);
+    }
+
+    pub fn output(&self) -> i16 {
+        let level = if self.multiplexed {
+            self.channel_outputs[self.current_channel as usize] as i32
+        } else {
+            let enabled_channels = self.enabled_channels();
+            let sum: i32 = self.channel_outputs[(8 - enabled_channels) as usize..]
+                .iter()
+                .map(|&output| output as i32)
+                .sum();
+            sum / enabled_channels as i32
+        };
+
+        (level * N163_OUTPUT_SCALE) as i16
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_u8(self.address)?;
+        writer.write_bool(self.auto_increment)?;
+        writer.write_u8(self.current_channel)?;
+        writer.write_u8(self.cycle_counter)?;
+        for output in &self.channel_outputs {
+            writer.write_i16(*output)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        self.address = reader.read_u8()?;
+        self.auto_increment = reader.read_bool()?;
+        self.current_channel = reader.read_u8()?;
+        self.cycle_counter = reader.read_u8()?;
+        for output in &mut self.channel_outputs {
+            *output = reader.read_i16()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{N163Audio, CHANNEL_UPDATE_CYCLES, N163_OUTPUT_SCALE};
+
+    #[test]
+    fn test_sound_ram() {
+        let mut audio = N163Audio::new();
+        audio.write_address(0x80 | 0x10);
+        audio.write_data(0x12);
+        audio.write_data(0x34);
+        audio.write_address(0x80 | 0x10);
+        assert_eq!((audio.read_data(), audio.read_data()), (0x12, 0x34));
+
+        // Without auto-increment the address stays put
+        audio.write_address(0x10);
+        assert_eq!((audio.read_data(), audio.read_data()), (0x12, 0x12));
+    }
+
+    #[test]
+    fn test_channel_output() {
+        let mut audio = N163Audio::new();
+        // An 8-sample waveform at its highest level, played by channel 7 alone at full volume
+        audio.write_address(0x80);
+        for _ in 0..4 {
+            audio.write_data(0xFF);
+        }
+        audio.write_address(0x7C);
+        audio.write_data(0xF8);
+        audio.write_address(0x7F);
+        audio.write_data(0x0F);
+
+        for _ in 0..CHANNEL_UPDATE_CYCLES {
+            audio.tick();
+        }
+        assert_eq!(audio.output(), 7 * 15 * N163_OUTPUT_SCALE as i16);
+    }
+}
@@ -0,0 +1,605 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// Built-in instruments 1-15 of the VRC7's OPLL derivative. Instrument 0 is the custom patch.
+const VRC7_PATCHES: [[u8; 8]; 15] = [
+    [0x03, 0x21, 0x05, 0x06, 0xE8, 0x81, 0x42, 0x27], // Buzzy bell
+    [0x13, 0x41, 0x14, 0x0D, 0xD8, 0xF6, 0x23, 0x12], // Guitar
+    [0x11, 0x11, 0x08, 0x08, 0xFA, 0xB2, 0x20, 0x12], // Wurly
+    [0x31, 0x61, 0x0C, 0x07, 0xA8, 0x64, 0x61, 0x27], // Flute
+    [0x32, 0x21, 0x1E, 0x06, 0xE1, 0x76, 0x01, 0x28], // Clarinet
+    [0x02, 0x01, 0x06, 0x00, 0xA3, 0xE2, 0xF4, 0xF4], // Synth
+    [0x21, 0x61, 0x1D, 0x07, 0x82, 0x81, 0x11, 0x07], // Trumpet
+    [0x23, 0x21, 0x22, 0x17, 0xA2, 0x72, 0x01, 0x17], // Organ
+    [0x35, 0x11, 0x25, 0x00, 0x40, 0x73, 0x72, 0x01], // Bells
+    [0xB5, 0x01, 0x0F, 0x0F, 0xA8, 0xA5, 0x51, 0x02], // Vibes
+    [0x17, 0xC1, 0x24, 0x07, 0xF8, 0xF8, 0x22, 0x12], // Vibraphone
+    [0x71, 0x23, 0x11, 0x06, 0x65, 0x74, 0x18, 0x16], // Tutti
+    [0x01, 0x02, 0xD3, 0x05, 0xC9, 0x95, 0x03, 0x02], // Fretless
+    [0x61, 0x63, 0x0C, 0x00, 0x94, 0xC0, 0x33, 0xF6], // Synth bass
+    [0x21, 0x72, 0x0D, 0x00, 0xC1, 0xD5, 0x56, 0x06], // Sweep
+];
+
+/// Frequency multipliers, doubled so that the 1/2 setting stays an integer.
+const MULTIPLIER_TABLE: [i32; 16] = [1, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 20, 24, 24, 30, 30];
+
+/// Key scale level attenuation for the top four F-number bits, in 0.75dB steps.
+const KSL_TABLE: [u8; 16] = [
+    0, 24, 32, 37, 40, 43, 45, 47, 48, 50, 51, 52, 53, 54, 55, 56,
+];
+
+const EG_INCREMENTS: [[u8; 8]; 4] = [
+    [0, 1, 0, 1, 0, 1, 0, 1],
+    [0, 1, 0, 1, 1, 1, 0, 1],
+    [0, 1, 1, 1, 0, 1, 1, 1],
+    [0, 1, 1, 1, 1, 1, 1, 1],
+];
+
+/// One AM step lasts 64 samples, a full tremolo period is 210 steps (about 3.7Hz).
+const AM_PERIOD: u16 = 210 * 64;
+
+/// Attenuation is kept in 0.375dB steps, 127 being silent.
+const MAX_ATTENUATION: u8 = 127;
+
+/// Scales the mix in 32nds, so that a channel at full volume (about ±4096) swings as far as a
+/// full-volume APU pulse.
+const OPLL_OUTPUT_SCALE: i32 = 19;
+
+#[rustfmt::skip]
+const LOGSIN_TABLE: [u16; 256] = [
+    2137, 1731, 1543, 1419, 1326, 1252, 1190, 1137, 1091, 1050, 1013, 979, 949, 920, 894, 869,
+    846, 825, 804, 785, 767, 749, 732, 717, 701, 687, 672, 659, 646, 633, 621, 609,
+    598, 587, 576, 566, 556, 546, 536, 527, 518, 509, 501, 492, 484, 476, 468, 461,
+    453, 446, 439, 432, 425, 418, 411, 405, 399, 392, 386, 380, 375, 369, 363, 358,
+    352, 347, 341, 336, 331, 326, 321, 316, 311, 307, 302, 297, 293, 289, 284, 280,
+    276, 271, 267, 263, 259, 255, 251, 248, 244, 240, 236, 233, 229, 226, 222, 219,
+    215, 212, 209, 205, 202, 199, 196, 193, 190, 187, 184, 181, 178, 175, 172, 169,
+    167, 164, 161, 159, 156, 153, 151, 148, 146, 143, 141, 138, 136, 134, 131, 129,
+    127, 125, 122, 120, 118, 116, 114, 112, 110, 108, 106, 104, 102, 100, 98, 96,
+    94, 92, 91, 89, 87, 85, 83, 82, 80, 78, 77, 75, 74, 72, 70, 69,
+    67, 66, 64, 63, 62, 60, 59, 57, 56, 55, 53, 52, 51, 49, 48, 47,
+    46, 45, 43, 42, 41, 40, 39, 38, 37, 36, 35, 34, 33, 32, 31, 30,
+    29, 28, 27, 26, 25, 24, 23, 23, 22, 21, 20, 20, 19, 18, 17, 17,
+    16, 15, 15, 14, 13, 13, 12, 12, 11, 10, 10, 9, 9, 8, 8, 7,
+    7, 7, 6, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2,
+    2, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0,
+];
+
+#[rustfmt::skip]
+const EXP_TABLE: [u16; 256] = [
+    4096, 4085, 4074, 4063, 4052, 4041, 4030, 4019, 4008, 3997, 3987, 3976, 3965, 3954, 3944, 3933,
+    3922, 3912, 3901, 3891, 3880, 3870, 3859, 3849, 3838, 3828, 3818, 3807, 3797, 3787, 3776, 3766,
+    3756, 3746, 3736, 3726, 3716, 3706, 3696, 3686, 3676, 3666, 3656, 3646, 3636, 3626, 3616, 3607,
+    3597, 3587, 3577, 3568, 3558, 3548, 3539, 3529, 3520, 3510, 3501, 3491, 3482, 3472, 3463, 3454,
+    3444, 3435, 3426, 3416, 3407, 3398, 3389, 3380, 3371, 3361, 3352, 3343, 3334, 3325, 3316, 3307,
+    3298, 3289, 3280, 3272, 3263, 3254, 3245, 3236, 3228, 3219, 3210, 3201, 3193, 3184, 3176, 3167,
+    3158, 3150, 3141, 3133, 3124, 3116, 3108, 3099, 3091, 3082, 3074, 3066, 3057, 3049, 3041, 3033,
+    3025, 3016, 3008, 3000, 2992, 2984, 2976, 2968, 2960, 2952, 2944, 2936, 2928, 2920, 2912, 2904,
+    2896, 2888, 2881, 2873, 2865, 2857, 2850, 2842, 2834, 2827, 2819, 2811, 2804, 2796, 2789, 2781,
+    2774, 2766, 2759, 2751, 2744, 2736, 2729, 2721, 2714, 2707, 2699, 2692, 2685, 2678, 2670, 2663,
+    2656, 2649, 2642, 2634, 2627, 2620, 2613, 2606, 2599, 2592, 2585, 2578, 2571, 2564, 2557, 2550,
+    2543, 2536, 2530, 2523, 2516, 2509, 2502, 2496, 2489, 2482, 2475, 2469, 2462, 2455, 2449, 2442,
+    2435, 2429, 2422, 2416, 2409, 2403, 2396, 2390, 2383, 2377, 2370, 2364, 2358, 2351, 2345, 2339,
+    2332, 2326, 2320, 2313, 2307, 2301, 2295, 2288, 2282, 2276, 2270, 2264, 2258, 2252, 2245, 2239,
+    2233, 2227, 2221, 2215, 2209, 2203, 2197, 2191, 2186, 2180, 2174, 2168, 2162, 2156, 2150, 2144,
+    2139, 2133, 2127, 2121, 2116, 2110, 2104, 2099, 2093, 2087, 2082, 2076, 2070, 2065, 2059, 2054,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum EnvelopeState {
+    Attack,
+    Decay,
+    Sustain,
+    Release,
+}
+
+impl EnvelopeState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => EnvelopeState::Attack,
+            1 => EnvelopeState::Decay,
+            2 => EnvelopeState::Sustain,
+            _ => EnvelopeState::Release,
+        }
+    }
+}
+
+/// Operator settings decoded from an instrument patch. Slot 0 is the modulator, slot 1 the carrier.
+struct OperatorParams {
+    am: bool,
+    vibrato: bool,
+    sustained: bool,
+    key_scale_rate: bool,
+    multiplier: u8,
+    key_scale_level: u8,
+    rectified: bool,
+    attack: u8,
+    decay: u8,
+    sustain_level: u8,
+    release: u8,
+}
+
+impl OperatorParams {
+    fn from_patch(patch: &[u8; 8], slot: usize) -> Self {
+        Self {
+            am: (patch[slot] & 0x80) != 0,
+            vibrato: (patch[slot] & 0x40) != 0,
+            sustained: (patch[slot] & 0x20) != 0,
+            key_scale_rate: (patch[slot] & 0x10) != 0,
+            multiplier: patch[slot] & 0x0F,
+            key_scale_level: patch[2 + slot] >> 6,
+            rectified: (patch[3] & (if slot == 0 { 0x08 } else { 0x10 })) != 0,
+            attack: patch[4 + slot] >> 4,
+            decay: patch[4 + slot] & 0x0F,
+            sustain_level: patch[6 + slot] >> 4,
+            release: patch[6 + slot] & 0x0F,
+        }
+    }
+}
+
+fn envelope_increment(rate: u8, counter: u32) -> u8 {
+    if rate == 0 {
+        0
+    } else if rate < 52 {
+        let shift = 13 - (rate >> 2);
+        if counter & ((1 << shift) - 1) != 0 {
+            0
+        } else {
+            EG_INCREMENTS[(rate & 3) as usize][((counter >> shift) & 7) as usize]
+        }
+    } else {
+        EG_INCREMENTS[(rate & 3) as usize][(counter & 7) as usize] << ((rate >> 2) - 12)
+    }
+}
+
+struct Operator {
+    phase: u32,
+    envelope: u8,
+    state: EnvelopeState,
+    output: [i16; 2],
+}
+
+impl Operator {
+    fn new() -> Self {
+        Self {
+            phase: 0,
+            envelope: MAX_ATTENUATION,
+            state: EnvelopeState::Release,
+            output: [0, 0],
+        }
+    }
+
+    fn key_on(&mut self) {
+        self.phase = 0;
+        self.state = EnvelopeState::Attack;
+    }
+
+    fn clock_phase(&mut self, params: &OperatorParams, fnum: u16, block: u8, pm_step: u16) {
+        let vibrato = if params.vibrato {
+            let depth = (fnum >> 6) as i32;
+            match pm_step {
+                1 | 3 => depth >> 1,
+                2 => depth,
+                5 | 7 => -(depth >> 1),
+                6 => -depth,
+                _ => 0,
+            }
+        } else {
+            0
+        };
+
+        let increment =
+            (((fnum as i32) * 2 + vibrato) * MULTIPLIER_TABLE[params.multiplier as usize]) << block
+                >> 2;
+        self.phase = self.phase.wrapping_add(increment as u32) & 0x7FFFF;
+    }
+
+    fn clock_envelope(
+        &mut self,
+        params: &OperatorParams,
+        rate_key_scale: u8,
+        release_rate: u8,
+        counter: u32,
+    ) {
+        let rate = match self.state {
+            EnvelopeState::Attack => params.attack,
+            EnvelopeState::Decay => params.decay,
+            EnvelopeState::Sustain => {
+                if params.sustained {
+                    0
+                } else {
+                    params.release
+                }
+            }
+            EnvelopeState::Release => release_rate,
+        };
+        let rate = if rate == 0 {
+            0
+        } else {
+            (rate * 4 + rate_key_scale).min(63)
+        };
+
+        match self.state {
+            EnvelopeState::Attack => {
+                if rate >= 60 {
+                    self.envelope = 0;
+                } else {
+                    let increment = envelope_increment(rate, counter) as u16;
+                    if increment > 0 {
+                        let delta = (((self.envelope as u16 + 1) * increment) >> 3).max(1);
+                        self.envelope = self.envelope.saturating_sub(delta as u8);
+                    }
+                }
+                if self.envelope == 0 {
+                    self.state = EnvelopeState::Decay;
+                }
+            }
+            EnvelopeState::Decay => {
+                self.envelope =
+                    (self.envelope + envelope_increment(rate, counter)).min(MAX_ATTENUATION);
+                if self.envelope >= params.sustain_level << 3 {
+                    self.state = EnvelopeState::Sustain;
+                }
+            }
+            EnvelopeState::Sustain | EnvelopeState::Release => {
+                self.envelope =
+                    (self.envelope + envelope_increment(rate, counter)).min(MAX_ATTENUATION);
+            }
+        }
+    }
+
+    /// Looks up the operator output for the current phase, offset by `modulation` (1024 units per cycle).
+    fn compute(&mut self, modulation: i32, attenuation: u16, rectified: bool) -> i16 {
+        let phase = (((self.phase >> 9) as i32) + modulation) & 0x3FF;
+        let negative = (phase & 0x200) != 0;
+
+        let value = if attenuation >= MAX_ATTENUATION as u16 || (negative && rectified) {
+            0
+        } else {
+            let quarter = (phase & 0xFF) as usize;
+            let index = if (phase & 0x100) != 0 {
+                255 - quarter
+            } else {
+                quarter
+            };
+            let level = LOGSIN_TABLE[index] as u32 + ((attenuation as u32) << 4);
+            if (level >> 8) >= 16 {
+                0
+            } else {
+                (EXP_TABLE[(level & 0xFF) as usize] >> (level >> 8)) as i16
+            }
+        };
+
+        let value = if negative { -value } else { value };
+        self.output = [self.output[1], value];
+        value
+    }
+
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u32(self.phase)?;
+        writer.write_u8(self.envelope)?;
+        writer.write_u8(self.state as u8)?;
+        writer.write_i16(self.output[0])?;
+        writer.write_i16(self.output[1])?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.phase = reader.read_u32()?;
+        self.envelope = reader.read_u8()?;
+        self.state = EnvelopeState::from_u8(reader.read_u8()?);
+        self.output[0] = reader.read_i16()?;
+        self.output[1] = reader.read_i16()?;
+
+        Ok(())
+    }
+}
+
+struct Channel {
+    fnum: u16,
+    block: u8,
+    key_on: bool,
+    sustain: bool,
+    instrument: u8,
+    volume: u8,
+    modulator: Operator,
+    carrier: Operator,
+}
+
+impl Channel {
+    fn new() -> Self {
+        Self {
+            fnum: 0,
+            block: 0,
+            key_on: false,
+            sustain: false,
+            instrument: 0,
+            volume: 0,
+            modulator: Operator::new(),
+            carrier: Operator::new(),
+        }
+    }
+
+    fn key_scale_level(&self, key_scale_level: u8) -> u16 {
+        if key_scale_level == 0 {
+            return 0;
+        }
+
+        let level = KSL_TABLE[(self.fnum >> 5) as usize] as i16 - 8 * (7 - self.block as i16);
+        ((level.max(0) as u16) << 1) >> (3 - key_scale_level)
+    }
+
+    fn clock(&mut self, patch: &[u8; 8], eg_counter: u32, am_level: u16, pm_step: u16) -> i16 {
+        let modulator = OperatorParams::from_patch(patch, 0);
+        let carrier = OperatorParams::from_patch(patch, 1);
+        let key_code = ((self.block << 1) | (self.fnum >> 8) as u8) & 0x0F;
+
+        // Modulator
+        let rate_key_scale = if modulator.key_scale_rate {
+            key_code
+        } else {
+            key_code >> 2
+        };
+        self.modulator
+            .clock_phase(&modulator, self.fnum, self.block, pm_step);
+        self.modulator
+            .clock_envelope(&modulator, rate_key_scale, modulator.release, eg_counter);
+
+        let feedback = patch[3] & 0b111;
+        let feedback_modulation = if feedback == 0 {
+            0
+        } else {
+            (self.modulator.output[0] as i32 + self.modulator.output[1] as i32) >> (9 - feedback)
+        };
+        let attenuation = self.modulator.envelope as u16
+            + ((patch[2] & 0x3F) as u16) * 2
+            + self.key_scale_level(modulator.key_scale_level)
+            + if modulator.am { am_level } else { 0 };
+        let modulator_out =
+            self.modulator
+                .compute(feedback_modulation, attenuation, modulator.rectified);
+
+        // Carrier
+        let release_rate = if self.sustain {
+            5
+        } else if carrier.sustained {
+            carrier.release
+        } else {
+            7
+        };
+        let rate_key_scale = if carrier.key_scale_rate {
+            key_code
+        } else {
+            key_code >> 2
+        };
+        self.carrier
+            .clock_phase(&carrier, self.fnum, self.block, pm_step);
+        self.carrier
+            .clock_envelope(&carrier, rate_key_scale, release_rate, eg_counter);
+
+        let attenuation = self.carrier.envelope as u16
+            + (self.volume as u16) * 8
+            + self.key_scale_level(carrier.key_scale_level)
+            + if carrier.am { am_level } else { 0 };
+        self.carrier
+            .compute((modulator_out >> 1) as i32, attenuation, carrier.rectified)
+    }
+
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u16(self.fnum)?;
+        writer.write_u8(self.block)?;
+        writer.write_bool(self.key_on)?;
+        writer.write_bool(self.sustain)?;
+        writer.write_u8(self.instrument)?;
+        writer.write_u8(self.volume)?;
+        self.modulator.save(writer)?;
+        self.carrier.save(writer)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.fnum = reader.read_u16()?;
+        self.block = reader.read_u8()?;
+        self.key_on = reader.read_bool()?;
+        self.sustain = reader.read_bool()?;
+        self.instrument = reader.read_u8()?;
+        self.volume = reader.read_u8()?;
+        self.modulator.load(reader)?;
+        self.carrier.load(reader)?;
+
+        Ok(())
+    }
+}
+
+/// A 6-channel YM2413 (OPLL) derivative as found in the VRC7. Each call to `clock` produces one
+/// sample; the chip runs at one sample per 36 CPU cycles.
+#[allow(clippy::upper_case_acronyms)]
+pub struct OPLL {
+    address: u8,
+    custom_patch: [u8; 8],
+    channels: [Channel; 6],
+    eg_counter: u32,
+    am_counter: u16,
+    pm_counter: u16,
+    output: i16,
+}
+
+impl OPLL {
+    pub fn new() -> Self {
+        Self {
+            address: 0,
+            custom_patch: [0; 8],
+            channels: core::array::from_fn(|_| Channel::new()),
+            eg_counter: 0,
+            am_counter: 0,
+            pm_counter: 0,
+            output: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    pub fn write_address(&mut self, value: u8) {
+        self.address = value;
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        match self.address {
+            0x00..=0x07 => self.custom_patch[self.address as usize] = value,
+            0x10..=0x15 => {
+                let channel = &mut self.channels[(self.address & 0x0F) as usize];
+                channel.fnum = (channel.fnum & 0x100) | value as u16;
+            }
+            0x20..=0x25 => {
+                let channel = &mut self.channels[(self.address & 0x0F) as usize];
+                channel.fnum = (channel.fnum & 0xFF) | (((value & 1) as u16) << 8);
+                channel.block = (value >> 1) & 0b111;
+                channel.sustain = (value & 0x20) != 0;
+
+                let key_on = (value & 0x10) != 0;
+                if key_on && !channel.key_on {
+                    channel.modulator.key_on();
+                    channel.carrier.key_on();
+                } else if !key_on && channel.key_on {
+                    channel.modulator.state = EnvelopeState::Release;
+                    channel.carrier.state = EnvelopeState::Release;
+                }
+                channel.key_on = key_on;
+            }
+            0x30..=0x35 => {
+                let channel = &mut self.channels[(self.address & 0x0F) as usize];
+                channel.instrument = value >> 4;
+                channel.volume = value & 0x0F;
+            }
+            _ => {}
+        }
+    }
+
+    pub fn clock(&mut self) {
+        self.eg_counter = self.eg_counter.wrapping_add(1);
+        self.am_counter = (self.am_counter + 1) % AM_PERIOD;
+        self.pm_counter = (self.pm_counter + 1) & 0x1FFF;
+
+        // Tremolo is a triangle from 0 to 13 (4.8dB), vibrato an 8-step table
+        let am_step = self.am_counter >> 6;
+        let am_level = (if am_step < 105 {
+            am_step
+        } else {
+            209 - am_step
+        }) >> 3;
+        let pm_step = self.pm_counter >> 10;
+
+        let mut output = 0;
+        for channel in self.channels.iter_mut() {
+            let patch = if channel.instrument == 0 {
+                self.custom_patch
+            } else {
+                VRC7_PATCHES[(channel.instrument - 1) as usize]
+            };
+            output += channel.clock(&patch, self.eg_counter, am_level, pm_step) as i32;
+        }
+        self.output = output.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    }
+
+    pub fn output(&self) -> i16 {
+        ((self.output as i32 * OPLL_OUTPUT_SCALE) >> 5) as i16
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.address)?;
+        writer.write_all(&self.custom_patch)?;
+        for channel in &self.channels {
+            channel.save(writer)?;
+        }
+        writer.write_u32(self.eg_counter)?;
+        writer.write_u16(self.am_counter)?;
+        writer.write_u16(self.pm_counter)?;
+        writer.write_i16(self.output)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.address = reader.read_u8()?;
+        reader.read_exact(&mut self.custom_patch)?;
+        for channel in &mut self.channels {
+            channel.load(reader)?;
+        }
+        self.eg_counter = reader.read_u32()?;
+        self.am_counter = reader.read_u16()?;
+        self.pm_counter = reader.read_u16()?;
+        self.output = reader.read_i16()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::reader_writer::test_helpers::{SliceReader, VecWriter};
+
+    use super::{EnvelopeState, OPLL};
+
+    fn play_note(opll: &mut OPLL) {
+        // Channel 0: instrument 3 (Wurly), full volume, A4 (F-number 288, block 4), key on
+        let fnum: u16 = 288;
+        opll.write_address(0x30);
+        opll.write_data(0x30);
+        opll.write_address(0x10);
+        opll.write_data(fnum as u8);
+        opll.write_address(0x20);
+        opll.write_data(0x10 | (4 << 1) | (fnum >> 8) as u8);
+    }
+
+    #[test]
+    fn test_note_produces_output() {
+        let mut opll = OPLL::new();
+        play_note(&mut opll);
+
+        let mut peak = 0;
+        for _ in 0..1000 {
+            opll.clock();
+            peak = peak.max(opll.output().abs());
+        }
+        // No louder than a full-volume APU pulse
+        assert!(peak > 1000 && peak < 2500);
+    }
+
+    #[test]
+    fn test_key_off_releases_both_operators() {
+        let mut opll = OPLL::new();
+        play_note(&mut opll);
+        for _ in 0..1000 {
+            opll.clock();
+        }
+        opll.write_data(4 << 1);
+
+        let channel = &opll.channels[0];
+        assert!(channel.modulator.state == EnvelopeState::Release);
+        assert!(channel.carrier.state == EnvelopeState::Release);
+    }
+
+    #[test]
+    fn test_save_load_round_trip() {
+        let mut opll = OPLL::new();
+        play_note(&mut opll);
+        for _ in 0..12345 {
+            opll.clock();
+        }
+
+        let mut writer = VecWriter(Vec::new());
+        opll.save(&mut writer).unwrap();
+
+        let mut restored = OPLL::new();
+        restored.load(&mut SliceReader(&writer.0)).unwrap();
+
+        for _ in 0..5000 {
+            opll.clock();
+            restored.clock();
+            assert_eq!(opll.output(), restored.output());
+        }
+    }
+}
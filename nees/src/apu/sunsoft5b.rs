@@ -0,0 +1,312 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// Tone, noise and envelope generators advance once every 16 CPU cycles.
+const CLOCK_DIVIDER: u8 = 16;
+
+/// Output level for each 5-bit volume step, 1.5dB apart.
+#[rustfmt::skip]
+const VOLUME_TABLE: [i16; 32] = [
+    0, 22, 27, 32, 38, 45, 53, 63, 75, 90, 106, 126, 150, 179, 212, 252,
+    300, 357, 424, 504, 598, 711, 845, 1005, 1194, 1419, 1687, 2005, 2383, 2832, 3366, 4000,
+];
+
+struct Tone {
+    period: u16,
+    counter: u16,
+    output: bool,
+}
+
+impl Tone {
+    fn new() -> Self {
+        Self {
+            period: 0,
+            counter: 0,
+            output: false,
+        }
+    }
+
+    fn clock(&mut self) {
+        self.counter += 1;
+        if self.counter >= self.period {
+            self.counter = 0;
+            self.output = !self.output;
+        }
+    }
+
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u16(self.period)?;
+        writer.write_u16(self.counter)?;
+        writer.write_bool(self.output)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.period = reader.read_u16()?;
+        self.counter = reader.read_u16()?;
+        self.output = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+/// The Sunsoft 5B, a licensed YM2149F (AY-3-8910 family): three square channels that can each
+/// mix in a shared noise generator, and a shared envelope generator.
+pub struct Sunsoft5BAudio {
+    address: u8,
+    registers: [u8; 16],
+    divider: u8,
+    tones: [Tone; 3],
+    noise_counter: u8,
+    noise_lfsr: u32,
+    envelope_counter: u16,
+    envelope_step: u8,
+    envelope_attack: bool,
+    envelope_holding: bool,
+}
+
+impl Sunsoft5BAudio {
+    pub fn new() -> Self {
+        Self {
+            address: 0,
+            registers: [0; 16],
+            divider: 0,
+            tones: [Tone::new(), Tone::new(), Tone::new()],
+            noise_counter: 0,
+            noise_lfsr: 1,
+            envelope_counter: 0,
+            envelope_step: 0,
+            envelope_attack: false,
+            envelope_holding: true,
+        }
+    }
+
+    pub fn write_address(&mut self, value: u8) {
+        self.address = value;
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        // The upper nibble must be zero or the write is ignored
+        if self.address > 0x0F {
+            return;
+        }
+
+        self.registers[self.address as usize] = value;
+        match self.address {
+            0x00..=0x05 => {
+                let channel = (self.address >> 1) as usize;
+                let base = channel * 2;
+                self.tones[channel].period =
+                    self.registers[base] as u16 | ((self.registers[base + 1] & 0x0F) as u16) << 8;
+            }
+            0x0D => {
+                self.envelope_counter = 0;
+                self.envelope_step = 0;
+                self.envelope_holding = false;
+                self.envelope_attack = (value & 0b0100) != 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn noise_period(&self) -> u8 {
+        self.registers[0x06] & 0x1F
+    }
+
+    fn envelope_period(&self) -> u16 {
+        self.registers[0x0B] as u16 | (self.registers[0x0C] as u16) << 8
+    }
+
+    fn envelope_volume(&self) -> u8 {
+        if self.envelope_attack {
+            self.envelope_step
+        } else {
+            31 - self.envelope_step
+        }
+    }
+
+    fn clock_noise(&mut self) {
+        // Noise steps at half the rate of a tone with the same period
+        self.noise_counter += 1;
+        if self.noise_counter >= self.noise_period() * 2 {
+            self.noise_counter = 0;
+            let feedback = (self.noise_lfsr ^ (self.noise_lfsr >> 3)) & 1;
+            self.noise_lfsr = (self.noise_lfsr >> 1) | (feedback << 16);
+        }
+    }
+
+    fn clock_envelope(&mut self) {
+        self.envelope_counter += 1;
+        if self.envelope_counter < self.envelope_period() {
+            return;
+        }
+        self.envelope_counter = 0;
+
+        if self.envelope_holding {
+            return;
+        }
+        if self.envelope_step < 31 {
+            self.envelope_step += 1;
+            return;
+        }
+
+        // End of a ramp: the shape decides whether to stop, hold or repeat
+        let shape = self.registers[0x0D];
+        let continue_ = (shape & 0b1000) != 0;
+        let alternate = (shape & 0b0010) != 0;
+        let hold = (shape & 0b0001) != 0;
+        if !continue_ {
+            self.envelope_holding = true;
+            self.envelope_attack = false;
+        } else if hold {
+            self.envelope_holding = true;
+            if alternate {
+                self.envelope_attack = !self.envelope_attack;
+            }
+        } else {
+            if alternate {
+                self.envelope_attack = !self.envelope_attack;
+            }
+            self.envelope_step = 0;
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.divider += 1;
+        if self.divider < CLOCK_DIVIDER {
+            return;
+        }
+        self.divider = 0;
+
+        for tone in &mut self.tones {
+            tone.clock();
+        }
+        self.clock_noise();
+        self.clock_envelope();
+    }
+
+    pub fn output(&self) -> i16 {
+        let mixer = self.registers[0x07];
+        let noise = (self.noise_lfsr & 1) != 0;
+
+        let mut sum = 0;
+        for (channel, tone) in self.tones.iter().enumerate() {
+            let tone_disabled = (mixer & (1 << channel)) != 0;
+            let noise_disabled = (mixer & (8 << channel)) != 0;
+            if !((tone.output || tone_disabled) && (noise || noise_disabled)) {
+                continue;
+            }
+
+            let volume = self.registers[0x08 + channel];
+            let level = if (volume & 0x10) != 0 {
+                self.envelope_volume()
+            } else if (volume & 0x0F) == 0 {
+                0
+            } else {
+                (volume & 0x0F) * 2 + 1
+            };
+            sum += VOLUME_TABLE[level as usize];
+        }
+
+        sum
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.address)?;
+        writer.write_all(&self.registers)?;
+        writer.write_u8(self.divider)?;
+        for tone in &self.tones {
+            tone.save(writer)?;
+        }
+        writer.write_u8(self.noise_counter)?;
+        writer.write_u32(self.noise_lfsr)?;
+        writer.write_u16(self.envelope_counter)?;
+        writer.write_u8(self.envelope_step)?;
+        writer.write_bool(self.envelope_attack)?;
+        writer.write_bool(self.envelope_holding)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.address = reader.read_u8()?;
+        reader.read_exact(&mut self.registers)?;
+        self.divider = reader.read_u8()?;
+        for tone in &mut self.tones {
+            tone.load(reader)?;
+        }
+        self.noise_counter = reader.read_u8()?;
+        self.noise_lfsr = reader.read_u32()?;
+        self.envelope_counter = reader.read_u16()?;
+        self.envelope_step = reader.read_u8()?;
+        self.envelope_attack = reader.read_bool()?;
+        self.envelope_holding = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Sunsoft5BAudio, CLOCK_DIVIDER};
+
+    /// The envelope volume over 64 envelope steps after writing a shape.
+    fn envelope(shape: u8) -> Vec<u8> {
+        let mut audio = Sunsoft5BAudio::new();
+        // One step per clock
+        audio.write_address(0x0B);
+        audio.write_data(1);
+        audio.write_address(0x0D);
+        audio.write_data(shape);
+
+        (0..64)
+            .map(|_| {
+                let volume = audio.envelope_volume();
+                for _ in 0..CLOCK_DIVIDER {
+                    audio.tick();
+                }
+                volume
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_envelope_shapes() {
+        let decay: &[u8] = &(0..32).rev().collect::<Vec<u8>>();
+        let rise: &[u8] = &(0..32).collect::<Vec<u8>>();
+        let low: &[u8] = &[0; 32];
+        let high: &[u8] = &[31; 32];
+
+        for (shape, expected) in [
+            (0x00, [decay, low]),
+            (0x04, [rise, low]),
+            (0x08, [decay, decay]),
+            (0x09, [decay, low]),
+            (0x0A, [decay, rise]),
+            (0x0B, [decay, high]),
+            (0x0C, [rise, rise]),
+            (0x0D, [rise, high]),
+            (0x0E, [rise, decay]),
+            (0x0F, [rise, low]),
+        ] {
+            assert_eq!(envelope(shape), expected.concat(), "shape {shape:#04X}");
+        }
+    }
+
+    #[test]
+    fn test_envelope_restart() {
+        let mut audio = Sunsoft5BAudio::new();
+        audio.write_address(0x0B);
+        audio.write_data(1);
+        audio.write_address(0x0D);
+        audio.write_data(0x00);
+        for _ in 0..CLOCK_DIVIDER * 10 {
+            audio.tick();
+        }
+        assert_eq!(audio.envelope_volume(), 21);
+
+        // Rewriting the shape starts the ramp over
+        audio.write_data(0x00);
+        assert_eq!(audio.envelope_volume(), 31);
+    }
+}
@@ -0,0 +1,320 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// Scales the 0-61 VRC6 output so that a pulse at full volume roughly matches an APU pulse.
+const VRC6_OUTPUT_SCALE: i16 = 326;
+
+struct VRC6Pulse {
+    enabled: bool,
+    ignore_duty: bool,
+    duty: u8,
+    volume: u8,
+    frequency: u16,
+    counter: u16,
+    step: u8,
+}
+
+impl VRC6Pulse {
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            ignore_duty: false,
+            duty: 0,
+            volume: 0,
+            frequency: 0,
+            counter: 0,
+            step: 15,
+        }
+    }
+
+    fn write_reg(&mut self, address: u8, value: u8) {
+        match address {
+            0 => {
+                self.ignore_duty = (value & 0x80) != 0;
+                self.duty = (value >> 4) & 0b111;
+                self.volume = value & 0b1111;
+            }
+            1 => {
+                self.frequency = (self.frequency & 0xF00) | value as u16;
+            }
+            2 => {
+                self.enabled = (value & 0x80) != 0;
+                self.frequency = (self.frequency & 0xFF) | (((value & 0b1111) as u16) << 8);
+                if !self.enabled {
+                    self.step = 15;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, frequency_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.counter == 0 {
+            self.counter = self.frequency >> frequency_shift;
+            self.step = self.step.wrapping_sub(1) & 0xF;
+        } else {
+            self.counter -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled && (self.ignore_duty || self.step <= self.duty) {
+            self.volume
+        } else {
+            0
+        }
+    }
+
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_bool(self.enabled)?;
+        writer.write_bool(self.ignore_duty)?;
+        writer.write_u8(self.duty)?;
+        writer.write_u8(self.volume)?;
+        writer.write_u16(self.frequency)?;
+        writer.write_u16(self.counter)?;
+        writer.write_u8(self.step)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.enabled = reader.read_bool()?;
+        self.ignore_duty = reader.read_bool()?;
+        self.duty = reader.read_u8()?;
+        self.volume = reader.read_u8()?;
+        self.frequency = reader.read_u16()?;
+        self.counter = reader.read_u16()?;
+        self.step = reader.read_u8()?;
+
+        Ok(())
+    }
+}
+
+struct VRC6Saw {
+    enabled: bool,
+    rate: u8,
+    frequency: u16,
+    counter: u16,
+    step: u8,
+    accumulator: u8,
+}
+
+impl VRC6Saw {
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            rate: 0,
+            frequency: 0,
+            counter: 0,
+            step: 0,
+            accumulator: 0,
+        }
+    }
+
+    fn write_reg(&mut self, address: u8, value: u8) {
+        match address {
+            0 => {
+                self.rate = value & 0b111111;
+            }
+            1 => {
+                self.frequency = (self.frequency & 0xF00) | value as u16;
+            }
+            2 => {
+                self.enabled = (value & 0x80) != 0;
+                self.frequency = (self.frequency & 0xFF) | (((value & 0b1111) as u16) << 8);
+                if !self.enabled {
+                    self.step = 0;
+                    self.accumulator = 0;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn tick(&mut self, frequency_shift: u8) {
+        if !self.enabled {
+            return;
+        }
+
+        if self.counter == 0 {
+            self.counter = self.frequency >> frequency_shift;
+
+            // The accumulator is added to on every other clock and reset on the 14th
+            self.step += 1;
+            if self.step == 14 {
+                self.step = 0;
+                self.accumulator = 0;
+            } else if (self.step & 1) == 0 {
+                self.accumulator = self.accumulator.wrapping_add(self.rate);
+            }
+        } else {
+            self.counter -= 1;
+        }
+    }
+
+    fn output(&self) -> u8 {
+        if self.enabled {
+            self.accumulator >> 3
+        } else {
+            0
+        }
+    }
+
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_bool(self.enabled)?;
+        writer.write_u8(self.rate)?;
+        writer.write_u16(self.frequency)?;
+        writer.write_u16(self.counter)?;
+        writer.write_u8(self.step)?;
+        writer.write_u8(self.accumulator)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.enabled = reader.read_bool()?;
+        self.rate = reader.read_u8()?;
+        self.frequency = reader.read_u16()?;
+        self.counter = reader.read_u16()?;
+        self.step = reader.read_u8()?;
+        self.accumulator = reader.read_u8()?;
+
+        Ok(())
+    }
+}
+
+/// The VRC6 expansion audio: two pulse channels with 8 duty settings and a sawtooth.
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC6Audio {
+    pulse1: VRC6Pulse,
+    pulse2: VRC6Pulse,
+    saw: VRC6Saw,
+    halt: bool,
+    frequency_shift: u8,
+}
+
+impl VRC6Audio {
+    pub fn new() -> Self {
+        Self {
+            pulse1: VRC6Pulse::new(),
+            pulse2: VRC6Pulse::new(),
+            saw: VRC6Saw::new(),
+            halt: false,
+            frequency_shift: 0,
+        }
+    }
+
+    /// Writes to $9000-$9002, $A000-$A002 or $B000-$B002 ($9003 is the frequency control).
+    pub fn write_reg(&mut self, address: u16, value: u8) {
+        let reg = (address & 0b11) as u8;
+        match address & 0xF003 {
+            0x9000..=0x9002 => self.pulse1.write_reg(reg, value),
+            0x9003 => {
+                self.halt = (value & 1) != 0;
+                self.frequency_shift = if (value & 4) != 0 {
+                    8
+                } else if (value & 2) != 0 {
+                    4
+                } else {
+                    0
+                };
+            }
+            0xA000..=0xA002 => self.pulse2.write_reg(reg, value),
+            0xB000..=0xB002 => self.saw.write_reg(reg, value),
+            _ => {}
+        }
+    }
+
+    pub fn tick(&mut self) {
+        if self.halt {
+            return;
+        }
+
+        self.pulse1.tick(self.frequency_shift);
+        self.pulse2.tick(self.frequency_shift);
+        self.saw.tick(self.frequency_shift);
+    }
+
+    pub fn output(&self) -> i16 {
+        let level = self.pulse1.output() + self.pulse2.output() + self.saw.output();
+        level as i16 * VRC6_OUTPUT_SCALE
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        self.pulse1.save(writer)?;
+        self.pulse2.save(writer)?;
+        self.saw.save(writer)?;
+        writer.write_bool(self.halt)?;
+        writer.write_u8(self.frequency_shift)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.pulse1.load(reader)?;
+        self.pulse2.load(reader)?;
+        self.saw.load(reader)?;
+        self.halt = reader.read_bool()?;
+        self.frequency_shift = reader.read_u8()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{VRC6Audio, VRC6_OUTPUT_SCALE};
+
+    /// The levels of the next `count` cycles.
+    fn levels(audio: &mut VRC6Audio, count: usize) -> Vec<i16> {
+        (0..count)
+            .map(|_| {
+                audio.tick();
+                audio.output() / VRC6_OUTPUT_SCALE
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_pulse_duty() {
+        let mut audio = VRC6Audio::new();
+        // Duty 3 of 16, full volume, clocked every cycle
+        audio.write_reg(0x9000, 0x3F);
+        audio.write_reg(0x9002, 0x80);
+        let period = levels(&mut audio, 16);
+        assert_eq!(period.iter().filter(|&&level| level == 15).count(), 4);
+        assert_eq!(period.iter().filter(|&&level| level == 0).count(), 12);
+
+        // Ignoring the duty holds the volume
+        audio.write_reg(0x9000, 0x85);
+        assert_eq!(levels(&mut audio, 16), [5; 16]);
+
+        // Halted, nothing moves
+        audio.write_reg(0x9000, 0x3F);
+        audio.write_reg(0x9003, 0x01);
+        let level = audio.output() / VRC6_OUTPUT_SCALE;
+        assert_eq!(levels(&mut audio, 16), [level; 16]);
+    }
+
+    #[test]
+    fn test_sawtooth() {
+        let mut audio = VRC6Audio::new();
+        // Adding 8 every other clock, resetting every 14
+        audio.write_reg(0xB000, 8);
+        audio.write_reg(0xB002, 0x80);
+        assert_eq!(
+            levels(&mut audio, 14),
+            [0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 0]
+        );
+
+        // The frequency divides the clock
+        audio.write_reg(0xB001, 1);
+        assert_eq!(levels(&mut audio, 8), [0, 0, 1, 1, 1, 1, 2, 2]);
+
+        audio.write_reg(0xB002, 0x00);
+        assert_eq!(audio.output(), 0);
+    }
+}
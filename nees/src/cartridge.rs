@@ -1,16 +1,197 @@
-use crate::reader_writer::{EasyReader, EasyWriter};
+use alloc::vec::Vec;
 
+use crate::{
+    mirroring::Mirroring,
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+/// What a PPU bus read is for, so boards that bank background and sprite patterns separately
+/// can tell the two apart.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum PPUFetch {
+    Background,
+    Sprite,
+    /// A CPU access through $2007.
+    Data,
+}
+
+/// The memories a board can map into the CPU and PPU address spaces.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CartMemory {
+    PrgRom,
+    /// Work RAM, battery-backed or not.
+    PrgRam,
+    /// CHR-ROM, or the CHR-RAM of boards that have that instead.
+    ChrRom,
+    /// The console's 2KB of nametable RAM, which the PPU owns but the board wires up.
+    Ciram,
+    /// Nametable RAM on the board itself, as on four-screen boards.
+    Vram,
+    /// Any other memory the board has, like the MMC5's ExRAM.
+    Internal,
+}
+
+/// A byte of one of the board's memories.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct MemoryLocation {
+    pub memory: CartMemory,
+    pub offset: usize,
+}
+
+impl MemoryLocation {
+    pub fn new(memory: CartMemory, offset: usize) -> Self {
+        Self { memory, offset }
+    }
+
+    /// The number of the bank this falls in, for banks of the given size.
+    pub fn bank(&self, bank_size: usize) -> usize {
+        self.offset / bank_size
+    }
+
+    pub(crate) fn read(&self, memory: &[u8]) -> u8 {
+        memory.get(self.offset).copied().unwrap_or(0)
+    }
+
+    fn write(&self, memory: &mut [u8], value: u8) {
+        if let Some(byte) = memory.get_mut(self.offset) {
+            *byte = value;
+        }
+    }
+}
+
+/// A board plugged into the console. Implement this and `CartridgeSaveLoad` to add a mapper,
+/// and register it with a `MapperRegistry`.
 pub trait Cartridge {
-    fn ppu_read(&mut self, address: u16, ciram: &[u8]) -> u8;
+    /// A read from the PPU bus at $0000-$3EFF. `ciram` is the console's 2KB of nametable RAM,
+    /// which boards wire up as they like; see `Nametables` for the usual mirrorings.
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], fetch: PPUFetch) -> u8;
     fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]);
-    fn cpu_read(&self, addr: u16) -> u8;
+    /// A read from any CPU bus address the console doesn't decode itself, which includes all of
+    /// $4018-$FFFF.
+    fn cpu_read(&mut self, addr: u16) -> u8;
     fn cpu_write(&mut self, addr: u16, value: u8);
+    /// Called once per visible scanline while rendering is enabled. Returns true to raise an
+    /// IRQ.
     fn scanline(&mut self) -> bool;
+
+    /// The current nametable mirroring.
+    fn mirroring(&self) -> Mirroring;
+
+    /// Sees CPU writes to the PPU registers ($2000-$2007), for boards that snoop them.
+    fn ppu_register_write(&mut self, _register: u8, _value: u8) {}
+
+    /// Called once per CPU cycle. Returns true while the cartridge holds the IRQ line asserted.
+    fn cpu_tick(&mut self) -> bool {
+        false
+    }
+
+    /// Expansion audio output, on the same scale as the APU's mixed sample.
+    fn audio_output(&self) -> i16 {
+        0
+    }
+
+    /// Chooses between hardware-accurate expansion audio and a cleaner approximation, for chips
+    /// where the two differ audibly.
+    fn set_authentic_audio(&mut self, _authentic: bool) {}
+
+    /// Enables bus conflicts on discrete boards whose PRG-ROM stays enabled during register
+    /// writes. Off by default, as iNES headers can't tell which boards have them.
+    fn set_bus_conflicts(&mut self, _enabled: bool) {}
+
+    /// Save data that survives power-off, to be persisted between sessions. Its format is up to
+    /// the board: battery RAM, EEPROM contents, flash sectors and so on.
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Restores data previously returned by `battery_data`.
+    fn load_battery_data(&mut self, _data: &[u8]) {}
+
+    /// The work RAM mapped at $6000-$7FFF, if the board has some enabled there. Reading it has
+    /// no side effects, unlike `cpu_read`.
+    fn work_ram(&self) -> Option<&[u8]> {
+        None
+    }
+
+    /// Where `work_ram` starts in the CPU's address space.
+    fn work_ram_address(&self) -> u16 {
+        0x6000
+    }
+
+    /// Where a CPU address at $4020-$FFFF is mapped to with the current banking, or None for
+    /// registers and open bus.
+    fn cpu_mapping(&self, _address: u16) -> Option<MemoryLocation> {
+        None
+    }
+
+    /// Where a PPU address at $0000-$3EFF is mapped to with the current banking.
+    fn ppu_mapping(&self, _address: u16) -> Option<MemoryLocation> {
+        None
+    }
+
+    /// One of the board's memories, empty if it doesn't have it. `CartMemory::Ciram` is never
+    /// the board's, so is always empty.
+    fn memory(&self, _memory: CartMemory) -> &[u8] {
+        &[]
+    }
+
+    fn memory_mut(&mut self, _memory: CartMemory) -> &mut [u8] {
+        &mut []
+    }
+
+    /// Reads a CPU address like `cpu_read`, but without side effects like acknowledging IRQs.
+    /// Registers read as 0, unless the board can read them back without side effects.
+    fn cpu_peek(&self, address: u16) -> u8 {
+        self.cpu_mapping(address)
+            .map_or(0, |location| location.read(self.memory(location.memory)))
+    }
+
+    /// Writes to the memory behind a CPU address, ROM included, without touching registers.
+    fn cpu_poke(&mut self, address: u16, value: u8) {
+        if let Some(location) = self.cpu_mapping(address) {
+            location.write(self.memory_mut(location.memory), value);
+        }
+    }
+
+    /// Reads a PPU address like `ppu_read`, but without moving the latches some boards bank
+    /// with.
+    fn ppu_peek(&self, address: u16, ciram: &[u8]) -> u8 {
+        match self.ppu_mapping(address) {
+            Some(location) if location.memory == CartMemory::Ciram => location.read(ciram),
+            Some(location) => location.read(self.memory(location.memory)),
+            None => 0,
+        }
+    }
+
+    /// Writes to the memory behind a PPU address, CHR-ROM included.
+    fn ppu_poke(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        match self.ppu_mapping(address) {
+            Some(location) if location.memory == CartMemory::Ciram => location.write(ciram, value),
+            Some(location) => location.write(self.memory_mut(location.memory), value),
+            None => {}
+        }
+    }
+
+    /// The number of disk sides, for disk-based systems.
+    fn disk_sides(&self) -> usize {
+        0
+    }
+
+    fn inserted_disk_side(&self) -> Option<usize> {
+        None
+    }
+
+    /// Inserts a disk side, or ejects the disk with None.
+    fn insert_disk_side(&mut self, _side: Option<usize>) {}
 }
 
+/// Savestate support. Boards write their registers and any RAM not in `battery_data`, and read
+/// them back in the same order.
 pub trait CartridgeSaveLoad {
     fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()>;
     fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()>;
 }
 
-pub trait CartridgeWithSaveLoad: Cartridge + CartridgeSaveLoad {}
\ No newline at end of file
+pub trait CartridgeWithSaveLoad: Cartridge + CartridgeSaveLoad {}
+
+impl<T: Cartridge + CartridgeSaveLoad> CartridgeWithSaveLoad for T {}
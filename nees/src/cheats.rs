@@ -0,0 +1,270 @@
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+
+/// Game Genie letters, in the order of the nibbles they stand for.
+const GAME_GENIE_LETTERS: &[u8; 16] = b"APZLGITYEOXUKSVN";
+
+/// Decodes a 6 or 8-letter Game Genie code into its ROM address, value and compare value.
+pub fn decode_game_genie(code: &str) -> anyhow::Result<(u16, u8, Option<u8>)> {
+    let mut n = [0_u16; 8];
+    if code.len() != 6 && code.len() != 8 {
+        anyhow::bail!("Game Genie codes have 6 or 8 letters");
+    }
+    for (nibble, letter) in n.iter_mut().zip(code.bytes()) {
+        *nibble = GAME_GENIE_LETTERS
+            .iter()
+            .position(|&l| l == letter.to_ascii_uppercase())
+            .ok_or_else(|| anyhow::anyhow!("{} isn't a Game Genie letter", letter as char))?
+            as u16;
+    }
+
+    let address = 0x8000
+        | ((n[3] & 7) << 12)
+        | ((n[4] & 8) << 8)
+        | ((n[5] & 7) << 8)
+        | ((n[1] & 8) << 4)
+        | ((n[2] & 7) << 4)
+        | (n[3] & 8)
+        | (n[4] & 7);
+    // 8-letter codes move bit 3 of the value to the last letter to make room for the compare
+    let (value_bit_3, compare) = if code.len() == 8 {
+        let compare = ((n[7] & 7) << 4) | ((n[6] & 8) << 4) | (n[6] & 7) | (n[5] & 8);
+        (n[7] & 8, Some(compare as u8))
+    } else {
+        (n[5] & 8, None)
+    };
+    let value = ((n[0] & 8) << 4) | ((n[1] & 7) << 4) | value_bit_3 | (n[0] & 7);
+
+    Ok((address, value as u8, compare))
+}
+
+/// Encodes a ROM patch as a Game Genie code, with 8 letters if it has a compare value.
+pub fn encode_game_genie(address: u16, value: u8, compare: Option<u8>) -> String {
+    let (address, value) = (address as usize, value as usize);
+    let mut n = [
+        ((value >> 4) & 8) | (value & 7),
+        ((address >> 4) & 8) | ((value >> 4) & 7),
+        (address >> 4) & 7,
+        (address & 8) | ((address >> 12) & 7),
+        ((address >> 8) & 8) | (address & 7),
+        (value & 8) | ((address >> 8) & 7),
+    ]
+    .to_vec();
+    if let Some(compare) = compare {
+        let compare = compare as usize;
+        // The Game Genie tells the lengths apart by bit 3 of the third letter
+        n[2] |= 8;
+        n[5] = (compare & 8) | ((address >> 8) & 7);
+        n.push(((compare >> 4) & 8) | (compare & 7));
+        n.push((value & 8) | ((compare >> 4) & 7));
+    }
+
+    n.iter()
+        .map(|&nibble| GAME_GENIE_LETTERS[nibble] as char)
+        .collect()
+}
+
+fn parse_hex<T: TryFrom<u32>>(hex: &str) -> anyhow::Result<T> {
+    u32::from_str_radix(hex, 16)
+        .ok()
+        .and_then(|value| T::try_from(value).ok())
+        .ok_or_else(|| anyhow::anyhow!("{} isn't a valid hex number here", hex))
+}
+
+/// A cheat code. Codes for ROM addresses change what the CPU reads from there, and ones for RAM
+/// freeze it to the value by writing it every frame.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Cheat {
+    /// As entered: a Game Genie code, `address:value[:compare]` in hex, or a Pro Action Replay
+    /// code (the address then the value, in hex).
+    pub code: String,
+    pub description: String,
+    pub address: u16,
+    pub value: u8,
+    /// Only substitute or freeze while the original value is this, for ROM addresses shared by
+    /// several banks.
+    pub compare: Option<u8>,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    /// Reads a code in any of the supported formats, enabled.
+    pub fn parse(code: &str) -> anyhow::Result<Self> {
+        let code = code.trim();
+        let (address, value, compare) = if code.contains(':') {
+            let mut parts = code.split(':');
+            let address = parse_hex(parts.next().unwrap_or_default())?;
+            let value = parse_hex(parts.next().unwrap_or_default())?;
+            let compare = parts.next().map(parse_hex).transpose()?;
+            if parts.next().is_some() {
+                anyhow::bail!("Raw codes are address:value[:compare]");
+            }
+            (address, value, compare)
+        } else if code.len() == 6
+            && code.bytes().all(|b| b.is_ascii_hexdigit())
+            && decode_game_genie(code).is_err()
+        {
+            // Pro Action Replay, unless it's all letters Game Genie codes use too
+            (parse_hex(&code[..4])?, parse_hex(&code[4..])?, None)
+        } else {
+            decode_game_genie(code)?
+        };
+
+        Ok(Self {
+            code: code.to_string(),
+            description: String::new(),
+            address,
+            value,
+            compare,
+            enabled: true,
+        })
+    }
+
+    /// RAM freezes are written each frame instead of patching reads.
+    pub fn is_freeze(&self) -> bool {
+        self.address < 0x8000
+    }
+
+    /// The code as a Game Genie code, if it patches ROM.
+    pub fn game_genie_code(&self) -> Option<String> {
+        (!self.is_freeze()).then(|| encode_game_genie(self.address, self.value, self.compare))
+    }
+}
+
+/// The cheat codes for a game, with their file format: a code per line, then optionally a
+/// description after a space. Disabled codes start with a `-`, and comments with a `#`.
+#[derive(Clone, Default, Debug)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_cheat_file(text: &str) -> anyhow::Result<Self> {
+        let mut cheats = Self::new();
+        for (line_no, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (disabled, line) = match line.strip_prefix('-') {
+                Some(line) => (true, line),
+                None => (false, line),
+            };
+            let (code, description) = line.split_once(' ').unwrap_or((line, ""));
+            let mut cheat =
+                Cheat::parse(code).map_err(|e| anyhow::anyhow!("Line {}: {}", line_no + 1, e))?;
+            cheat.description = description.trim().to_string();
+            cheat.enabled = !disabled;
+            cheats.add(cheat);
+        }
+        Ok(cheats)
+    }
+
+    pub fn to_cheat_file(&self) -> String {
+        let mut text = String::new();
+        for cheat in &self.cheats {
+            if !cheat.enabled {
+                text.push('-');
+            }
+            text.push_str(&cheat.code);
+            if !cheat.description.is_empty() {
+                text.push(' ');
+                text.push_str(&cheat.description);
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn add(&mut self, cheat: Cheat) {
+        self.cheats.push(cheat);
+    }
+
+    pub fn remove(&mut self, index: usize) -> Cheat {
+        self.cheats.remove(index)
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.cheats.get_mut(index) {
+            cheat.enabled = enabled;
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.cheats.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cheats.is_empty()
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter().filter(|cheat| cheat.enabled)
+    }
+
+    /// What the CPU reads from a ROM address, given what's really there.
+    pub(crate) fn substitute(&self, address: u16, value: u8) -> u8 {
+        self.enabled()
+            .find(|cheat| {
+                !cheat.is_freeze()
+                    && cheat.address == address
+                    && cheat.compare.is_none_or(|compare| compare == value)
+            })
+            .map_or(value, |cheat| cheat.value)
+    }
+
+    pub(crate) fn freezes(&self) -> impl Iterator<Item = &Cheat> {
+        self.enabled().filter(|cheat| cheat.is_freeze())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_game_genie, encode_game_genie, Cheats};
+
+    #[test]
+    fn test_game_genie() {
+        // Infinite lives in Super Mario Bros.
+        assert_eq!(decode_game_genie("SXIOPO").unwrap(), (0x91D9, 0xAD, None));
+        assert_eq!(encode_game_genie(0x91D9, 0xAD, None), "SXIOPO");
+
+        let code = encode_game_genie(0xD1DD, 0x14, Some(0x9A));
+        assert_eq!(code.len(), 8);
+        assert_eq!(
+            decode_game_genie(&code).unwrap(),
+            (0xD1DD, 0x14, Some(0x9A))
+        );
+        assert!(decode_game_genie("SXIOP1").is_err());
+    }
+
+    #[test]
+    fn test_cheat_file() {
+        let text = "# Super Mario Bros.\nSXIOPO Infinite lives\n-0075:09 Always big\n\
+                    C0DE:EA:60\n07B201\n";
+        let cheats = Cheats::from_cheat_file(text).unwrap();
+        assert_eq!(cheats.len(), 4);
+        assert_eq!(cheats.substitute(0x91D9, 0xCE), 0xAD);
+        assert_eq!(cheats.substitute(0xC0DE, 0x60), 0xEA);
+        assert_eq!(cheats.substitute(0xC0DE, 0x61), 0x61);
+        // The disabled freeze isn't applied
+        let freezes: Vec<_> = cheats.freezes().map(|c| (c.address, c.value)).collect();
+        assert_eq!(freezes, [(0x07B2, 0x01)]);
+
+        let saved = Cheats::from_cheat_file(&cheats.to_cheat_file()).unwrap();
+        assert_eq!(
+            saved.iter().collect::<Vec<_>>(),
+            cheats.iter().collect::<Vec<_>>()
+        );
+        assert!(Cheats::from_cheat_file("SXIOPO\n12:34:56:78").is_err());
+    }
+}
@@ -38,6 +38,183 @@ pub struct MOS6502<T: Bus> {
     pub optable: [fn(&mut Self, &OperandType, &mut T); 256],
 }
 
+/// How an instruction finds its operand.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum AddressingMode {
+    /// No operand, like `CLC`.
+    Imp,
+    /// Operates on the accumulator, like `ASL A`.
+    Acc,
+    /// An 8-bit constant, like `LDA #$10`.
+    Imm,
+    /// A zero page address, like `LDA $10`.
+    Zp,
+    /// A zero page address plus X, wrapping within the zero page.
+    Zpx,
+    /// A zero page address plus Y, wrapping within the zero page.
+    Zpy,
+    /// A 16-bit address, like `LDA $1234`.
+    Abs,
+    /// A 16-bit address plus X.
+    Absx,
+    /// A 16-bit address plus Y.
+    Absy,
+    /// A pointer to the jump target, like `JMP ($1234)`.
+    Ind,
+    /// A zero page pointer indexed by X before reading it, like `LDA ($10,X)`.
+    Indx,
+    /// A zero page pointer indexed by Y after reading it, like `LDA ($10),Y`.
+    Indy,
+    /// A signed branch offset from the next instruction.
+    Rel,
+}
+
+impl AddressingMode {
+    /// The number of operand bytes following the opcode.
+    pub fn operand_len(self) -> u16 {
+        match self {
+            AddressingMode::Imp | AddressingMode::Acc => 0,
+            AddressingMode::Abs
+            | AddressingMode::Absx
+            | AddressingMode::Absy
+            | AddressingMode::Ind => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// Instruction names, including the unofficial ones.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mnemonic {
+    ADC,
+    AHX,
+    ALR,
+    ANC,
+    AND,
+    ARR,
+    ASL,
+    AXS,
+    BCC,
+    BCS,
+    BEQ,
+    BIT,
+    BMI,
+    BNE,
+    BPL,
+    BRK,
+    BVC,
+    BVS,
+    CLC,
+    CLD,
+    CLI,
+    CLV,
+    CMP,
+    CPX,
+    CPY,
+    DCP,
+    DEC,
+    DEX,
+    DEY,
+    EOR,
+    INC,
+    INX,
+    INY,
+    ISB,
+    JAM,
+    JMP,
+    JSR,
+    LAS,
+    LAX,
+    LDA,
+    LDX,
+    LDY,
+    LSR,
+    LXA,
+    NOP,
+    ORA,
+    PHA,
+    PHP,
+    PLA,
+    PLP,
+    RLA,
+    ROL,
+    ROR,
+    RRA,
+    RTI,
+    RTS,
+    SAX,
+    SBC,
+    SEC,
+    SED,
+    SEI,
+    SHX,
+    SHY,
+    SLO,
+    SRE,
+    STA,
+    STX,
+    STY,
+    TAS,
+    TAX,
+    TAY,
+    TSX,
+    TXA,
+    TXS,
+    TYA,
+    XAA,
+}
+
+/// The instruction for each opcode. Everything that decodes opcodes goes through these tables.
+#[rustfmt::skip]
+pub const MNEMONICS: [Mnemonic; 256] = {
+    use Mnemonic::*;
+    [
+/*        |  0  |  1  |  2  |  3  |  4  |  5  |  6  |  7  |  8  |  9  |  A  |  B  |  C  |  D  |  E  |  F  |     */
+/* 0 */ BRK, ORA, JAM, SLO, NOP, ORA, ASL, SLO, PHP, ORA, ASL, ANC, NOP, ORA, ASL, SLO, /* 0 */
+/* 1 */ BPL, ORA, JAM, SLO, NOP, ORA, ASL, SLO, CLC, ORA, NOP, SLO, NOP, ORA, ASL, SLO, /* 1 */
+/* 2 */ JSR, AND, JAM, RLA, BIT, AND, ROL, RLA, PLP, AND, ROL, ANC, BIT, AND, ROL, RLA, /* 2 */
+/* 3 */ BMI, AND, JAM, RLA, NOP, AND, ROL, RLA, SEC, AND, NOP, RLA, NOP, AND, ROL, RLA, /* 3 */
+/* 4 */ RTI, EOR, JAM, SRE, NOP, EOR, LSR, SRE, PHA, EOR, LSR, ALR, JMP, EOR, LSR, SRE, /* 4 */
+/* 5 */ BVC, EOR, JAM, SRE, NOP, EOR, LSR, SRE, CLI, EOR, NOP, SRE, NOP, EOR, LSR, SRE, /* 5 */
+/* 6 */ RTS, ADC, JAM, RRA, NOP, ADC, ROR, RRA, PLA, ADC, ROR, ARR, JMP, ADC, ROR, RRA, /* 6 */
+/* 7 */ BVS, ADC, JAM, RRA, NOP, ADC, ROR, RRA, SEI, ADC, NOP, RRA, NOP, ADC, ROR, RRA, /* 7 */
+/* 8 */ NOP, STA, NOP, SAX, STY, STA, STX, SAX, DEY, NOP, TXA, XAA, STY, STA, STX, SAX, /* 8 */
+/* 9 */ BCC, STA, JAM, AHX, STY, STA, STX, SAX, TYA, STA, TXS, TAS, SHY, STA, SHX, AHX, /* 9 */
+/* A */ LDY, LDA, LDX, LAX, LDY, LDA, LDX, LAX, TAY, LDA, TAX, LXA, LDY, LDA, LDX, LAX, /* A */
+/* B */ BCS, LDA, JAM, LAX, LDY, LDA, LDX, LAX, CLV, LDA, TSX, LAS, LDY, LDA, LDX, LAX, /* B */
+/* C */ CPY, CMP, NOP, DCP, CPY, CMP, DEC, DCP, INY, CMP, DEX, AXS, CPY, CMP, DEC, DCP, /* C */
+/* D */ BNE, CMP, JAM, DCP, NOP, CMP, DEC, DCP, CLD, CMP, NOP, DCP, NOP, CMP, DEC, DCP, /* D */
+/* E */ CPX, SBC, NOP, ISB, CPX, SBC, INC, ISB, INX, SBC, NOP, SBC, CPX, SBC, INC, ISB, /* E */
+/* F */ BEQ, SBC, JAM, ISB, NOP, SBC, INC, ISB, SED, SBC, NOP, ISB, NOP, SBC, INC, ISB  /* F */
+    ]
+};
+
+/// The addressing mode for each opcode.
+#[rustfmt::skip]
+pub const ADDRESSING_MODES: [AddressingMode; 256] = {
+    use AddressingMode::*;
+    [
+/*        |  0  |  1  |  2  |  3  |  4  |  5  |  6  |  7  |  8  |  9  |  A  |  B  |  C  |  D  |  E  |  F  |     */
+/* 0 */  Imp, Indx,  Imp, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Acc,  Imm,  Abs,  Abs,  Abs,  Abs, /* 0 */
+/* 1 */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpx,  Zpx,  Imp, Absy,  Imp, Absy, Absx, Absx, Absx, Absx, /* 1 */
+/* 2 */  Abs, Indx,  Imp, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Acc,  Imm,  Abs,  Abs,  Abs,  Abs, /* 2 */
+/* 3 */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpx,  Zpx,  Imp, Absy,  Imp, Absy, Absx, Absx, Absx, Absx, /* 3 */
+/* 4 */  Imp, Indx,  Imp, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Acc,  Imm,  Abs,  Abs,  Abs,  Abs, /* 4 */
+/* 5 */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpx,  Zpx,  Imp, Absy,  Imp, Absy, Absx, Absx, Absx, Absx, /* 5 */
+/* 6 */  Imp, Indx,  Imp, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Acc,  Imm,  Ind,  Abs,  Abs,  Abs, /* 6 */
+/* 7 */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpx,  Zpx,  Imp, Absy,  Imp, Absy, Absx, Absx, Absx, Absx, /* 7 */
+/* 8 */  Imm, Indx,  Imm, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Imp,  Imm,  Abs,  Abs,  Abs,  Abs, /* 8 */
+/* 9 */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpy,  Zpy,  Imp, Absy,  Imp, Absy, Absx, Absx, Absy, Absy, /* 9 */
+/* A */  Imm, Indx,  Imm, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Imp,  Imm,  Abs,  Abs,  Abs,  Abs, /* A */
+/* B */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpy,  Zpy,  Imp, Absy,  Imp, Absy, Absx, Absx, Absy, Absy, /* B */
+/* C */  Imm, Indx,  Imm, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Imp,  Imm,  Abs,  Abs,  Abs,  Abs, /* C */
+/* D */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpx,  Zpx,  Imp, Absy,  Imp, Absy, Absx, Absx, Absx, Absx, /* D */
+/* E */  Imm, Indx,  Imm, Indx,   Zp,   Zp,   Zp,   Zp,  Imp,  Imm,  Imp,  Imm,  Abs,  Abs,  Abs,  Abs, /* E */
+/* F */  Rel, Indy,  Imp, Indy,  Zpx,  Zpx,  Zpx,  Zpx,  Imp, Absy,  Imp, Absy, Absx, Absx, Absx, Absx  /* F */
+    ]
+};
+
 const TICKTABLE: [u32; 256] = [
     /*        |  0  |  1  |  2  |  3  |  4  |  5  |  6  |  7  |  8  |  9  |  A  |  B  |  C  |  D  |  E  |  F  |     */
     /* 0 */
@@ -191,48 +368,109 @@ impl<T: Bus> MOS6502<T> {
         OperandType::Accumulator
     }
 
+    fn addressing_fn(mode: AddressingMode) -> fn(&mut Self, &mut T) -> OperandType {
+        match mode {
+            AddressingMode::Imp => Self::imp,
+            AddressingMode::Acc => Self::acc,
+            AddressingMode::Imm => Self::imm,
+            AddressingMode::Zp => Self::zp,
+            AddressingMode::Zpx => Self::zpx,
+            AddressingMode::Zpy => Self::zpy,
+            AddressingMode::Abs => Self::abso,
+            AddressingMode::Absx => Self::absx,
+            AddressingMode::Absy => Self::absy,
+            AddressingMode::Ind => Self::ind,
+            AddressingMode::Indx => Self::indx,
+            AddressingMode::Indy => Self::indy,
+            AddressingMode::Rel => Self::rel,
+        }
+    }
+
+    fn operation_fn(mnemonic: Mnemonic) -> fn(&mut Self, &OperandType, &mut T) {
+        match mnemonic {
+            Mnemonic::ADC => Self::adc,
+            Mnemonic::AND => Self::and,
+            Mnemonic::ASL => Self::asl,
+            Mnemonic::BCC => Self::bcc,
+            Mnemonic::BCS => Self::bcs,
+            Mnemonic::BEQ => Self::beq,
+            Mnemonic::BIT => Self::bit,
+            Mnemonic::BMI => Self::bmi,
+            Mnemonic::BNE => Self::bne,
+            Mnemonic::BPL => Self::bpl,
+            Mnemonic::BRK => Self::brk,
+            Mnemonic::BVC => Self::bvc,
+            Mnemonic::BVS => Self::bvs,
+            Mnemonic::CLC => Self::clc,
+            Mnemonic::CLD => Self::cld,
+            Mnemonic::CLI => Self::cli,
+            Mnemonic::CLV => Self::clv,
+            Mnemonic::CMP => Self::cmp,
+            Mnemonic::CPX => Self::cpx,
+            Mnemonic::CPY => Self::cpy,
+            Mnemonic::DCP => Self::dcp,
+            Mnemonic::DEC => Self::dec,
+            Mnemonic::DEX => Self::dex,
+            Mnemonic::DEY => Self::dey,
+            Mnemonic::EOR => Self::eor,
+            Mnemonic::INC => Self::inc,
+            Mnemonic::INX => Self::inx,
+            Mnemonic::INY => Self::iny,
+            Mnemonic::ISB => Self::isb,
+            Mnemonic::JMP => Self::jmp,
+            Mnemonic::JSR => Self::jsr,
+            Mnemonic::LAX => Self::lax,
+            Mnemonic::LDA => Self::lda,
+            Mnemonic::LDX => Self::ldx,
+            Mnemonic::LDY => Self::ldy,
+            Mnemonic::LSR => Self::lsr,
+            Mnemonic::NOP => Self::nop,
+            Mnemonic::ORA => Self::ora,
+            Mnemonic::PHA => Self::pha,
+            Mnemonic::PHP => Self::php,
+            Mnemonic::PLA => Self::pla,
+            Mnemonic::PLP => Self::plp,
+            Mnemonic::RLA => Self::rla,
+            Mnemonic::ROL => Self::rol,
+            Mnemonic::ROR => Self::ror,
+            Mnemonic::RRA => Self::rra,
+            Mnemonic::RTI => Self::rti,
+            Mnemonic::RTS => Self::rts,
+            Mnemonic::SAX => Self::sax,
+            Mnemonic::SBC => Self::sbc,
+            Mnemonic::SEC => Self::sec,
+            Mnemonic::SED => Self::sed,
+            Mnemonic::SEI => Self::sei,
+            Mnemonic::SLO => Self::slo,
+            Mnemonic::SRE => Self::sre,
+            Mnemonic::STA => Self::sta,
+            Mnemonic::STX => Self::stx,
+            Mnemonic::STY => Self::sty,
+            Mnemonic::TAX => Self::tax,
+            Mnemonic::TAY => Self::tay,
+            Mnemonic::TSX => Self::tsx,
+            Mnemonic::TXA => Self::txa,
+            Mnemonic::TXS => Self::txs,
+            Mnemonic::TYA => Self::tya,
+            Mnemonic::LAS => Self::las,
+            // The remaining unstable and halting opcodes aren't emulated
+            Mnemonic::AHX
+            | Mnemonic::ALR
+            | Mnemonic::ANC
+            | Mnemonic::ARR
+            | Mnemonic::AXS
+            | Mnemonic::JAM
+            | Mnemonic::LXA
+            | Mnemonic::SHX
+            | Mnemonic::SHY
+            | Mnemonic::TAS
+            | Mnemonic::XAA => Self::nop,
+        }
+    }
+
     pub fn new() -> Self {
-        #[rustfmt::skip]
-        let addrtable = [
-/*        |  0  |  1  |  2  |  3  |  4  |  5  |  6  |  7  |  8  |  9  |  A  |  B  |  C  |  D  |  E  |  F  |     */
-/* 0 */     Self::imp, Self::indx, Self:: imp, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: acc, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* 0 */
-/* 1 */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpx, Self:: zpx, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absx, Self::absx, /* 1 */
-/* 2 */    Self::abso, Self::indx, Self:: imp, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: acc, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* 2 */
-/* 3 */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpx, Self:: zpx, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absx, Self::absx, /* 3 */
-/* 4 */     Self::imp, Self::indx, Self:: imp, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: acc, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* 4 */
-/* 5 */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpx, Self:: zpx, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absx, Self::absx, /* 5 */
-/* 6 */     Self::imp, Self::indx, Self:: imp, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: acc, Self:: imm, Self::ind, Self::abso, Self::abso, Self::abso, /* 6 */
-/* 7 */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpx, Self:: zpx, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absx, Self::absx, /* 7 */
-/* 8 */     Self::imm, Self::indx, Self:: imm, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: imp, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* 8 */
-/* 9 */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpy, Self:: zpy, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absy, Self::absy, /* 9 */
-/* A */     Self::imm, Self::indx, Self:: imm, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: imp, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* A */
-/* B */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpy, Self:: zpy, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absy, Self::absy, /* B */
-/* C */     Self::imm, Self::indx, Self:: imm, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: imp, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* C */
-/* D */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpx, Self:: zpx, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absx, Self::absx, /* D */
-/* E */     Self::imm, Self::indx, Self:: imm, Self::indx, Self::  zp, Self::  zp, Self::  zp, Self::  zp, Self:: imp, Self:: imm, Self:: imp, Self:: imm, Self::abso, Self::abso, Self::abso, Self::abso, /* E */
-/* F */     Self::rel, Self::indy, Self:: imp, Self::indy, Self:: zpx, Self:: zpx, Self:: zpx, Self:: zpx, Self:: imp, Self::absy, Self:: imp, Self::absy, Self::absx, Self::absx, Self::absx, Self::absx  /* F */
-        ];
-
-        #[rustfmt::skip]
-        let optable = [
-    /*        |  0  |  1  |  2  |  3  |  4  |  5  |  6  |  7  |  8  |  9  |  A  |  B  |  C  |  D  |  E  |  F  |      */
-	/* 0 */      Self::brk, Self::ora,  Self::nop,  Self::slo,  Self::nop, Self::ora, Self::asl, Self::slo, Self::php, Self::ora, Self::asl, Self::nop, Self::nop, Self::ora, Self::asl, Self::slo, /* 0 */
-	/* 1 */      Self::bpl, Self::ora,  Self::nop,  Self::slo,  Self::nop, Self::ora, Self::asl, Self::slo, Self::clc, Self::ora, Self::nop, Self::slo, Self::nop, Self::ora, Self::asl, Self::slo, /* 1 */
-	/* 2 */      Self::jsr, Self::and,  Self::nop,  Self::rla,  Self::bit, Self::and, Self::rol, Self::rla, Self::plp, Self::and, Self::rol, Self::nop, Self::bit, Self::and, Self::rol, Self::rla, /* 2 */
-	/* 3 */      Self::bmi, Self::and,  Self::nop,  Self::rla,  Self::nop, Self::and, Self::rol, Self::rla, Self::sec, Self::and, Self::nop, Self::rla, Self::nop, Self::and, Self::rol, Self::rla, /* 3 */
-	/* 4 */      Self::rti, Self::eor,  Self::nop,  Self::sre,  Self::nop, Self::eor, Self::lsr, Self::sre, Self::pha, Self::eor, Self::lsr, Self::nop, Self::jmp, Self::eor, Self::lsr, Self::sre, /* 4 */
-	/* 5 */      Self::bvc, Self::eor,  Self::nop,  Self::sre,  Self::nop, Self::eor, Self::lsr, Self::sre, Self::cli, Self::eor, Self::nop, Self::sre, Self::nop, Self::eor, Self::lsr, Self::sre, /* 5 */
-	/* 6 */      Self::rts, Self::adc,  Self::nop,  Self::rra,  Self::nop, Self::adc, Self::ror, Self::rra, Self::pla, Self::adc, Self::ror, Self::nop, Self::jmp, Self::adc, Self::ror, Self::rra, /* 6 */
-	/* 7 */      Self::bvs, Self::adc,  Self::nop,  Self::rra,  Self::nop, Self::adc, Self::ror, Self::rra, Self::sei, Self::adc, Self::nop, Self::rra, Self::nop, Self::adc, Self::ror, Self::rra, /* 7 */
-	/* 8 */      Self::nop, Self::sta,  Self::nop,  Self::sax,  Self::sty, Self::sta, Self::stx, Self::sax, Self::dey, Self::nop, Self::txa, Self::nop, Self::sty, Self::sta, Self::stx, Self::sax, /* 8 */
-	/* 9 */      Self::bcc, Self::sta,  Self::nop,  Self::nop,  Self::sty, Self::sta, Self::stx, Self::sax, Self::tya, Self::sta, Self::txs, Self::nop, Self::nop, Self::sta, Self::nop, Self::nop, /* 9 */
-	/* A */      Self::ldy, Self::lda,  Self::ldx,  Self::lax,  Self::ldy, Self::lda, Self::ldx, Self::lax, Self::tay, Self::lda, Self::tax, Self::nop, Self::ldy, Self::lda, Self::ldx, Self::lax, /* A */
-	/* B */      Self::bcs, Self::lda,  Self::nop,  Self::lax,  Self::ldy, Self::lda, Self::ldx, Self::lax, Self::clv, Self::lda, Self::tsx, Self::lax, Self::ldy, Self::lda, Self::ldx, Self::lax, /* B */
-	/* C */      Self::cpy, Self::cmp,  Self::nop,  Self::dcp,  Self::cpy, Self::cmp, Self::dec, Self::dcp, Self::iny, Self::cmp, Self::dex, Self::nop, Self::cpy, Self::cmp, Self::dec, Self::dcp, /* C */
-	/* D */      Self::bne, Self::cmp,  Self::nop,  Self::dcp,  Self::nop, Self::cmp, Self::dec, Self::dcp, Self::cld, Self::cmp, Self::nop, Self::dcp, Self::nop, Self::cmp, Self::dec, Self::dcp, /* D */
-	/* E */      Self::cpx, Self::sbc,  Self::nop,  Self::isb,  Self::cpx, Self::sbc, Self::inc, Self::isb, Self::inx, Self::sbc, Self::nop, Self::sbc, Self::cpx, Self::sbc, Self::inc, Self::isb, /* E */
-	/* F */      Self::beq, Self::sbc,  Self::nop,  Self::isb,  Self::nop, Self::sbc, Self::inc, Self::isb, Self::sed, Self::sbc, Self::nop, Self::isb, Self::nop, Self::sbc, Self::inc, Self::isb  /* F */
-        ];
+        let addrtable = ADDRESSING_MODES.map(Self::addressing_fn);
+        let optable = MNEMONICS.map(Self::operation_fn);
 
         Self {
             a: 0,
@@ -268,15 +506,17 @@ impl<T: Bus> MOS6502<T> {
         self.pc = (bus.cpu_read(0xFFFA) as u16) | ((bus.cpu_read(0xFFFB) as u16) << 8);
     }
 
-    pub fn irq6502(&mut self, bus: &mut T) {
+    /// Returns whether the IRQ was taken, which it isn't while they're inhibited.
+    pub fn irq6502(&mut self, bus: &mut T) -> bool {
         if self.status.interrupt_inhibit() {
-            return;
+            return false;
         }
         
         self.push_stack16(self.pc, bus);
         self.push_stack8(self.status.0, bus);
         self.status.set_interrupt_inhibit(true);
         self.pc = (bus.cpu_read(0xFFFE) as u16) | ((bus.cpu_read(0xFFFF) as u16) << 8);
+        true
     }
 
     pub fn step(&mut self, bus: &mut T) {
@@ -321,6 +561,14 @@ impl<T: Bus> MOS6502<T> {
         }
     }
 
+    /// Read-modify-write instructions write the unmodified value back before the result.
+    fn modify_value(&mut self, value: u8, result: u8, operand: &OperandType, bus: &mut T) {
+        if let OperandType::Memory(ea) = operand {
+            bus.cpu_write(*ea, value);
+        }
+        self.put_value(result, operand, bus);
+    }
+
     fn signcalc(&mut self, value: u8) {
         self.status.set_negative((value & 0x80) != 0);
     }
@@ -387,7 +635,7 @@ impl<T: Bus> MOS6502<T> {
         self.zerocalc(result);
         self.signcalc(result);
 
-        self.put_value(result, operand, bus);
+        self.modify_value(value, result, operand, bus);
     }
 
     fn branch(&mut self, operand: &OperandType, _bus: &mut T) {
@@ -523,7 +771,7 @@ impl<T: Bus> MOS6502<T> {
 
         self.zerocalc(result);
         self.signcalc(result);
-        self.put_value(result, operand, bus);
+        self.modify_value(value, result, operand, bus);
     }
 
     fn dex(&mut self, _operand: &OperandType, _bus: &mut T) {
@@ -556,7 +804,7 @@ impl<T: Bus> MOS6502<T> {
         self.zerocalc(result);
         self.signcalc(result);
 
-        self.put_value(result, operand, bus);
+        self.modify_value(value, result, operand, bus);
     }
 
     fn inx(&mut self, _operand: &OperandType, _bus: &mut T) {
@@ -624,7 +872,7 @@ impl<T: Bus> MOS6502<T> {
         self.zerocalc(result);
         self.signcalc(result);
 
-        self.put_value(result, operand, bus);
+        self.modify_value(value, result, operand, bus);
     }
 
     fn nop(&mut self, _operand: &OperandType, _bus: &mut T) {}
@@ -671,7 +919,7 @@ impl<T: Bus> MOS6502<T> {
         self.zerocalc(result);
         self.signcalc(result);
 
-        self.put_value(result, operand, bus);
+        self.modify_value(value, result, operand, bus);
     }
 
     fn ror(&mut self, operand: &OperandType, bus: &mut T) {
@@ -685,7 +933,7 @@ impl<T: Bus> MOS6502<T> {
         self.zerocalc(result);
         self.signcalc(result);
 
-        self.put_value(result, operand, bus);
+        self.modify_value(value, result, operand, bus);
     }
 
     fn rti(&mut self, _operand: &OperandType, bus: &mut T) {
@@ -777,6 +1025,17 @@ impl<T: Bus> MOS6502<T> {
         self.ldx(operand, bus);
     }
 
+    fn las(&mut self, operand: &OperandType, bus: &mut T) {
+        self.penaltyop = true;
+        let value = self.get_value(operand, bus) & self.sp;
+        self.a = value;
+        self.x = value;
+        self.sp = value;
+
+        self.zerocalc(value);
+        self.signcalc(value);
+    }
+
     fn sax(&mut self, operand: &OperandType, bus: &mut T) {
         self.sta(operand, bus);
         self.stx(operand, bus);
@@ -880,6 +1139,25 @@ mod tests {
         assert_eq!(cpu.pc, 0xEAEB);
     }
 
+    #[test]
+    fn test_las() {
+        let mut device = SimpleMem {
+            memory: vec![0xea; 256 * 256],
+        };
+        // LAS $0200,Y
+        device.memory[0xEAEA..0xEAED].copy_from_slice(&[0xBB, 0x00, 0x02]);
+        device.memory[0x0201] = 0xF3;
+
+        let mut cpu = MOS6502::new();
+        cpu.reset(&mut device);
+        cpu.y = 1;
+        cpu.sp = 0x3F;
+
+        cpu.step(&mut device);
+        assert_eq!((cpu.a, cpu.x, cpu.sp), (0x33, 0x33, 0x33));
+        assert_eq!(cpu.pc, 0xEAED);
+    }
+
     #[test]
     fn dorman_tests() {
         let memory = std::fs::read("dorman/6502_functional_test.bin").unwrap();
@@ -0,0 +1,399 @@
+use alloc::vec::Vec;
+
+use crate::{
+    cartridge::CartMemory,
+    disassembler::{Mnemonic, MNEMONICS},
+    nes001::{Access, NES001},
+};
+
+/// Limits a breakpoint to when a certain bank is mapped at the address, for code that's
+/// switched in and out of the same addresses.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct BankCondition {
+    pub memory: CartMemory,
+    /// In bytes, like 0x4000 for 16KB PRG-ROM banks.
+    pub size: usize,
+    pub bank: usize,
+}
+
+impl BankCondition {
+    fn matches(&self, nes: &NES001, address: u16) -> bool {
+        nes.cpu_mapping(address).is_some_and(|location| {
+            location.memory == self.memory && location.bank(self.size) == self.bank
+        })
+    }
+}
+
+/// Stops on executing, reading or writing a range of CPU addresses.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Breakpoint {
+    pub start: u16,
+    /// The last address covered, inclusive.
+    pub end: u16,
+    pub execute: bool,
+    pub read: bool,
+    pub write: bool,
+    pub bank: Option<BankCondition>,
+    pub enabled: bool,
+}
+
+impl Breakpoint {
+    pub fn execute(address: u16) -> Self {
+        Self {
+            start: address,
+            end: address,
+            execute: true,
+            read: false,
+            write: false,
+            bank: None,
+            enabled: true,
+        }
+    }
+
+    pub fn read(start: u16, end: u16) -> Self {
+        Self {
+            start,
+            end,
+            execute: false,
+            read: true,
+            ..Self::execute(start)
+        }
+    }
+
+    pub fn write(start: u16, end: u16) -> Self {
+        Self {
+            start,
+            end,
+            execute: false,
+            write: true,
+            ..Self::execute(start)
+        }
+    }
+
+    pub fn in_bank(self, bank: BankCondition) -> Self {
+        Self {
+            bank: Some(bank),
+            ..self
+        }
+    }
+
+    fn covers(&self, nes: &NES001, address: u16) -> bool {
+        self.enabled
+            && (self.start..=self.end).contains(&address)
+            && self.bank.is_none_or(|bank| bank.matches(nes, address))
+    }
+}
+
+/// Stops on the CPU reading or writing a range of PPU addresses through PPUDATA.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Watchpoint {
+    pub start: u16,
+    /// The last address covered, inclusive.
+    pub end: u16,
+    pub read: bool,
+    pub write: bool,
+    pub enabled: bool,
+}
+
+impl Watchpoint {
+    fn covers(&self, address: u16) -> bool {
+        self.enabled && (self.start..=self.end).contains(&address)
+    }
+}
+
+/// Events to stop on, besides breakpoints.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct BreakOn {
+    pub nmi: bool,
+    pub irq: bool,
+    pub brk: bool,
+    /// Opcodes that lock up a real 6502, which the emulator runs as NOPs.
+    pub jam: bool,
+}
+
+/// How far to run.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Step {
+    /// One instruction, following JSRs and interrupts.
+    Into,
+    /// One instruction, running a JSR's subroutine until it returns.
+    Over,
+    /// Until the current subroutine or interrupt handler returns.
+    Out,
+    /// Until the first instruction on the scanline, with -1 for the pre-render one.
+    Scanline(i16),
+    /// Until the end of the frame, then the frames after it.
+    Frames(u32),
+}
+
+/// Why `Debugger::run` stopped.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Stop {
+    /// The step finished.
+    Done,
+    /// A breakpoint, with the access that set it off unless it was for executing.
+    Breakpoint(usize, Option<Access>),
+    Watchpoint(usize, Access),
+    Nmi,
+    Irq,
+    Brk,
+    Jam,
+    /// Stepping over or out of a subroutine, or to a scanline, took too long.
+    Timeout,
+}
+
+/// Runs the console an instruction at a time, stopping on breakpoints, watchpoints and events.
+/// The console stops just before the CPU starts an instruction, or at the end of a frame when
+/// advancing by frames, and `NES001::tick_frame` finishes a frame that was stopped partway.
+#[derive(Clone, Debug)]
+pub struct Debugger {
+    pub breakpoints: Vec<Breakpoint>,
+    pub watchpoints: Vec<Watchpoint>,
+    pub break_on: BreakOn,
+    /// How many frames stepping over, out or to a scanline can take before giving up.
+    pub timeout_frames: u32,
+}
+
+impl Default for Debugger {
+    fn default() -> Self {
+        Self {
+            breakpoints: Vec::new(),
+            watchpoints: Vec::new(),
+            break_on: BreakOn::default(),
+            timeout_frames: 600,
+        }
+    }
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs until the step is done or something stops it. The instruction the console is
+    /// stopped at always runs, even with a breakpoint on it, so running again carries on.
+    pub fn run<T: FnMut(i16)>(
+        &self,
+        nes: &mut NES001,
+        step: Step,
+        waveout_callback: &mut T,
+        framebuffer: &mut [u32],
+    ) -> Stop {
+        let start = nes.registers();
+        let return_address = match step {
+            Step::Over => {
+                let instruction = &nes.disassemble(start.pc, 1)[0];
+                (instruction.mnemonic == Mnemonic::JSR).then(|| instruction.next_address())
+            }
+            _ => None,
+        };
+        let mut left_scanline = false;
+        let mut frames = 0;
+        let mut resuming = nes.at_instruction();
+
+        loop {
+            if let Step::Scanline(scanline) = step {
+                left_scanline |= nes.position().0 != scanline;
+            }
+
+            let mut returning = false;
+            if nes.at_instruction() {
+                if !resuming {
+                    if let Some(stop) = self.check_instruction(nes) {
+                        return stop;
+                    }
+                    let registers = nes.registers();
+                    let done = match step {
+                        Step::Into => true,
+                        Step::Over => return_address.is_none_or(|address| {
+                            registers.pc == address && registers.sp >= start.sp
+                        }),
+                        Step::Scanline(scanline) => left_scanline && nes.position().0 == scanline,
+                        Step::Out | Step::Frames(_) => false,
+                    };
+                    if done {
+                        return Stop::Done;
+                    }
+                }
+                let opcode = nes.peek(nes.registers().pc);
+                returning = matches!(MNEMONICS[opcode as usize], Mnemonic::RTS | Mnemonic::RTI);
+            }
+            resuming = false;
+
+            let trace = nes.tick_instruction(waveout_callback, framebuffer);
+            for &access in &trace.accesses {
+                if let Some(stop) = self.check_access(nes, access) {
+                    return stop;
+                }
+            }
+            if trace.nmi && self.break_on.nmi {
+                return Stop::Nmi;
+            }
+            if trace.irq && self.break_on.irq {
+                return Stop::Irq;
+            }
+            if returning && step == Step::Out && nes.registers().sp > start.sp {
+                return Stop::Done;
+            }
+
+            if trace.frame_end {
+                frames += 1;
+                match step {
+                    Step::Frames(count) if frames >= count => return Stop::Done,
+                    Step::Frames(_) => {}
+                    _ if frames > self.timeout_frames => return Stop::Timeout,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn check_instruction(&self, nes: &NES001) -> Option<Stop> {
+        let pc = nes.registers().pc;
+        if let Some(index) = self
+            .breakpoints
+            .iter()
+            .position(|breakpoint| breakpoint.execute && breakpoint.covers(nes, pc))
+        {
+            return Some(Stop::Breakpoint(index, None));
+        }
+
+        let opcode = nes.peek(pc);
+        match MNEMONICS[opcode as usize] {
+            Mnemonic::BRK if self.break_on.brk => Some(Stop::Brk),
+            Mnemonic::JAM if self.break_on.jam => Some(Stop::Jam),
+            _ => None,
+        }
+    }
+
+    fn check_access(&self, nes: &NES001, access: Access) -> Option<Stop> {
+        let breakpoint = |matches: &dyn Fn(&Breakpoint) -> bool| {
+            self.breakpoints
+                .iter()
+                .position(matches)
+                .map(|index| Stop::Breakpoint(index, Some(access)))
+        };
+        let watchpoint = |matches: &dyn Fn(&Watchpoint) -> bool| {
+            self.watchpoints
+                .iter()
+                .position(matches)
+                .map(|index| Stop::Watchpoint(index, access))
+        };
+
+        match access {
+            Access::Read(address, _) => breakpoint(&|b| b.read && b.covers(nes, address)),
+            Access::Write(address, _) => breakpoint(&|b| b.write && b.covers(nes, address)),
+            Access::PpuRead(address, _) => watchpoint(&|w| w.read && w.covers(address)),
+            Access::PpuWrite(address, _) => watchpoint(&|w| w.write && w.covers(address)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BankCondition, Breakpoint, Debugger, Step, Stop, Watchpoint};
+    use crate::{
+        cartridge::CartMemory,
+        ines::test_helpers::TestRom,
+        nes001::{Access, NES001},
+    };
+
+    /// NROM-128 calling a subroutine in a loop. NMIs stay off.
+    fn looping_rom() -> NES001 {
+        #[rustfmt::skip]
+        let rom = TestRom::new(0, 1, 1)
+            .prg_with(|_| 0xEA)
+            .code(0xC000, &[
+                0xA2, 0x00,       // $C000: LDX #$00
+                0x20, 0x10, 0xC0, // $C002: JSR $C010
+                0x8D, 0x07, 0x20, // $C005: STA $2007
+                0x4C, 0x02, 0xC0, // $C008: JMP $C002
+            ])
+            .code(0xC010, &[
+                0xE8,             // $C010: INX
+                0x8A,             // $C011: TXA
+                0x60,             // $C012: RTS
+            ])
+            .vectors(0xC000, 0xC000, 0xC000);
+        rom.nes()
+    }
+
+    fn run(debugger: &Debugger, nes: &mut NES001, step: Step) -> Stop {
+        let mut framebuffer = vec![0; 256 * 240];
+        debugger.run(nes, step, &mut |_| {}, &mut framebuffer)
+    }
+
+    #[test]
+    fn test_breakpoints_and_stepping() {
+        let mut nes = looping_rom();
+        let mut debugger = Debugger::new();
+        // The only bank is bank 0, so this one never stops
+        debugger
+            .breakpoints
+            .push(Breakpoint::execute(0xC010).in_bank(BankCondition {
+                memory: CartMemory::PrgRom,
+                size: 0x4000,
+                bank: 1,
+            }));
+        debugger.breakpoints.push(Breakpoint::execute(0xC010));
+
+        assert_eq!(
+            run(&debugger, &mut nes, Step::Frames(1)),
+            Stop::Breakpoint(1, None)
+        );
+        assert_eq!(nes.registers().pc, 0xC010);
+        // Resuming from a breakpoint runs its instruction
+        assert_eq!(run(&debugger, &mut nes, Step::Into), Stop::Done);
+        assert_eq!(nes.registers().pc, 0xC011);
+        assert_eq!(run(&debugger, &mut nes, Step::Out), Stop::Done);
+        assert_eq!(nes.registers().pc, 0xC005);
+        assert_eq!(run(&debugger, &mut nes, Step::Into), Stop::Done);
+        assert_eq!(run(&debugger, &mut nes, Step::Into), Stop::Done);
+        assert_eq!(nes.registers().pc, 0xC002);
+
+        debugger.breakpoints.clear();
+        assert_eq!(run(&debugger, &mut nes, Step::Over), Stop::Done);
+        assert_eq!((nes.registers().pc, nes.registers().x), (0xC005, 2));
+
+        debugger.watchpoints.push(Watchpoint {
+            start: 0x0000,
+            end: 0x1FFF,
+            read: false,
+            write: true,
+            enabled: true,
+        });
+        debugger.breakpoints.push(Breakpoint::write(0x2007, 0x2007));
+        assert_eq!(
+            run(&debugger, &mut nes, Step::Into),
+            Stop::Breakpoint(0, Some(Access::Write(0x2007, 2)))
+        );
+        debugger.breakpoints.clear();
+        assert!(matches!(
+            run(&debugger, &mut nes, Step::Frames(1)),
+            Stop::Watchpoint(0, Access::PpuWrite(_, 3))
+        ));
+    }
+
+    #[test]
+    fn test_stopping_mid_frame_resumes_cleanly() {
+        let mut framebuffer = vec![0; 256 * 240];
+        let mut stepped = looping_rom();
+        let mut plain = looping_rom();
+        let debugger = Debugger::new();
+
+        assert_eq!(
+            run(&debugger, &mut stepped, Step::Scanline(100)),
+            Stop::Done
+        );
+        assert_eq!(stepped.position().0, 100);
+        run(&debugger, &mut stepped, Step::Over);
+        stepped.tick_frame(&mut |_| {}, &mut framebuffer);
+        assert_eq!(stepped.position(), (-1, 0));
+        assert_eq!(run(&debugger, &mut stepped, Step::Frames(1)), Stop::Done);
+
+        plain.tick_frame(&mut |_| {}, &mut framebuffer);
+        plain.tick_frame(&mut |_| {}, &mut framebuffer);
+        assert_eq!(stepped.registers(), plain.registers());
+        assert_eq!(stepped.ram(), plain.ram());
+    }
+}
@@ -0,0 +1,372 @@
+use alloc::{collections::BTreeMap, format, string::String, vec::Vec};
+use core::fmt;
+
+pub use crate::cpu::{AddressingMode, Mnemonic, ADDRESSING_MODES, MNEMONICS};
+
+/// One decoded instruction.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u8,
+    pub mnemonic: Mnemonic,
+    pub mode: AddressingMode,
+    /// The operand bytes as a little-endian number, 0 if there aren't any.
+    pub operand: u16,
+}
+
+impl Instruction {
+    /// Decodes the instruction at `address`, reading its bytes with `read`.
+    pub fn decode(address: u16, mut read: impl FnMut(u16) -> u8) -> Self {
+        let opcode = read(address);
+        let mode = ADDRESSING_MODES[opcode as usize];
+        let operand = match mode.operand_len() {
+            0 => 0,
+            1 => read(address.wrapping_add(1)) as u16,
+            _ => read(address.wrapping_add(1)) as u16 | (read(address.wrapping_add(2)) as u16) << 8,
+        };
+
+        Self {
+            address,
+            opcode,
+            mnemonic: MNEMONICS[opcode as usize],
+            mode,
+            operand,
+        }
+    }
+
+    /// The size of the instruction in bytes.
+    pub fn size(&self) -> u16 {
+        1 + self.mode.operand_len()
+    }
+
+    /// The instruction's bytes, as they are in memory.
+    pub fn bytes(&self) -> Vec<u8> {
+        let [lo, hi] = self.operand.to_le_bytes();
+        [self.opcode, lo, hi][..self.size() as usize].to_vec()
+    }
+
+    /// The address of the next instruction in memory.
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.size())
+    }
+
+    /// Whether the opcode is missing from the 6502's documentation, which includes the extra
+    /// NOPs and the second SBC #imm.
+    pub fn unofficial(&self) -> bool {
+        match self.mnemonic {
+            Mnemonic::NOP => self.opcode != 0xEA,
+            Mnemonic::SBC => self.opcode == 0xEB,
+            Mnemonic::AHX
+            | Mnemonic::ALR
+            | Mnemonic::ANC
+            | Mnemonic::ARR
+            | Mnemonic::AXS
+            | Mnemonic::DCP
+            | Mnemonic::ISB
+            | Mnemonic::JAM
+            | Mnemonic::LAS
+            | Mnemonic::LAX
+            | Mnemonic::LXA
+            | Mnemonic::RLA
+            | Mnemonic::RRA
+            | Mnemonic::SAX
+            | Mnemonic::SHX
+            | Mnemonic::SHY
+            | Mnemonic::SLO
+            | Mnemonic::SRE
+            | Mnemonic::TAS
+            | Mnemonic::XAA => true,
+            _ => false,
+        }
+    }
+
+    /// The address named by the operand, before indexing. Branches give their destination.
+    pub fn target(&self) -> Option<u16> {
+        match self.mode {
+            AddressingMode::Imp | AddressingMode::Acc | AddressingMode::Imm => None,
+            AddressingMode::Rel => Some(
+                self.next_address()
+                    .wrapping_add(self.operand as u8 as i8 as u16),
+            ),
+            _ => Some(self.operand),
+        }
+    }
+
+    /// The operand in assembler syntax, with addresses replaced by labels from `symbols`.
+    pub fn operand_text(&self, symbols: Option<&Symbols>) -> String {
+        let address = || {
+            let target = self.target().unwrap_or_default();
+            match symbols.and_then(|symbols| symbols.get(target)) {
+                Some(label) => String::from(label),
+                None if self.mode.operand_len() == 1 && self.mode != AddressingMode::Rel => {
+                    format!("${:02X}", target)
+                }
+                None => format!("${:04X}", target),
+            }
+        };
+
+        match self.mode {
+            AddressingMode::Imp => String::new(),
+            AddressingMode::Acc => String::from("A"),
+            AddressingMode::Imm => format!("#${:02X}", self.operand),
+            AddressingMode::Zp | AddressingMode::Abs | AddressingMode::Rel => address(),
+            AddressingMode::Zpx | AddressingMode::Absx => format!("{},X", address()),
+            AddressingMode::Zpy | AddressingMode::Absy => format!("{},Y", address()),
+            AddressingMode::Ind => format!("({})", address()),
+            AddressingMode::Indx => format!("({},X)", address()),
+            AddressingMode::Indy => format!("({}),Y", address()),
+        }
+    }
+
+    /// The instruction in assembler syntax, like `LDA ($10),Y`.
+    pub fn text(&self, symbols: Option<&Symbols>) -> String {
+        let operand = self.operand_text(symbols);
+        if operand.is_empty() {
+            format!("{:?}", self.mnemonic)
+        } else {
+            format!("{:?} {}", self.mnemonic, operand)
+        }
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text(None))
+    }
+}
+
+/// Decodes `count` instructions in a row starting at `address`.
+pub fn disassemble_with(
+    address: u16,
+    count: usize,
+    mut read: impl FnMut(u16) -> u8,
+) -> Vec<Instruction> {
+    let mut instructions = Vec::with_capacity(count);
+    let mut address = address;
+    for _ in 0..count {
+        let instruction = Instruction::decode(address, &mut read);
+        address = instruction.next_address();
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// Decodes a block of code, like a PRG-ROM bank, that's mapped at `origin`. An instruction cut
+/// off by the end of the block is left out.
+pub fn disassemble(code: &[u8], origin: u16) -> Vec<Instruction> {
+    let mut instructions = Vec::new();
+    let mut offset = 0;
+    while offset < code.len() {
+        let instruction = Instruction::decode(origin.wrapping_add(offset as u16), |address| {
+            code.get(address.wrapping_sub(origin) as usize)
+                .copied()
+                .unwrap_or(0)
+        });
+        offset += instruction.size() as usize;
+        if offset > code.len() {
+            break;
+        }
+        instructions.push(instruction);
+    }
+    instructions
+}
+
+/// Formats instructions as a listing with addresses and bytes, putting labels on lines of their
+/// own. Unofficial opcodes are marked with a `*`.
+pub fn listing(instructions: &[Instruction], symbols: Option<&Symbols>) -> String {
+    let mut text = String::new();
+    for instruction in instructions {
+        if let Some(label) = symbols.and_then(|symbols| symbols.get(instruction.address)) {
+            text += &format!("{}:\n", label);
+        }
+        let bytes: Vec<String> = instruction
+            .bytes()
+            .iter()
+            .map(|byte| format!("{:02X}", byte))
+            .collect();
+        text += &format!(
+            "{:04X}  {:<8} {}{}\n",
+            instruction.address,
+            bytes.join(" "),
+            if instruction.unofficial() { '*' } else { ' ' },
+            instruction.text(symbols)
+        );
+    }
+    text
+}
+
+/// Labels for CPU addresses.
+#[derive(Clone, Default, Debug)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+}
+
+impl Symbols {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a symbol file. Each line can be a VICE label from `ld65 -Ln` (`al 00C000 .reset`), an
+    /// FCEUX `.nl` entry (`$C000#reset#comment`) or an assignment (`reset = $C000`). Blank lines
+    /// and ones starting with `;` are skipped.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut symbols = Self::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            let (address, label) = parse_symbol(line)
+                .ok_or_else(|| anyhow::anyhow!("Line {}: {} isn't a symbol", number + 1, line))?;
+            symbols.insert(address, label);
+        }
+        Ok(symbols)
+    }
+
+    pub fn insert(&mut self, address: u16, label: &str) {
+        self.labels.insert(address, String::from(label));
+    }
+
+    pub fn get(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(String::as_str)
+    }
+
+    /// The address of a label, for looking up symbols by name.
+    pub fn address(&self, label: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(_, name)| name.as_str() == label)
+            .map(|(&address, _)| address)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.labels.is_empty()
+    }
+}
+
+fn parse_address(hex: &str) -> Option<u16> {
+    let value = u32::from_str_radix(hex.trim().trim_start_matches('$'), 16).ok()?;
+    u16::try_from(value).ok()
+}
+
+fn parse_symbol(line: &str) -> Option<(u16, &str)> {
+    if let Some(rest) = line.strip_prefix("al ") {
+        let (address, label) = rest.trim().split_once(char::is_whitespace)?;
+        return Some((
+            parse_address(address)?,
+            label.trim().trim_start_matches('.'),
+        ));
+    }
+    if line.starts_with('$') && line.contains('#') {
+        let mut fields = line.split('#');
+        let address = parse_address(fields.next()?)?;
+        return Some((address, fields.next().filter(|label| !label.is_empty())?));
+    }
+    let (label, address) = line.split_once('=')?;
+    let label = label.trim().trim_end_matches(':').trim();
+    let address = address.trim();
+    if label.is_empty() || !address.starts_with('$') {
+        return None;
+    }
+    Some((parse_address(address)?, label))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, listing, AddressingMode, Instruction, Symbols, MNEMONICS};
+    use crate::{bus::Bus, cpu::MOS6502, disassembler::Mnemonic};
+
+    struct FlatMemory([u8; 0x10000]);
+
+    impl Bus for FlatMemory {
+        fn cpu_read(&mut self, address: u16) -> u8 {
+            self.0[address as usize]
+        }
+
+        fn cpu_write(&mut self, address: u16, value: u8) {
+            self.0[address as usize] = value;
+        }
+    }
+
+    #[test]
+    fn test_sizes_match_cpu() {
+        let mut memory = FlatMemory([0; 0x10000]);
+        for opcode in 0..=255_u8 {
+            let instruction = Instruction::decode(0x0200, |_| opcode);
+            if matches!(
+                instruction.mnemonic,
+                Mnemonic::BRK | Mnemonic::JMP | Mnemonic::JSR | Mnemonic::RTI | Mnemonic::RTS
+            ) || instruction.mode == AddressingMode::Rel
+            {
+                continue;
+            }
+            memory.0[0x0200] = opcode;
+            let mut cpu = MOS6502::new();
+            cpu.pc = 0x0200;
+            cpu.step(&mut memory);
+            assert_eq!(cpu.pc, 0x0200 + instruction.size(), "opcode {:02X}", opcode);
+        }
+    }
+
+    #[test]
+    fn test_operands() {
+        let code = [
+            0xA9, 0x10, // LDA #$10
+            0xB5, 0x20, // LDA $20,X
+            0xB6, 0x21, // LDX $21,Y
+            0xBD, 0x34, 0x12, // LDA $1234,X
+            0x6C, 0xFC, 0xFF, // JMP ($FFFC)
+            0xA1, 0x40, // LDA ($40,X)
+            0xB1, 0x41, // LDA ($41),Y
+            0x0A, // ASL A
+            0xD0, 0xF0, // BNE $8003
+            0x02, // JAM
+        ];
+        let instructions = disassemble(&code, 0x8000);
+        let text: Vec<_> = instructions.iter().map(|i| i.to_string()).collect();
+        assert_eq!(
+            text,
+            [
+                "LDA #$10",
+                "LDA $20,X",
+                "LDX $21,Y",
+                "LDA $1234,X",
+                "JMP ($FFFC)",
+                "LDA ($40,X)",
+                "LDA ($41),Y",
+                "ASL A",
+                "BNE $8003",
+                "JAM"
+            ]
+        );
+        assert_eq!(instructions[8].target(), Some(0x8003));
+        assert!(instructions[9].unofficial());
+        assert_eq!(
+            MNEMONICS.iter().filter(|&&m| m == Mnemonic::JAM).count(),
+            12
+        );
+    }
+
+    #[test]
+    fn test_symbols() {
+        let symbols = Symbols::parse(
+            "; labels\n\
+             al 008000 .reset\n\
+             $0010#pointer#Where to read from\n\
+             PPUCTRL = $2000\n",
+        )
+        .unwrap();
+        assert_eq!(symbols.address("pointer"), Some(0x10));
+
+        let code = [0x8D, 0x00, 0x20, 0xB1, 0x10, 0xC7, 0x11, 0x4C, 0x00, 0x80];
+        assert_eq!(
+            listing(&disassemble(&code, 0x8000), Some(&symbols)),
+            "reset:\n\
+             8000  8D 00 20  STA PPUCTRL\n\
+             8003  B1 10     LDA (pointer),Y\n\
+             8005  C7 11    *DCP $11\n\
+             8007  4C 00 80  JMP reset\n"
+        );
+        assert!(Symbols::parse("nonsense").is_err());
+    }
+}
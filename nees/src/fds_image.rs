@@ -0,0 +1,161 @@
+use alloc::{vec, vec::Vec};
+
+/// The size of a side in the .fds layout.
+pub const SIDE_SIZE: usize = 65500;
+/// QD images store each side as the raw Quick Disk contents, CRCs included.
+const QD_SIDE_SIZE: usize = 65536;
+
+const FDS_HEADER: &[u8] = b"FDS\x1A";
+const DISK_INFO_MAGIC: &[u8] = b"\x01*NINTENDO-HVC*";
+
+/// 28300 bits of gap before the first block, and 976 bits between blocks.
+const LEADING_GAP: usize = 28300 / 8;
+const BLOCK_GAP: usize = 976 / 8;
+/// Each block on disk starts with this mark after its gap.
+const BLOCK_START_MARK: u8 = 0x80;
+
+/// A Famicom Disk System image, either in the .fds layout (with or without the fwNES header)
+/// or a QD dump. The sides are kept in the .fds layout: blocks back to back, without gaps or
+/// CRCs.
+#[allow(clippy::upper_case_acronyms)]
+pub struct FDSImage {
+    pub sides: Vec<Vec<u8>>,
+}
+
+impl FDSImage {
+    pub fn new(data: &[u8]) -> anyhow::Result<Self> {
+        let data = if data.starts_with(FDS_HEADER) {
+            data.get(16..)
+                .ok_or_else(|| anyhow::anyhow!("The fwNES header is cut short"))?
+        } else {
+            data
+        };
+        if !data.starts_with(DISK_INFO_MAGIC) {
+            anyhow::bail!("Not a Famicom Disk System image");
+        }
+
+        let is_qd = data.len() % SIDE_SIZE != 0 && data.len() % QD_SIDE_SIZE == 0;
+        let sides = if is_qd {
+            data.chunks(QD_SIDE_SIZE).map(strip_crcs).collect()
+        } else {
+            data.chunks(SIDE_SIZE)
+                .map(|side| {
+                    let mut side = side.to_vec();
+                    side.resize(SIDE_SIZE, 0);
+                    side
+                })
+                .collect()
+        };
+
+        Ok(Self { sides })
+    }
+}
+
+/// The length of the block at the start of `data`, or None once the blocks run out. File data
+/// blocks take their size from the file header block before them.
+fn block_length(data: &[u8], file_size: usize) -> Option<usize> {
+    match data.first()? {
+        1 => Some(56),
+        2 => Some(2),
+        3 => Some(16),
+        4 => Some(1 + file_size),
+        _ => None,
+    }
+}
+
+/// Calls `f` with each block in a side, given the number of CRC bytes after each one.
+fn for_each_block(side: &[u8], crc_len: usize, mut f: impl FnMut(&[u8])) {
+    let mut pos = 0;
+    let mut file_size = 0;
+    while let Some(len) = side
+        .get(pos..)
+        .and_then(|data| block_length(data, file_size))
+    {
+        let Some(block) = side.get(pos..pos + len) else {
+            break;
+        };
+        if block[0] == 3 {
+            file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+        }
+        f(block);
+        pos += len + crc_len;
+    }
+}
+
+fn strip_crcs(side: &[u8]) -> Vec<u8> {
+    let mut output = Vec::with_capacity(SIDE_SIZE);
+    for_each_block(side, 2, |block| output.extend_from_slice(block));
+    output.resize(SIDE_SIZE, 0);
+    output
+}
+
+/// Lays out a side the way the drive sees it, with gaps, start marks and (fake) CRCs.
+pub fn side_to_raw(side: &[u8]) -> Vec<u8> {
+    let mut raw = vec![0; LEADING_GAP];
+    for_each_block(side, 0, |block| {
+        raw.push(BLOCK_START_MARK);
+        raw.extend_from_slice(block);
+        raw.extend_from_slice(&[0x4D, 0x62]);
+        raw.resize(raw.len() + BLOCK_GAP, 0);
+    });
+    // Leave room for the BIOS to append files
+    raw.resize(raw.len().max(LEADING_GAP + SIDE_SIZE), 0);
+    raw
+}
+
+/// Recovers the .fds layout of a side from the drive's view of it.
+pub fn side_from_raw(raw: &[u8]) -> Vec<u8> {
+    let mut side = Vec::with_capacity(SIDE_SIZE);
+    let mut pos = 0;
+    let mut file_size = 0;
+    loop {
+        while pos < raw.len() && raw[pos] != BLOCK_START_MARK {
+            pos += 1;
+        }
+        pos += 1;
+
+        let Some(len) = raw
+            .get(pos..)
+            .and_then(|data| block_length(data, file_size))
+        else {
+            break;
+        };
+        let Some(block) = raw.get(pos..pos + len) else {
+            break;
+        };
+        if block[0] == 3 {
+            file_size = u16::from_le_bytes([block[13], block[14]]) as usize;
+        }
+        side.extend_from_slice(block);
+        pos += len + 2;
+    }
+
+    side.resize(SIDE_SIZE, 0);
+    side
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{side_from_raw, side_to_raw, FDSImage, SIDE_SIZE};
+
+    #[test]
+    fn test_raw_round_trip() {
+        let mut side = vec![0; SIDE_SIZE];
+        side[0] = 1;
+        side[1..15].copy_from_slice(b"*NINTENDO-HVC*");
+        side[56..58].copy_from_slice(&[2, 1]);
+        side[58] = 3;
+        side[58 + 13] = 4;
+        side[74..79].copy_from_slice(&[4, 0xDE, 0xAD, 0xBE, 0xEF]);
+
+        assert_eq!(side_from_raw(&side_to_raw(&side)), side);
+    }
+
+    #[test]
+    fn test_bad_images_are_errors() {
+        assert!(FDSImage::new(b"FDS\x1A\x01").is_err());
+        assert!(FDSImage::new(b"NES\x1A").is_err());
+        let image = FDSImage::new(b"\x01*NINTENDO-HVC*").unwrap();
+        assert_eq!(image.sides.len(), 1);
+    }
+}
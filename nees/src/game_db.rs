@@ -0,0 +1,220 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
+use crate::{crc32::crc32, ines::INES, mirroring::Mirroring, sha1::sha1};
+
+/// The database built into the emulator, in the NES 2.0 header database's XML format.
+const BUILTIN_DATABASE: &str = include_str!("nes20db.xml");
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Region {
+    NTSC,
+    PAL,
+    /// Works on both NTSC and PAL consoles.
+    Multiple,
+    Dendy,
+}
+
+/// What a database knows about a dump: its title and the header it should have had.
+#[derive(Clone, Debug)]
+pub struct GameInfo {
+    pub title: String,
+    /// Of the PRG-ROM followed by the CHR-ROM.
+    pub crc32: u32,
+    pub sha1: Option<[u8; 20]>,
+    pub mapper_no: u16,
+    pub submapper: u8,
+    /// None for boards where the mapper controls it.
+    pub mirroring: Option<Mirroring>,
+    pub has_battery: bool,
+    pub prg_ram_size: usize,
+    pub prg_nvram_size: usize,
+    pub chr_ram_size: usize,
+    pub region: Region,
+}
+
+impl GameInfo {
+    /// Overrides the parts of a header the database knows better.
+    pub fn apply(&self, ines: &mut INES) {
+        ines.mapper_no = self.mapper_no;
+        ines.submapper = Some(self.submapper);
+        ines.has_battery = self.has_battery;
+        ines.prg_ram_size = Some(self.prg_ram_size);
+        ines.prg_nvram_size = Some(self.prg_nvram_size);
+        match self.mirroring {
+            Some(Mirroring::FourScreen) => ines.four_screen = true,
+            Some(mirroring) => {
+                ines.mirroring = mirroring;
+                ines.four_screen = false;
+            }
+            None => {}
+        }
+        if ines.is_chr_ram && self.chr_ram_size > 0 {
+            ines.chr_rom = vec![0; self.chr_ram_size];
+        }
+    }
+}
+
+/// Known dumps, looked up by the hashes of their ROM data so bad headers can be fixed.
+pub struct GameDatabase {
+    games: Vec<GameInfo>,
+}
+
+/// The value of an attribute in the first tag with the given name.
+fn attribute<'a>(xml: &'a str, tag: &str, name: &str) -> Option<&'a str> {
+    let start = xml.find(&["<", tag, " "].concat())?;
+    let element = &xml[start..start + xml[start..].find('>')?];
+    let value_start = element.find(&[" ", name, "=\""].concat())? + name.len() + 3;
+    let value_len = element[value_start..].find('"')?;
+    Some(&element[value_start..value_start + value_len])
+}
+
+fn number(xml: &str, tag: &str, name: &str) -> Option<usize> {
+    attribute(xml, tag, name)?.parse().ok()
+}
+
+fn parse_sha1(hex: &str) -> Option<[u8; 20]> {
+    let mut digest = [0; 20];
+    if hex.len() != 40 {
+        return None;
+    }
+    for (i, byte) in digest.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+    }
+    Some(digest)
+}
+
+/// The database names each game in a comment with its file path, like
+/// `<!-- 001\Super Mario Bros. (World).nes -->`.
+fn title_from_comment(before_game: &str) -> String {
+    let Some(comment) = before_game
+        .trim_end()
+        .strip_suffix("-->")
+        .and_then(|text| text.rsplit_once("<!--"))
+        .map(|(_, comment)| comment.trim())
+    else {
+        return String::new();
+    };
+
+    let file_name = comment.rsplit(['\\', '/']).next().unwrap_or(comment);
+    file_name
+        .rsplit_once('.')
+        .map_or(file_name, |(name, _)| name)
+        .to_string()
+}
+
+fn parse_game(game: &str, title: String) -> Option<GameInfo> {
+    let mirroring = match attribute(game, "pcb", "mirroring") {
+        Some("H") => Some(Mirroring::Horizontal),
+        Some("V") => Some(Mirroring::Vertical),
+        Some("4") => Some(Mirroring::FourScreen),
+        _ => None,
+    };
+    let region = match number(game, "console", "region") {
+        Some(1) => Region::PAL,
+        Some(2) => Region::Multiple,
+        Some(3) => Region::Dendy,
+        _ => Region::NTSC,
+    };
+
+    Some(GameInfo {
+        title,
+        crc32: u32::from_str_radix(attribute(game, "rom", "crc32")?, 16).ok()?,
+        sha1: attribute(game, "rom", "sha1").and_then(parse_sha1),
+        mapper_no: number(game, "pcb", "mapper")? as u16,
+        submapper: number(game, "pcb", "submapper").unwrap_or(0) as u8,
+        mirroring,
+        has_battery: number(game, "pcb", "battery") == Some(1),
+        prg_ram_size: number(game, "prgram", "size").unwrap_or(0),
+        prg_nvram_size: number(game, "prgnvram", "size").unwrap_or(0),
+        chr_ram_size: number(game, "chrram", "size").unwrap_or(0),
+        region,
+    })
+}
+
+impl GameDatabase {
+    /// The database compiled into the emulator.
+    pub fn builtin() -> Self {
+        Self::from_nes20db(BUILTIN_DATABASE)
+    }
+
+    /// Reads the NES 2.0 header database (nes20db.xml), which covers the No-Intro set. Games
+    /// missing the ROM hash or mapper are skipped.
+    pub fn from_nes20db(xml: &str) -> Self {
+        let mut games = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find("<game>") {
+            let Some(len) = rest[start..].find("</game>") else {
+                break;
+            };
+            let title = title_from_comment(&rest[..start]);
+            games.extend(parse_game(&rest[start..start + len], title));
+            rest = &rest[start + len..];
+        }
+
+        Self { games }
+    }
+
+    pub fn len(&self) -> usize {
+        self.games.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.games.is_empty()
+    }
+
+    /// Finds a dump by the CRC-32 of its PRG and CHR-ROM, confirmed by SHA-1 when the database
+    /// has it.
+    pub fn find(&self, ines: &INES) -> Option<&GameInfo> {
+        let mut rom = ines.prg_rom.clone();
+        if !ines.is_chr_ram {
+            rom.extend_from_slice(&ines.chr_rom);
+        }
+        let crc = crc32(&rom);
+
+        let mut digest = None;
+        self.games.iter().find(|game| {
+            game.crc32 == crc
+                && game
+                    .sha1
+                    .is_none_or(|expected| *digest.get_or_insert_with(|| sha1(&rom)) == expected)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GameDatabase, Region};
+    use crate::{crc32::crc32, ines::test_helpers::TestRom, mirroring::Mirroring};
+
+    #[test]
+    fn test_header_override() {
+        // A plain iNES header claiming NROM with horizontal mirroring
+        let rom = TestRom::new(0, 1, 1).prg_with(|offset| offset as u8);
+        let crc = crc32(&rom.build()[16..]);
+
+        let xml = format!(
+            "<nes20db>\n<!-- 042\\Some Game (Europe).nes -->\n<game>\n\
+             <rom size=\"24576\" crc32=\"{:08X}\"/>\n\
+             <pcb mapper=\"3\" submapper=\"0\" mirroring=\"V\" battery=\"1\"/>\n\
+             <prgnvram size=\"8192\"/>\n<console type=\"0\" region=\"1\"/>\n</game>\n</nes20db>",
+            crc
+        );
+        let database = GameDatabase::from_nes20db(&xml);
+        assert_eq!(database.len(), 1);
+
+        let mut ines = rom.ines();
+        let game = database.find(&ines).unwrap();
+        assert_eq!(game.title, "Some Game (Europe)");
+        assert_eq!(game.region, Region::PAL);
+
+        game.apply(&mut ines);
+        assert_eq!(ines.mapper_no, 3);
+        assert_eq!(ines.mirroring, Mirroring::Vertical);
+        assert!(ines.has_battery);
+        assert_eq!(ines.prg_nvram_size, Some(8192));
+    }
+}
@@ -1,15 +1,25 @@
 use alloc::{vec, vec::Vec};
 
+use crate::{mirroring::Mirroring, unif};
+
 #[allow(clippy::upper_case_acronyms)]
 pub struct INES {
-    pub mapper_no: u8,
+    pub mapper_no: u16,
+    /// Only NES 2.0 headers carry a submapper.
+    pub submapper: Option<u8>,
+    pub has_battery: bool,
+    /// NES 2.0 PRG-RAM and battery-backed PRG-NVRAM sizes in bytes.
+    pub prg_ram_size: Option<usize>,
+    pub prg_nvram_size: Option<usize>,
     pub prg_rom_size_16k_chunks: u8,
     pub prg_rom: Vec<u8>,
 
-    pub chr_rom_size_8kb_chunks: u8,
     pub chr_rom: Vec<u8>,
     pub is_chr_ram: bool,
-    pub ppu_address_ciram_a10_shift_count: u8,
+    /// The soldered mirroring, from header flag 6 bit 0.
+    pub mirroring: Mirroring,
+    /// Header flag 6 bit 3, four-screen VRAM on most boards.
+    pub four_screen: bool,
 }
 
 struct SimpleBinaryReader<'a> {
@@ -30,74 +40,249 @@ impl<'a> SimpleBinaryReader<'a> {
 
     fn read_exact(&mut self, buf: &mut [u8]) -> Option<()> {
         let len = buf.len();
-        buf.copy_from_slice(&self.data[self.pos..self.pos + len]);
+        buf.copy_from_slice(self.data.get(self.pos..self.pos + len)?);
         self.pos += len;
         Some(())
     }
 
     fn seek_relative(&mut self, offset: usize) -> Option<()> {
+        if self.pos + offset > self.data.len() {
+            return None;
+        }
         self.pos += offset;
         Some(())
     }
 }
 
 impl INES {
-    pub fn new(rom_data: &[u8]) -> Self {
+    /// Reads an iNES or UNIF image, telling them apart by their magic.
+    pub fn from_file(data: &[u8]) -> anyhow::Result<Self> {
+        if data.starts_with(unif::UNIF_MAGIC) {
+            Self::from_unif(data)
+        } else if data.starts_with(b"NES\x1A") {
+            Self::new(data)
+        } else {
+            anyhow::bail!("Not an iNES or UNIF file")
+        }
+    }
+
+    /// Reads a UNIF (.unf) image, which names its board instead of giving a mapper number.
+    pub fn from_unif(data: &[u8]) -> anyhow::Result<Self> {
+        unif::parse(data)
+    }
+
+    pub fn new(rom_data: &[u8]) -> anyhow::Result<Self> {
         let mut f = SimpleBinaryReader::new(rom_data);
+        let short_header = || anyhow::anyhow!("The iNES header is cut short");
         let mut nesbuf = [0_u8; 4];
-        f.read_exact(&mut nesbuf)
-            .expect("Failed to read NES header");
-        assert!(nesbuf == [b'N', b'E', b'S', 0x1A]);
+        f.read_exact(&mut nesbuf).ok_or_else(short_header)?;
+        if nesbuf != [b'N', b'E', b'S', 0x1A] {
+            anyhow::bail!("Not an iNES file");
+        }
 
-        let prg_rom_size_16k_chunks = f.read_u8().unwrap();
-        let chr_rom_size_8kb_chunks = f.read_u8().unwrap();
+        let prg_rom_size_16k_chunks = f.read_u8().ok_or_else(short_header)?;
+        let chr_rom_size_8kb_chunks = f.read_u8().ok_or_else(short_header)?;
         let is_chr_ram = chr_rom_size_8kb_chunks == 0;
-        let flags6 = f.read_u8().unwrap();
-        let flags7 = f.read_u8().unwrap();
-        let _flags8 = f.read_u8().unwrap();
-        let _flags9 = f.read_u8().unwrap();
-        let _flags10 = f.read_u8().unwrap();
+        let flags6 = f.read_u8().ok_or_else(short_header)?;
+        let flags7 = f.read_u8().ok_or_else(short_header)?;
+        let flags8 = f.read_u8().ok_or_else(short_header)?;
+        let _flags9 = f.read_u8().ok_or_else(short_header)?;
+        let flags10 = f.read_u8().ok_or_else(short_header)?;
+        let flags11 = f.read_u8().ok_or_else(short_header)?;
 
-        let mut padding = [0_u8; 5];
-        f.read_exact(&mut padding).unwrap();
+        let mut padding = [0_u8; 4];
+        f.read_exact(&mut padding).ok_or_else(short_header)?;
 
         let mirroring = (flags6 & 1) == 1;
-        let _has_wram = ((flags6 >> 1) & 1) == 1;
+        let has_battery = ((flags6 >> 1) & 1) == 1;
         let has_trainer = ((flags6 >> 2) & 1) == 1;
-        let mut mapper_no = (flags7 & 0xF0) | (flags6 >> 4);
+        let four_screen = ((flags6 >> 3) & 1) == 1;
+        let is_nes2 = (flags7 & 0x0C) == 0x08;
+        let mut mapper_no = ((flags7 & 0xF0) | (flags6 >> 4)) as u16;
+        let mut submapper = None;
+        let mut prg_ram_size = None;
+        let mut prg_nvram_size = None;
+        let mut chr_ram_size = 8192;
 
-        if padding != [0, 0, 0, 0, 0] {
-            mapper_no = flags6 >> 4;
+        if is_nes2 {
+            mapper_no |= ((flags8 & 0x0F) as u16) << 8;
+            submapper = Some(flags8 >> 4);
+            prg_ram_size = Some(nes2_ram_size(flags10 & 0x0F));
+            prg_nvram_size = Some(nes2_ram_size(flags10 >> 4));
+            let chr_ram = nes2_ram_size(flags11 & 0x0F) + nes2_ram_size(flags11 >> 4);
+            if chr_ram > 0 {
+                chr_ram_size = chr_ram;
+            }
+        } else if flags11 != 0 || padding != [0, 0, 0, 0] {
+            // Old dumps have garbage where the upper mapper nibble should be
+            mapper_no = (flags6 >> 4) as u16;
         }
 
         if has_trainer {
-            f.seek_relative(512).unwrap();
+            f.seek_relative(512)
+                .ok_or_else(|| anyhow::anyhow!("The trainer is cut short"))?;
         }
 
         let mut prg_rom = vec![0; 16384 * (prg_rom_size_16k_chunks as usize)];
-        f.read_exact(&mut prg_rom).unwrap();
+        f.read_exact(&mut prg_rom)
+            .ok_or_else(|| anyhow::anyhow!("The PRG-ROM is cut short"))?;
 
         let mut chr_rom = vec![
             0;
-            8192 * (if is_chr_ram {
-                1
+            if is_chr_ram {
+                chr_ram_size
             } else {
-                chr_rom_size_8kb_chunks as usize
-            })
+                8192 * chr_rom_size_8kb_chunks as usize
+            }
         ];
         if !is_chr_ram {
-            f.read_exact(&mut chr_rom).unwrap();
+            f.read_exact(&mut chr_rom)
+                .ok_or_else(|| anyhow::anyhow!("The CHR-ROM is cut short"))?;
         }
 
-        Self {
+        Ok(Self {
             mapper_no,
+            submapper,
+            has_battery,
+            prg_ram_size,
+            prg_nvram_size,
             prg_rom_size_16k_chunks,
             prg_rom,
 
-            chr_rom_size_8kb_chunks,
             chr_rom,
             is_chr_ram,
-            ppu_address_ciram_a10_shift_count: if mirroring { 10 } else { 11 },
+            mirroring: if mirroring {
+                Mirroring::Vertical
+            } else {
+                Mirroring::Horizontal
+            },
+            four_screen,
+        })
+    }
+
+    /// The mirroring of boards without a mirroring register: four-screen when the header asks
+    /// for it, else the soldered setting.
+    pub fn fixed_mirroring(&self) -> Mirroring {
+        if self.four_screen {
+            Mirroring::FourScreen
+        } else {
+            self.mirroring
         }
     }
 }
+
+/// NES 2.0 encodes RAM sizes as a shift count, with zero meaning none.
+fn nes2_ram_size(shift: u8) -> usize {
+    if shift == 0 {
+        0
+    } else {
+        64 << shift
+    }
+}
+
+#[cfg(test)]
+pub mod test_helpers {
+    use super::INES;
+    use crate::nes001::NES001;
+
+    /// Builds iNES images for tests, with horizontal mirroring and empty PRG and CHR-ROM unless
+    /// told otherwise.
+    pub struct TestRom {
+        header: [u8; 16],
+        prg: Vec<u8>,
+        chr: Vec<u8>,
+    }
+
+    impl TestRom {
+        pub fn new(mapper_no: u8, prg_16k_chunks: u8, chr_8k_chunks: u8) -> Self {
+            let mut header = [0; 16];
+            header[..4].copy_from_slice(b"NES\x1A");
+            header[4] = prg_16k_chunks;
+            header[5] = chr_8k_chunks;
+            header[6] = mapper_no << 4;
+            header[7] = mapper_no & 0xF0;
+            Self {
+                header,
+                prg: vec![0; prg_16k_chunks as usize * 0x4000],
+                chr: vec![0; chr_8k_chunks as usize * 0x2000],
+            }
+        }
+
+        /// Makes it a NES 2.0 header with a submapper.
+        pub fn submapper(mut self, submapper: u8) -> Self {
+            self.header[7] |= 0x08;
+            self.header[8] = submapper << 4;
+            self
+        }
+
+        /// Makes it a NES 2.0 header with PRG-RAM and battery-backed PRG-NVRAM sizes, as shift
+        /// counts of 64 bytes.
+        pub fn prg_ram(mut self, ram_shift: u8, nvram_shift: u8) -> Self {
+            self.header[7] |= 0x08;
+            self.header[10] = (nvram_shift << 4) | ram_shift;
+            self
+        }
+
+        /// Sets flag 6 bits like the battery (2) and vertical mirroring (1).
+        pub fn flags6(mut self, flags: u8) -> Self {
+            self.header[6] |= flags;
+            self
+        }
+
+        /// Fills the PRG-ROM with a byte for each offset.
+        pub fn prg_with(mut self, f: impl Fn(usize) -> u8) -> Self {
+            for (offset, byte) in self.prg.iter_mut().enumerate() {
+                *byte = f(offset);
+            }
+            self
+        }
+
+        /// Puts code at a CPU address in the last 16KB of PRG-ROM, which boards map at $C000
+        /// and NROM-128 at $8000 as well.
+        pub fn code(mut self, address: u16, code: &[u8]) -> Self {
+            let offset = self.prg.len() - 0x4000 + (address & 0x3FFF) as usize;
+            self.prg[offset..offset + code.len()].copy_from_slice(code);
+            self
+        }
+
+        pub fn vectors(self, nmi: u16, reset: u16, irq: u16) -> Self {
+            let mut vectors = [0; 6];
+            for (i, vector) in [nmi, reset, irq].into_iter().enumerate() {
+                vectors[i * 2..i * 2 + 2].copy_from_slice(&vector.to_le_bytes());
+            }
+            self.code(0xFFFA, &vectors)
+        }
+
+        pub fn build(&self) -> Vec<u8> {
+            [&self.header[..], &self.prg, &self.chr].concat()
+        }
+
+        pub fn ines(&self) -> INES {
+            INES::new(&self.build()).unwrap()
+        }
+
+        pub fn nes(&self) -> NES001 {
+            NES001::from_rom(&self.build()).unwrap()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{test_helpers::TestRom, INES};
+
+    #[test]
+    fn test_bad_roms_are_errors() {
+        let mut rom = TestRom::new(0, 2, 1).build();
+        rom.truncate(8);
+        assert!(INES::new(&rom).is_err());
+        rom.resize(16 + 100, 0);
+        assert!(INES::new(&rom).is_err());
+        rom.resize(16 + 0x8000, 0);
+        assert!(INES::new(&rom).is_err());
+        rom.resize(16 + 0x8000 + 0x2000, 0);
+        assert_eq!(INES::new(&rom).unwrap().prg_rom.len(), 0x8000);
+        assert!(INES::new(b"NES").is_err());
+        assert!(INES::from_file(b"XYZ\x1A").is_err());
+    }
+}
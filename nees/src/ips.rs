@@ -0,0 +1,132 @@
+use alloc::vec::Vec;
+
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+/// A record can't start at this offset, as it would read as the footer.
+const EOF_OFFSET: usize = 0x454F46;
+const MAX_OFFSET: usize = 0xFFFFFF;
+const MAX_RECORD_SIZE: usize = 0xFFFF;
+
+/// Builds an IPS patch that turns `original` into `modified`. A modified image shorter than
+/// the original gets the common truncation extension after the footer. Fails for changes past
+/// 16MB, which IPS can't address.
+pub fn diff(original: &[u8], modified: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut patch = HEADER.to_vec();
+
+    let mut pos = 0;
+    while pos < modified.len() {
+        if original.get(pos) == Some(&modified[pos]) {
+            pos += 1;
+            continue;
+        }
+
+        let mut start = pos;
+        if start == EOF_OFFSET {
+            start -= 1;
+        }
+        let mut end = pos;
+        while end < modified.len()
+            && end - start < MAX_RECORD_SIZE
+            && original.get(end) != Some(&modified[end])
+        {
+            end += 1;
+        }
+
+        if start > MAX_OFFSET {
+            anyhow::bail!("IPS patches can't reach past 16MB");
+        }
+        patch.extend_from_slice(&(start as u32).to_be_bytes()[1..]);
+        patch.extend_from_slice(&((end - start) as u16).to_be_bytes());
+        patch.extend_from_slice(&modified[start..end]);
+        pos = end;
+    }
+
+    patch.extend_from_slice(FOOTER);
+    if modified.len() < original.len() {
+        patch.extend_from_slice(&(modified.len() as u32).to_be_bytes()[1..]);
+    }
+
+    Ok(patch)
+}
+
+/// Applies an IPS patch, including RLE records and the truncation extension.
+pub fn apply(data: &[u8], patch: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if !patch.starts_with(HEADER) {
+        anyhow::bail!("Not an IPS patch");
+    }
+
+    let mut output = data.to_vec();
+    let mut pos = HEADER.len();
+    let mut read = |len: usize| take(patch, &mut pos, len);
+
+    loop {
+        let offset = read(3)?;
+        if offset == FOOTER {
+            break;
+        }
+        let offset = u32::from_be_bytes([0, offset[0], offset[1], offset[2]]) as usize;
+        let size = read(2)?;
+        let size = u16::from_be_bytes([size[0], size[1]]) as usize;
+
+        let (len, record): (usize, &[u8]) = if size == 0 {
+            let rle = read(3)?;
+            let len = u16::from_be_bytes([rle[0], rle[1]]) as usize;
+            (len, &rle[2..])
+        } else {
+            (size, read(size)?)
+        };
+
+        if output.len() < offset + len {
+            output.resize(offset + len, 0);
+        }
+        for (i, byte) in output[offset..offset + len].iter_mut().enumerate() {
+            *byte = if size == 0 { record[0] } else { record[i] };
+        }
+    }
+
+    if let Ok(truncate) = read(3) {
+        let len = u32::from_be_bytes([0, truncate[0], truncate[1], truncate[2]]) as usize;
+        output.truncate(len);
+    }
+
+    Ok(output)
+}
+
+fn take<'a>(patch: &'a [u8], pos: &mut usize, len: usize) -> anyhow::Result<&'a [u8]> {
+    let bytes = patch
+        .get(*pos..*pos + len)
+        .ok_or_else(|| anyhow::anyhow!("Truncated IPS patch"))?;
+    *pos += len;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{apply, diff};
+
+    #[test]
+    fn test_diff_round_trip() {
+        let original = [0_u8; 64];
+        let mut modified = original;
+        modified[3] = 1;
+        modified[40..45].copy_from_slice(&[5, 4, 3, 2, 1]);
+
+        let patch = diff(&original, &modified).unwrap();
+        assert_eq!(apply(&original, &patch).unwrap(), modified);
+        assert_eq!(
+            apply(&original, &diff(&original, &original[..10]).unwrap()).unwrap(),
+            &original[..10]
+        );
+    }
+
+    #[test]
+    fn test_diff_past_16mb() {
+        let original = vec![0_u8; 0x1000001];
+        let mut modified = original.clone();
+        modified[0xFFFFFF] = 1;
+        assert!(diff(&original, &modified).is_ok());
+        modified[0xFFFFFF] = 0;
+        modified[0x1000000] = 1;
+        assert!(diff(&original, &modified).is_err());
+    }
+}
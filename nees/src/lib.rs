@@ -7,11 +7,25 @@ mod reader_writer;
 mod apu;
 mod bit_helpers;
 mod bus;
-mod cartridge;
 mod cpu;
-mod ines;
+mod fds_image;
 mod mappers;
 mod ppu;
+mod sha1;
+mod unif;
 
+pub mod cartridge;
+pub mod cheats;
+pub mod crc32;
+pub mod debugger;
+pub mod disassembler;
+pub mod game_db;
+pub mod ines;
+pub mod ips;
+pub mod mirroring;
 pub mod nes001;
-pub use reader_writer::{EasyReader, EasyWriter};
\ No newline at end of file
+pub mod nsf;
+pub mod nsf_player;
+pub mod ram_search;
+pub use mappers::{MapperConstructor, MapperRegistry};
+pub use reader_writer::{EasyReader, EasyWriter};
@@ -0,0 +1,191 @@
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+/// The Action 53 multicart board. $5000-$5FFF selects one of four registers, which is then
+/// written through $8000-$FFFF: the CHR-RAM bank ($00), the inner PRG bank ($01), the mode
+/// ($80) and the outer PRG bank ($81). The mode picks the mirroring, an NROM, UNROM or inverted
+/// UNROM PRG layout and how many bits of the inner bank reach past the 32KB outer bank.
+pub struct Action53 {
+    ines: INES,
+    nametables: Nametables,
+    register_select: u8,
+    chr_bank: u8,
+    inner_bank: u8,
+    mode: u8,
+    outer_bank: u8,
+}
+
+impl Action53 {
+    pub fn new(mut ines: INES) -> Self {
+        if ines.is_chr_ram && ines.chr_rom.len() < 0x8000 {
+            ines.chr_rom.resize(0x8000, 0);
+        }
+
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            register_select: 0,
+            chr_bank: 0,
+            inner_bank: 0,
+            // Powers on in the last bank, where the menu lives
+            mode: 0,
+            outer_bank: 0xFF,
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let bank = (self.chr_bank & 0b11) as usize;
+        (bank * 0x2000 + address.lower_8k() as usize) % self.ines.chr_rom.len()
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        let a14 = ((address >> 14) & 1) as usize;
+        let outer = (self.outer_bank as usize) << 1;
+        let inner = self.inner_bank as usize & 0x0F;
+        let size_mask = (2 << ((self.mode >> 4) & 0b11)) - 1;
+
+        let bank = match ((self.mode >> 2) & 0b11, a14) {
+            (0 | 1, _) => (outer & !size_mask) | (((inner << 1) | a14) & size_mask),
+            // UNROM with the first bank of the outer bank fixed at $8000
+            (2, 0) => outer,
+            // UNROM with the last bank of the outer bank fixed at $C000
+            (3, 1) => outer | 1,
+            _ => (outer & !size_mask) | (inner & size_mask),
+        };
+        let num_16k_chunks = self.ines.prg_rom_size_16k_chunks as usize;
+        (bank % num_16k_chunks) * 0x4000 + address.lower_16k() as usize
+    }
+
+    /// In the one-screen modes, bit 4 of the CHR and inner PRG bank writes selects the screen.
+    fn write_one_screen_select(&mut self, value: u8) {
+        if (self.mode & 0b10) == 0 {
+            self.mode = (self.mode & !1) | ((value >> 4) & 1);
+        }
+    }
+}
+
+impl Cartridge for Action53 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        if address >= 0x8000 {
+            self.ines.prg_rom[self.prg_addr(address)]
+        } else {
+            0
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000..=0x5FFF => self.register_select = value & 0x81,
+            0x8000..=0xFFFF => match self.register_select {
+                0x00 => {
+                    self.chr_bank = value;
+                    self.write_one_screen_select(value);
+                }
+                0x01 => {
+                    self.inner_bank = value;
+                    self.write_one_screen_select(value);
+                }
+                0x80 => self.mode = value,
+                _ => self.outer_bank = value,
+            },
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mode & 0b11 {
+            0 => Mirroring::SingleScreenA,
+            1 => Mirroring::SingleScreenB,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for Action53 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.register_select)?;
+        writer.write_u8(self.chr_bank)?;
+        writer.write_u8(self.inner_bank)?;
+        writer.write_u8(self.mode)?;
+        writer.write_u8(self.outer_bank)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.register_select = reader.read_u8()?;
+        self.chr_bank = reader.read_u8()?;
+        self.inner_bank = reader.read_u8()?;
+        self.mode = reader.read_u8()?;
+        self.outer_bank = reader.read_u8()?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
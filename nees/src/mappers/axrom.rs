@@ -0,0 +1,170 @@
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::bus_conflict;
+
+pub struct AxROM {
+    ines: INES,
+    nametables: Nametables,
+    prg_bank: u8,
+    nametable_page: u8,
+    bus_conflicts: bool,
+}
+
+impl AxROM {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            prg_bank: 0,
+            nametable_page: 0,
+            bus_conflicts: false,
+        }
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        ((self.prg_bank as usize) << 15 | address.lower_32k() as usize) % self.ines.prg_rom.len()
+    }
+}
+
+impl Cartridge for AxROM {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[address.lower_8k() as usize]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            self.ines.chr_rom[address.lower_8k() as usize] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        if address < 0x8000 {
+            return 0;
+        }
+
+        self.ines.prg_rom[self.prg_addr(address)]
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let rom_value = self.cpu_read(address);
+            let value = bus_conflict(value, rom_value, self.bus_conflicts);
+            self.prg_bank = value & 0x07;
+            self.nametable_page = (value >> 4) & 1;
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.nametable_page == 0 {
+            Mirroring::SingleScreenA
+        } else {
+            Mirroring::SingleScreenB
+        }
+    }
+
+    fn set_bus_conflicts(&mut self, enabled: bool) {
+        self.bus_conflicts = enabled;
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                address.lower_8k() as usize,
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for AxROM {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.prg_bank)?;
+        writer.write_u8(self.nametable_page)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.prg_bank = reader.read_u8()?;
+        self.nametable_page = reader.read_u8()?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AxROM;
+    use crate::{cartridge::Cartridge, ines::test_helpers::TestRom, mirroring::Mirroring};
+
+    #[test]
+    fn test_bus_conflicts() {
+        // $8010 in every bank holds $11
+        let rom = TestRom::new(7, 8, 0).prg_with(|offset| match offset % 0x8000 {
+            0x10 => 0x11,
+            _ => 0xFF,
+        });
+        let mut cart = AxROM::new(rom.ines());
+        let bank = |cart: &AxROM| cart.cpu_mapping(0x8000).unwrap().offset / 0x8000;
+        cart.cpu_write(0x8010, 0x03);
+        assert_eq!(
+            (bank(&cart), cart.mirroring()),
+            (3, Mirroring::SingleScreenA)
+        );
+
+        // The ROM's zero bits win
+        cart.set_bus_conflicts(true);
+        cart.cpu_write(0x8010, 0x13);
+        assert_eq!(
+            (bank(&cart), cart.mirroring()),
+            (1, Mirroring::SingleScreenB)
+        );
+    }
+}
@@ -0,0 +1,339 @@
+use alloc::vec::Vec;
+
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{cycle_irq::CpuCycleIrq, eeprom::I2CEeprom};
+
+/// Bandai's FCG-1/FCG-2 and their successor the LZ93D50. The FCG chips take register writes at
+/// $6000-$7FFF and load the IRQ counter directly; the LZ93D50 moves the registers to
+/// $8000-$FFFF, adds a latch for the counter and drives a serial EEPROM from $800D.
+///
+/// Mapper 153 instead has 8KB of battery RAM and uses CHR bank bit 0 as PRG A18, mapper 159
+/// saves to an X24C01, and the Datach (157) pairs a 24C02 with an X24C01 clocked by CHR bank
+/// bit 3. The Datach's barcode reader is not emulated.
+#[allow(clippy::upper_case_acronyms)]
+pub struct BandaiFCG {
+    ines: INES,
+    nametables: Nametables,
+    ram: [u8; 1024 * 8], // 8KB
+    has_ram: bool,
+    fcg_registers: bool,
+    lz93d50_registers: bool,
+    eeprom: Option<I2CEeprom>,
+    datach_eeprom: Option<I2CEeprom>,
+    chr_banks: [u8; 8],
+    prg_bank: u8,
+    mirroring: u8,
+    eeprom_control: u8,
+    datach_scl: bool,
+    irq_latch: u16,
+    irq: CpuCycleIrq,
+}
+
+impl BandaiFCG {
+    pub fn new(ines: INES) -> Self {
+        let submapper = ines.submapper.unwrap_or(0);
+        let (fcg_registers, lz93d50_registers) = match (ines.mapper_no, submapper) {
+            (16, 4) => (true, false),
+            (16, 5) | (153 | 157 | 159, _) => (false, true),
+            // Plain iNES mapper 16 could be either
+            _ => (true, true),
+        };
+        let eeprom = match (ines.mapper_no, submapper) {
+            (16, 4) | (153, _) => None,
+            (159, _) => Some(I2CEeprom::new_24c01()),
+            _ => Some(I2CEeprom::new_24c02()),
+        };
+        let datach_eeprom = (ines.mapper_no == 157).then(I2CEeprom::new_24c01);
+        let has_ram = ines.mapper_no == 153;
+
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            ram: [0; 1024 * 8],
+            has_ram,
+            fcg_registers,
+            lz93d50_registers,
+            eeprom,
+            datach_eeprom,
+            chr_banks: [0; 8],
+            prg_bank: 0,
+            mirroring: 0,
+            eeprom_control: 0,
+            datach_scl: false,
+            irq_latch: 0,
+            // The counter wraps from $0000 to $FFFF, raising the IRQ as it does
+            irq: CpuCycleIrq::new(0xFFFF),
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        if self.ines.is_chr_ram {
+            // CHR-RAM boards use the bank registers for other things
+            return address.lower_8k() as usize % self.ines.chr_rom.len();
+        }
+
+        let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+        let bank = self.chr_banks[(address >> 10) as usize & 7] as usize % num_1k_chunks;
+        bank * 1024 + (address & 0x3FF) as usize
+    }
+
+    fn ram_enabled(&self) -> bool {
+        self.has_ram && (self.eeprom_control & 0x20) != 0
+    }
+
+    /// Mapper 153 selects the 256KB half with bit 0 of any CHR bank register.
+    fn prg_outer_bank(&self) -> usize {
+        if self.ines.mapper_no == 153 {
+            let a18 = self.chr_banks.iter().fold(0, |acc, bank| acc | (bank & 1));
+            (a18 as usize) << 4
+        } else {
+            0
+        }
+    }
+
+    fn write_register(&mut self, address: u16, value: u8) {
+        // The FCG loads the counter directly, the LZ93D50 only loads it from the latch on $800A
+        let direct = address < 0x8000;
+        match address & 0x0F {
+            reg @ 0x0..=0x7 => {
+                self.chr_banks[reg as usize] = value;
+                if let Some(datach_eeprom) = &mut self.datach_eeprom {
+                    self.datach_scl = (value & 0x08) != 0;
+                    datach_eeprom.write_lines(self.datach_scl, eeprom_sda(self.eeprom_control));
+                }
+            }
+            0x8 => self.prg_bank = value & 0x0F,
+            0x9 => self.mirroring = value & 0b11,
+            0xA => {
+                self.irq.counting = (value & 1) != 0;
+                self.irq.irq_enabled = (value & 1) != 0;
+                self.irq.acknowledge();
+                if !direct {
+                    self.irq.counter = self.irq_latch;
+                }
+            }
+            0xB => {
+                self.irq_latch = (self.irq_latch & 0xFF00) | value as u16;
+                if direct {
+                    self.irq.counter = (self.irq.counter & 0xFF00) | value as u16;
+                }
+            }
+            0xC => {
+                self.irq_latch = (self.irq_latch & 0x00FF) | (value as u16) << 8;
+                if direct {
+                    self.irq.counter = (self.irq.counter & 0x00FF) | (value as u16) << 8;
+                }
+            }
+            0xD => {
+                self.eeprom_control = value;
+                let sda = eeprom_sda(value);
+                if let Some(eeprom) = &mut self.eeprom {
+                    eeprom.write_lines((value & 0x20) != 0, sda);
+                }
+                if let Some(datach_eeprom) = &mut self.datach_eeprom {
+                    datach_eeprom.write_lines(self.datach_scl, sda);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn eeprom_read(&self) -> u8 {
+        let sda = self.eeprom.as_ref().is_none_or(|e| e.sda_out())
+            && self.datach_eeprom.as_ref().is_none_or(|e| e.sda_out());
+        (sda as u8) << 4
+    }
+}
+
+/// Bit 6 of $800D drives SDA, unless bit 7 releases the line so the EEPROM can be read.
+fn eeprom_sda(eeprom_control: u8) -> bool {
+    (eeprom_control & 0xC0) != 0
+}
+
+impl Cartridge for BandaiFCG {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF if !self.ram_enabled() && self.eeprom.is_some() => self.eeprom_read(),
+            _ => self
+                .cpu_mapping(address)
+                .map_or(0, |location| location.read(self.memory(location.memory))),
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let num_16k_chunks = self.ines.prg_rom_size_16k_chunks as usize;
+        let bank = match address {
+            0x6000..=0x7FFF if self.ram_enabled() => {
+                let addr = address.lower_8k() as usize;
+                return Some(MemoryLocation::new(CartMemory::PrgRam, addr));
+            }
+            0x8000..=0xBFFF => self.prg_outer_bank() | self.prg_bank as usize,
+            0xC000..=0xFFFF => self.prg_outer_bank() | 0x0F,
+            _ => return None,
+        } % num_16k_chunks;
+
+        let addr = bank * 0x4000 + address.lower_16k() as usize;
+        Some(MemoryLocation::new(CartMemory::PrgRom, addr))
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if self.ram_enabled() => self.ram[address.lower_8k() as usize] = value,
+            0x6000..=0x7FFF if self.fcg_registers => self.write_register(address, value),
+            0x8000..=0xFFFF if self.lz93d50_registers => self.write_register(address, value),
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.irq.tick()
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        self.ram_enabled().then_some(&self.ram[..])
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        if self.has_ram {
+            return Some(self.ram.to_vec());
+        }
+
+        let mut data = Vec::new();
+        for eeprom in [&self.eeprom, &self.datach_eeprom].into_iter().flatten() {
+            data.extend_from_slice(eeprom.data());
+        }
+        (!data.is_empty()).then_some(data)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        if self.has_ram {
+            super::load_battery_ram(&mut self.ram, data);
+            return;
+        }
+
+        let mut remaining = data;
+        for eeprom in [&mut self.eeprom, &mut self.datach_eeprom]
+            .into_iter()
+            .flatten()
+        {
+            eeprom.load_data(remaining);
+            remaining = &remaining[eeprom.data().len().min(remaining.len())..];
+        }
+    }
+}
+
+impl CartridgeSaveLoad for BandaiFCG {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        for eeprom in [&self.eeprom, &self.datach_eeprom].into_iter().flatten() {
+            eeprom.save(writer)?;
+        }
+        writer.write_all(&self.chr_banks)?;
+        writer.write_u8(self.prg_bank)?;
+        writer.write_u8(self.mirroring)?;
+        writer.write_u8(self.eeprom_control)?;
+        writer.write_bool(self.datach_scl)?;
+        writer.write_u16(self.irq_latch)?;
+        self.irq.save(writer)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        for eeprom in [&mut self.eeprom, &mut self.datach_eeprom]
+            .into_iter()
+            .flatten()
+        {
+            eeprom.load(reader)?;
+        }
+        reader.read_exact(&mut self.chr_banks)?;
+        self.prg_bank = reader.read_u8()?;
+        self.mirroring = reader.read_u8()?;
+        self.eeprom_control = reader.read_u8()?;
+        self.datach_scl = reader.read_bool()?;
+        self.irq_latch = reader.read_u16()?;
+        self.irq.load(reader)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,78 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// The CHR latches of the MMC2 and MMC4. Each 4KB pattern table has two bank registers, and a
+/// latch that switches between them when the PPU fetches tile $FD or $FE from that table. The
+/// switch takes effect after the triggering fetch.
+pub struct ChrLatch {
+    fd_banks: [u8; 2],
+    fe_banks: [u8; 2],
+    latches: [u8; 2],
+    /// The MMC2 only triggers on $0FD8 and $0FE8 in the lower table, rather than on all eight
+    /// addresses of the tile's upper plane.
+    exact_lower_trigger: bool,
+}
+
+impl ChrLatch {
+    pub fn new(exact_lower_trigger: bool) -> Self {
+        Self {
+            fd_banks: [0; 2],
+            fe_banks: [0; 2],
+            latches: [0xFE; 2],
+            exact_lower_trigger,
+        }
+    }
+
+    pub fn write_fd_bank(&mut self, table: usize, value: u8) {
+        self.fd_banks[table] = value & 0b11111;
+    }
+
+    pub fn write_fe_bank(&mut self, table: usize, value: u8) {
+        self.fe_banks[table] = value & 0b11111;
+    }
+
+    /// The 4KB bank currently mapped at a pattern table address.
+    pub fn bank(&self, address: u16) -> u8 {
+        let table = ((address >> 12) & 1) as usize;
+        if self.latches[table] == 0xFD {
+            self.fd_banks[table]
+        } else {
+            self.fe_banks[table]
+        }
+    }
+
+    /// Updates the latches from a pattern table read; call after fetching the value.
+    pub fn snoop(&mut self, address: u16) {
+        let table = ((address >> 12) & 1) as usize;
+        let tile_address = if table == 0 && self.exact_lower_trigger {
+            address
+        } else {
+            address & 0x1FF8
+        };
+
+        match tile_address & 0x0FFF {
+            0xFD8 => self.latches[table] = 0xFD,
+            0xFE8 => self.latches[table] = 0xFE,
+            _ => {}
+        }
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        for table in 0..2 {
+            writer.write_u8(self.fd_banks[table])?;
+            writer.write_u8(self.fe_banks[table])?;
+            writer.write_u8(self.latches[table])?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        for table in 0..2 {
+            self.fd_banks[table] = reader.read_u8()?;
+            self.fe_banks[table] = reader.read_u8()?;
+            self.latches[table] = reader.read_u8()?;
+        }
+
+        Ok(())
+    }
+}
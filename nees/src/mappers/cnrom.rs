@@ -0,0 +1,168 @@
+use crate::{
+    bit_helpers::{SubType, BIT_13, MASK_16K, MASK_32K},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::bus_conflict;
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct CNROM {
+    ines: INES,
+    nametables: Nametables,
+    chr_bank: u8,
+    bus_conflicts: bool,
+}
+
+impl CNROM {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            nametables: Nametables::new(ines.fixed_mirroring()),
+            ines,
+            chr_bank: 0,
+            bus_conflicts: false,
+        }
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        (address
+            & (if self.ines.prg_rom_size_16k_chunks == 1 {
+                MASK_16K
+            } else {
+                MASK_32K
+            })) as usize
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        ((self.chr_bank as usize) * 0x2000 + address.lower_8k() as usize) % self.ines.chr_rom.len()
+    }
+}
+
+impl Cartridge for CNROM {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        if address < 0x8000 {
+            return 0;
+        }
+
+        self.ines.prg_rom[self.prg_addr(address)]
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if address >= 0x8000 {
+            let rom_value = self.cpu_read(address);
+            self.chr_bank = bus_conflict(value, rom_value, self.bus_conflicts);
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.ines.fixed_mirroring()
+    }
+
+    fn set_bus_conflicts(&mut self, enabled: bool) {
+        self.bus_conflicts = enabled;
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for CNROM {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.chr_bank)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+        self.nametables.save(writer)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.chr_bank = reader.read_u8()?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+        self.nametables.load(reader)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CNROM;
+    use crate::{cartridge::Cartridge, ines::test_helpers::TestRom};
+
+    #[test]
+    fn test_bus_conflicts() {
+        let rom = TestRom::new(3, 2, 4).prg_with(|offset| match offset {
+            0x10 => 0x01,
+            _ => 0xFF,
+        });
+        let mut cart = CNROM::new(rom.ines());
+        let bank = |cart: &CNROM| cart.ppu_mapping(0x0000).unwrap().offset / 0x2000;
+        cart.cpu_write(0x8010, 0x03);
+        assert_eq!(bank(&cart), 3);
+
+        // The ROM's zero bits win
+        cart.set_bus_conflicts(true);
+        cart.cpu_write(0x8010, 0x03);
+        assert_eq!(bank(&cart), 1);
+        cart.cpu_write(0x8011, 0x03);
+        assert_eq!(bank(&cart), 3);
+    }
+}
@@ -0,0 +1,74 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+/// A down counter clocked by CPU cycles that raises an IRQ when it expires. On expiry the
+/// counter is reloaded, which also covers boards that simply wrap around (reload = $FFFF).
+pub struct CpuCycleIrq {
+    pub counter: u16,
+    pub reload: u16,
+    pub counting: bool,
+    pub irq_enabled: bool,
+    pending: bool,
+}
+
+impl CpuCycleIrq {
+    pub fn new(reload: u16) -> Self {
+        Self {
+            counter: 0,
+            reload,
+            counting: false,
+            irq_enabled: false,
+            pending: false,
+        }
+    }
+
+    /// Steps the counter once, regardless of whether it is counting. Returns true if it expired.
+    pub fn clock(&mut self) -> bool {
+        if self.counter == 0 {
+            self.counter = self.reload;
+            if self.irq_enabled {
+                self.pending = true;
+            }
+            true
+        } else {
+            self.counter -= 1;
+            false
+        }
+    }
+
+    /// Advances by one CPU cycle and returns the IRQ line state.
+    pub fn tick(&mut self) -> bool {
+        if self.counting {
+            self.clock();
+        }
+
+        self.pending
+    }
+
+    pub fn pending(&self) -> bool {
+        self.pending
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.pending = false;
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u16(self.counter)?;
+        writer.write_u16(self.reload)?;
+        writer.write_bool(self.counting)?;
+        writer.write_bool(self.irq_enabled)?;
+        writer.write_bool(self.pending)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.counter = reader.read_u16()?;
+        self.reload = reader.read_u16()?;
+        self.counting = reader.read_bool()?;
+        self.irq_enabled = reader.read_bool()?;
+        self.pending = reader.read_bool()?;
+
+        Ok(())
+    }
+}
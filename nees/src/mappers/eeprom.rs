@@ -0,0 +1,316 @@
+use alloc::{vec, vec::Vec};
+
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Idle,
+    DeviceAddress,
+    WordAddress,
+    WriteData,
+    ReadData,
+}
+
+impl Phase {
+    fn to_u8(self) -> u8 {
+        match self {
+            Phase::Idle => 0,
+            Phase::DeviceAddress => 1,
+            Phase::WordAddress => 2,
+            Phase::WriteData => 3,
+            Phase::ReadData => 4,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Phase::DeviceAddress,
+            2 => Phase::WordAddress,
+            3 => Phase::WriteData,
+            4 => Phase::ReadData,
+            _ => Phase::Idle,
+        }
+    }
+}
+
+/// A serial EEPROM driven bit by bit over its SCL and SDA lines.
+///
+/// The 24C02 speaks standard I2C: a device address byte, a word address byte, then data, all
+/// MSB first. The Xicor X24C01 skips the device address; its first byte holds a 7-bit word
+/// address followed by the read/write bit, and everything is sent LSB first.
+pub struct I2CEeprom {
+    data: Vec<u8>,
+    is_x24c01: bool,
+    phase: Phase,
+    next_phase: Phase,
+    /// Clock within the current byte: 1-8 after each data bit, 9 after the acknowledge bit.
+    bit: u8,
+    shift: u8,
+    address: u8,
+    output: bool,
+    scl: bool,
+    sda: bool,
+}
+
+impl I2CEeprom {
+    pub fn new_24c01() -> Self {
+        Self::new(128, true)
+    }
+
+    pub fn new_24c02() -> Self {
+        Self::new(256, false)
+    }
+
+    fn new(size: usize, is_x24c01: bool) -> Self {
+        Self {
+            data: vec![0xFF; size],
+            is_x24c01,
+            phase: Phase::Idle,
+            next_phase: Phase::Idle,
+            bit: 0,
+            shift: 0,
+            address: 0,
+            output: true,
+            scl: false,
+            sda: false,
+        }
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn load_data(&mut self, data: &[u8]) {
+        let len = data.len().min(self.data.len());
+        self.data[..len].copy_from_slice(&data[..len]);
+    }
+
+    /// The level the chip drives onto SDA; high when released.
+    pub fn sda_out(&self) -> bool {
+        self.output
+    }
+
+    pub fn write_lines(&mut self, scl: bool, sda: bool) {
+        if self.scl && scl && sda != self.sda {
+            // SDA changing while SCL is high frames a transfer
+            if sda {
+                self.phase = Phase::Idle;
+            } else {
+                self.phase = if self.is_x24c01 {
+                    Phase::WordAddress
+                } else {
+                    Phase::DeviceAddress
+                };
+                self.bit = 0;
+                self.shift = 0;
+            }
+            self.output = true;
+        } else if !self.scl && scl {
+            self.clock_rising(sda);
+        } else if self.scl && !scl {
+            self.clock_falling();
+        }
+
+        self.scl = scl;
+        self.sda = sda;
+    }
+
+    fn clock_rising(&mut self, sda: bool) {
+        match self.phase {
+            Phase::Idle => {}
+            Phase::ReadData => {
+                self.bit += 1;
+                // The master leaves SDA high instead of acknowledging to end a read
+                if self.bit == 9 && sda {
+                    self.next_phase = Phase::Idle;
+                }
+            }
+            _ => {
+                if self.bit < 8 {
+                    if self.is_x24c01 {
+                        self.shift |= (sda as u8) << self.bit;
+                    } else {
+                        self.shift = (self.shift << 1) | sda as u8;
+                    }
+                }
+                self.bit += 1;
+            }
+        }
+    }
+
+    fn clock_falling(&mut self) {
+        match (self.phase, self.bit) {
+            (Phase::Idle, _) => {}
+            (Phase::ReadData, 1..=7) => self.output = self.read_bit(self.bit),
+            (Phase::ReadData, 8) => {
+                self.output = true;
+                self.next_phase = Phase::ReadData;
+            }
+            (Phase::ReadData, 9) => {
+                self.address = self.address.wrapping_add(1);
+                self.start_phase(self.next_phase);
+            }
+            (_, 8) => {
+                let ack = self.receive_byte(self.shift);
+                self.output = !ack;
+            }
+            (_, 9) => self.start_phase(self.next_phase),
+            _ => {}
+        }
+    }
+
+    /// Handles a completed byte written by the master, returning whether it is acknowledged.
+    fn receive_byte(&mut self, byte: u8) -> bool {
+        match self.phase {
+            Phase::DeviceAddress if (byte & 0xF0) == 0xA0 => {
+                self.next_phase = if (byte & 1) != 0 {
+                    Phase::ReadData
+                } else {
+                    Phase::WordAddress
+                };
+                true
+            }
+            Phase::WordAddress if self.is_x24c01 => {
+                self.address = byte & 0x7F;
+                self.next_phase = if (byte & 0x80) != 0 {
+                    Phase::ReadData
+                } else {
+                    Phase::WriteData
+                };
+                true
+            }
+            Phase::WordAddress => {
+                self.address = byte;
+                self.next_phase = Phase::WriteData;
+                true
+            }
+            Phase::WriteData => {
+                let index = self.address as usize % self.data.len();
+                self.data[index] = byte;
+                // Writes wrap around within a page
+                let page_mask = if self.is_x24c01 { 0x03 } else { 0x07 };
+                self.address =
+                    (self.address & !page_mask) | (self.address.wrapping_add(1) & page_mask);
+                self.next_phase = Phase::WriteData;
+                true
+            }
+            _ => {
+                self.next_phase = Phase::Idle;
+                false
+            }
+        }
+    }
+
+    fn start_phase(&mut self, phase: Phase) {
+        self.phase = phase;
+        self.bit = 0;
+        self.shift = 0;
+        self.output = if phase == Phase::ReadData {
+            self.read_bit(0)
+        } else {
+            true
+        };
+    }
+
+    fn read_bit(&self, bit: u8) -> bool {
+        let byte = self.data[self.address as usize % self.data.len()];
+        let shift = if self.is_x24c01 { bit } else { 7 - bit };
+        ((byte >> shift) & 1) != 0
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.data)?;
+        writer.write_u8(self.phase.to_u8())?;
+        writer.write_u8(self.next_phase.to_u8())?;
+        writer.write_u8(self.bit)?;
+        writer.write_u8(self.shift)?;
+        writer.write_u8(self.address)?;
+        writer.write_bool(self.output)?;
+        writer.write_bool(self.scl)?;
+        writer.write_bool(self.sda)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.data)?;
+        self.phase = Phase::from_u8(reader.read_u8()?);
+        self.next_phase = Phase::from_u8(reader.read_u8()?);
+        self.bit = reader.read_u8()?;
+        self.shift = reader.read_u8()?;
+        self.address = reader.read_u8()?;
+        self.output = reader.read_bool()?;
+        self.scl = reader.read_bool()?;
+        self.sda = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::I2CEeprom;
+
+    fn start(eeprom: &mut I2CEeprom) {
+        eeprom.write_lines(false, true);
+        eeprom.write_lines(true, true);
+        eeprom.write_lines(true, false);
+        eeprom.write_lines(false, false);
+    }
+
+    fn stop(eeprom: &mut I2CEeprom) {
+        eeprom.write_lines(false, false);
+        eeprom.write_lines(true, false);
+        eeprom.write_lines(true, true);
+    }
+
+    /// Clocks out a byte MSB first and returns whether it was acknowledged.
+    fn send(eeprom: &mut I2CEeprom, byte: u8) -> bool {
+        for bit in (0..8).rev() {
+            let sda = ((byte >> bit) & 1) != 0;
+            eeprom.write_lines(false, sda);
+            eeprom.write_lines(true, sda);
+            eeprom.write_lines(false, sda);
+        }
+        eeprom.write_lines(false, true);
+        eeprom.write_lines(true, true);
+        let ack = !eeprom.sda_out();
+        eeprom.write_lines(false, true);
+        ack
+    }
+
+    fn receive(eeprom: &mut I2CEeprom, ack: bool) -> u8 {
+        let mut byte = 0;
+        for _ in 0..8 {
+            eeprom.write_lines(false, true);
+            eeprom.write_lines(true, true);
+            byte = (byte << 1) | eeprom.sda_out() as u8;
+        }
+        eeprom.write_lines(false, !ack);
+        eeprom.write_lines(true, !ack);
+        eeprom.write_lines(false, !ack);
+        byte
+    }
+
+    #[test]
+    fn test_24c02_write_then_random_read() {
+        let mut eeprom = I2CEeprom::new_24c02();
+
+        start(&mut eeprom);
+        assert!(send(&mut eeprom, 0xA0));
+        assert!(send(&mut eeprom, 0x12));
+        assert!(send(&mut eeprom, 0x5A));
+        assert!(send(&mut eeprom, 0xC3));
+        stop(&mut eeprom);
+        assert_eq!(&eeprom.data()[0x12..0x14], &[0x5A, 0xC3]);
+
+        start(&mut eeprom);
+        assert!(send(&mut eeprom, 0xA0));
+        assert!(send(&mut eeprom, 0x12));
+        start(&mut eeprom);
+        assert!(send(&mut eeprom, 0xA1));
+        assert_eq!(receive(&mut eeprom, true), 0x5A);
+        assert_eq!(receive(&mut eeprom, false), 0xC3);
+        stop(&mut eeprom);
+    }
+}
@@ -0,0 +1,500 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    apu::FDSAudio,
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    fds_image::{side_from_raw, side_to_raw, FDSImage, SIDE_SIZE},
+    ips,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::cycle_irq::CpuCycleIrq;
+
+/// CPU cycles from power-on or the end of the disk until the head reaches the first byte.
+const HEAD_RETURN_CYCLES: u32 = 50000;
+/// CPU cycles per byte under the head, at about 96.4kbit/s.
+const BYTE_CYCLES: u32 = 150;
+/// How long a newly inserted side reads as absent, so the BIOS sees the swap. About a second.
+const DISK_SWAP_CYCLES: u32 = 1_789_773;
+
+/// The Famicom Disk System RAM adapter: 32KB of PRG-RAM at $6000-$DFFF, the BIOS at
+/// $E000-$FFFF, 8KB of CHR-RAM, a CPU-cycle timer IRQ, the disk drive interface and the
+/// wavetable sound channel.
+///
+/// The drive is modelled as a stream of bytes under the head, with each side laid out with the
+/// gaps and start marks the BIOS looks for. Modified sides are saved as an IPS patch against
+/// the original image.
+#[allow(clippy::upper_case_acronyms)]
+pub struct FDS {
+    bios: Vec<u8>,
+    ram: Vec<u8>,
+    chr_ram: Vec<u8>,
+    nametables: Nametables,
+    original: Vec<u8>,
+    sides: Vec<Vec<u8>>,
+    modified: bool,
+
+    inserted_side: Option<usize>,
+    pending_side: Option<usize>,
+    swap_delay: u32,
+
+    timer: CpuCycleIrq,
+    timer_repeat: bool,
+    disk_io_enabled: bool,
+    sound_enabled: bool,
+
+    /// $4025
+    motor_on: bool,
+    transfer_reset: bool,
+    read_mode: bool,
+    horizontal_mirroring: bool,
+    crc_control: bool,
+    disk_ready: bool,
+    disk_irq_enabled: bool,
+
+    disk_irq: bool,
+    transfer_complete: bool,
+    end_of_head: bool,
+    scanning: bool,
+    gap_ended: bool,
+    position: usize,
+    delay: u32,
+    read_data: u8,
+    write_data: u8,
+
+    audio: FDSAudio,
+}
+
+impl FDS {
+    pub fn new(image: FDSImage, bios: Vec<u8>) -> Self {
+        let sides: Vec<Vec<u8>> = image.sides.iter().map(|side| side_to_raw(side)).collect();
+        let original = Self::image_from_raw(&sides);
+        let inserted_side = (!sides.is_empty()).then_some(0);
+
+        Self {
+            bios,
+            ram: vec![0; 0x8000],
+            chr_ram: vec![0; 0x2000],
+            nametables: Nametables::default(),
+            original,
+            sides,
+            modified: false,
+
+            inserted_side,
+            pending_side: None,
+            swap_delay: 0,
+
+            timer: CpuCycleIrq::new(0),
+            timer_repeat: false,
+            disk_io_enabled: false,
+            sound_enabled: false,
+
+            motor_on: false,
+            transfer_reset: false,
+            read_mode: true,
+            horizontal_mirroring: false,
+            crc_control: false,
+            disk_ready: false,
+            disk_irq_enabled: false,
+
+            disk_irq: false,
+            transfer_complete: false,
+            end_of_head: true,
+            scanning: false,
+            gap_ended: false,
+            position: 0,
+            delay: 0,
+            read_data: 0,
+            write_data: 0,
+
+            audio: FDSAudio::new(),
+        }
+    }
+
+    /// The sides in the .fds layout, back to back. Unmodified sides come out of the round trip
+    /// through the drive's layout unchanged, so this is the basis for the IPS patches.
+    fn image_from_raw(sides: &[Vec<u8>]) -> Vec<u8> {
+        sides.iter().flat_map(|side| side_from_raw(side)).collect()
+    }
+
+    fn disk_status(&self) -> u8 {
+        let no_disk = self.inserted_side.is_none();
+        (no_disk as u8) | ((no_disk || !self.scanning) as u8) << 1 | (no_disk as u8) << 2 | 0x40
+    }
+
+    fn write_control(&mut self, value: u8) {
+        self.motor_on = (value & 0x01) != 0;
+        self.transfer_reset = (value & 0x02) != 0;
+        self.read_mode = (value & 0x04) != 0;
+        self.horizontal_mirroring = (value & 0x08) != 0;
+        self.crc_control = (value & 0x10) != 0;
+        self.disk_ready = (value & 0x40) != 0;
+        self.disk_irq_enabled = (value & 0x80) != 0;
+        self.disk_irq = false;
+    }
+
+    fn tick_timer(&mut self) {
+        if self.timer.counting && self.timer.clock() && !self.timer_repeat {
+            self.timer.counting = false;
+        }
+    }
+
+    /// Moves the disk under the head by one CPU cycle's worth.
+    fn tick_drive(&mut self) {
+        if self.swap_delay > 0 {
+            self.swap_delay -= 1;
+            if self.swap_delay == 0 {
+                self.inserted_side = self.pending_side.take();
+            }
+            return;
+        }
+
+        let Some(side) = self.inserted_side else {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        };
+        if !self.motor_on {
+            self.end_of_head = true;
+            self.scanning = false;
+            return;
+        }
+        if self.transfer_reset && !self.scanning {
+            return;
+        }
+        if self.end_of_head {
+            self.delay = HEAD_RETURN_CYCLES;
+            self.end_of_head = false;
+            self.position = 0;
+            self.gap_ended = false;
+            return;
+        }
+        if self.delay > 0 {
+            self.delay -= 1;
+            return;
+        }
+
+        self.scanning = true;
+        if self.read_mode {
+            let data = self.sides[side].get(self.position).copied().unwrap_or(0);
+            let mut raise_irq = self.disk_irq_enabled;
+            if !self.disk_ready {
+                self.gap_ended = false;
+            } else if data != 0 && !self.gap_ended {
+                // The start mark itself isn't handed to the CPU
+                self.gap_ended = true;
+                raise_irq = false;
+            }
+
+            if self.gap_ended {
+                self.transfer_complete = true;
+                self.read_data = data;
+                self.disk_irq |= raise_irq;
+            }
+        } else {
+            let mut data = 0;
+            if !self.crc_control {
+                self.transfer_complete = true;
+                data = self.write_data;
+                self.disk_irq |= self.disk_irq_enabled;
+            }
+            if !self.disk_ready {
+                data = 0;
+            }
+            // CRCs aren't checked on reads, so a placeholder is written in their place
+            if let Some(byte) = self.sides[side].get_mut(self.position) {
+                *byte = data;
+                self.modified = true;
+            }
+            self.gap_ended = false;
+        }
+
+        self.position += 1;
+        if self.position >= self.sides[side].len() {
+            self.motor_on = false;
+        } else {
+            self.delay = BYTE_CYCLES;
+        }
+    }
+}
+
+impl Cartridge for FDS {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.chr_ram[address.lower_8k() as usize]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else {
+            self.chr_ram[address.lower_8k() as usize] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        let value = self.cpu_peek(address);
+        match address {
+            0x4030 if self.disk_io_enabled => {
+                self.timer.acknowledge();
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4031 if self.disk_io_enabled => {
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            _ => {}
+        }
+        value
+    }
+
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            0x4030 if self.disk_io_enabled => {
+                (self.timer.pending() as u8)
+                    | (self.transfer_complete as u8) << 1
+                    | (self.end_of_head as u8) << 6
+            }
+            0x4031 if self.disk_io_enabled => self.read_data,
+            0x4032 if self.disk_io_enabled => self.disk_status(),
+            // The battery is always good
+            0x4033 if self.disk_io_enabled => 0x80,
+            0x4040..=0x4097 if self.sound_enabled => self.audio.read_reg(address),
+            _ => self
+                .cpu_mapping(address)
+                .map_or(0, |location| location.read(self.memory(location.memory))),
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4020 => self.timer.reload = (self.timer.reload & 0xFF00) | value as u16,
+            0x4021 => self.timer.reload = (self.timer.reload & 0x00FF) | (value as u16) << 8,
+            0x4022 => {
+                self.timer_repeat = (value & 0x01) != 0;
+                self.timer.counting = (value & 0x02) != 0 && self.disk_io_enabled;
+                if self.timer.counting {
+                    self.timer.counter = self.timer.reload;
+                } else {
+                    self.timer.acknowledge();
+                }
+            }
+            0x4023 => {
+                self.disk_io_enabled = (value & 0x01) != 0;
+                self.sound_enabled = (value & 0x02) != 0;
+                if !self.disk_io_enabled {
+                    self.timer.counting = false;
+                    self.timer.acknowledge();
+                    self.disk_irq = false;
+                }
+            }
+            0x4024 if self.disk_io_enabled => {
+                self.write_data = value;
+                self.transfer_complete = false;
+                self.disk_irq = false;
+            }
+            0x4025 if self.disk_io_enabled => self.write_control(value),
+            0x4040..=0x4097 if self.sound_enabled => self.audio.write_reg(address, value),
+            0x6000..=0xDFFF => self.ram[(address - 0x6000) as usize] = value,
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.horizontal_mirroring {
+            Mirroring::Horizontal
+        } else {
+            Mirroring::Vertical
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.tick_timer();
+        self.tick_drive();
+        self.audio.tick();
+
+        self.timer.pending() || self.disk_irq
+    }
+
+    fn audio_output(&self) -> i16 {
+        self.audio.output()
+    }
+
+    fn disk_sides(&self) -> usize {
+        self.sides.len()
+    }
+
+    fn inserted_disk_side(&self) -> Option<usize> {
+        self.pending_side.or(self.inserted_side)
+    }
+
+    fn insert_disk_side(&mut self, side: Option<usize>) {
+        let side = side.filter(|&side| side < self.sides.len());
+        self.inserted_side = None;
+        self.pending_side = side;
+        self.swap_delay = if side.is_some() { DISK_SWAP_CYCLES } else { 0 };
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        Some(&self.ram[..0x2000])
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x6000..=0xDFFF => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                (address - 0x6000) as usize,
+            )),
+            0xE000..=0xFFFF => Some(MemoryLocation::new(
+                CartMemory::PrgRom,
+                address.lower_8k() as usize % self.bios.len(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                address.lower_8k() as usize,
+            ))
+        }
+    }
+
+    /// The BIOS stands in for PRG-ROM, and the CHR-RAM for CHR-ROM.
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.bios,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.chr_ram,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.bios,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.chr_ram,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        if !self.modified {
+            return None;
+        }
+        ips::diff(&self.original, &Self::image_from_raw(&self.sides)).ok()
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let Ok(image) = ips::apply(&self.original, data) else {
+            return;
+        };
+        for (raw, side) in self.sides.iter_mut().zip(image.chunks(SIDE_SIZE)) {
+            *raw = side_to_raw(side);
+        }
+        self.modified = true;
+    }
+}
+
+impl CartridgeSaveLoad for FDS {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_all(&self.chr_ram)?;
+        for side in &self.sides {
+            writer.write_u32(side.len() as u32)?;
+            writer.write_all(side)?;
+        }
+        writer.write_bool(self.modified)?;
+
+        writer.write_u8(self.inserted_side.map_or(0xFF, |side| side as u8))?;
+        writer.write_u8(self.pending_side.map_or(0xFF, |side| side as u8))?;
+        writer.write_u32(self.swap_delay)?;
+
+        self.timer.save(writer)?;
+        writer.write_bool(self.timer_repeat)?;
+        writer.write_bool(self.disk_io_enabled)?;
+        writer.write_bool(self.sound_enabled)?;
+
+        writer.write_bool(self.motor_on)?;
+        writer.write_bool(self.transfer_reset)?;
+        writer.write_bool(self.read_mode)?;
+        writer.write_bool(self.horizontal_mirroring)?;
+        writer.write_bool(self.crc_control)?;
+        writer.write_bool(self.disk_ready)?;
+        writer.write_bool(self.disk_irq_enabled)?;
+
+        writer.write_bool(self.disk_irq)?;
+        writer.write_bool(self.transfer_complete)?;
+        writer.write_bool(self.end_of_head)?;
+        writer.write_bool(self.scanning)?;
+        writer.write_bool(self.gap_ended)?;
+        writer.write_u32(self.position as u32)?;
+        writer.write_u32(self.delay)?;
+        writer.write_u8(self.read_data)?;
+        writer.write_u8(self.write_data)?;
+
+        self.audio.save(writer)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        reader.read_exact(&mut self.chr_ram)?;
+        for side in &mut self.sides {
+            side.resize(reader.read_u32()? as usize, 0);
+            reader.read_exact(side)?;
+        }
+        self.modified = reader.read_bool()?;
+
+        let read_side = |value: u8| (value != 0xFF).then_some(value as usize);
+        self.inserted_side = read_side(reader.read_u8()?);
+        self.pending_side = read_side(reader.read_u8()?);
+        self.swap_delay = reader.read_u32()?;
+
+        self.timer.load(reader)?;
+        self.timer_repeat = reader.read_bool()?;
+        self.disk_io_enabled = reader.read_bool()?;
+        self.sound_enabled = reader.read_bool()?;
+
+        self.motor_on = reader.read_bool()?;
+        self.transfer_reset = reader.read_bool()?;
+        self.read_mode = reader.read_bool()?;
+        self.horizontal_mirroring = reader.read_bool()?;
+        self.crc_control = reader.read_bool()?;
+        self.disk_ready = reader.read_bool()?;
+        self.disk_irq_enabled = reader.read_bool()?;
+
+        self.disk_irq = reader.read_bool()?;
+        self.transfer_complete = reader.read_bool()?;
+        self.end_of_head = reader.read_bool()?;
+        self.scanning = reader.read_bool()?;
+        self.gap_ended = reader.read_bool()?;
+        self.position = reader.read_u32()? as usize;
+        self.delay = reader.read_u32()?;
+        self.read_data = reader.read_u8()?;
+        self.write_data = reader.read_u8()?;
+
+        self.audio.load(reader)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,166 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+const MANUFACTURER_ID: u8 = 0xBF;
+const DEVICE_ID: u8 = 0xB7;
+
+/// The progress through the SST39SF040's command sequences. Each command is preceded by
+/// writing $AA to $5555 and $55 to $2AAA.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ready,
+    Unlock1,
+    Unlock2,
+    Program,
+    Erase,
+    EraseUnlock1,
+    EraseUnlock2,
+}
+
+impl State {
+    fn to_u8(self) -> u8 {
+        match self {
+            State::Ready => 0,
+            State::Unlock1 => 1,
+            State::Unlock2 => 2,
+            State::Program => 3,
+            State::Erase => 4,
+            State::EraseUnlock1 => 5,
+            State::EraseUnlock2 => 6,
+        }
+    }
+
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => State::Unlock1,
+            2 => State::Unlock2,
+            3 => State::Program,
+            4 => State::Erase,
+            5 => State::EraseUnlock1,
+            6 => State::EraseUnlock2,
+            _ => State::Ready,
+        }
+    }
+}
+
+/// The command interface of an SST39SF040 flash chip, which homebrew boards use as PRG-ROM so
+/// that games can save by reprogramming it. Addresses are offsets into the chip, whose contents
+/// are passed in on each access.
+pub struct SST39SF040 {
+    state: State,
+    software_id: bool,
+    modified: bool,
+}
+
+impl SST39SF040 {
+    pub fn new() -> Self {
+        Self {
+            state: State::Ready,
+            software_id: false,
+            modified: false,
+        }
+    }
+
+    /// Whether the contents have been reprogrammed since power-on.
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn read(&self, flash: &[u8], address: usize) -> u8 {
+        if self.software_id {
+            if (address & 1) == 0 {
+                MANUFACTURER_ID
+            } else {
+                DEVICE_ID
+            }
+        } else {
+            flash[address % flash.len()]
+        }
+    }
+
+    pub fn write(&mut self, flash: &mut [u8], address: usize, value: u8) {
+        // Only A0-A14 are decoded for commands
+        let command_address = address & 0x7FFF;
+        self.state = match (self.state, command_address, value) {
+            (State::Program, _, _) => {
+                // Programming can only clear bits
+                flash[address % flash.len()] &= value;
+                self.modified = true;
+                State::Ready
+            }
+            // A program's data byte can be $F0, so only reset outside of one
+            (_, _, 0xF0) => {
+                self.software_id = false;
+                State::Ready
+            }
+            (State::Ready, 0x5555, 0xAA) => State::Unlock1,
+            (State::Unlock1, 0x2AAA, 0x55) => State::Unlock2,
+            (State::Unlock2, 0x5555, 0xA0) => State::Program,
+            (State::Unlock2, 0x5555, 0x80) => State::Erase,
+            (State::Unlock2, 0x5555, 0x90) => {
+                self.software_id = true;
+                State::Ready
+            }
+            (State::Erase, 0x5555, 0xAA) => State::EraseUnlock1,
+            (State::EraseUnlock1, 0x2AAA, 0x55) => State::EraseUnlock2,
+            (State::EraseUnlock2, 0x5555, 0x10) => {
+                flash.fill(0xFF);
+                self.modified = true;
+                State::Ready
+            }
+            (State::EraseUnlock2, _, 0x30) => {
+                let sector = (address % flash.len()) & !0xFFF;
+                flash[sector..sector + 0x1000].fill(0xFF);
+                self.modified = true;
+                State::Ready
+            }
+            _ => State::Ready,
+        };
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.state.to_u8())?;
+        writer.write_bool(self.software_id)?;
+        writer.write_bool(self.modified)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.state = State::from_u8(reader.read_u8()?);
+        self.software_id = reader.read_bool()?;
+        self.modified = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SST39SF040;
+
+    fn program(chip: &mut SST39SF040, flash: &mut [u8], address: usize, value: u8) {
+        chip.write(flash, 0x5555, 0xAA);
+        chip.write(flash, 0x2AAA, 0x55);
+        chip.write(flash, 0x5555, 0xA0);
+        chip.write(flash, address, value);
+    }
+
+    #[test]
+    fn test_program_f0() {
+        let mut chip = SST39SF040::new();
+        let mut flash = vec![0xFF; 0x80000];
+        program(&mut chip, &mut flash, 0x1234, 0xF0);
+        program(&mut chip, &mut flash, 0x1235, 0x42);
+        assert_eq!(chip.read(&flash, 0x1234), 0xF0);
+        assert_eq!(chip.read(&flash, 0x1235), 0x42);
+        assert!(chip.modified());
+
+        // Outside of a program, $F0 still resets the chip out of software ID mode
+        chip.write(&mut flash, 0x5555, 0xAA);
+        chip.write(&mut flash, 0x2AAA, 0x55);
+        chip.write(&mut flash, 0x5555, 0x90);
+        assert_eq!(chip.read(&flash, 0), 0xBF);
+        chip.write(&mut flash, 0, 0xF0);
+        assert_eq!(chip.read(&flash, 0x1234), 0xF0);
+    }
+}
@@ -0,0 +1,292 @@
+use alloc::vec::Vec;
+
+use crate::{
+    apu::Sunsoft5BAudio,
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, cycle_irq::CpuCycleIrq, load_battery_ram};
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct FME7 {
+    ines: INES,
+    nametables: Nametables,
+    ram: [u8; 1024 * 8], // 8KB
+    command: u8,
+    chr_banks: [u8; 8],
+    prg_banks: [u8; 4],
+    mirroring: u8,
+    irq: CpuCycleIrq,
+    audio: Sunsoft5BAudio,
+}
+
+impl FME7 {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            ram: [0; 1024 * 8],
+            command: 0,
+            chr_banks: [0; 8],
+            prg_banks: [0; 4],
+            mirroring: 0,
+            // The counter wraps from $0000 to $FFFF, raising the IRQ as it does
+            irq: CpuCycleIrq::new(0xFFFF),
+            audio: Sunsoft5BAudio::new(),
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+        let bank = self.chr_banks[(address >> 10) as usize & 7] as usize % num_1k_chunks;
+        bank * 1024 + (address & 0x3FF) as usize
+    }
+
+    /// Bit 6 of the $6000 bank register maps RAM instead of ROM, bit 7 enables that RAM.
+    fn ram_selected(&self) -> bool {
+        (self.prg_banks[0] & 0x40) != 0
+    }
+
+    fn ram_enabled(&self) -> bool {
+        (self.prg_banks[0] & 0xC0) == 0xC0
+    }
+
+    fn write_parameter(&mut self, value: u8) {
+        match self.command {
+            0x0..=0x7 => self.chr_banks[self.command as usize] = value,
+            0x8..=0xB => self.prg_banks[(self.command - 8) as usize] = value,
+            0xC => self.mirroring = value & 0b11,
+            0xD => {
+                self.irq.irq_enabled = (value & 0x01) != 0;
+                self.irq.counting = (value & 0x80) != 0;
+                self.irq.acknowledge();
+            }
+            0xE => self.irq.counter = (self.irq.counter & 0xFF00) | value as u16,
+            _ => self.irq.counter = (self.irq.counter & 0x00FF) | (value as u16) << 8,
+        }
+    }
+}
+
+impl Cartridge for FME7 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if self.ram_enabled() => {
+                self.ram[address.lower_8k() as usize] = value;
+            }
+            0x8000..=0x9FFF => self.command = value & 0x0F,
+            0xA000..=0xBFFF => self.write_parameter(value),
+            0xC000..=0xDFFF => self.audio.write_address(value),
+            0xE000..=0xFFFF => self.audio.write_data(value),
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.audio.tick();
+        self.irq.tick()
+    }
+
+    fn audio_output(&self) -> i16 {
+        self.audio.output()
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        (self.ram_selected() && self.ram_enabled()).then_some(&self.ram[..])
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+        let bank = match address {
+            0x6000..=0x7FFF if self.ram_selected() => {
+                return self
+                    .ram_enabled()
+                    .then(|| MemoryLocation::new(CartMemory::PrgRam, address.lower_8k() as usize));
+            }
+            0x6000..=0x7FFF => self.prg_banks[0] as usize & 0x3F,
+            0x8000..=0x9FFF => self.prg_banks[1] as usize & 0x3F,
+            0xA000..=0xBFFF => self.prg_banks[2] as usize & 0x3F,
+            0xC000..=0xDFFF => self.prg_banks[3] as usize & 0x3F,
+            0xE000..=0xFFFF => num_8k_chunks - 1,
+            _ => return None,
+        } % num_8k_chunks;
+
+        let addr = bank * 0x2000 + address.lower_8k() as usize;
+        Some(MemoryLocation::new(CartMemory::PrgRom, addr))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+}
+
+impl CartridgeSaveLoad for FME7 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_u8(self.command)?;
+        writer.write_all(&self.chr_banks)?;
+        writer.write_all(&self.prg_banks)?;
+        writer.write_u8(self.mirroring)?;
+        self.irq.save(writer)?;
+        self.audio.save(writer)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        self.command = reader.read_u8()?;
+        reader.read_exact(&mut self.chr_banks)?;
+        reader.read_exact(&mut self.prg_banks)?;
+        self.mirroring = reader.read_u8()?;
+        self.irq.load(reader)?;
+        self.audio.load(reader)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FME7;
+    use crate::{cartridge::Cartridge, ines::test_helpers::TestRom};
+
+    /// 64KB of PRG-ROM with each 8KB bank filled with its number.
+    fn fme7() -> FME7 {
+        FME7::new(
+            TestRom::new(69, 4, 1)
+                .prg_with(|offset| (offset / 0x2000) as u8)
+                .ines(),
+        )
+    }
+
+    fn command(cart: &mut FME7, command: u8, value: u8) {
+        cart.cpu_write(0x8000, command);
+        cart.cpu_write(0xA000, value);
+    }
+
+    #[test]
+    fn test_prg_ram_select() {
+        let mut cart = fme7();
+        command(&mut cart, 0x8, 0x03);
+        assert_eq!(cart.cpu_read(0x6000), 3);
+
+        // Selected but disabled, the RAM is neither readable nor writable
+        command(&mut cart, 0x8, 0x40);
+        cart.cpu_write(0x6000, 0x55);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+        assert!(cart.work_ram().is_none());
+
+        command(&mut cart, 0x8, 0xC0);
+        cart.cpu_write(0x6000, 0x55);
+        assert_eq!(cart.cpu_read(0x6000), 0x55);
+
+        // Back to ROM, which the RAM write left alone
+        command(&mut cart, 0x8, 0x03);
+        assert_eq!(cart.cpu_read(0x6000), 3);
+        command(&mut cart, 0x8, 0xC0);
+        assert_eq!(cart.cpu_read(0x6000), 0x55);
+    }
+
+    #[test]
+    fn test_irq_wrap() {
+        let mut cart = fme7();
+        command(&mut cart, 0xE, 0x01);
+        command(&mut cart, 0xF, 0x00);
+        command(&mut cart, 0xD, 0x81);
+        assert!(!cart.cpu_tick());
+        assert!(cart.cpu_tick());
+        assert_eq!(cart.irq.counter, 0xFFFF);
+
+        // The counter keeps going, and the line stays up until $D is written
+        assert!(cart.cpu_tick());
+        assert_eq!(cart.irq.counter, 0xFFFE);
+        command(&mut cart, 0xD, 0x80);
+        assert!(!cart.cpu_tick());
+
+        // Counting without the IRQ enabled wraps silently
+        command(&mut cart, 0xE, 0x00);
+        command(&mut cart, 0xF, 0x00);
+        assert!(!cart.cpu_tick());
+        assert_eq!(cart.irq.counter, 0xFFFF);
+    }
+}
@@ -0,0 +1,161 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::Mirroring,
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::flash::SST39SF040;
+
+/// Membler Industries' GTROM (Cheapocabra): 512KB of self-flashable PRG in 32KB banks, two 8KB
+/// banks of CHR-RAM and two 8KB pages of four-screen nametable RAM. The register at
+/// $5000-$5FFF and $7000-$7FFF also drives two LEDs, which aren't shown.
+#[allow(clippy::upper_case_acronyms)]
+pub struct GTROM {
+    ines: INES,
+    flash: SST39SF040,
+    nametable_ram: Vec<u8>,
+    bank_select: u8,
+}
+
+impl GTROM {
+    pub fn new(mut ines: INES) -> Self {
+        if ines.is_chr_ram && ines.chr_rom.len() < 0x4000 {
+            ines.chr_rom.resize(0x4000, 0);
+        }
+
+        Self {
+            ines,
+            flash: SST39SF040::new(),
+            nametable_ram: vec![0; 0x4000],
+            bank_select: 0,
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let bank = ((self.bank_select >> 4) & 1) as usize;
+        (bank * 0x2000 + address.lower_8k() as usize) % self.ines.chr_rom.len()
+    }
+
+    fn nametable_addr(&self, address: u16) -> usize {
+        let page = ((self.bank_select >> 5) & 1) as usize;
+        page * 0x2000 + address.lower_8k() as usize
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        let bank = (self.bank_select & 0x0F) as usize;
+        (bank * 0x8000 + address.lower_32k() as usize) % self.ines.prg_rom.len()
+    }
+}
+
+impl Cartridge for GTROM {
+    fn ppu_read(&mut self, address: u16, _ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametable_ram[self.nametable_addr(address)]
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, _ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            let addr = self.nametable_addr(address);
+            self.nametable_ram[addr] = value;
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        if address >= 0x8000 {
+            self.flash.read(&self.ines.prg_rom, self.prg_addr(address))
+        } else {
+            0
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000..=0x5FFF | 0x7000..=0x7FFF => self.bank_select = value,
+            0x8000..=0xFFFF => {
+                let addr = self.prg_addr(address);
+                self.flash.write(&mut self.ines.prg_rom, addr, value);
+            }
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::MapperControlled
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        self.flash.modified().then(|| self.ines.prg_rom.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        if data.len() == self.ines.prg_rom.len() {
+            self.ines.prg_rom.copy_from_slice(data);
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        Some(if (address & BIT_13) == BIT_13 {
+            MemoryLocation::new(CartMemory::Vram, self.nametable_addr(address))
+        } else {
+            MemoryLocation::new(CartMemory::ChrRom, self.chr_addr(address))
+        })
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => &self.nametable_ram,
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => &mut self.nametable_ram,
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for GTROM {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.bank_select)?;
+        writer.write_all(&self.nametable_ram)?;
+        writer.write_all(&self.ines.chr_rom)?;
+        self.flash.save(writer)?;
+        writer.write_all(&self.ines.prg_rom)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.bank_select = reader.read_u8()?;
+        reader.read_exact(&mut self.nametable_ram)?;
+        reader.read_exact(&mut self.ines.chr_rom)?;
+        self.flash.load(reader)?;
+        reader.read_exact(&mut self.ines.prg_rom)?;
+
+        Ok(())
+    }
+}
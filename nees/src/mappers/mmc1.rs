@@ -1,189 +1,297 @@
+use alloc::{vec, vec::Vec};
+
 use crate::{
-    bit_helpers::SubType,
-    cartridge::{Cartridge, CartridgeSaveLoad, CartridgeWithSaveLoad},
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
     ines::INES,
+    mirroring::{Mirroring, Nametables},
     reader_writer::{EasyReader, EasyWriter},
 };
 
+use super::{battery_ram, load_battery_ram};
+
+/// The Nintendo MMC1 and the SxROM boards built around it. Boards with more than 256KB of PRG-ROM
+/// (SUROM, SXROM) or more than 8KB of PRG-RAM (SOROM, SXROM) repurpose the CHR bank bits, which
+/// only ever address 8KB of CHR-RAM there, as the upper PRG-ROM and PRG-RAM address lines.
 #[allow(clippy::upper_case_acronyms)]
 pub struct MMC1 {
     ines: INES,
-    control_reg: u8,
+    nametables: Nametables,
+    ram: Vec<u8>,
+    /// SEROM, SHROM and SH1ROM wire PRG-ROM straight to the CPU, ignoring the PRG bank.
+    fixed_prg: bool,
+    /// The MMC1A has no WRAM disable bit.
+    is_mmc1a: bool,
+
     sr: u8,
     shift_count: u8,
+    /// Set by a serial port write and cleared every CPU cycle, so that the second write of a
+    /// read-modify-write instruction is ignored like on hardware.
+    written_this_cycle: bool,
 
-    chr_bank_4_lo: u8,
-    chr_bank_4_hi: u8,
-    chr_bank_8: u8,
-
-    prg_bank_lo: u8,
-    prg_bank_hi: u8,
-    prg_bank_32: u8,
-
-    mirroring: u8,
-
-    ram: [u8; 1024 * 32], // 32KB
+    control_reg: u8,
+    chr_bank_0: u8,
+    chr_bank_1: u8,
+    prg_bank: u8,
+    /// PPU A12 of the last pattern fetch, which picks the CHR register driving the extra lines in
+    /// 4KB CHR mode.
+    chr_a12: bool,
 }
 
 impl MMC1 {
     pub fn new(ines: INES) -> Self {
-        let prg_rom_size_16k_chunks = ines.prg_rom_size_16k_chunks;
+        let ram_size = match (ines.prg_ram_size, ines.prg_nvram_size) {
+            (Some(ram), Some(nvram)) => ram + nvram,
+            _ => 1024 * 8,
+        };
+        let fixed_prg = ines.mapper_no == 1 && ines.submapper == Some(5);
+        let is_mmc1a = ines.mapper_no == 155;
+
         Self {
+            nametables: Nametables::default(),
             ines,
-            control_reg: 0x1c,
+            ram: vec![0; ram_size],
+            fixed_prg,
+            is_mmc1a,
+
             sr: 0,
             shift_count: 0,
+            written_this_cycle: false,
 
-            mirroring: 3,
-            chr_bank_4_lo: 0,
-            chr_bank_4_hi: 0,
-            chr_bank_8: 0,
+            control_reg: 0x1F,
+            chr_bank_0: 0,
+            chr_bank_1: 0,
+            prg_bank: 0,
+            chr_a12: false,
+        }
+    }
 
-            prg_bank_lo: 0,
-            prg_bank_hi: prg_rom_size_16k_chunks - 1,
-            prg_bank_32: 0,
+    fn is_chr_4k_mode(&self) -> bool {
+        (self.control_reg & 0b10000) != 0
+    }
 
-            ram: [0; 1024 * 32],
+    /// The CHR register currently driving the CHR address lines.
+    fn active_chr_bank(&self) -> u8 {
+        if self.is_chr_4k_mode() && self.chr_a12 {
+            self.chr_bank_1
+        } else {
+            self.chr_bank_0
         }
     }
 
-    fn ppu_addr_to_ciram_addr(&self, ppuaddr: u16) -> u16 {
-        let mut a10_shift_count = self.ines.ppu_address_ciram_a10_shift_count;
-        match self.mirroring {
-            0 => {
-                // one-screen, lower bank
-            }
-            1 => {
-                // one-screen, upper bank
+    fn chr_addr(&self, address: u16) -> usize {
+        let num_4k_chunks = self.ines.chr_rom.len() / 0x1000;
+        let bank = if self.is_chr_4k_mode() {
+            if address < 0x1000 {
+                self.chr_bank_0
+            } else {
+                self.chr_bank_1
             }
-            2 => a10_shift_count = 10,
-            3 => {
-                // horizontal
-                a10_shift_count = 11;
+        } else {
+            (self.chr_bank_0 & 0x1E) | ((address >> 12) & 1) as u8
+        };
+        (bank as usize % num_4k_chunks) * 0x1000 + address.lower_4k() as usize
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        if self.fixed_prg {
+            return address.lower_32k() as usize % self.ines.prg_rom.len();
+        }
+
+        let num_16k_chunks = self.ines.prg_rom_size_16k_chunks as usize;
+        // SUROM and SXROM select a 256KB half with CHR bank bit 4
+        let outer = if num_16k_chunks > 16 {
+            (self.active_chr_bank() & 0x10) as usize
+        } else {
+            0
+        };
+        let bank = (self.prg_bank & 0x0F) as usize;
+        let bank = match ((self.control_reg >> 2) & 0b11, address >= 0xC000) {
+            (0 | 1, false) => bank & 0x0E,
+            (0 | 1, true) => bank | 0x01,
+            // fix first bank at $8000 and switch 16 KB bank at $C000
+            (2, false) => 0,
+            (2, true) => bank,
+            // fix last bank at $C000 and switch 16 KB bank at $8000
+            (_, false) => bank,
+            (_, true) => 0x0F,
+        };
+        (((outer | bank) % num_16k_chunks) * 0x4000) + address.lower_16k() as usize
+    }
+
+    fn ram_addr(&self, address: u16) -> Option<usize> {
+        let wram_disabled = !self.is_mmc1a && (self.prg_bank & 0x10) != 0;
+        if self.ram.is_empty() || wram_disabled {
+            return None;
+        }
+
+        // SOROM selects its 8KB bank with CHR bank bit 3, SXROM with bits 2-3
+        let chr_bank = self.active_chr_bank();
+        let bank = match self.ram.len() / 0x2000 {
+            2 => (chr_bank >> 3) & 1,
+            _ => (chr_bank >> 2) & 0b11,
+        } as usize;
+        Some((bank * 0x2000 + address.lower_8k() as usize) % self.ram.len())
+    }
+
+    /// NES 2.0 puts the battery-backed RAM first, like SOROM, whose second 8KB bank is lost at
+    /// power off.
+    fn battery_ram_len(&self) -> usize {
+        match self.ines.prg_nvram_size {
+            Some(nvram) if nvram > 0 => nvram.min(self.ram.len()),
+            _ => self.ram.len(),
+        }
+    }
+
+    fn write_serial(&mut self, address: u16, value: u8) {
+        if (value & 0x80) != 0 {
+            self.sr = 0;
+            self.shift_count = 0;
+            self.control_reg |= 0x0C;
+            return;
+        }
+
+        self.sr = (self.sr >> 1) | ((value & 1) << 4);
+        self.shift_count += 1;
+        if self.shift_count == 5 {
+            match (address >> 13) & 0b11 {
+                0 => self.control_reg = self.sr,
+                1 => self.chr_bank_0 = self.sr,
+                2 => self.chr_bank_1 = self.sr,
+                _ => self.prg_bank = self.sr,
             }
-            _ => {}
+            self.sr = 0;
+            self.shift_count = 0;
         }
-        (ppuaddr & 0x3ff) | (((ppuaddr >> a10_shift_count) & 1) << 10)
     }
 }
 
-const BIT_13: u16 = 1 << 13;
-
 impl Cartridge for MMC1 {
-    fn ppu_read(&mut self, address: u16, ciram: &[u8]) -> u8 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.ppu_addr_to_ciram_addr(address) as usize]
-        } else if self.ines.is_chr_ram {
-            self.ines.chr_rom[address as usize]
-        } else if (self.control_reg & 0b10000) != 0 {
-            // switch two separate 4 KB banks
-            if address < 0x1000 {
-                self.ines.chr_rom
-                    [(self.chr_bank_4_lo as usize) * 0x1000 + address.lower_4k() as usize]
-            } else {
-                self.ines.chr_rom
-                    [(self.chr_bank_4_hi as usize) * 0x1000 + address.lower_4k() as usize]
-            }
+            self.nametables.read(self.mirroring(), address, ciram)
         } else {
-            // switch 8 KB at a time
-            self.ines.chr_rom[(self.chr_bank_8 % self.ines.chr_rom_size_8kb_chunks) as usize
-                * 0x2000
-                + (address & 0x1FFF) as usize]
+            self.chr_a12 = (address & 0x1000) != 0;
+            self.ines.chr_rom[self.chr_addr(address)]
         }
     }
 
     fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.ppu_addr_to_ciram_addr(address) as usize] = value;
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
         } else if self.ines.is_chr_ram {
-            self.ines.chr_rom[address as usize] = value;
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
         }
     }
 
-    fn cpu_read(&self, address: u16) -> u8 {
-        if address >= 0x6000 && address <= 0x7FFF {
-            self.ram[address as usize]
-        } else if address >= 0x8000 {
-            if (self.control_reg & 0b01000) != 0 {
-                if address >= 0xC000 {
-                    self.ines.prg_rom
-                        [(self.prg_bank_hi as usize) * 0x4000 + address.lower_16k() as usize]
-                } else {
-                    self.ines.prg_rom
-                        [(self.prg_bank_lo as usize) * 0x4000 + address.lower_16k() as usize]
-                }
-            } else {
-                self.ines.prg_rom
-                    [(self.prg_bank_32 as usize) * 0x8000 + address.lower_32k() as usize]
-            }
-        } else {
-            0
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => match self.ram_addr(address) {
+                Some(addr) => self.ram[addr],
+                None => 0,
+            },
+            0x8000..=0xFFFF => self.ines.prg_rom[self.prg_addr(address)],
+            _ => 0,
         }
     }
 
     fn cpu_write(&mut self, address: u16, value: u8) {
-        if address >= 0x6000 && address <= 0x7FFF {
-            self.ram[address as usize] = value;
-        } else if address >= 0x8000 {
-            if (value & 0x80) != 0 {
-                self.sr = 0;
-                self.shift_count = 0;
-            } else {
-                self.sr = (self.sr >> 1) | ((value & 1) << 4);
-                self.shift_count += 1;
-                if self.shift_count == 5 {
-                    let reg = (address >> 13) & 0b11;
-                    match reg {
-                        0 => {
-                            // Control
-                            self.mirroring = self.sr & 0b11;
-                            self.control_reg = self.sr & 0x1f;
-                        }
-                        1 => {
-                            // CHR bank 0
-                            if (self.control_reg & 0b10000) != 0 {
-                                self.chr_bank_4_lo = self.sr & 0x1f;
-                            } else {
-                                self.chr_bank_8 = self.sr & 0x1e;
-                            }
-                        }
-                        2 => {
-                            if (self.control_reg & 0b10000) != 0 {
-                                self.chr_bank_4_hi = self.sr & 0x1F;
-                            }
-                        }
-                        3 => {
-                            let prg_mode = (self.control_reg >> 2) & 0x03;
-                            match prg_mode {
-                                0 | 1 => {
-                                    // switch 32 KB at $8000
-                                    self.prg_bank_32 = (self.sr & 0x0e) >> 1;
-                                }
-                                2 => {
-                                    // fix first bank at $8000 and switch 16 KB bank at $C000
-                                    self.prg_bank_lo = 0;
-                                    self.prg_bank_hi = self.sr & 0x0f;
-                                }
-                                3 => {
-                                    // fix last bank at $C000 and switch 16 KB bank at $8000)
-                                    self.prg_bank_lo = self.sr & 0x0f;
-                                    self.prg_bank_hi = self.ines.prg_rom_size_16k_chunks - 1;
-                                }
-                                _ => {}
-                            }
-                        }
-                        _ => {}
-                    }
-                    self.sr = 0;
-                    self.shift_count = 0;
+        match address {
+            0x6000..=0x7FFF => {
+                if let Some(addr) = self.ram_addr(address) {
+                    self.ram[addr] = value;
                 }
             }
+            0x8000..=0xFFFF => {
+                // Only the first of writes on consecutive cycles reaches the shift register
+                let consecutive = self.written_this_cycle;
+                self.written_this_cycle = true;
+                if !consecutive {
+                    self.write_serial(address, value);
+                }
+            }
+            _ => {}
         }
     }
 
     fn scanline(&mut self) -> bool {
         false
     }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control_reg & 0b11 {
+            0 => Mirroring::SingleScreenA,
+            1 => Mirroring::SingleScreenB,
+            2 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.written_this_cycle = false;
+        false
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        let start = self.ram_addr(0x6000)?;
+        self.ram.get(start..(start + 0x2000).min(self.ram.len()))
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram[..self.battery_ram_len()])
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let len = self.battery_ram_len();
+        load_battery_ram(&mut self.ram[..len], data);
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x6000..=0x7FFF => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                self.ram_addr(address)?,
+            )),
+            0x8000..=0xFFFF => Some(MemoryLocation::new(
+                CartMemory::PrgRom,
+                self.prg_addr(address),
+            )),
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
 }
 
 impl CartridgeSaveLoad for MMC1 {
@@ -191,14 +299,15 @@ impl CartridgeSaveLoad for MMC1 {
         writer.write_u8(self.control_reg)?;
         writer.write_u8(self.sr)?;
         writer.write_u8(self.shift_count)?;
-        writer.write_u8(self.chr_bank_4_lo)?;
-        writer.write_u8(self.chr_bank_4_hi)?;
-        writer.write_u8(self.chr_bank_8)?;
-        writer.write_u8(self.prg_bank_lo)?;
-        writer.write_u8(self.prg_bank_hi)?;
-        writer.write_u8(self.prg_bank_32)?;
-        writer.write_u8(self.mirroring)?;
+        writer.write_bool(self.written_this_cycle)?;
+        writer.write_u8(self.chr_bank_0)?;
+        writer.write_u8(self.chr_bank_1)?;
+        writer.write_u8(self.prg_bank)?;
+        writer.write_bool(self.chr_a12)?;
         writer.write_all(&self.ram)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
 
         Ok(())
     }
@@ -207,17 +316,116 @@ impl CartridgeSaveLoad for MMC1 {
         self.control_reg = reader.read_u8()?;
         self.sr = reader.read_u8()?;
         self.shift_count = reader.read_u8()?;
-        self.chr_bank_4_lo = reader.read_u8()?;
-        self.chr_bank_4_hi = reader.read_u8()?;
-        self.chr_bank_8 = reader.read_u8()?;
-        self.prg_bank_lo = reader.read_u8()?;
-        self.prg_bank_hi = reader.read_u8()?;
-        self.prg_bank_32 = reader.read_u8()?;
-        self.mirroring = reader.read_u8()?;
+        self.written_this_cycle = reader.read_bool()?;
+        self.chr_bank_0 = reader.read_u8()?;
+        self.chr_bank_1 = reader.read_u8()?;
+        self.prg_bank = reader.read_u8()?;
+        self.chr_a12 = reader.read_bool()?;
         reader.read_exact(&mut self.ram)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
 
         Ok(())
     }
 }
 
-impl CartridgeWithSaveLoad for MMC1 {}
+#[cfg(test)]
+mod tests {
+    use super::MMC1;
+    use crate::{
+        cartridge::{Cartridge, PPUFetch},
+        ines::test_helpers::TestRom,
+    };
+
+    /// Each 16KB of PRG-ROM filled with its number, and CHR-RAM.
+    fn rom(prg_16k_chunks: u8) -> TestRom {
+        TestRom::new(1, prg_16k_chunks, 0).prg_with(|offset| (offset / 0x4000) as u8)
+    }
+
+    /// Shifts a register value in a bit at a time, a cycle apart.
+    fn write_register(cart: &mut MMC1, address: u16, value: u8) {
+        for i in 0..5 {
+            cart.cpu_write(address, value >> i);
+            cart.cpu_tick();
+        }
+    }
+
+    #[test]
+    fn test_consecutive_writes_ignored() {
+        let mut cart = MMC1::new(rom(8).ines());
+        // Like the two writes of INC, only the first goes in
+        cart.cpu_write(0xE000, 1);
+        cart.cpu_write(0xE000, 1);
+        cart.cpu_tick();
+        for _ in 0..4 {
+            cart.cpu_write(0xE000, 0);
+            cart.cpu_tick();
+        }
+        assert_eq!(cart.cpu_read(0x8000), 1);
+        assert_eq!(cart.cpu_read(0xC000), 7);
+    }
+
+    #[test]
+    fn test_surom_outer_bank() {
+        let mut cart = MMC1::new(rom(32).ines());
+        write_register(&mut cart, 0xE000, 2);
+        assert_eq!((cart.cpu_read(0x8000), cart.cpu_read(0xC000)), (2, 15));
+
+        write_register(&mut cart, 0xA000, 0x10);
+        assert_eq!((cart.cpu_read(0x8000), cart.cpu_read(0xC000)), (18, 31));
+
+        // In 4KB CHR mode the register for the last pattern fetch drives the line
+        write_register(&mut cart, 0x8000, 0x1C);
+        cart.ppu_read(0x1000, &[0; 0x800], PPUFetch::Background);
+        assert_eq!(cart.cpu_read(0x8000), 2);
+        cart.ppu_read(0x0000, &[0; 0x800], PPUFetch::Background);
+        assert_eq!(cart.cpu_read(0x8000), 18);
+    }
+
+    #[test]
+    fn test_sorom_saves_only_the_battery_bank() {
+        // 8KB of RAM and 8KB of battery-backed RAM
+        let sorom = || MMC1::new(rom(16).prg_ram(7, 7).flags6(0x02).ines());
+        let mut cart = sorom();
+        cart.cpu_write(0x6000, 0x11);
+        write_register(&mut cart, 0xA000, 0x08);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+        cart.cpu_write(0x6000, 0x22);
+
+        let battery = cart.battery_data().unwrap();
+        assert_eq!(battery.len(), 0x2000);
+        let mut restored = sorom();
+        restored.load_battery_data(&battery);
+        assert_eq!(restored.cpu_read(0x6000), 0x11);
+        write_register(&mut restored, 0xA000, 0x08);
+        assert_eq!(restored.cpu_read(0x6000), 0);
+    }
+
+    #[test]
+    fn test_sxrom_ram_banks() {
+        let mut cart = MMC1::new(rom(32).prg_ram(0, 9).flags6(0x02).ines());
+        write_register(&mut cart, 0xA000, 0x0C);
+        cart.cpu_write(0x6000, 0x33);
+        write_register(&mut cart, 0xA000, 0x00);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+
+        let battery = cart.battery_data().unwrap();
+        assert_eq!(battery.len(), 0x8000);
+        assert_eq!(battery[3 * 0x2000], 0x33);
+    }
+
+    #[test]
+    fn test_wram_disable() {
+        let mut cart = MMC1::new(rom(8).ines());
+        cart.cpu_write(0x6000, 0x44);
+        write_register(&mut cart, 0xE000, 0x10);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+
+        // The MMC1A has no disable bit
+        let mut cart = MMC1::new(TestRom::new(155, 8, 0).ines());
+        cart.cpu_write(0x6000, 0x44);
+        write_register(&mut cart, 0xE000, 0x10);
+        assert_eq!(cart.cpu_read(0x6000), 0x44);
+    }
+}
@@ -1,17 +1,45 @@
+use alloc::{vec, vec::Vec};
+
 use crate::{
-    cartridge::{Cartridge, CartridgeSaveLoad, CartridgeWithSaveLoad},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
     ines::INES,
+    mirroring::{Mirroring, Nametables},
     reader_writer::{EasyReader, EasyWriter},
 };
 
+use super::{battery_ram, load_battery_ram};
+
+/// Boards built around the MMC3 or a chip compatible with a subset of it.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Board {
+    MMC3,
+    /// The MMC6 has 1KB of internal RAM at $7000, with separate protection for each 512B half.
+    MMC6,
+    /// TKSROM and TLSROM wire CHR bank bit 7 to CIRAM A10 in place of the mirroring register.
+    TxSROM,
+    /// TQROM has both CHR-ROM and 8KB of CHR-RAM, with CHR bank bit 6 choosing between them.
+    TQROM,
+    /// The Namco 108 only has the bank registers, and no PRG or CHR mode bits.
+    Namco108,
+}
+
 #[allow(clippy::upper_case_acronyms)]
 pub struct MMC3 {
     ines: INES,
+    nametables: Nametables,
+    board: Board,
+    /// The MMC3A and some MMC3B only raise an IRQ when the counter is decremented or explicitly
+    /// reloaded to zero, not every time it's reloaded from a zero latch.
+    old_irq_behaviour: bool,
     ram: [u8; 1024 * 8], // 8KB,
+    chr_ram: Vec<u8>,
     mirroring: u8,
     bank_to_update: u8,
     prg_rom_bank_mode: bool,
     chr_a12_inversion: bool,
+    ram_protect: u8,
+    mmc6_ram_enabled: bool,
 
     chr_banks: [u8; 8],
     prg_banks: [u8; 4],
@@ -20,144 +48,243 @@ pub struct MMC3 {
     irq_counter: u16,
     irq_enabled: bool,
     irq_reload: bool,
+    irq_pending: bool,
 }
 
 impl MMC3 {
     pub fn new(ines: INES) -> Self {
-        let prg_banks = [
-            0,
-            1,
-            ines.prg_rom_size_16k_chunks * 2 - 2,
-            ines.prg_rom_size_16k_chunks * 2 - 1,
-        ];
-
-        Self {
+        let board = match (ines.mapper_no, ines.submapper) {
+            (4, Some(1)) => Board::MMC6,
+            (118, _) => Board::TxSROM,
+            (119, _) => Board::TQROM,
+            (206, _) => Board::Namco108,
+            _ => Board::MMC3,
+        };
+        let old_irq_behaviour = ines.mapper_no == 4 && ines.submapper == Some(4);
+        let chr_ram = match board {
+            Board::TQROM => vec![0; 1024 * 8],
+            _ => Vec::new(),
+        };
+
+        let mut mmc3 = Self {
+            nametables: Nametables::new(ines.fixed_mirroring()),
             ines,
+            board,
+            old_irq_behaviour,
             mirroring: 0,
             bank_to_update: 0,
             prg_rom_bank_mode: false,
             chr_a12_inversion: false,
+            // Enabled and writable, for dumps of games that never set it
+            ram_protect: 0x80,
+            mmc6_ram_enabled: false,
             registers: [0; 8],
             chr_banks: [0; 8],
-            prg_banks,
+            prg_banks: [0; 4],
 
             irq_latch: 0,
             irq_counter: 0,
             irq_enabled: false,
             irq_reload: false,
+            irq_pending: false,
 
             ram: [0; 1024 * 8],
+            chr_ram,
+        };
+        mmc3.update_banks();
+        mmc3
+    }
+
+    /// The MMC6 only has 1KB, which is all its saves hold.
+    fn battery_ram_len(&self) -> usize {
+        match self.board {
+            Board::MMC6 => 0x400,
+            _ => self.ram.len(),
         }
     }
 
-    fn ppu_addr_to_ciram_addr(&self, ppuaddr: u16) -> u16 {
-        let a10_shift_count = match self.mirroring {
-            0 => 10,
-            _ => 11,
+    /// TxSROM picks the CIRAM page of each nametable with the matching CHR bank.
+    fn txsrom_ciram_addr(&self, address: u16) -> usize {
+        let a10 = (self.chr_banks[((address >> 10) & 3) as usize] >> 7) as usize;
+        (a10 << 10) | (address & 0x3ff) as usize
+    }
+
+    fn update_banks(&mut self) {
+        let r = &self.registers;
+        let (two_kb, one_kb) = if self.chr_a12_inversion {
+            (4, 0)
+        } else {
+            (0, 4)
         };
-        (ppuaddr & 0x3ff) | (((ppuaddr >> a10_shift_count) & 1) << 10)
+        self.chr_banks[two_kb] = r[0] & 0xFE;
+        self.chr_banks[two_kb + 1] = r[0] | 0x01;
+        self.chr_banks[two_kb + 2] = r[1] & 0xFE;
+        self.chr_banks[two_kb + 3] = r[1] | 0x01;
+        self.chr_banks[one_kb..one_kb + 4].copy_from_slice(&r[2..6]);
+
+        let second_last = self.ines.prg_rom_size_16k_chunks * 2 - 2;
+        if self.prg_rom_bank_mode {
+            self.prg_banks[0] = second_last;
+            self.prg_banks[2] = r[6] & 0x3F;
+        } else {
+            self.prg_banks[0] = r[6] & 0x3F;
+            self.prg_banks[2] = second_last;
+        }
+        self.prg_banks[1] = r[7] & 0x3F;
+        self.prg_banks[3] = second_last + 1;
+    }
+
+    /// Resolves a pattern table address to CHR memory, returning whether it is in TQROM's
+    /// CHR-RAM along with the offset.
+    fn chr_addr(&self, address: u16) -> (bool, usize) {
+        let bank = self.chr_banks[(address >> 10) as usize & 7] as usize;
+        let offset = (address & 0x3FF) as usize;
+        if self.board == Board::TQROM && (bank & 0x40) != 0 {
+            return (true, (bank & 7) * 1024 + offset);
+        }
+
+        let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+        (false, (bank % num_1k_chunks) * 1024 + offset)
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+        let bank = match address {
+            0x8000..=0x9FFF => self.prg_banks[0],
+            0xA000..=0xBFFF => self.prg_banks[1],
+            0xC000..=0xDFFF => self.prg_banks[2],
+            0xE000..=0xFFFF => self.prg_banks[3],
+            _ => 0,
+        } as usize
+            % num_8k_chunks;
+
+        bank * 8192 + (address & 0x1FFF) as usize
+    }
+
+    /// The RAM offset a read from $6000-$7FFF comes from, or None for open bus.
+    fn ram_addr(&self, address: u16) -> Option<usize> {
+        match self.board {
+            Board::Namco108 => None,
+            Board::MMC6 => self.mmc6_ram_addr(address),
+            _ => ((self.ram_protect & 0x80) != 0).then_some((address & 0x1FFF) as usize),
+        }
+    }
+
+    fn ram_read(&self, address: u16) -> u8 {
+        self.ram_addr(address).map_or(0, |addr| self.ram[addr])
+    }
+
+    fn ram_write(&mut self, address: u16, value: u8) {
+        if self.board == Board::MMC6 {
+            self.mmc6_ram_write(address, value);
+        } else if (self.ram_protect & 0xC0) == 0x80 {
+            self.ram[(address & 0x1FFF) as usize] = value;
+        }
+    }
+
+    /// Bits 5 and 7 of the protect register enable reads from the lower and upper half, and
+    /// bits 4 and 6 writes.
+    fn mmc6_half_access(&self, address: u16) -> (bool, bool) {
+        let shift = if (address & 0x200) != 0 { 6 } else { 4 };
+        let bits = self.ram_protect >> shift;
+        ((bits & 0b10) != 0, (bits & 0b11) == 0b11)
+    }
+
+    fn mmc6_ram_addr(&self, address: u16) -> Option<usize> {
+        if address < 0x7000 || !self.mmc6_ram_enabled || (self.ram_protect & 0xA0) == 0 {
+            // Open bus
+            return None;
+        }
+
+        // When only the other half is readable the chip drives zeros, which reads the same as
+        // open bus here
+        self.mmc6_half_access(address)
+            .0
+            .then_some((address & 0x3FF) as usize)
+    }
+
+    fn mmc6_ram_write(&mut self, address: u16, value: u8) {
+        if address >= 0x7000 && self.mmc6_ram_enabled && self.mmc6_half_access(address).1 {
+            self.ram[(address & 0x3FF) as usize] = value;
+        }
     }
 }
 
 const BIT_13: u16 = 1 << 13;
 
 impl Cartridge for MMC3 {
-    fn ppu_read(&mut self, address: u16, ciram: &[u8]) -> u8 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.ppu_addr_to_ciram_addr(address) as usize]
+            if self.board == Board::TxSROM {
+                ciram[self.txsrom_ciram_addr(address)]
+            } else {
+                self.nametables.read(self.mirroring(), address, ciram)
+            }
         } else {
-            let num_1k_chunks = (self.ines.chr_rom_size_8kb_chunks as usize) * 8;
-            let bank = match address {
-                0x0000..=0x3FF => self.chr_banks[0],
-                0x0400..=0x7FF => self.chr_banks[1],
-                0x0800..=0xBFF => self.chr_banks[2],
-                0x0C00..=0xFFF => self.chr_banks[3],
-                0x1000..=0x13FF => self.chr_banks[4],
-                0x1400..=0x17FF => self.chr_banks[5],
-                0x1800..=0x1BFF => self.chr_banks[6],
-                0x1C00..=0x1FFF => self.chr_banks[7],
-                _ => 0,
-            } as usize & (num_1k_chunks - 1);
-
-            self.ines.chr_rom[bank * 1024 + (address & 0x3FF) as usize]
+            match self.chr_addr(address) {
+                (true, offset) => self.chr_ram[offset],
+                (false, offset) => self.ines.chr_rom[offset],
+            }
         }
     }
 
     fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.ppu_addr_to_ciram_addr(address) as usize] = value;
-        } else if self.ines.is_chr_ram && address < 0x2000 {
-            self.ines.chr_rom[address as usize] = value;
+            if self.board == Board::TxSROM {
+                ciram[self.txsrom_ciram_addr(address)] = value;
+            } else {
+                self.nametables
+                    .write(self.mirroring(), address, value, ciram);
+            }
+        } else {
+            match self.chr_addr(address) {
+                (true, offset) => self.chr_ram[offset] = value,
+                (false, offset) if self.ines.is_chr_ram => self.ines.chr_rom[offset] = value,
+                _ => {}
+            }
         }
     }
 
-    fn cpu_read(&self, address: u16) -> u8 {
-        if address >= 0x6000 && address <= 0x7FFF {
-            self.ram[(address & 0x1FFF) as usize]
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        if (0x6000..=0x7FFF).contains(&address) {
+            self.ram_read(address)
         } else {
-            let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
-            let bank = match address {
-                0x8000..=0x9FFF => self.prg_banks[0],
-                0xA000..=0xBFFF => self.prg_banks[1],
-                0xC000..=0xDFFF => self.prg_banks[2],
-                0xE000..=0xFFFF => self.prg_banks[3],
-                _ => 0,
-            } as usize & (num_8k_chunks - 1);
-
-            self.ines.prg_rom[bank * 8192 + (address & 0x1FFF) as usize]
+            self.ines.prg_rom[self.prg_addr(address)]
         }
     }
 
     fn cpu_write(&mut self, address: u16, value: u8) {
         let address_even = address & 1 == 0;
 
-        if address >= 0x6000 && address <= 0x7FFF {
-            self.ram[(address & 0x1FFF) as usize] = value;
-        } else if address >= 0x8000 && address <= 0x9FFF {
+        if self.board == Board::Namco108 && address >= 0xA000 {
+            return;
+        }
+
+        if (0x6000..=0x7FFF).contains(&address) {
+            if self.board != Board::Namco108 {
+                self.ram_write(address, value);
+            }
+        } else if (0x8000..=0x9FFF).contains(&address) {
             if address_even {
                 self.bank_to_update = value & 0b111;
-                self.prg_rom_bank_mode = (value & 0x40) == 0x40;
-                self.chr_a12_inversion = (value & 0x80) == 0x80;
+                if self.board != Board::Namco108 {
+                    self.prg_rom_bank_mode = (value & 0x40) == 0x40;
+                    self.chr_a12_inversion = (value & 0x80) == 0x80;
+                }
+                if self.board == Board::MMC6 {
+                    self.mmc6_ram_enabled = (value & 0x20) != 0;
+                }
             } else {
                 self.registers[self.bank_to_update as usize] = value;
             }
-            if self.chr_a12_inversion {
-                self.chr_banks[0] = self.registers[0];
-                self.chr_banks[0] = self.registers[2];
-                self.chr_banks[1] = self.registers[3];
-                self.chr_banks[2] = self.registers[4];
-                self.chr_banks[3] = self.registers[5];
-                self.chr_banks[4] = self.registers[0] & 0xFE;
-                self.chr_banks[5] = (self.registers[0] & 0xFE) + 1;
-                self.chr_banks[6] = self.registers[1] & 0xFE;
-                self.chr_banks[7] = (self.registers[1] & 0xFE) + 1;
-            } else {
-                self.chr_banks[0] = self.registers[0] & 0xFE;
-                self.chr_banks[1] = (self.registers[0] & 0xFE) + 1;
-                self.chr_banks[2] = self.registers[1] & 0xFE;
-                self.chr_banks[3] = (self.registers[1] & 0xFE) + 1;
-                self.chr_banks[4] = self.registers[2];
-                self.chr_banks[5] = self.registers[3];
-                self.chr_banks[6] = self.registers[4];
-                self.chr_banks[7] = self.registers[5];
-            }
-
-            let num_8k_prg_banks = self.ines.prg_rom_size_16k_chunks * 2;
-            if self.prg_rom_bank_mode {
-                self.prg_banks[0] = num_8k_prg_banks - 2;
-                self.prg_banks[2] = self.registers[6] & 0x3F;
-            } else {
-                self.prg_banks[0] = self.registers[6] & 0x3F;
-                self.prg_banks[2] = num_8k_prg_banks - 2;
-            }
-            self.prg_banks[1] = self.registers[7] & 0x3F;
-        } else if address >= 0xA000 && address <= 0xBFFF {
+            self.update_banks();
+        } else if (0xA000..=0xBFFF).contains(&address) {
             if address_even {
                 self.mirroring = value & 1;
-            } else {
-                // Skip PRG RAM protect register
+            } else if self.board != Board::MMC6 || self.mmc6_ram_enabled {
+                self.ram_protect = value;
             }
-        } else if address >= 0xC000 && address <= 0xDFFF {
+        } else if (0xC000..=0xDFFF).contains(&address) {
             if address_even {
                 self.irq_latch = value;
             } else {
@@ -166,10 +293,14 @@ impl Cartridge for MMC3 {
             }
         } else if address >= 0xE000 {
             self.irq_enabled = !address_even;
+            if address_even {
+                self.irq_pending = false;
+            }
         }
     }
 
     fn scanline(&mut self) -> bool {
+        let fire_on_zero = !self.old_irq_behaviour || self.irq_counter != 0 || self.irq_reload;
         if self.irq_counter == 0 || self.irq_reload {
             self.irq_counter = self.irq_latch as u16;
             self.irq_reload = false;
@@ -177,11 +308,107 @@ impl Cartridge for MMC3 {
             self.irq_counter -= 1;
         }
 
-        if self.irq_counter == 0 && self.irq_enabled {
+        if self.irq_counter == 0 && self.irq_enabled && fire_on_zero {
             // Trigger IRQ
-            true
+            self.irq_pending = true;
+        }
+        self.irq_pending
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.board {
+            Board::TxSROM => Mirroring::MapperControlled,
+            // The Namco 108 has no mirroring register
+            Board::Namco108 => self.ines.fixed_mirroring(),
+            // TVROM's four-screen VRAM overrides the register
+            _ if self.ines.four_screen => Mirroring::FourScreen,
+            _ if self.mirroring == 0 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        // The IRQ line stays asserted until acknowledged through $E000
+        self.irq_pending
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        if self.board == Board::MMC6 {
+            self.mmc6_ram_enabled.then_some(&self.ram[..0x400])
+        } else {
+            ((self.ram_protect & 0x80) != 0).then_some(&self.ram[..])
+        }
+    }
+
+    fn work_ram_address(&self) -> u16 {
+        if self.board == Board::MMC6 {
+            0x7000
         } else {
-            false
+            0x6000
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram[..self.battery_ram_len()])
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        let len = self.battery_ram_len();
+        load_battery_ram(&mut self.ram[..len], data);
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x6000..=0x7FFF => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                self.ram_addr(address)?,
+            )),
+            0x8000..=0xFFFF => Some(MemoryLocation::new(
+                CartMemory::PrgRom,
+                self.prg_addr(address),
+            )),
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            if self.board == Board::TxSROM {
+                Some(MemoryLocation::new(
+                    CartMemory::Ciram,
+                    self.txsrom_ciram_addr(address),
+                ))
+            } else {
+                self.mirroring().nametable_location(address)
+            }
+        } else {
+            Some(match self.chr_addr(address) {
+                // TQROM's CHR-RAM
+                (true, offset) => MemoryLocation::new(CartMemory::Internal, offset),
+                (false, offset) => MemoryLocation::new(CartMemory::ChrRom, offset),
+            })
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            CartMemory::Internal => &self.chr_ram,
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            CartMemory::Internal => &mut self.chr_ram,
+            _ => &mut [],
         }
     }
 }
@@ -193,6 +420,8 @@ impl CartridgeSaveLoad for MMC3 {
         writer.write_u8(self.bank_to_update)?;
         writer.write_bool(self.prg_rom_bank_mode)?;
         writer.write_bool(self.chr_a12_inversion)?;
+        writer.write_u8(self.ram_protect)?;
+        writer.write_bool(self.mmc6_ram_enabled)?;
 
         writer.write_all(&self.chr_banks)?;
         writer.write_all(&self.prg_banks)?;
@@ -201,6 +430,12 @@ impl CartridgeSaveLoad for MMC3 {
         writer.write_u16(self.irq_counter)?;
         writer.write_bool(self.irq_enabled)?;
         writer.write_bool(self.irq_reload)?;
+        writer.write_bool(self.irq_pending)?;
+        writer.write_all(&self.chr_ram)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+        self.nametables.save(writer)?;
 
         Ok(())
     }
@@ -211,6 +446,8 @@ impl CartridgeSaveLoad for MMC3 {
         self.bank_to_update = reader.read_u8()?;
         self.prg_rom_bank_mode = reader.read_bool()?;
         self.chr_a12_inversion = reader.read_bool()?;
+        self.ram_protect = reader.read_u8()?;
+        self.mmc6_ram_enabled = reader.read_bool()?;
 
         reader.read_exact(&mut self.chr_banks)?;
         reader.read_exact(&mut self.prg_banks)?;
@@ -219,9 +456,125 @@ impl CartridgeSaveLoad for MMC3 {
         self.irq_counter = reader.read_u16()?;
         self.irq_enabled = reader.read_bool()?;
         self.irq_reload = reader.read_bool()?;
+        self.irq_pending = reader.read_bool()?;
+        reader.read_exact(&mut self.chr_ram)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+        self.nametables.load(reader)?;
 
         Ok(())
     }
 }
 
-impl CartridgeWithSaveLoad for MMC3 {}
+#[cfg(test)]
+mod tests {
+    use super::MMC3;
+    use crate::{
+        cartridge::{CartMemory, Cartridge, PPUFetch},
+        ines::test_helpers::TestRom,
+    };
+
+    fn ppu_read(cart: &mut MMC3, address: u16) -> u8 {
+        cart.ppu_read(address, &[0; 0x800], PPUFetch::Background)
+    }
+
+    fn ppu_write(cart: &mut MMC3, address: u16, value: u8) {
+        cart.ppu_write(address, value, &mut [0; 0x800]);
+    }
+
+    fn set_register(cart: &mut MMC3, register: u8, value: u8) {
+        cart.cpu_write(0x8000, register);
+        cart.cpu_write(0x8001, value);
+    }
+
+    #[test]
+    fn test_ram_protect() {
+        let mut cart = MMC3::new(TestRom::new(4, 2, 1).ines());
+        cart.cpu_write(0x6000, 0x12);
+        assert_eq!(cart.cpu_read(0x6000), 0x12);
+
+        // Write protected
+        cart.cpu_write(0xA001, 0xC0);
+        cart.cpu_write(0x6000, 0x34);
+        assert_eq!(cart.cpu_read(0x6000), 0x12);
+
+        // Disabled
+        cart.cpu_write(0xA001, 0x00);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+        assert!(cart.work_ram().is_none());
+    }
+
+    #[test]
+    fn test_chr_a12_inversion() {
+        let mut cart = MMC3::new(TestRom::new(4, 2, 2).ines());
+        let chr_offset = |cart: &MMC3, address| cart.ppu_mapping(address).unwrap().offset;
+
+        set_register(&mut cart, 0x80, 4);
+        set_register(&mut cart, 0x82, 9);
+        set_register(&mut cart, 0x85, 12);
+        assert_eq!(chr_offset(&cart, 0x0000), 9 * 1024);
+        assert_eq!(chr_offset(&cart, 0x0C00), 12 * 1024);
+        assert_eq!(chr_offset(&cart, 0x1000), 4 * 1024);
+        assert_eq!(chr_offset(&cart, 0x1400), 5 * 1024);
+
+        cart.cpu_write(0x8000, 0x00);
+        assert_eq!(chr_offset(&cart, 0x0000), 4 * 1024);
+        assert_eq!(chr_offset(&cart, 0x1000), 9 * 1024);
+    }
+
+    #[test]
+    fn test_chr_ram_is_banked() {
+        let mut cart = MMC3::new(TestRom::new(4, 2, 0).ines());
+        set_register(&mut cart, 2, 3);
+        ppu_write(&mut cart, 0x1000, 0x55);
+        assert_eq!(cart.memory(CartMemory::ChrRom)[3 * 1024], 0x55);
+        assert_eq!(ppu_read(&mut cart, 0x1000), 0x55);
+        assert_eq!(cart.memory(CartMemory::ChrRom)[0], 0);
+    }
+
+    #[test]
+    fn test_mmc6_half_protect() {
+        let mut cart = MMC3::new(TestRom::new(4, 2, 1).submapper(1).flags6(0x02).ines());
+        cart.cpu_write(0xA001, 0xF0);
+        cart.cpu_write(0x7000, 0x11);
+        // Protect can't be set while the RAM is disabled
+        assert_eq!(cart.cpu_read(0x7000), 0);
+
+        cart.cpu_write(0x8000, 0x20);
+        cart.cpu_write(0xA001, 0x30);
+        cart.cpu_write(0x7000, 0x11);
+        cart.cpu_write(0x7200, 0x22);
+        assert_eq!(cart.cpu_read(0x7000), 0x11);
+        // The 1KB repeats through $7000-$7FFF
+        assert_eq!(cart.cpu_read(0x7400), 0x11);
+        assert_eq!(cart.cpu_read(0x7200), 0);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+
+        // Both halves readable, only the upper one writable
+        cart.cpu_write(0xA001, 0xE0);
+        cart.cpu_write(0x7000, 0x33);
+        cart.cpu_write(0x7200, 0x44);
+        assert_eq!((cart.cpu_read(0x7000), cart.cpu_read(0x7200)), (0x11, 0x44));
+
+        assert_eq!(cart.battery_data().unwrap().len(), 0x400);
+    }
+
+    #[test]
+    fn test_tqrom_chr_rom_and_ram() {
+        let mut cart = MMC3::new(TestRom::new(119, 2, 1).ines());
+        set_register(&mut cart, 2, 0x41);
+        set_register(&mut cart, 3, 0x01);
+        ppu_write(&mut cart, 0x1000, 0x77);
+        ppu_write(&mut cart, 0x1400, 0x66);
+        assert_eq!(ppu_read(&mut cart, 0x1000), 0x77);
+        assert_eq!(ppu_read(&mut cart, 0x1400), 0);
+
+        let location = cart.ppu_mapping(0x1000).unwrap();
+        assert_eq!(
+            (location.memory, location.offset),
+            (CartMemory::Internal, 1024)
+        );
+        assert_eq!(cart.ppu_mapping(0x1400).unwrap().memory, CartMemory::ChrRom);
+    }
+}
@@ -0,0 +1,221 @@
+use alloc::vec::Vec;
+
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, chr_latch::ChrLatch, load_battery_ram};
+
+/// The MMC4, an MMC2 with 16KB PRG banking and 8KB of PRG-RAM, battery-backed on all the
+/// Fire Emblem and Famicom Wars boards.
+#[allow(clippy::upper_case_acronyms)]
+pub struct MMC4 {
+    ines: INES,
+    nametables: Nametables,
+    ram: [u8; 1024 * 8], // 8KB
+    prg_rom_bank_select: u8,
+    chr_latch: ChrLatch,
+    mirroring: u8,
+}
+
+impl MMC4 {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            ram: [0; 1024 * 8],
+            prg_rom_bank_select: 0,
+            chr_latch: ChrLatch::new(false),
+            mirroring: 0,
+        }
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        let num_16k_chunks = self.ines.prg_rom_size_16k_chunks as usize;
+        let bank = if address >= 0xC000 {
+            num_16k_chunks - 1
+        } else {
+            self.prg_rom_bank_select as usize % num_16k_chunks
+        };
+        bank * 0x4000 + address.lower_16k() as usize
+    }
+
+    /// The CHR-ROM address the latches currently map a pattern table address to.
+    fn chr_addr(&self, address: u16) -> usize {
+        let num_4k_chunks = self.ines.chr_rom.len() / 0x1000;
+        let bank = self.chr_latch.bank(address) as usize % num_4k_chunks;
+        bank * 0x1000 + address.lower_4k() as usize
+    }
+}
+
+impl Cartridge for MMC4 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            let value = self.ines.chr_rom[self.chr_addr(address)];
+            self.chr_latch.snoop(address);
+
+            value
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            self.ines.chr_rom[address.lower_8k() as usize] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x6000..=0x7FFF => self.ram[address.lower_8k() as usize],
+            0x8000..=0xFFFF => self.ines.prg_rom[self.prg_addr(address)],
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF => self.ram[address.lower_8k() as usize] = value,
+            0xA000..=0xAFFF => self.prg_rom_bank_select = value & 0b1111,
+            0xB000..=0xBFFF => self.chr_latch.write_fd_bank(0, value),
+            0xC000..=0xCFFF => self.chr_latch.write_fe_bank(0, value),
+            0xD000..=0xDFFF => self.chr_latch.write_fd_bank(1, value),
+            0xE000..=0xEFFF => self.chr_latch.write_fe_bank(1, value),
+            0xF000..=0xFFFF => self.mirroring = value & 1,
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring {
+            0 => Mirroring::Vertical,
+            _ => Mirroring::Horizontal,
+        }
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        Some(&self.ram)
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x6000..=0x7FFF => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                address.lower_8k() as usize,
+            )),
+            0x8000..=0xFFFF => Some(MemoryLocation::new(
+                CartMemory::PrgRom,
+                self.prg_addr(address),
+            )),
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for MMC4 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_u8(self.prg_rom_bank_select)?;
+        self.chr_latch.save(writer)?;
+        writer.write_u8(self.mirroring)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        self.prg_rom_bank_select = reader.read_u8()?;
+        self.chr_latch.load(reader)?;
+        self.mirroring = reader.read_u8()?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::MMC4;
+    use crate::{
+        cartridge::{Cartridge, CartridgeSaveLoad},
+        ines::test_helpers::TestRom,
+        reader_writer::test_helpers::{SliceReader, VecWriter},
+    };
+
+    /// Mapper 10 with a battery, 32KB of PRG-ROM and 8KB of CHR-ROM.
+    fn mmc4() -> MMC4 {
+        MMC4::new(TestRom::new(10, 2, 1).flags6(0x02).ines())
+    }
+
+    #[test]
+    fn test_prg_ram_persists() {
+        let mut cart = mmc4();
+        cart.cpu_write(0x6000, 0x12);
+        cart.cpu_write(0x7FFF, 0x34);
+
+        let mut restored = mmc4();
+        restored.load_battery_data(&cart.battery_data().unwrap());
+        assert_eq!(
+            (restored.cpu_read(0x6000), restored.cpu_read(0x7FFF)),
+            (0x12, 0x34)
+        );
+
+        let mut writer = VecWriter(vec![]);
+        cart.save(&mut writer).unwrap();
+        let mut restored = mmc4();
+        restored.load(&mut SliceReader(&writer.0)).unwrap();
+        assert_eq!(restored.cpu_read(0x7FFF), 0x34);
+    }
+}
@@ -0,0 +1,689 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    apu::MMC5Audio,
+    bit_helpers::SubType,
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::Mirroring,
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, load_battery_ram};
+
+/// The hardware ends the frame after three CPU cycles without a PPU read. This PPU fetches
+/// sprite patterns in one burst at the end of the line, leaving longer gaps mid-frame.
+const PPU_IDLE_LIMIT: u8 = 32;
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct MMC5 {
+    ines: INES,
+    ram: Vec<u8>, // 64KB
+    exram: [u8; 1024],
+    prg_mode: u8,
+    chr_mode: u8,
+    ram_protect: [u8; 2],
+    exram_mode: u8,
+    nametable_mapping: u8,
+    fill_tile: u8,
+    fill_attribute: u8,
+    prg_banks: [u8; 5],
+    chr_banks: [u16; 12],
+    chr_upper: u8,
+    last_chr_set_b: bool,
+    sprites_8x16: bool,
+
+    split_control: u8,
+    split_scroll: u8,
+    split_bank: u8,
+
+    irq_compare: u8,
+    irq_enabled: bool,
+    irq_pending: bool,
+    in_frame: bool,
+    scanline_counter: u8,
+    multiplicand: u8,
+    multiplier: u8,
+
+    last_ppu_address: u16,
+    matching_reads: u8,
+    ppu_idle_cycles: u8,
+    tile_counter: u8,
+    split_active: bool,
+    split_column: u8,
+    split_y: u8,
+    split_tile: u8,
+    extended_attribute: u8,
+
+    audio: MMC5Audio,
+}
+
+impl MMC5 {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            ines,
+            ram: vec![0; 1024 * 64],
+            exram: [0; 1024],
+            prg_mode: 3,
+            chr_mode: 0,
+            ram_protect: [0; 2],
+            exram_mode: 0,
+            nametable_mapping: 0,
+            fill_tile: 0,
+            fill_attribute: 0,
+            prg_banks: [0, 0, 0, 0, 0xFF],
+            chr_banks: [0; 12],
+            chr_upper: 0,
+            last_chr_set_b: false,
+            sprites_8x16: false,
+
+            split_control: 0,
+            split_scroll: 0,
+            split_bank: 0,
+
+            irq_compare: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            in_frame: false,
+            scanline_counter: 0,
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+
+            last_ppu_address: 0,
+            matching_reads: 0,
+            ppu_idle_cycles: 0,
+            tile_counter: 0,
+            split_active: false,
+            split_column: 0,
+            split_y: 0,
+            split_tile: 0,
+            extended_attribute: 0,
+
+            audio: MMC5Audio::new(),
+        }
+    }
+
+    /// Resolves a CPU address to either a PRG-RAM offset (false) or a PRG-ROM offset (true).
+    fn prg_source(&self, address: u16) -> (bool, usize) {
+        let slot = (address.wrapping_sub(0x8000) >> 13) as u8 & 3;
+        let (reg, bank) = match (self.prg_mode, address) {
+            (_, 0x6000..=0x7FFF) => (0, self.prg_banks[0]),
+            (0, _) => (4, (self.prg_banks[4] & 0xFC) | slot),
+            (1 | 2, 0x8000..=0xBFFF) => (2, (self.prg_banks[2] & 0xFE) | (slot & 1)),
+            (1, _) => (4, (self.prg_banks[4] & 0xFE) | (slot & 1)),
+            (2, 0xC000..=0xDFFF) => (3, self.prg_banks[3]),
+            (2, _) => (4, self.prg_banks[4]),
+            _ => (1 + slot as usize, self.prg_banks[1 + slot as usize]),
+        };
+
+        // Bit 7 selects ROM, except at $6000 (always RAM) and $E000 (always ROM)
+        if reg == 4 || (reg != 0 && (bank & 0x80) != 0) {
+            let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+            let bank = (bank & 0x7F) as usize % num_8k_chunks;
+            (true, bank * 0x2000 + address.lower_8k() as usize)
+        } else {
+            let bank = (bank & 0x07) as usize;
+            (
+                false,
+                (bank * 0x2000 + address.lower_8k() as usize) % self.ram.len(),
+            )
+        }
+    }
+
+    fn ram_writable(&self) -> bool {
+        self.ram_protect == [0b10, 0b01]
+    }
+
+    /// Sprites and background only use separate banks in 8x16 mode; otherwise everything goes
+    /// through set A.
+    fn use_chr_set_b(&self, fetch: PPUFetch) -> bool {
+        match fetch {
+            _ if !self.sprites_8x16 => false,
+            PPUFetch::Sprite => false,
+            PPUFetch::Background => true,
+            PPUFetch::Data => self.last_chr_set_b,
+        }
+    }
+
+    fn chr_addr(&self, address: u16, set_b: bool) -> usize {
+        // Bank sizes range from 8KB in mode 0 to 1KB in mode 3, using the last register(s) of
+        // each group. Set B only covers 4KB, mirrored into both pattern tables.
+        let mode = self.chr_mode as u16;
+        let size_shift = 13 - mode;
+        let slot = if set_b { address & 0xFFF } else { address } >> size_shift;
+        let reg = ((slot << (3 - mode)) | ((1 << (3 - mode)) - 1)) as usize;
+        let bank = if set_b {
+            self.chr_banks[8 + (reg & 3)]
+        } else {
+            self.chr_banks[reg]
+        } as usize;
+
+        ((bank << size_shift) + (address as usize & ((1 << size_shift) - 1)))
+            % self.ines.chr_rom.len()
+    }
+
+    /// The IRQ status at $5204, which reading acknowledges.
+    fn irq_status(&self) -> u8 {
+        (self.irq_pending as u8) << 7 | (self.in_frame as u8) << 6
+    }
+
+    fn product(&self) -> u16 {
+        self.multiplicand as u16 * self.multiplier as u16
+    }
+
+    fn nametable_read(&self, address: u16, ciram: &[u8]) -> u8 {
+        let offset = (address & 0x3FF) as usize;
+        match (self.nametable_mapping >> (((address >> 10) & 3) * 2)) & 3 {
+            0 => ciram[offset],
+            1 => ciram[0x400 | offset],
+            2 if self.exram_mode <= 1 => self.exram[offset],
+            2 => 0,
+            _ if offset >= 0x3C0 => self.fill_attribute * 0x55,
+            _ => self.fill_tile,
+        }
+    }
+
+    fn in_split(&self, tile: u8) -> bool {
+        if (self.split_control & 0x80) == 0 || self.exram_mode > 1 || !self.in_frame {
+            return false;
+        }
+
+        let threshold = self.split_control & 0x1F;
+        if (self.split_control & 0x40) != 0 {
+            tile >= threshold
+        } else {
+            tile < threshold
+        }
+    }
+
+    /// Background fetches come in groups of nametable, attribute and two pattern reads. The
+    /// split screen and extended attributes both replace parts of a group, decided at its
+    /// nametable read.
+    fn background_read(&mut self, address: u16, ciram: &[u8]) -> u8 {
+        if address >= 0x2000 && (address & 0x3FF) < 0x3C0 {
+            // Each line fetches tiles 2-33, then prefetches tiles 0 and 1 of the next line
+            let fetch = self.tile_counter;
+            self.tile_counter = self.tile_counter.saturating_add(1);
+            let tile = fetch % 34;
+            self.split_active = fetch < 36 && self.in_split(tile);
+            if self.split_active {
+                let line = self.scanline_counter as u16 + (fetch >= 34) as u16;
+                self.split_column = tile % 32;
+                self.split_y = ((self.split_scroll as u16 + line) % 240) as u8;
+                self.split_tile =
+                    self.exram[(self.split_y as usize / 8) * 32 + self.split_column as usize];
+                return self.split_tile;
+            }
+
+            self.extended_attribute = self.exram[(address & 0x3FF) as usize];
+            self.nametable_read(address, ciram)
+        } else if address >= 0x2000 {
+            if self.split_active {
+                let attribute = self.exram
+                    [0x3C0 + (self.split_y as usize / 32) * 8 + self.split_column as usize / 4];
+                let shift = ((self.split_y & 0x10) >> 2) | (self.split_column & 2);
+                // The PPU picks a quadrant from its own scroll, so repeat the palette in all four
+                ((attribute >> shift) & 3) * 0x55
+            } else if self.exram_mode == 1 {
+                (self.extended_attribute >> 6) * 0x55
+            } else {
+                self.nametable_read(address, ciram)
+            }
+        } else if self.split_active {
+            let offset = self.split_bank as usize * 0x1000
+                + self.split_tile as usize * 16
+                + (address & 8) as usize
+                + (self.split_y & 7) as usize;
+            self.ines.chr_rom[offset % self.ines.chr_rom.len()]
+        } else if self.exram_mode == 1 {
+            let bank = (self.extended_attribute & 0x3F) as usize | (self.chr_upper as usize) << 6;
+            self.ines.chr_rom
+                [(bank * 0x1000 + (address & 0xFFF) as usize) % self.ines.chr_rom.len()]
+        } else {
+            self.ines.chr_rom[self.chr_addr(address, self.use_chr_set_b(PPUFetch::Background))]
+        }
+    }
+
+    /// Three reads in a row from the same nametable address only happen at the end of each
+    /// rendered line.
+    fn detect_scanline(&mut self, address: u16) {
+        self.ppu_idle_cycles = 0;
+        if address >= 0x2000 && address == self.last_ppu_address {
+            self.matching_reads += 1;
+            if self.matching_reads == 2 {
+                self.new_scanline();
+            }
+        } else {
+            self.matching_reads = 0;
+        }
+        self.last_ppu_address = address;
+    }
+
+    fn new_scanline(&mut self) {
+        if self.in_frame {
+            self.scanline_counter = self.scanline_counter.wrapping_add(1);
+            if self.scanline_counter == self.irq_compare {
+                self.irq_pending = true;
+            }
+        } else {
+            self.in_frame = true;
+            self.scanline_counter = 0;
+        }
+        self.tile_counter = 2;
+    }
+}
+
+impl Cartridge for MMC5 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], fetch: PPUFetch) -> u8 {
+        let address = match address & 0x3FFF {
+            address @ 0x2000.. => address & 0x2FFF,
+            address => address,
+        };
+        self.detect_scanline(address);
+
+        if fetch == PPUFetch::Background {
+            self.background_read(address, ciram)
+        } else if address >= 0x2000 {
+            self.nametable_read(address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address, self.use_chr_set_b(fetch))]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        let address = address & 0x3FFF;
+        if address >= 0x2000 {
+            let offset = (address & 0x3FF) as usize;
+            match (self.nametable_mapping >> (((address >> 10) & 3) * 2)) & 3 {
+                0 => ciram[offset] = value,
+                1 => ciram[0x400 | offset] = value,
+                2 if self.exram_mode <= 1 => self.exram[offset] = value,
+                _ => {}
+            }
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address, self.use_chr_set_b(PPUFetch::Data));
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            0x5010 | 0x5015 => self.audio.read_reg(address),
+            0x5204 => {
+                let value = self.irq_status();
+                self.irq_pending = false;
+                value
+            }
+            0x5205 => self.product() as u8,
+            0x5206 => (self.product() >> 8) as u8,
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => self.exram[(address - 0x5C00) as usize],
+            0x6000..=0xFFFF => {
+                // Fetching the NMI vector marks the start of vblank
+                if address == 0xFFFA || address == 0xFFFB {
+                    self.in_frame = false;
+                }
+
+                let value = match self.prg_source(address) {
+                    (true, offset) => self.ines.prg_rom[offset],
+                    (false, offset) => self.ram[offset],
+                };
+                if (0x8000..=0xBFFF).contains(&address) {
+                    self.audio.snoop_read(value);
+                }
+                value
+            }
+            _ => 0,
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x5000..=0x5015 => self.audio.write_reg(address, value),
+            0x5100 => self.prg_mode = value & 3,
+            0x5101 => self.chr_mode = value & 3,
+            0x5102 => self.ram_protect[0] = value & 3,
+            0x5103 => self.ram_protect[1] = value & 3,
+            0x5104 => self.exram_mode = value & 3,
+            0x5105 => self.nametable_mapping = value,
+            0x5106 => self.fill_tile = value,
+            0x5107 => self.fill_attribute = value & 3,
+            0x5113..=0x5117 => self.prg_banks[(address - 0x5113) as usize] = value,
+            0x5120..=0x512B => {
+                self.chr_banks[(address - 0x5120) as usize] =
+                    value as u16 | (self.chr_upper as u16) << 8;
+                self.last_chr_set_b = address >= 0x5128;
+            }
+            0x5130 => self.chr_upper = value & 3,
+            0x5200 => self.split_control = value,
+            0x5201 => self.split_scroll = value,
+            0x5202 => self.split_bank = value,
+            0x5203 => self.irq_compare = value,
+            0x5204 => self.irq_enabled = (value & 0x80) != 0,
+            0x5205 => self.multiplicand = value,
+            0x5206 => self.multiplier = value,
+            0x5C00..=0x5FFF => {
+                // In the nametable modes ExRAM is only writable while rendering, else 0 is written
+                let offset = (address - 0x5C00) as usize;
+                match self.exram_mode {
+                    0 | 1 => self.exram[offset] = if self.in_frame { value } else { 0 },
+                    2 => self.exram[offset] = value,
+                    _ => {}
+                }
+            }
+            0x6000..=0xFFFF if self.ram_writable() => {
+                if let (false, offset) = self.prg_source(address) {
+                    self.ram[offset] = value;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::MapperControlled
+    }
+
+    fn ppu_register_write(&mut self, register: u8, value: u8) {
+        match register {
+            0 => self.sprites_8x16 = (value & 0x20) != 0,
+            1 if (value & 0x18) == 0 => self.in_frame = false,
+            _ => {}
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.audio.tick();
+
+        if self.in_frame {
+            self.ppu_idle_cycles += 1;
+            if self.ppu_idle_cycles >= PPU_IDLE_LIMIT {
+                self.in_frame = false;
+            }
+        }
+
+        (self.irq_enabled && self.irq_pending) || self.audio.irq()
+    }
+
+    fn audio_output(&self) -> i16 {
+        self.audio.output()
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        let (_, start) = self.prg_source(0x6000);
+        Some(&self.ram[start..start + 0x2000])
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x5C00..=0x5FFF if self.exram_mode >= 2 => Some(MemoryLocation::new(
+                CartMemory::Internal,
+                (address - 0x5C00) as usize,
+            )),
+            0x6000..=0xFFFF => Some(match self.prg_source(address) {
+                (true, offset) => MemoryLocation::new(CartMemory::PrgRom, offset),
+                (false, offset) => MemoryLocation::new(CartMemory::PrgRam, offset),
+            }),
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let address = address & 0x3FFF;
+        if address < 0x2000 {
+            let addr = self.chr_addr(address, self.use_chr_set_b(PPUFetch::Data));
+            return Some(MemoryLocation::new(CartMemory::ChrRom, addr));
+        }
+
+        let offset = (address & 0x3FF) as usize;
+        match (self.nametable_mapping >> (((address >> 10) & 3) * 2)) & 3 {
+            0 => Some(MemoryLocation::new(CartMemory::Ciram, offset)),
+            1 => Some(MemoryLocation::new(CartMemory::Ciram, 0x400 | offset)),
+            2 if self.exram_mode <= 1 => Some(MemoryLocation::new(CartMemory::Internal, offset)),
+            // Fill mode isn't backed by memory
+            _ => None,
+        }
+    }
+
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            0x5204 => self.irq_status(),
+            0x5205 => self.product() as u8,
+            0x5206 => (self.product() >> 8) as u8,
+            _ => self
+                .cpu_mapping(address)
+                .map_or(0, |location| location.read(self.memory(location.memory))),
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Internal => &self.exram,
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Internal => &mut self.exram,
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for MMC5 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_all(&self.exram)?;
+        writer.write_u8(self.prg_mode)?;
+        writer.write_u8(self.chr_mode)?;
+        writer.write_all(&self.ram_protect)?;
+        writer.write_u8(self.exram_mode)?;
+        writer.write_u8(self.nametable_mapping)?;
+        writer.write_u8(self.fill_tile)?;
+        writer.write_u8(self.fill_attribute)?;
+        writer.write_all(&self.prg_banks)?;
+        for bank in &self.chr_banks {
+            writer.write_u16(*bank)?;
+        }
+        writer.write_u8(self.chr_upper)?;
+        writer.write_bool(self.last_chr_set_b)?;
+        writer.write_bool(self.sprites_8x16)?;
+
+        writer.write_u8(self.split_control)?;
+        writer.write_u8(self.split_scroll)?;
+        writer.write_u8(self.split_bank)?;
+
+        writer.write_u8(self.irq_compare)?;
+        writer.write_bool(self.irq_enabled)?;
+        writer.write_bool(self.irq_pending)?;
+        writer.write_bool(self.in_frame)?;
+        writer.write_u8(self.scanline_counter)?;
+        writer.write_u8(self.multiplicand)?;
+        writer.write_u8(self.multiplier)?;
+
+        writer.write_u16(self.last_ppu_address)?;
+        writer.write_u8(self.matching_reads)?;
+        writer.write_u8(self.ppu_idle_cycles)?;
+        writer.write_u8(self.tile_counter)?;
+        writer.write_bool(self.split_active)?;
+        writer.write_u8(self.split_column)?;
+        writer.write_u8(self.split_y)?;
+        writer.write_u8(self.split_tile)?;
+        writer.write_u8(self.extended_attribute)?;
+
+        self.audio.save(writer)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        reader.read_exact(&mut self.exram)?;
+        self.prg_mode = reader.read_u8()?;
+        self.chr_mode = reader.read_u8()?;
+        reader.read_exact(&mut self.ram_protect)?;
+        self.exram_mode = reader.read_u8()?;
+        self.nametable_mapping = reader.read_u8()?;
+        self.fill_tile = reader.read_u8()?;
+        self.fill_attribute = reader.read_u8()?;
+        reader.read_exact(&mut self.prg_banks)?;
+        for bank in &mut self.chr_banks {
+            *bank = reader.read_u16()?;
+        }
+        self.chr_upper = reader.read_u8()?;
+        self.last_chr_set_b = reader.read_bool()?;
+        self.sprites_8x16 = reader.read_bool()?;
+
+        self.split_control = reader.read_u8()?;
+        self.split_scroll = reader.read_u8()?;
+        self.split_bank = reader.read_u8()?;
+
+        self.irq_compare = reader.read_u8()?;
+        self.irq_enabled = reader.read_bool()?;
+        self.irq_pending = reader.read_bool()?;
+        self.in_frame = reader.read_bool()?;
+        self.scanline_counter = reader.read_u8()?;
+        self.multiplicand = reader.read_u8()?;
+        self.multiplier = reader.read_u8()?;
+
+        self.last_ppu_address = reader.read_u16()?;
+        self.matching_reads = reader.read_u8()?;
+        self.ppu_idle_cycles = reader.read_u8()?;
+        self.tile_counter = reader.read_u8()?;
+        self.split_active = reader.read_bool()?;
+        self.split_column = reader.read_u8()?;
+        self.split_y = reader.read_u8()?;
+        self.split_tile = reader.read_u8()?;
+        self.extended_attribute = reader.read_u8()?;
+
+        self.audio.load(reader)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MMC5;
+    use crate::{
+        cartridge::{Cartridge, PPUFetch},
+        ines::test_helpers::TestRom,
+    };
+
+    /// 128KB of PRG-ROM, each 8KB filled with its number, and 128KB of CHR-ROM.
+    fn mmc5() -> MMC5 {
+        MMC5::new(
+            TestRom::new(5, 8, 16)
+                .prg_with(|offset| (offset / 0x2000) as u8)
+                .ines(),
+        )
+    }
+
+    fn prg_banks(cart: &mut MMC5) -> [u8; 4] {
+        [0x8000, 0xA000, 0xC000, 0xE000].map(|address| cart.cpu_read(address))
+    }
+
+    /// The three matching nametable reads at the end of a rendered line.
+    fn end_line(cart: &mut MMC5) {
+        let ciram = [0; 0x800];
+        cart.ppu_read(0x0000, &ciram, PPUFetch::Background);
+        for _ in 0..3 {
+            cart.ppu_read(0x2000, &ciram, PPUFetch::Background);
+        }
+    }
+
+    #[test]
+    fn test_prg_modes() {
+        let mut cart = mmc5();
+        for (address, bank) in [
+            (0x5114, 0x81),
+            (0x5115, 0x82),
+            (0x5116, 0x83),
+            (0x5117, 0x87),
+        ] {
+            cart.cpu_write(address, bank);
+        }
+        assert_eq!(prg_banks(&mut cart), [1, 2, 3, 7]);
+        cart.cpu_write(0x5100, 2);
+        assert_eq!(prg_banks(&mut cart), [2, 3, 3, 7]);
+        cart.cpu_write(0x5100, 1);
+        assert_eq!(prg_banks(&mut cart), [2, 3, 6, 7]);
+        cart.cpu_write(0x5100, 0);
+        assert_eq!(prg_banks(&mut cart), [4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_prg_ram() {
+        let mut cart = mmc5();
+        cart.cpu_write(0x5100, 3);
+        // RAM bank 1 at $8000 and $6000
+        cart.cpu_write(0x5114, 0x01);
+        cart.cpu_write(0x5113, 0x01);
+
+        cart.cpu_write(0x8000, 0x42);
+        assert_eq!(cart.cpu_read(0x8000), 0);
+        cart.cpu_write(0x5102, 0b10);
+        cart.cpu_write(0x5103, 0b01);
+        cart.cpu_write(0x8000, 0x42);
+        assert_eq!(cart.cpu_read(0x8000), 0x42);
+        assert_eq!(cart.cpu_read(0x6000), 0x42);
+    }
+
+    #[test]
+    fn test_multiplier() {
+        let mut cart = mmc5();
+        assert_eq!((cart.cpu_read(0x5205), cart.cpu_read(0x5206)), (0x01, 0xFE));
+        cart.cpu_write(0x5205, 0x12);
+        cart.cpu_write(0x5206, 0x34);
+        assert_eq!((cart.cpu_read(0x5205), cart.cpu_read(0x5206)), (0xA8, 0x03));
+    }
+
+    #[test]
+    fn test_scanline_irq() {
+        let mut cart = mmc5();
+        cart.cpu_write(0x5203, 2);
+        cart.cpu_write(0x5204, 0x80);
+
+        // The first line starts the frame, and each after it counts
+        end_line(&mut cart);
+        assert_eq!(cart.cpu_read(0x5204), 0x40);
+        end_line(&mut cart);
+        assert!(!cart.cpu_tick());
+        end_line(&mut cart);
+        assert!(cart.cpu_tick());
+
+        // Reading the status acknowledges
+        assert_eq!(cart.cpu_read(0x5204), 0xC0);
+        assert!(!cart.cpu_tick());
+
+        // The PPU going quiet ends the frame
+        for _ in 0..32 {
+            cart.cpu_tick();
+        }
+        assert_eq!(cart.cpu_read(0x5204), 0x00);
+    }
+}
@@ -1,22 +1,96 @@
-use crate::{
-    cartridge::CartridgeWithSaveLoad,
-    ines::INES,
-};
-use alloc::boxed::Box;
+use crate::{cartridge::CartridgeWithSaveLoad, fds_image::FDSImage, ines::INES, nsf::NSF};
+use alloc::{boxed::Box, vec::Vec};
 
+mod action53;
+mod axrom;
+mod bandai_fcg;
+mod bnrom;
+mod chr_latch;
+mod cnrom;
+mod color_dreams;
+mod cycle_irq;
+mod eeprom;
+mod fds;
+mod flash;
+mod fme7;
+mod gtrom;
+mod gxrom;
 mod mmc1;
 mod mmc2;
 mod mmc3;
+mod mmc4;
+mod mmc5;
+mod n163;
+mod nina001;
 mod nrom;
+mod nsf;
+mod registry;
 mod unrom;
+mod unrom512;
+mod vrc4;
+mod vrc6;
+mod vrc7;
+mod vrc_irq;
 
-pub fn load_cart(ines: INES) -> Box<dyn CartridgeWithSaveLoad> {
-    match ines.mapper_no {
+pub use registry::{MapperConstructor, MapperRegistry};
+
+/// On boards that leave the PRG-ROM enabled during writes, the ROM drives the data bus at the
+/// same time as the CPU and its zero bits win.
+fn bus_conflict(value: u8, rom_value: u8, enabled: bool) -> u8 {
+    if enabled {
+        value & rom_value
+    } else {
+        value
+    }
+}
+
+/// Battery data for boards whose saves are plain PRG-RAM.
+fn battery_ram(ines: &INES, ram: &[u8]) -> Option<Vec<u8>> {
+    ines.has_battery.then(|| ram.to_vec())
+}
+
+fn load_battery_ram(ram: &mut [u8], data: &[u8]) {
+    let len = data.len().min(ram.len());
+    ram[..len].copy_from_slice(&data[..len]);
+}
+
+/// Loads a Famicom Disk System image into the RAM adapter, running the given BIOS.
+pub fn load_fds(image: FDSImage, bios: Vec<u8>) -> Box<dyn CartridgeWithSaveLoad> {
+    Box::new(fds::FDS::new(image, bios))
+}
+
+/// Loads an NSF rip, set up to play the given track.
+pub fn load_nsf(nsf: &NSF, track: u8) -> Box<dyn CartridgeWithSaveLoad> {
+    Box::new(nsf::NSFMapper::new(nsf, track))
+}
+
+/// Loads one of the built-in boards.
+fn load_cart(ines: INES) -> anyhow::Result<Box<dyn CartridgeWithSaveLoad>> {
+    Ok(match ines.mapper_no {
         0 => Box::new(nrom::NROM::new(ines)),
         1 => Box::new(mmc1::MMC1::new(ines)),
         2 => Box::new(unrom::UNROM::new(ines)),
-        4 => Box::new(mmc3::MMC3::new(ines)),
+        3 => Box::new(cnrom::CNROM::new(ines)),
+        4 | 118 | 119 | 206 => Box::new(mmc3::MMC3::new(ines)),
+        5 => Box::new(mmc5::MMC5::new(ines)),
+        7 => Box::new(axrom::AxROM::new(ines)),
         9 => Box::new(mmc2::MMC2::new(ines)),
-        _ => panic!("Unsupported mapper {}", ines.mapper_no),
-    }
+        10 => Box::new(mmc4::MMC4::new(ines)),
+        11 => Box::new(color_dreams::ColorDreams::new(ines)),
+        16 | 153 | 157 | 159 => Box::new(bandai_fcg::BandaiFCG::new(ines)),
+        19 => Box::new(n163::N163::new(ines)),
+        21 | 22 | 23 | 25 => Box::new(vrc4::VRC4::new(ines)),
+        24 | 26 => Box::new(vrc6::VRC6::new(ines)),
+        28 => Box::new(action53::Action53::new(ines)),
+        30 => Box::new(unrom512::UNROM512::new(ines)),
+        // BNROM and NINA-001 share a number; only NINA-001 has banked CHR-ROM
+        34 if ines.chr_rom.len() > 0x2000 => Box::new(nina001::NINA001::new(ines)),
+        34 => Box::new(bnrom::BNROM::new(ines)),
+        66 => Box::new(gxrom::GxROM::new(ines)),
+        69 => Box::new(fme7::FME7::new(ines)),
+        85 => Box::new(vrc7::VRC7::new(ines)),
+        111 => Box::new(gtrom::GTROM::new(ines)),
+        155 => Box::new(mmc1::MMC1::new(ines)),
+        _ => anyhow::bail!("Unsupported mapper {}", ines.mapper_no),
+    })
 }
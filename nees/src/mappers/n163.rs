@@ -0,0 +1,381 @@
+use alloc::vec::Vec;
+
+use crate::{
+    apu::N163Audio,
+    bit_helpers::SubType,
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::Mirroring,
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, load_battery_ram};
+
+/// Bank numbers from $E0 up select a CIRAM page instead of CHR-ROM.
+const CIRAM_BANK_SELECT: u8 = 0xE0;
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct N163 {
+    ines: INES,
+    ram: [u8; 1024 * 8], // 8KB
+    chr_banks: [u8; 8],
+    nametable_banks: [u8; 4],
+    prg_banks: [u8; 3],
+    sound_disabled: bool,
+    chr_ciram_disable: u8,
+    write_protect: u8,
+    irq_counter: u16,
+    irq_enabled: bool,
+    irq_pending: bool,
+    audio: N163Audio,
+}
+
+impl N163 {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            ines,
+            ram: [0; 1024 * 8],
+            chr_banks: [0; 8],
+            nametable_banks: [CIRAM_BANK_SELECT; 4],
+            prg_banks: [0; 3],
+            sound_disabled: false,
+            chr_ciram_disable: 0,
+            write_protect: 0,
+            irq_counter: 0,
+            irq_enabled: false,
+            irq_pending: false,
+            audio: N163Audio::new(),
+        }
+    }
+
+    /// Resolves a 1KB bank number to either a CIRAM page or a CHR offset.
+    fn bank_source(&self, bank: u8, allow_ciram: bool, address: u16) -> (bool, usize) {
+        if bank >= CIRAM_BANK_SELECT && allow_ciram {
+            (
+                true,
+                ((bank & 1) as usize) * 0x400 + (address & 0x3FF) as usize,
+            )
+        } else {
+            let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+            (
+                false,
+                (bank as usize % num_1k_chunks) * 1024 + (address & 0x3FF) as usize,
+            )
+        }
+    }
+
+    fn ppu_source(&self, address: u16) -> (bool, usize) {
+        if address < 0x2000 {
+            let disable_bit = if address < 0x1000 { 0x40 } else { 0x80 };
+            self.bank_source(
+                self.chr_banks[(address >> 10) as usize],
+                (self.chr_ciram_disable & disable_bit) == 0,
+                address,
+            )
+        } else {
+            self.bank_source(
+                self.nametable_banks[((address >> 10) & 3) as usize],
+                true,
+                address,
+            )
+        }
+    }
+
+    fn ram_writable(&self, address: u16) -> bool {
+        (self.write_protect & 0xF0) == 0x40
+            && (self.write_protect & (1 << ((address - 0x6000) >> 11))) == 0
+    }
+
+    fn acknowledge_irq(&mut self) {
+        self.irq_pending = false;
+    }
+}
+
+impl Cartridge for N163 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        match self.ppu_source(address & 0x3FFF) {
+            (true, offset) => ciram[offset],
+            (false, offset) => self.ines.chr_rom[offset],
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        match self.ppu_source(address & 0x3FFF) {
+            (true, offset) => ciram[offset] = value,
+            (false, offset) if self.ines.is_chr_ram => self.ines.chr_rom[offset] = value,
+            _ => {}
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            // Reading sound RAM can advance the address
+            0x4800..=0x4FFF => self.audio.read_data(),
+            _ => self.cpu_peek(address),
+        }
+    }
+
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            0x5000..=0x57FF => self.irq_counter as u8,
+            0x5800..=0x5FFF => ((self.irq_counter >> 8) as u8) | ((self.irq_enabled as u8) << 7),
+            _ => self
+                .cpu_mapping(address)
+                .map_or(0, |location| location.read(self.memory(location.memory))),
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+        let bank = match address {
+            0x6000..=0x7FFF => {
+                let addr = address.lower_8k() as usize;
+                return Some(MemoryLocation::new(CartMemory::PrgRam, addr));
+            }
+            0x8000..=0x9FFF => self.prg_banks[0] as usize,
+            0xA000..=0xBFFF => self.prg_banks[1] as usize,
+            0xC000..=0xDFFF => self.prg_banks[2] as usize,
+            0xE000..=0xFFFF => num_8k_chunks - 1,
+            _ => return None,
+        } % num_8k_chunks;
+
+        let addr = bank * 0x2000 + address.lower_8k() as usize;
+        Some(MemoryLocation::new(CartMemory::PrgRom, addr))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        Some(match self.ppu_source(address & 0x3FFF) {
+            (true, offset) => MemoryLocation::new(CartMemory::Ciram, offset),
+            (false, offset) => MemoryLocation::new(CartMemory::ChrRom, offset),
+        })
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x4800..=0x4FFF => self.audio.write_data(value),
+            0x5000..=0x57FF => {
+                self.irq_counter = (self.irq_counter & 0x7F00) | value as u16;
+                self.acknowledge_irq();
+            }
+            0x5800..=0x5FFF => {
+                self.irq_counter = (self.irq_counter & 0xFF) | (((value & 0x7F) as u16) << 8);
+                self.irq_enabled = (value & 0x80) != 0;
+                self.acknowledge_irq();
+            }
+            0x6000..=0x7FFF if self.ram_writable(address) => {
+                self.ram[address.lower_8k() as usize] = value;
+            }
+            0x8000..=0xBFFF => self.chr_banks[((address - 0x8000) >> 11) as usize] = value,
+            0xC000..=0xDFFF => self.nametable_banks[((address - 0xC000) >> 11) as usize] = value,
+            0xE000..=0xE7FF => {
+                self.prg_banks[0] = value & 0x3F;
+                self.sound_disabled = (value & 0x40) != 0;
+            }
+            0xE800..=0xEFFF => {
+                self.prg_banks[1] = value & 0x3F;
+                self.chr_ciram_disable = value & 0xC0;
+            }
+            0xF000..=0xF7FF => self.prg_banks[2] = value & 0x3F,
+            0xF800..=0xFFFF => {
+                self.write_protect = value;
+                self.audio.write_address(value);
+            }
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::MapperControlled
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        if !self.sound_disabled {
+            self.audio.tick();
+        }
+
+        if self.irq_enabled && self.irq_counter < 0x7FFF {
+            self.irq_counter += 1;
+            if self.irq_counter == 0x7FFF {
+                self.irq_pending = true;
+            }
+        }
+
+        self.irq_pending
+    }
+
+    fn audio_output(&self) -> i16 {
+        if self.sound_disabled {
+            0
+        } else {
+            self.audio.output()
+        }
+    }
+
+    fn set_authentic_audio(&mut self, authentic: bool) {
+        self.audio.set_multiplexed(authentic);
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        Some(&self.ram)
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+}
+
+impl CartridgeSaveLoad for N163 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_all(&self.chr_banks)?;
+        writer.write_all(&self.nametable_banks)?;
+        writer.write_all(&self.prg_banks)?;
+        writer.write_bool(self.sound_disabled)?;
+        writer.write_u8(self.chr_ciram_disable)?;
+        writer.write_u8(self.write_protect)?;
+        writer.write_u16(self.irq_counter)?;
+        writer.write_bool(self.irq_enabled)?;
+        writer.write_bool(self.irq_pending)?;
+        self.audio.save(writer)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        reader.read_exact(&mut self.chr_banks)?;
+        reader.read_exact(&mut self.nametable_banks)?;
+        reader.read_exact(&mut self.prg_banks)?;
+        self.sound_disabled = reader.read_bool()?;
+        self.chr_ciram_disable = reader.read_u8()?;
+        self.write_protect = reader.read_u8()?;
+        self.irq_counter = reader.read_u16()?;
+        self.irq_enabled = reader.read_bool()?;
+        self.irq_pending = reader.read_bool()?;
+        self.audio.load(reader)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::N163;
+    use crate::{
+        cartridge::{CartMemory, Cartridge, MemoryLocation},
+        ines::test_helpers::TestRom,
+    };
+
+    fn n163() -> N163 {
+        N163::new(TestRom::new(19, 8, 16).ines())
+    }
+
+    fn ppu_location(cart: &N163, address: u16) -> (CartMemory, usize) {
+        let MemoryLocation { memory, offset } = cart.ppu_mapping(address).unwrap();
+        (memory, offset)
+    }
+
+    #[test]
+    fn test_ciram_banks() {
+        let mut cart = n163();
+        cart.cpu_write(0x8000, 0xE1);
+        cart.cpu_write(0xA000, 0xE0);
+        assert_eq!(ppu_location(&cart, 0x0010), (CartMemory::Ciram, 0x410));
+        assert_eq!(ppu_location(&cart, 0x1010), (CartMemory::Ciram, 0x010));
+
+        // $E800 bits 6 and 7 keep each pattern table on CHR-ROM
+        cart.cpu_write(0xE800, 0x40);
+        assert_eq!(
+            ppu_location(&cart, 0x0010),
+            (CartMemory::ChrRom, 0x61 * 1024 + 0x10)
+        );
+        assert_eq!(ppu_location(&cart, 0x1010), (CartMemory::Ciram, 0x010));
+        cart.cpu_write(0xE800, 0x80);
+        assert_eq!(ppu_location(&cart, 0x0010), (CartMemory::Ciram, 0x410));
+        assert_eq!(
+            ppu_location(&cart, 0x1010),
+            (CartMemory::ChrRom, 0x60 * 1024 + 0x10)
+        );
+
+        // Nametables can come from CHR-ROM too
+        assert_eq!(ppu_location(&cart, 0x2400), (CartMemory::Ciram, 0x000));
+        cart.cpu_write(0xC800, 0xE1);
+        cart.cpu_write(0xD000, 0x05);
+        assert_eq!(ppu_location(&cart, 0x2400), (CartMemory::Ciram, 0x400));
+        assert_eq!(
+            ppu_location(&cart, 0x2800),
+            (CartMemory::ChrRom, 0x05 * 1024)
+        );
+    }
+
+    #[test]
+    fn test_ram_write_protect() {
+        let mut cart = n163();
+        cart.cpu_write(0x6000, 0x11);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+
+        // Writable with $4x in the upper bits, except the 2KB windows with their bit set
+        cart.cpu_write(0xF800, 0x42);
+        for address in [0x6000, 0x6800, 0x7000, 0x7800] {
+            cart.cpu_write(address, 0x22);
+        }
+        let values: Vec<u8> = [0x6000, 0x6800, 0x7000, 0x7800]
+            .iter()
+            .map(|&address| cart.cpu_read(address))
+            .collect();
+        assert_eq!(values, [0x22, 0, 0x22, 0x22]);
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut cart = n163();
+        cart.cpu_write(0x5000, 0xFD);
+        cart.cpu_write(0x5800, 0xFF);
+        assert!(!cart.cpu_tick());
+        assert!(cart.cpu_tick());
+
+        // The counter stops at $7FFF, and the line stays up until a counter write
+        assert!(cart.cpu_tick());
+        assert_eq!((cart.cpu_read(0x5000), cart.cpu_read(0x5800)), (0xFF, 0xFF));
+        cart.cpu_write(0x5000, 0x00);
+        assert!(!cart.cpu_tick());
+
+        // Disabled, it holds still
+        cart.cpu_write(0x5000, 0x34);
+        cart.cpu_write(0x5800, 0x12);
+        cart.cpu_tick();
+        assert_eq!((cart.cpu_read(0x5000), cart.cpu_read(0x5800)), (0x34, 0x12));
+    }
+}
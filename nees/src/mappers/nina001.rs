@@ -0,0 +1,164 @@
+use alloc::vec::Vec;
+
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, load_battery_ram};
+
+/// AVE NINA-001, which shares mapper 34 with BNROM. Its bank registers sit at the top of the
+/// work RAM, which still stores the written values.
+#[allow(clippy::upper_case_acronyms)]
+pub struct NINA001 {
+    ines: INES,
+    nametables: Nametables,
+    ram: [u8; 1024 * 8], // 8KB
+    prg_bank: u8,
+    chr_banks: [u8; 2],
+}
+
+impl NINA001 {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            nametables: Nametables::new(ines.fixed_mirroring()),
+            ines,
+            ram: [0; 1024 * 8],
+            prg_bank: 0,
+            chr_banks: [0; 2],
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let bank = self.chr_banks[(address >> 12) as usize & 1] as usize;
+        (bank * 0x1000 + address.lower_4k() as usize) % self.ines.chr_rom.len()
+    }
+}
+
+impl Cartridge for NINA001 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if (0x6000..=0x7FFF).contains(&address) {
+            self.ram[address.lower_8k() as usize] = value;
+        }
+
+        match address {
+            0x7FFD => self.prg_bank = value & 1,
+            0x7FFE => self.chr_banks[0] = value & 0x0F,
+            0x7FFF => self.chr_banks[1] = value & 0x0F,
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        self.ines.fixed_mirroring()
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        Some(&self.ram)
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x6000..=0x7FFF => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                address.lower_8k() as usize,
+            )),
+            0x8000..=0xFFFF => {
+                let addr = (self.prg_bank as usize) << 15 | address.lower_32k() as usize;
+                Some(MemoryLocation::new(
+                    CartMemory::PrgRom,
+                    addr % self.ines.prg_rom.len(),
+                ))
+            }
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+}
+
+impl CartridgeSaveLoad for NINA001 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_u8(self.prg_bank)?;
+        writer.write_all(&self.chr_banks)?;
+        self.nametables.save(writer)?;
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        self.prg_bank = reader.read_u8()?;
+        reader.read_exact(&mut self.chr_banks)?;
+        self.nametables.load(reader)?;
+
+        Ok(())
+    }
+}
@@ -1,29 +1,39 @@
 use crate::{
     bit_helpers::{SubType, BIT_13, MASK_16K, MASK_32K},
-    cartridge::{Cartridge, CartridgeSaveLoad, CartridgeWithSaveLoad},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
     ines::INES,
+    mirroring::{Mirroring, Nametables},
     reader_writer::{EasyReader, EasyWriter},
 };
 
 #[allow(clippy::upper_case_acronyms)]
 pub struct NROM {
     ines: INES,
+    nametables: Nametables,
 }
 
 impl NROM {
     pub fn new(ines: INES) -> Self {
-        Self { ines }
+        Self {
+            nametables: Nametables::new(ines.fixed_mirroring()),
+            ines,
+        }
     }
 
-    fn nrom_ppu_addr_to_ciram_addr(&self, ppuaddr: u16) -> u16 {
-        (ppuaddr & 0x3ff) | (((ppuaddr >> self.ines.ppu_address_ciram_a10_shift_count) & 1) << 10)
+    fn prg_addr(&self, address: u16) -> usize {
+        (address
+            & (if self.ines.prg_rom_size_16k_chunks == 1 {
+                MASK_16K
+            } else {
+                MASK_32K
+            })) as usize
     }
 }
 
 impl Cartridge for NROM {
-    fn ppu_read(&mut self, address: u16, ciram: &[u8]) -> u8 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.nrom_ppu_addr_to_ciram_addr(address) as usize]
+            self.nametables.read(self.mirroring(), address, ciram)
         } else {
             self.ines.chr_rom[address.lower_8k() as usize]
         }
@@ -31,17 +41,13 @@ impl Cartridge for NROM {
 
     fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.nrom_ppu_addr_to_ciram_addr(address) as usize] = value;
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
         }
     }
 
-    fn cpu_read(&self, address: u16) -> u8 {
-        self.ines.prg_rom[(address
-            & (if self.ines.prg_rom_size_16k_chunks == 1 {
-                MASK_16K
-            } else {
-                MASK_32K
-            })) as usize]
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.ines.prg_rom[self.prg_addr(address)]
     }
 
     fn cpu_write(&mut self, _address: u16, _value: u8) {
@@ -51,16 +57,55 @@ impl Cartridge for NROM {
     fn scanline(&mut self) -> bool {
         false
     }
+
+    fn mirroring(&self) -> Mirroring {
+        self.ines.fixed_mirroring()
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                address.lower_8k() as usize,
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
 }
 
 impl CartridgeSaveLoad for NROM {
-    fn save(&self, _writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        self.nametables.save(writer)?;
+
         Ok(())
     }
 
-    fn load(&mut self, _reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.nametables.load(reader)?;
+
         Ok(())
     }
 }
-
-impl CartridgeWithSaveLoad for NROM {}
@@ -0,0 +1,486 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    apu::{FDSAudio, MMC5Audio, N163Audio, Sunsoft5BAudio, VRC6Audio, OPLL},
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    mirroring::{Mirroring, Nametables},
+    nsf::NSF,
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+/// Where the player driver is mapped. Nothing else decodes $4100-$41FF.
+const DRIVER_ADDRESS: u16 = 0x4100;
+/// Reading this acknowledges the play timer's IRQ.
+const IRQ_ACKNOWLEDGE: u16 = 0x41FF;
+const CPU_CLOCK_HZ: u32 = 1_789_773;
+const OPLL_CLOCK_DIVIDER: u8 = 36;
+
+/// A tiny driver that calls init once with the song number, then play from an IRQ raised at the
+/// rip's play rate. Returns the code along with the addresses of the idle loop and IRQ handler.
+#[rustfmt::skip]
+fn driver(song: u8, init: u16, play: u16) -> (Vec<u8>, u16, u16) {
+    let [init_lo, init_hi] = init.to_le_bytes();
+    let [play_lo, play_hi] = play.to_le_bytes();
+    let [ack_lo, ack_hi] = IRQ_ACKNOWLEDGE.to_le_bytes();
+
+    let mut code = vec![
+        0x78,                   // SEI
+        0xD8,                   // CLD
+        0xA2, 0xFF,             // LDX #$FF
+        0x9A,                   // TXS
+        0xA9, 0x0F,             // LDA #$0F
+        0x8D, 0x15, 0x40,       // STA $4015
+        0xA9, 0x40,             // LDA #$40
+        0x8D, 0x17, 0x40,       // STA $4017
+        0xA9, song,             // LDA #song
+        0xA2, 0x00,             // LDX #$00 (NTSC)
+        0x20, init_lo, init_hi, // JSR init
+        0x58,                   // CLI
+    ];
+    let idle = DRIVER_ADDRESS + code.len() as u16;
+    let [idle_lo, idle_hi] = idle.to_le_bytes();
+    code.extend_from_slice(&[
+        0x4C, idle_lo, idle_hi, // JMP idle
+    ]);
+
+    let irq = DRIVER_ADDRESS + code.len() as u16;
+    code.extend_from_slice(&[
+        0xAD, ack_lo, ack_hi,   // LDA ack
+        0x20, play_lo, play_hi, // JSR play
+        0x40,                   // RTI
+    ]);
+
+    (code, idle, irq)
+}
+
+/// Plays an NSF rip: its program banked into $8000-$FFFF through $5FF8-$5FFF, 8KB of RAM at
+/// $6000, whichever expansion audio chips it asks for and a driver that calls its init and play
+/// routines. FDS rips get 40KB of RAM at $6000-$FFFF instead, which bank writes copy into.
+pub struct NSFMapper {
+    data: Vec<u8>,
+    banks: [u8; 8],
+    ram: Vec<u8>,
+    fds_ram: Option<Vec<u8>>,
+    driver: Vec<u8>,
+    nametables: Nametables,
+    idle_address: u16,
+    irq_address: u16,
+    play_period: u32,
+    play_timer: u32,
+    irq_pending: bool,
+
+    vrc6: Option<VRC6Audio>,
+    vrc7: Option<OPLL>,
+    opll_divider: u8,
+    fds: Option<FDSAudio>,
+    mmc5: Option<MMC5Audio>,
+    mmc5_exram: Vec<u8>,
+    multiplicand: u8,
+    multiplier: u8,
+    n163: Option<N163Audio>,
+    sunsoft5b: Option<Sunsoft5BAudio>,
+}
+
+impl NSFMapper {
+    pub fn new(nsf: &NSF, song: u8) -> Self {
+        let (driver, idle_address, irq_address) = driver(song, nsf.init_address, nsf.play_address);
+        let play_period = (nsf.play_speed_ntsc as u64 * CPU_CLOCK_HZ as u64 / 1_000_000) as u32;
+        let chips = nsf.expansion;
+
+        let banks = if nsf.is_bankswitched() {
+            nsf.bankswitch_init
+        } else {
+            [0, 1, 2, 3, 4, 5, 6, 7]
+        };
+
+        let mut mapper = Self {
+            data: nsf.banked_data(),
+            banks,
+            ram: vec![0; 0x2000],
+            fds_ram: None,
+            driver,
+            nametables: Nametables::default(),
+            idle_address,
+            irq_address,
+            play_period: play_period.max(1),
+            play_timer: play_period.max(1),
+            irq_pending: false,
+
+            vrc6: chips.vrc6().then(VRC6Audio::new),
+            vrc7: chips.vrc7().then(OPLL::new),
+            opll_divider: 0,
+            fds: chips.fds().then(FDSAudio::new),
+            mmc5: chips.mmc5().then(MMC5Audio::new),
+            mmc5_exram: vec![0; 0x400],
+            multiplicand: 0xFF,
+            multiplier: 0xFF,
+            n163: chips.n163().then(N163Audio::new),
+            sunsoft5b: chips.sunsoft5b().then(Sunsoft5BAudio::new),
+        };
+
+        if chips.fds() {
+            let mut fds_ram = vec![0; 0xA000];
+            if nsf.is_bankswitched() {
+                // $6000-$7FFF start with the banks for $E000 and $F000
+                let init = &nsf.bankswitch_init;
+                for (slot, &bank) in [init[6], init[7]].iter().chain(init).enumerate() {
+                    mapper.copy_bank(&mut fds_ram, slot, bank);
+                }
+            } else {
+                let start = nsf.load_address.saturating_sub(0x6000) as usize;
+                let len = nsf.data.len().min(fds_ram.len().saturating_sub(start));
+                fds_ram[start..start + len].copy_from_slice(&nsf.data[..len]);
+            }
+            mapper.fds_ram = Some(fds_ram);
+        }
+
+        mapper
+    }
+
+    fn bank_offset(&self, bank: u8) -> usize {
+        (bank as usize * 0x1000) % self.data.len()
+    }
+
+    /// Copies a 4KB bank into FDS RAM, slot 0 being $6000.
+    fn copy_bank(&self, fds_ram: &mut [u8], slot: usize, bank: u8) {
+        let offset = self.bank_offset(bank);
+        fds_ram[slot * 0x1000..(slot + 1) * 0x1000]
+            .copy_from_slice(&self.data[offset..offset + 0x1000]);
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        let bank = self.banks[((address >> 12) & 7) as usize];
+        self.bank_offset(bank) + (address & 0x0FFF) as usize
+    }
+
+    /// The vectors always lead into the driver.
+    fn vector(&self, address: u16) -> Option<u16> {
+        match address {
+            0xFFFA | 0xFFFB => Some(self.idle_address),
+            0xFFFC | 0xFFFD => Some(DRIVER_ADDRESS),
+            0xFFFE | 0xFFFF => Some(self.irq_address),
+            _ => None,
+        }
+    }
+
+    fn write_bank(&mut self, register: u16, value: u8) {
+        match self.fds_ram.take() {
+            Some(mut fds_ram) => {
+                // Registers $5FF6-$5FFF cover $6000-$FFFF
+                self.copy_bank(&mut fds_ram, (register - 0x5FF6) as usize, value);
+                self.fds_ram = Some(fds_ram);
+            }
+            None if register >= 0x5FF8 => self.banks[(register - 0x5FF8) as usize] = value,
+            None => {}
+        }
+    }
+}
+
+impl Cartridge for NSFMapper {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            0
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        match address {
+            IRQ_ACKNOWLEDGE => {
+                self.irq_pending = false;
+                0
+            }
+            0x4800..=0x4FFF if self.n163.is_some() => {
+                self.n163.as_mut().map_or(0, |n163| n163.read_data())
+            }
+            0x5010 | 0x5015 if self.mmc5.is_some() => {
+                self.mmc5.as_mut().map_or(0, |mmc5| mmc5.read_reg(address))
+            }
+            _ => {
+                let value = self.cpu_peek(address);
+                if let (Some(mmc5), 0x8000..=0xBFFF) = (&mut self.mmc5, address) {
+                    mmc5.snoop_read(value);
+                }
+                value
+            }
+        }
+    }
+
+    fn cpu_peek(&self, address: u16) -> u8 {
+        if let Some(vector) = self.vector(address) {
+            return vector.to_le_bytes()[(address & 1) as usize];
+        }
+
+        match address {
+            0x4040..=0x4097 if self.fds.is_some() => {
+                self.fds.as_ref().map_or(0, |fds| fds.read_reg(address))
+            }
+            0x4100..=0x41FF => self
+                .driver
+                .get((address - DRIVER_ADDRESS) as usize)
+                .copied()
+                .unwrap_or(0),
+            0x5205 if self.mmc5.is_some() => {
+                (self.multiplicand as u16 * self.multiplier as u16) as u8
+            }
+            0x5206 if self.mmc5.is_some() => {
+                ((self.multiplicand as u16 * self.multiplier as u16) >> 8) as u8
+            }
+            _ => self
+                .cpu_mapping(address)
+                .map_or(0, |location| location.read(self.memory(location.memory))),
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if self.vector(address).is_some() {
+            return None;
+        }
+
+        match address {
+            0x5C00..=0x5FF5 if self.mmc5.is_some() => Some(MemoryLocation::new(
+                CartMemory::Internal,
+                (address & 0x3FF) as usize,
+            )),
+            0x6000..=0xFFFF if self.fds_ram.is_some() => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                (address - 0x6000) as usize,
+            )),
+            0x6000..=0x7FFF => Some(MemoryLocation::new(
+                CartMemory::PrgRam,
+                address.lower_8k() as usize,
+            )),
+            0x8000..=0xFFFF => Some(MemoryLocation::new(
+                CartMemory::PrgRom,
+                self.prg_addr(address),
+            )),
+            _ => None,
+        }
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            None
+        }
+    }
+
+    /// The rip's data stands in for PRG-ROM.
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.data,
+            CartMemory::PrgRam => self.fds_ram.as_deref().unwrap_or(&self.ram),
+            CartMemory::Vram => self.nametables.vram(),
+            CartMemory::Internal => &self.mmc5_exram,
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.data,
+            CartMemory::PrgRam => match &mut self.fds_ram {
+                Some(ram) => ram,
+                None => &mut self.ram,
+            },
+            CartMemory::Vram => self.nametables.vram_mut(),
+            CartMemory::Internal => &mut self.mmc5_exram,
+            _ => &mut [],
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if let (Some(fds), 0x4040..=0x408A) = (&mut self.fds, address) {
+            fds.write_reg(address, value);
+        }
+        if let (Some(vrc6), 0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002) =
+            (&mut self.vrc6, address)
+        {
+            vrc6.write_reg(address, value);
+        }
+        if let Some(opll) = &mut self.vrc7 {
+            match address {
+                0x9010 => opll.write_address(value),
+                0x9030 => opll.write_data(value),
+                _ => {}
+            }
+        }
+        if let Some(mmc5) = &mut self.mmc5 {
+            match address {
+                0x5000..=0x5015 => mmc5.write_reg(address, value),
+                0x5205 => self.multiplicand = value,
+                0x5206 => self.multiplier = value,
+                0x5C00..=0x5FF5 => self.mmc5_exram[(address & 0x3FF) as usize] = value,
+                _ => {}
+            }
+        }
+        if let Some(n163) = &mut self.n163 {
+            match address {
+                0x4800..=0x4FFF => n163.write_data(value),
+                0xF800..=0xFFFF => n163.write_address(value),
+                _ => {}
+            }
+        }
+        if let Some(sunsoft5b) = &mut self.sunsoft5b {
+            match address {
+                0xC000..=0xDFFF => sunsoft5b.write_address(value),
+                0xE000..=0xFFFF => sunsoft5b.write_data(value),
+                _ => {}
+            }
+        }
+
+        match address {
+            0x5FF6..=0x5FFF => self.write_bank(address, value),
+            0x6000..=0xFFFF if self.fds_ram.is_some() => {
+                if let Some(ram) = &mut self.fds_ram {
+                    ram[(address - 0x6000) as usize] = value;
+                }
+            }
+            0x6000..=0x7FFF => self.ram[address.lower_8k() as usize] = value,
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        Mirroring::Vertical
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.play_timer -= 1;
+        if self.play_timer == 0 {
+            self.play_timer = self.play_period;
+            self.irq_pending = true;
+        }
+
+        if let Some(vrc6) = &mut self.vrc6 {
+            vrc6.tick();
+        }
+        if let Some(opll) = &mut self.vrc7 {
+            self.opll_divider += 1;
+            if self.opll_divider == OPLL_CLOCK_DIVIDER {
+                self.opll_divider = 0;
+                opll.clock();
+            }
+        }
+        if let Some(fds) = &mut self.fds {
+            fds.tick();
+        }
+        if let Some(mmc5) = &mut self.mmc5 {
+            mmc5.tick();
+        }
+        if let Some(n163) = &mut self.n163 {
+            n163.tick();
+        }
+        if let Some(sunsoft5b) = &mut self.sunsoft5b {
+            sunsoft5b.tick();
+        }
+
+        self.irq_pending
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        match &self.fds_ram {
+            Some(ram) => Some(&ram[..0x2000]),
+            None => Some(&self.ram),
+        }
+    }
+
+    fn audio_output(&self) -> i16 {
+        let outputs = [
+            self.vrc6.as_ref().map(VRC6Audio::output),
+            self.vrc7.as_ref().map(OPLL::output),
+            self.fds.as_ref().map(FDSAudio::output),
+            self.mmc5.as_ref().map(MMC5Audio::output),
+            self.n163.as_ref().map(N163Audio::output),
+            self.sunsoft5b.as_ref().map(Sunsoft5BAudio::output),
+        ];
+        let sum: i32 = outputs.iter().flatten().map(|&output| output as i32).sum();
+        sum.clamp(i16::MIN as i32, i16::MAX as i32) as i16
+    }
+}
+
+impl CartridgeSaveLoad for NSFMapper {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.banks)?;
+        writer.write_all(&self.ram)?;
+        if let Some(ram) = &self.fds_ram {
+            writer.write_all(ram)?;
+        }
+        writer.write_u32(self.play_timer)?;
+        writer.write_bool(self.irq_pending)?;
+
+        if let Some(vrc6) = &self.vrc6 {
+            vrc6.save(writer)?;
+        }
+        if let Some(opll) = &self.vrc7 {
+            opll.save(writer)?;
+            writer.write_u8(self.opll_divider)?;
+        }
+        if let Some(fds) = &self.fds {
+            fds.save(writer)?;
+        }
+        if let Some(mmc5) = &self.mmc5 {
+            mmc5.save(writer)?;
+            writer.write_all(&self.mmc5_exram)?;
+            writer.write_u8(self.multiplicand)?;
+            writer.write_u8(self.multiplier)?;
+        }
+        if let Some(n163) = &self.n163 {
+            n163.save(writer)?;
+        }
+        if let Some(sunsoft5b) = &self.sunsoft5b {
+            sunsoft5b.save(writer)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.banks)?;
+        reader.read_exact(&mut self.ram)?;
+        if let Some(ram) = &mut self.fds_ram {
+            reader.read_exact(ram)?;
+        }
+        self.play_timer = reader.read_u32()?;
+        self.irq_pending = reader.read_bool()?;
+
+        if let Some(vrc6) = &mut self.vrc6 {
+            vrc6.load(reader)?;
+        }
+        if let Some(opll) = &mut self.vrc7 {
+            opll.load(reader)?;
+            self.opll_divider = reader.read_u8()?;
+        }
+        if let Some(fds) = &mut self.fds {
+            fds.load(reader)?;
+        }
+        if let Some(mmc5) = &mut self.mmc5 {
+            mmc5.load(reader)?;
+            reader.read_exact(&mut self.mmc5_exram)?;
+            self.multiplicand = reader.read_u8()?;
+            self.multiplier = reader.read_u8()?;
+        }
+        if let Some(n163) = &mut self.n163 {
+            n163.load(reader)?;
+        }
+        if let Some(sunsoft5b) = &mut self.sunsoft5b {
+            sunsoft5b.load(reader)?;
+        }
+
+        Ok(())
+    }
+}
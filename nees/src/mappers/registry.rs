@@ -0,0 +1,87 @@
+use alloc::{boxed::Box, collections::BTreeMap};
+
+use crate::{cartridge::CartridgeWithSaveLoad, ines::INES};
+
+/// Builds a board from a ROM's header and contents.
+pub type MapperConstructor = Box<dyn Fn(INES) -> Box<dyn CartridgeWithSaveLoad>>;
+
+/// Picks the board for a ROM by its mapper number, and its submapper on NES 2.0 headers. Boards
+/// registered here take priority over the built-in ones, so they can also replace them.
+#[derive(Default)]
+pub struct MapperRegistry {
+    mappers: BTreeMap<(u16, Option<u8>), MapperConstructor>,
+}
+
+impl MapperRegistry {
+    /// A registry with only the built-in boards.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a board for every submapper of a mapper number.
+    pub fn register(
+        &mut self,
+        mapper_no: u16,
+        constructor: impl Fn(INES) -> Box<dyn CartridgeWithSaveLoad> + 'static,
+    ) {
+        self.mappers
+            .insert((mapper_no, None), Box::new(constructor));
+    }
+
+    /// Registers a board for a single submapper, which takes priority over one registered for
+    /// the whole mapper number.
+    pub fn register_submapper(
+        &mut self,
+        mapper_no: u16,
+        submapper: u8,
+        constructor: impl Fn(INES) -> Box<dyn CartridgeWithSaveLoad> + 'static,
+    ) {
+        self.mappers
+            .insert((mapper_no, Some(submapper)), Box::new(constructor));
+    }
+
+    pub fn load(&self, ines: INES) -> anyhow::Result<Box<dyn CartridgeWithSaveLoad>> {
+        let constructor = ines
+            .submapper
+            .and_then(|submapper| self.mappers.get(&(ines.mapper_no, Some(submapper))))
+            .or_else(|| self.mappers.get(&(ines.mapper_no, None)));
+
+        match constructor {
+            Some(constructor) => Ok(constructor(ines)),
+            None => super::load_cart(ines),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::boxed::Box;
+
+    use super::MapperRegistry;
+    use crate::{
+        ines::{test_helpers::TestRom, INES},
+        mappers::nrom::NROM,
+        mirroring::Mirroring,
+    };
+
+    /// An NES 2.0 header for 16KB of PRG-ROM and 8KB of CHR-ROM, with horizontal mirroring.
+    fn rom(mapper_no: u8, submapper: u8) -> INES {
+        TestRom::new(mapper_no, 1, 1).submapper(submapper).ines()
+    }
+
+    #[test]
+    fn test_submapper_boards_take_priority() {
+        let mut registry = MapperRegistry::new();
+        assert!(registry.load(rom(15, 0)).is_err());
+
+        registry.register(15, |ines| Box::new(NROM::new(ines)));
+        registry.register_submapper(15, 1, |mut ines| {
+            ines.four_screen = true;
+            Box::new(NROM::new(ines))
+        });
+        let cart = registry.load(rom(15, 0)).unwrap();
+        assert_eq!(cart.mirroring(), Mirroring::Horizontal);
+        let cart = registry.load(rom(15, 1)).unwrap();
+        assert_eq!(cart.mirroring(), Mirroring::FourScreen);
+    }
+}
@@ -1,33 +1,45 @@
 use crate::{
     bit_helpers::{SubType, BIT_13},
-    cartridge::{Cartridge, CartridgeSaveLoad, CartridgeWithSaveLoad},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
     ines::INES,
+    mirroring::{Mirroring, Nametables},
     reader_writer::{EasyReader, EasyWriter},
 };
 
+use super::bus_conflict;
+
 #[allow(clippy::upper_case_acronyms)]
 pub struct UNROM {
     ines: INES,
+    nametables: Nametables,
     selected_bank: u8,
+    bus_conflicts: bool,
 }
 
 impl UNROM {
     pub fn new(ines: INES) -> Self {
         Self {
+            nametables: Nametables::new(ines.fixed_mirroring()),
             ines,
             selected_bank: 0,
+            bus_conflicts: false,
         }
     }
 
-    fn ppu_addr_to_ciram_addr(&self, ppuaddr: u16) -> u16 {
-        (ppuaddr & 0x3ff) | (((ppuaddr >> self.ines.ppu_address_ciram_a10_shift_count) & 1) << 10)
+    fn prg_addr(&self, address: u16) -> usize {
+        if address >= 0xC000 {
+            (((self.ines.prg_rom_size_16k_chunks as usize) - 1) << 14)
+                | address.lower_16k() as usize
+        } else {
+            ((self.selected_bank as usize) << 14) | address.lower_16k() as usize
+        }
     }
 }
 
 impl Cartridge for UNROM {
-    fn ppu_read(&mut self, address: u16, ciram: &[u8]) -> u8 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.ppu_addr_to_ciram_addr(address) as usize]
+            self.nametables.read(self.mirroring(), address, ciram)
         } else {
             self.ines.chr_rom[address.lower_8k() as usize]
         }
@@ -35,42 +47,106 @@ impl Cartridge for UNROM {
 
     fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
         if (address & BIT_13) == BIT_13 {
-            ciram[self.ppu_addr_to_ciram_addr(address) as usize] = value;
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
         } else if self.ines.is_chr_ram {
             self.ines.chr_rom[address.lower_8k() as usize] = value;
         }
     }
 
-    fn cpu_read(&self, address: u16) -> u8 {
-        if address >= 0xC000 {
-            self.ines.prg_rom[(((self.ines.prg_rom_size_16k_chunks as usize) - 1) << 14)
-                | address.lower_16k() as usize]
-        } else {
-            self.ines.prg_rom[((self.selected_bank as usize) << 14) | address.lower_16k() as usize]
-        }
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.ines.prg_rom[self.prg_addr(address)]
     }
 
     fn cpu_write(&mut self, address: u16, value: u8) {
         if address >= 0x8000 {
-            self.selected_bank = value & 0x0F;
+            let rom_value = self.cpu_read(address);
+            self.selected_bank = bus_conflict(value, rom_value, self.bus_conflicts) & 0x0F;
         }
     }
 
     fn scanline(&mut self) -> bool {
         false
     }
+
+    fn mirroring(&self) -> Mirroring {
+        self.ines.fixed_mirroring()
+    }
+
+    fn set_bus_conflicts(&mut self, enabled: bool) {
+        self.bus_conflicts = enabled;
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                address.lower_8k() as usize,
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
 }
 
 impl CartridgeSaveLoad for UNROM {
     fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
         writer.write_u8(self.selected_bank)?;
+        self.nametables.save(writer)?;
         Ok(())
     }
 
     fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
         self.selected_bank = reader.read_u8()?;
+        self.nametables.load(reader)?;
         Ok(())
     }
 }
 
-impl CartridgeWithSaveLoad for UNROM {}
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::UNROM;
+    use crate::{cartridge::Cartridge, ines::test_helpers::TestRom};
+
+    #[test]
+    fn test_bus_conflicts() {
+        // $8010 in every bank holds $02
+        let rom = TestRom::new(2, 8, 0).prg_with(|offset| match offset % 0x4000 {
+            0x10 => 0x02,
+            _ => 0xFF,
+        });
+        let mut cart = UNROM::new(rom.ines());
+        let bank = |cart: &UNROM| cart.cpu_mapping(0x8000).unwrap().offset / 0x4000;
+        cart.cpu_write(0x8010, 0x07);
+        assert_eq!(bank(&cart), 7);
+
+        // The ROM's zero bits win
+        cart.set_bus_conflicts(true);
+        cart.cpu_write(0x8010, 0x07);
+        assert_eq!(bank(&cart), 2);
+        cart.cpu_write(0x8011, 0x07);
+        assert_eq!(bank(&cart), 7);
+    }
+}
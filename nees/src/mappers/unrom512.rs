@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::flash::SST39SF040;
+
+/// The CHR-RAM bank that four-screen boards map to the nametables.
+const FOUR_SCREEN_CHR_OFFSET: usize = 0x6000;
+
+/// RetroUSB's UNROM-512: UNROM with 32KB of banked CHR-RAM and up to 512KB of PRG. Boards with
+/// the battery flag set have self-flashable PRG, which takes the flash commands at $8000-$BFFF
+/// and leaves the bank register at $C000-$FFFF.
+///
+/// The header's four-screen and vertical bits together pick horizontal, vertical, switchable
+/// one-screen or four-screen mirroring, the latter using the last 8KB of CHR-RAM.
+#[allow(clippy::upper_case_acronyms)]
+pub struct UNROM512 {
+    ines: INES,
+    nametables: Nametables,
+    flash: Option<SST39SF040>,
+    one_screen: bool,
+    four_screen: bool,
+    bank_select: u8,
+}
+
+impl UNROM512 {
+    pub fn new(mut ines: INES) -> Self {
+        if ines.is_chr_ram && ines.chr_rom.len() < 0x8000 {
+            ines.chr_rom.resize(0x8000, 0);
+        }
+        let flash = ines.has_battery.then(SST39SF040::new);
+        let vertical = ines.mirroring == Mirroring::Vertical;
+        let one_screen = ines.four_screen && !vertical;
+        let four_screen = ines.four_screen && vertical;
+
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            flash,
+            one_screen,
+            four_screen,
+            bank_select: 0,
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let bank = ((self.bank_select >> 5) & 0b11) as usize;
+        (bank * 0x2000 + address.lower_8k() as usize) % self.ines.chr_rom.len()
+    }
+
+    fn prg_addr(&self, address: u16) -> usize {
+        let num_16k_chunks = self.ines.prg_rom_size_16k_chunks as usize;
+        let bank = if address >= 0xC000 {
+            num_16k_chunks - 1
+        } else {
+            (self.bank_select & 0x1F) as usize % num_16k_chunks
+        };
+        bank * 0x4000 + address.lower_16k() as usize
+    }
+}
+
+impl Cartridge for UNROM512 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            if self.four_screen {
+                self.ines.chr_rom[FOUR_SCREEN_CHR_OFFSET + address.lower_8k() as usize]
+            } else {
+                self.nametables.read(self.mirroring(), address, ciram)
+            }
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            if self.four_screen {
+                self.ines.chr_rom[FOUR_SCREEN_CHR_OFFSET + address.lower_8k() as usize] = value;
+            } else {
+                self.nametables
+                    .write(self.mirroring(), address, value, ciram);
+            }
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        if address < 0x8000 {
+            return 0;
+        }
+
+        let addr = self.prg_addr(address);
+        match &self.flash {
+            Some(flash) => flash.read(&self.ines.prg_rom, addr),
+            None => self.ines.prg_rom[addr],
+        }
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x8000..=0xBFFF if self.flash.is_some() => {
+                let addr = self.prg_addr(address);
+                if let Some(flash) = &mut self.flash {
+                    flash.write(&mut self.ines.prg_rom, addr, value);
+                }
+            }
+            0x8000..=0xFFFF => self.bank_select = value,
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        if self.four_screen {
+            Mirroring::FourScreen
+        } else if !self.one_screen {
+            self.ines.mirroring
+        } else if (self.bank_select & 0x80) == 0 {
+            Mirroring::SingleScreenA
+        } else {
+            Mirroring::SingleScreenB
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        let flash = self.flash.as_ref()?;
+        flash.modified().then(|| self.ines.prg_rom.clone())
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        if self.flash.is_some() && data.len() == self.ines.prg_rom.len() {
+            self.ines.prg_rom.copy_from_slice(data);
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        (address >= 0x8000).then(|| MemoryLocation::new(CartMemory::PrgRom, self.prg_addr(address)))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == 0 {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        } else if self.four_screen {
+            let addr = FOUR_SCREEN_CHR_OFFSET + address.lower_8k() as usize;
+            Some(MemoryLocation::new(CartMemory::ChrRom, addr))
+        } else {
+            self.mirroring().nametable_location(address)
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+}
+
+impl CartridgeSaveLoad for UNROM512 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_u8(self.bank_select)?;
+        writer.write_all(&self.ines.chr_rom)?;
+        if let Some(flash) = &self.flash {
+            flash.save(writer)?;
+            writer.write_all(&self.ines.prg_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.bank_select = reader.read_u8()?;
+        reader.read_exact(&mut self.ines.chr_rom)?;
+        if let Some(flash) = &mut self.flash {
+            flash.load(reader)?;
+            reader.read_exact(&mut self.ines.prg_rom)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,324 @@
+use alloc::vec::Vec;
+
+use crate::{
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, load_battery_ram, vrc_irq::VrcIrq};
+
+const A0: u16 = 1 << 0;
+const A1: u16 = 1 << 1;
+const A2: u16 = 1 << 2;
+const A3: u16 = 1 << 3;
+const A6: u16 = 1 << 6;
+const A7: u16 = 1 << 7;
+
+/// The Konami VRC4, and its predecessor the VRC2 which lacks the IRQ counter, PRG swap mode and
+/// single-screen mirroring. Each board wires two CPU address lines to the chip's register
+/// selects; which ones is given by the mapper and NES 2.0 submapper numbers.
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC4 {
+    ines: INES,
+    nametables: Nametables,
+    ram: [u8; 1024 * 8], // 8KB
+    is_vrc2: bool,
+    has_ram: bool,
+    /// VRC2a only connects the upper 7 bits of the CHR bank numbers.
+    chr_shift: u8,
+    register_lines: (u16, u16),
+    prg_banks: [u8; 2],
+    chr_banks: [u16; 8],
+    mirroring: u8,
+    prg_swap_mode: bool,
+    microwire_latch: u8,
+    irq: VrcIrq,
+}
+
+impl VRC4 {
+    pub fn new(ines: INES) -> Self {
+        // Plain iNES can't tell the variants sharing a mapper number apart, so their lines are
+        // combined; games only ever write to addresses that decode the same on either wiring.
+        let (is_vrc2, register_lines) = match (ines.mapper_no, ines.submapper) {
+            (21, Some(1)) => (false, (A1, A2)),
+            (21, Some(2)) => (false, (A6, A7)),
+            (21, _) => (false, (A1 | A6, A2 | A7)),
+            (22, _) => (true, (A1, A0)),
+            (23, Some(1)) => (false, (A0, A1)),
+            (23, Some(2)) => (false, (A2, A3)),
+            (23, Some(3)) => (true, (A0, A1)),
+            (23, _) => (false, (A0 | A2, A1 | A3)),
+            (25, Some(1)) => (false, (A1, A0)),
+            (25, Some(2)) => (false, (A3, A2)),
+            (25, Some(3)) => (true, (A1, A0)),
+            _ => (false, (A1 | A3, A0 | A2)),
+        };
+        // VRC2 boards without a battery only have the microwire latch at $6000
+        let has_ram = !is_vrc2 || ines.has_battery;
+        let chr_shift = (ines.mapper_no == 22) as u8;
+
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            ram: [0; 1024 * 8],
+            is_vrc2,
+            has_ram,
+            chr_shift,
+            register_lines,
+            prg_banks: [0; 2],
+            chr_banks: [0; 8],
+            mirroring: 0,
+            prg_swap_mode: false,
+            microwire_latch: 0,
+            irq: VrcIrq::new(),
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+        let bank = (self.chr_banks[(address >> 10) as usize & 7] >> self.chr_shift) as usize;
+        (bank % num_1k_chunks) * 1024 + (address & 0x3FF) as usize
+    }
+
+    /// Maps a register write address onto its canonical $x000-$x003 form.
+    fn register(&self, address: u16) -> u16 {
+        let (low, high) = self.register_lines;
+        let high_bit = ((address & high) != 0) as u16;
+        let low_bit = ((address & low) != 0) as u16;
+        (address & 0xF000) | (high_bit << 1) | low_bit
+    }
+
+    fn write_chr_bank(&mut self, reg: u16, value: u8) {
+        // $B000-$E003: each pair of registers holds the low and high bits of one bank
+        let index = (((reg >> 12) - 0xB) * 2 + ((reg >> 1) & 1)) as usize;
+        let bank = &mut self.chr_banks[index];
+        if (reg & 1) == 0 {
+            *bank = (*bank & 0x1F0) | (value & 0x0F) as u16;
+        } else {
+            *bank = (*bank & 0x0F) | ((value & 0x1F) as u16) << 4;
+        }
+    }
+}
+
+impl Cartridge for VRC4 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+
+    fn cpu_peek(&self, address: u16) -> u8 {
+        match address {
+            // Only bit 0 is driven, the rest is open bus
+            0x6000..=0x6FFF if !self.has_ram => 0x60 | self.microwire_latch,
+            _ => self
+                .cpu_mapping(address)
+                .map_or(0, |location| location.read(self.memory(location.memory))),
+        }
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+        let bank = match address {
+            0x6000..=0x7FFF if self.has_ram => {
+                let addr = address.lower_8k() as usize;
+                return Some(MemoryLocation::new(CartMemory::PrgRam, addr));
+            }
+            0x8000..=0x9FFF if self.prg_swap_mode => num_8k_chunks - 2,
+            0x8000..=0x9FFF => self.prg_banks[0] as usize,
+            0xA000..=0xBFFF => self.prg_banks[1] as usize,
+            0xC000..=0xDFFF if self.prg_swap_mode => self.prg_banks[0] as usize,
+            0xC000..=0xDFFF => num_8k_chunks - 2,
+            0xE000..=0xFFFF => num_8k_chunks - 1,
+            _ => return None,
+        } % num_8k_chunks;
+
+        let addr = bank * 0x2000 + address.lower_8k() as usize;
+        Some(MemoryLocation::new(CartMemory::PrgRom, addr))
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        match address {
+            0x6000..=0x7FFF if self.has_ram => self.ram[address.lower_8k() as usize] = value,
+            0x6000..=0x6FFF => self.microwire_latch = value & 1,
+            0x8000..=0xFFFF => {
+                let reg = self.register(address);
+                match reg {
+                    0x8000..=0x8003 => self.prg_banks[0] = value & 0x1F,
+                    0x9000..=0x9003 if self.is_vrc2 => self.mirroring = value & 1,
+                    0x9000..=0x9001 => self.mirroring = value & 0b11,
+                    0x9002..=0x9003 => self.prg_swap_mode = (value & 0b10) != 0,
+                    0xA000..=0xA003 => self.prg_banks[1] = value & 0x1F,
+                    0xB000..=0xE003 => self.write_chr_bank(reg, value),
+                    _ if self.is_vrc2 => {}
+                    0xF000 => self.irq.write_latch_nibble(false, value),
+                    0xF001 => self.irq.write_latch_nibble(true, value),
+                    0xF002 => self.irq.write_control(value),
+                    0xF003 => self.irq.acknowledge(),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.mirroring & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        !self.is_vrc2 && self.irq.tick()
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        self.has_ram.then_some(&self.ram[..])
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+}
+
+impl CartridgeSaveLoad for VRC4 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_all(&self.prg_banks)?;
+        for bank in &self.chr_banks {
+            writer.write_u16(*bank)?;
+        }
+        writer.write_u8(self.mirroring)?;
+        writer.write_bool(self.prg_swap_mode)?;
+        writer.write_u8(self.microwire_latch)?;
+        self.irq.save(writer)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        reader.read_exact(&mut self.prg_banks)?;
+        for bank in &mut self.chr_banks {
+            *bank = reader.read_u16()?;
+        }
+        self.mirroring = reader.read_u8()?;
+        self.prg_swap_mode = reader.read_bool()?;
+        self.microwire_latch = reader.read_u8()?;
+        self.irq.load(reader)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{A0, A1, A2, A3, A6, A7, VRC4};
+    use crate::ines::test_helpers::TestRom;
+
+    /// Mapper, submapper, VRC2, the lines selecting register 1 and those selecting 2.
+    type Board = (u8, Option<u8>, bool, &'static [u16], &'static [u16]);
+
+    #[test]
+    fn test_register_lines() {
+        #[rustfmt::skip]
+        let boards: &[Board] = &[
+            (21, Some(1), false, &[A1], &[A2]),
+            (21, Some(2), false, &[A6], &[A7]),
+            (21, None, false, &[A1, A6], &[A2, A7]),
+            (22, None, true, &[A1], &[A0]),
+            (23, Some(1), false, &[A0], &[A1]),
+            (23, Some(2), false, &[A2], &[A3]),
+            (23, Some(3), true, &[A0], &[A1]),
+            (23, None, false, &[A0, A2], &[A1, A3]),
+            (25, Some(1), false, &[A1], &[A0]),
+            (25, Some(2), false, &[A3], &[A2]),
+            (25, Some(3), true, &[A1], &[A0]),
+            (25, None, false, &[A1, A3], &[A0, A2]),
+        ];
+
+        for &(mapper_no, submapper, is_vrc2, low, high) in boards {
+            let rom = TestRom::new(mapper_no, 8, 16);
+            let rom = match submapper {
+                Some(submapper) => rom.submapper(submapper),
+                None => rom,
+            };
+            let cart = VRC4::new(rom.ines());
+            let board = (mapper_no, submapper);
+            assert_eq!(cart.is_vrc2, is_vrc2, "{:?}", board);
+            assert_eq!(cart.register(0x9000), 0x9000, "{:?}", board);
+            for (&low, &high) in low.iter().zip(high) {
+                assert_eq!(cart.register(0x9000 | low), 0x9001, "{:?}", board);
+                assert_eq!(cart.register(0x9000 | high), 0x9002, "{:?}", board);
+                assert_eq!(cart.register(0x9000 | low | high), 0x9003, "{:?}", board);
+            }
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use alloc::vec::Vec;
+
+use crate::{
+    apu::VRC6Audio,
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, load_battery_ram, vrc_irq::VrcIrq};
+
+/// Konami's VRC6, with its expansion audio. $B003 picks one of the four CHR banking modes and
+/// the CIRAM mirroring; its nametables-from-CHR-ROM bit isn't emulated, as no game sets it.
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC6 {
+    ines: INES,
+    nametables: Nametables,
+    swap_address_lines: bool,
+    ram: [u8; 1024 * 8], // 8KB
+    prg_bank_16: u8,
+    prg_bank_8: u8,
+    chr_banks: [u8; 8],
+    banking_control: u8,
+    irq: VrcIrq,
+    audio: VRC6Audio,
+}
+
+impl VRC6 {
+    /// Mapper 26 (VRC6b) has the A0 and A1 register lines swapped compared to mapper 24 (VRC6a).
+    pub fn new(ines: INES) -> Self {
+        let swap_address_lines = ines.mapper_no == 26;
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            swap_address_lines,
+            ram: [0; 1024 * 8],
+            prg_bank_16: 0,
+            prg_bank_8: 0,
+            chr_banks: [0; 8],
+            banking_control: 0,
+            irq: VrcIrq::new(),
+            audio: VRC6Audio::new(),
+        }
+    }
+
+    /// The 1KB CHR bank at a pattern table address. Mode 0 has eight 1KB banks, mode 1 four
+    /// 2KB banks, and modes 2 and 3 four 1KB banks then two 2KB banks. $B003 bit 5 makes the
+    /// 2KB banks use PPU A10 in place of the register's low bit, which otherwise repeats the
+    /// same 1KB twice.
+    fn chr_bank(&self, address: u16) -> u8 {
+        let slot = (address >> 10) as usize & 7;
+        let register = match self.banking_control & 0b11 {
+            0 => return self.chr_banks[slot],
+            1 => slot / 2,
+            _ if slot < 4 => return self.chr_banks[slot],
+            _ => 2 + slot / 2,
+        };
+
+        let bank = self.chr_banks[register];
+        if (self.banking_control & 0x20) != 0 {
+            (bank & 0xFE) | (slot & 1) as u8
+        } else {
+            bank
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+        let bank = self.chr_bank(address) as usize % num_1k_chunks;
+        bank * 1024 + (address & 0x3FF) as usize
+    }
+
+    fn ram_enabled(&self) -> bool {
+        (self.banking_control & 0x80) != 0
+    }
+}
+
+impl Cartridge for VRC6 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if (0x6000..=0x7FFF).contains(&address) {
+            if self.ram_enabled() {
+                self.ram[address.lower_8k() as usize] = value;
+            }
+            return;
+        }
+
+        let address = if self.swap_address_lines {
+            (address & 0xFFFC) | ((address & 1) << 1) | ((address >> 1) & 1)
+        } else {
+            address
+        };
+
+        match address & 0xF003 {
+            0x8000..=0x8003 => self.prg_bank_16 = value & 0x0F,
+            0x9000..=0x9003 | 0xA000..=0xA002 | 0xB000..=0xB002 => {
+                self.audio.write_reg(address, value)
+            }
+            0xB003 => self.banking_control = value,
+            0xC000..=0xC003 => self.prg_bank_8 = value & 0x1F,
+            0xD000..=0xD003 => self.chr_banks[(address & 3) as usize] = value,
+            0xE000..=0xE003 => self.chr_banks[4 + (address & 3) as usize] = value,
+            0xF000 => self.irq.write_latch(value),
+            0xF001 => self.irq.write_control(value),
+            0xF002 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match (self.banking_control >> 2) & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        self.audio.tick();
+        self.irq.tick()
+    }
+
+    fn audio_output(&self) -> i16 {
+        self.audio.output()
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        self.ram_enabled().then_some(&self.ram[..])
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+        let addr = match address {
+            0x6000..=0x7FFF if self.ram_enabled() => {
+                let addr = address.lower_8k() as usize;
+                return Some(MemoryLocation::new(CartMemory::PrgRam, addr));
+            }
+            0x8000..=0xBFFF => {
+                let bank = self.prg_bank_16 as usize % self.ines.prg_rom_size_16k_chunks as usize;
+                bank * 0x4000 + address.lower_16k() as usize
+            }
+            0xC000..=0xDFFF => {
+                let bank = self.prg_bank_8 as usize % num_8k_chunks;
+                bank * 0x2000 + address.lower_8k() as usize
+            }
+            0xE000..=0xFFFF => (num_8k_chunks - 1) * 0x2000 + address.lower_8k() as usize,
+            _ => return None,
+        };
+        Some(MemoryLocation::new(CartMemory::PrgRom, addr))
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+}
+
+impl CartridgeSaveLoad for VRC6 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_u8(self.prg_bank_16)?;
+        writer.write_u8(self.prg_bank_8)?;
+        writer.write_all(&self.chr_banks)?;
+        writer.write_u8(self.banking_control)?;
+        self.irq.save(writer)?;
+        self.audio.save(writer)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        self.prg_bank_16 = reader.read_u8()?;
+        self.prg_bank_8 = reader.read_u8()?;
+        reader.read_exact(&mut self.chr_banks)?;
+        self.banking_control = reader.read_u8()?;
+        self.irq.load(reader)?;
+        self.audio.load(reader)?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::VRC6;
+    use crate::{
+        cartridge::{Cartridge, MemoryLocation},
+        ines::test_helpers::TestRom,
+        mirroring::Mirroring,
+    };
+
+    /// 128KB of PRG-ROM, each 8KB filled with its number, and 128KB of CHR-ROM.
+    fn vrc6(mapper_no: u8) -> VRC6 {
+        VRC6::new(
+            TestRom::new(mapper_no, 8, 16)
+                .prg_with(|offset| (offset / 0x2000) as u8)
+                .ines(),
+        )
+    }
+
+    fn chr_bank(cart: &VRC6, address: u16) -> usize {
+        let MemoryLocation { offset, .. } = cart.ppu_mapping(address).unwrap();
+        offset / 1024
+    }
+
+    #[test]
+    fn test_prg_banking() {
+        let mut cart = vrc6(24);
+        cart.cpu_write(0x8000, 2);
+        cart.cpu_write(0xC000, 7);
+        let banks: Vec<u8> = [0x8000, 0xA000, 0xC000, 0xE000]
+            .iter()
+            .map(|&address| cart.cpu_read(address))
+            .collect();
+        assert_eq!(banks, [4, 5, 7, 15]);
+    }
+
+    #[test]
+    fn test_chr_modes() {
+        let mut cart = vrc6(24);
+        for (i, address) in [
+            0xD000, 0xD001, 0xD002, 0xD003, 0xE000, 0xE001, 0xE002, 0xE003,
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            cart.cpu_write(address, 10 + i as u8);
+        }
+        let banks = |cart: &VRC6| -> Vec<usize> {
+            (0..8).map(|slot| chr_bank(cart, slot * 0x400)).collect()
+        };
+        assert_eq!(banks(&cart), [10, 11, 12, 13, 14, 15, 16, 17]);
+
+        // 2KB banks repeat the register's 1KB, unless bit 5 brings in PPU A10
+        cart.cpu_write(0xB003, 0x01);
+        assert_eq!(banks(&cart), [10, 10, 11, 11, 12, 12, 13, 13]);
+        cart.cpu_write(0xB003, 0x21);
+        assert_eq!(banks(&cart), [10, 11, 10, 11, 12, 13, 12, 13]);
+
+        cart.cpu_write(0xB003, 0x22);
+        assert_eq!(banks(&cart), [10, 11, 12, 13, 14, 15, 14, 15]);
+        cart.cpu_write(0xB003, 0x03);
+        assert_eq!(banks(&cart), [10, 11, 12, 13, 14, 14, 15, 15]);
+    }
+
+    #[test]
+    fn test_mirroring_and_ram() {
+        let mut cart = vrc6(24);
+        assert_eq!(cart.mirroring(), Mirroring::Vertical);
+        cart.cpu_write(0x6000, 0x12);
+        assert_eq!(cart.cpu_read(0x6000), 0);
+
+        cart.cpu_write(0xB003, 0x84);
+        assert_eq!(cart.mirroring(), Mirroring::Horizontal);
+        cart.cpu_write(0x6000, 0x12);
+        assert_eq!(cart.cpu_read(0x6000), 0x12);
+        cart.cpu_write(0xB003, 0x8C);
+        assert_eq!(cart.mirroring(), Mirroring::SingleScreenB);
+    }
+
+    #[test]
+    fn test_vrc6b_swaps_address_lines() {
+        let mut cart = vrc6(26);
+        cart.cpu_write(0xD001, 3);
+        cart.cpu_write(0xD002, 5);
+        assert_eq!(chr_bank(&cart, 0x0800), 3);
+        assert_eq!(chr_bank(&cart, 0x0400), 5);
+        // $B003 has both lines set either way
+        cart.cpu_write(0xB003, 0x04);
+        assert_eq!(cart.mirroring(), Mirroring::Horizontal);
+    }
+
+    #[test]
+    fn test_irq() {
+        let mut cart = vrc6(24);
+        // Cycle mode, counting up from $F0 and firing when it passes $FF
+        cart.cpu_write(0xF000, 0xF0);
+        cart.cpu_write(0xF001, 0x06);
+        assert!((0..15).all(|_| !cart.cpu_tick()));
+        assert!(cart.cpu_tick());
+        assert!(cart.cpu_tick());
+        cart.cpu_write(0xF002, 0);
+        assert!(!cart.cpu_tick());
+
+        // Scanline mode clocks every 113 2/3 cycles
+        cart.cpu_write(0xF000, 0xFF);
+        cart.cpu_write(0xF001, 0x02);
+        assert!((0..113).all(|_| !cart.cpu_tick()));
+        assert!(cart.cpu_tick());
+    }
+}
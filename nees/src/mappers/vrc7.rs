@@ -0,0 +1,247 @@
+use alloc::vec::Vec;
+
+use crate::{
+    apu::OPLL,
+    bit_helpers::{SubType, BIT_13},
+    cartridge::{CartMemory, Cartridge, CartridgeSaveLoad, MemoryLocation, PPUFetch},
+    ines::INES,
+    mirroring::{Mirroring, Nametables},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+use super::{battery_ram, load_battery_ram, vrc_irq::VrcIrq};
+
+/// The OPLL produces one sample every 36 CPU cycles (49.7kHz).
+const OPLL_CLOCK_DIVIDER: u8 = 36;
+
+#[allow(clippy::upper_case_acronyms)]
+pub struct VRC7 {
+    ines: INES,
+    nametables: Nametables,
+    ram: [u8; 1024 * 8], // 8KB
+    prg_banks: [u8; 3],
+    chr_banks: [u8; 8],
+    control: u8,
+    irq: VrcIrq,
+    opll: OPLL,
+    opll_divider: u8,
+}
+
+impl VRC7 {
+    pub fn new(ines: INES) -> Self {
+        Self {
+            nametables: Nametables::default(),
+            ines,
+            ram: [0; 1024 * 8],
+            prg_banks: [0; 3],
+            chr_banks: [0; 8],
+            control: 0,
+            irq: VrcIrq::new(),
+            opll: OPLL::new(),
+            opll_divider: 0,
+        }
+    }
+
+    fn chr_addr(&self, address: u16) -> usize {
+        let num_1k_chunks = self.ines.chr_rom.len() / 1024;
+        let bank = self.chr_banks[(address >> 10) as usize & 7] as usize % num_1k_chunks;
+        bank * 1024 + (address & 0x3FF) as usize
+    }
+
+    fn ram_enabled(&self) -> bool {
+        (self.control & 0x80) != 0
+    }
+
+    fn audio_silenced(&self) -> bool {
+        (self.control & 0x40) != 0
+    }
+}
+
+impl Cartridge for VRC7 {
+    fn ppu_read(&mut self, address: u16, ciram: &[u8], _fetch: PPUFetch) -> u8 {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables.read(self.mirroring(), address, ciram)
+        } else {
+            self.ines.chr_rom[self.chr_addr(address)]
+        }
+    }
+
+    fn ppu_write(&mut self, address: u16, value: u8, ciram: &mut [u8]) {
+        if (address & BIT_13) == BIT_13 {
+            self.nametables
+                .write(self.mirroring(), address, value, ciram);
+        } else if self.ines.is_chr_ram {
+            let addr = self.chr_addr(address);
+            self.ines.chr_rom[addr] = value;
+        }
+    }
+
+    fn cpu_read(&mut self, address: u16) -> u8 {
+        self.cpu_peek(address)
+    }
+
+    fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        let num_8k_chunks = (self.ines.prg_rom_size_16k_chunks as usize) * 2;
+        let bank = match address {
+            0x6000..=0x7FFF if self.ram_enabled() => {
+                let addr = address.lower_8k() as usize;
+                return Some(MemoryLocation::new(CartMemory::PrgRam, addr));
+            }
+            0x8000..=0x9FFF => self.prg_banks[0] as usize,
+            0xA000..=0xBFFF => self.prg_banks[1] as usize,
+            0xC000..=0xDFFF => self.prg_banks[2] as usize,
+            0xE000..=0xFFFF => num_8k_chunks - 1,
+            _ => return None,
+        } % num_8k_chunks;
+
+        let addr = bank * 0x2000 + address.lower_8k() as usize;
+        Some(MemoryLocation::new(CartMemory::PrgRom, addr))
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if (0x6000..=0x7FFF).contains(&address) {
+            if self.ram_enabled() {
+                self.ram[address.lower_8k() as usize] = value;
+            }
+            return;
+        } else if address < 0x8000 {
+            return;
+        }
+
+        if (address & 0xF010) == 0x9010 {
+            // Audio registers live at $9010 (address) and $9030 (data)
+            if (address & 0x20) != 0 {
+                self.opll.write_data(value);
+            } else {
+                self.opll.write_address(value);
+            }
+            return;
+        }
+
+        // VRC7a decodes the second register of each pair on A4, VRC7b on A3
+        let second = (address & 0x18) != 0;
+        let reg = ((address >> 12) - 8) * 2 + second as u16;
+        match reg {
+            0..=2 => self.prg_banks[reg as usize] = value & 0x3F,
+            4..=11 => self.chr_banks[(reg - 4) as usize] = value,
+            12 => {
+                if (value & 0x40) != 0 {
+                    self.opll.reset();
+                }
+                self.control = value;
+            }
+            13 => self.irq.write_latch(value),
+            14 => self.irq.write_control(value),
+            15 => self.irq.acknowledge(),
+            _ => {}
+        }
+    }
+
+    fn scanline(&mut self) -> bool {
+        false
+    }
+
+    fn mirroring(&self) -> Mirroring {
+        match self.control & 0b11 {
+            0 => Mirroring::Vertical,
+            1 => Mirroring::Horizontal,
+            2 => Mirroring::SingleScreenA,
+            _ => Mirroring::SingleScreenB,
+        }
+    }
+
+    fn cpu_tick(&mut self) -> bool {
+        if !self.audio_silenced() {
+            self.opll_divider += 1;
+            if self.opll_divider == OPLL_CLOCK_DIVIDER {
+                self.opll_divider = 0;
+                self.opll.clock();
+            }
+        }
+
+        self.irq.tick()
+    }
+
+    fn audio_output(&self) -> i16 {
+        if self.audio_silenced() {
+            0
+        } else {
+            self.opll.output()
+        }
+    }
+
+    fn work_ram(&self) -> Option<&[u8]> {
+        self.ram_enabled().then_some(&self.ram[..])
+    }
+
+    fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        if (address & BIT_13) == BIT_13 {
+            self.mirroring().nametable_location(address)
+        } else {
+            Some(MemoryLocation::new(
+                CartMemory::ChrRom,
+                self.chr_addr(address),
+            ))
+        }
+    }
+
+    fn memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::PrgRom => &self.ines.prg_rom,
+            CartMemory::PrgRam => &self.ram,
+            CartMemory::ChrRom => &self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram(),
+            _ => &[],
+        }
+    }
+
+    fn memory_mut(&mut self, memory: CartMemory) -> &mut [u8] {
+        match memory {
+            CartMemory::PrgRom => &mut self.ines.prg_rom,
+            CartMemory::PrgRam => &mut self.ram,
+            CartMemory::ChrRom => &mut self.ines.chr_rom,
+            CartMemory::Vram => self.nametables.vram_mut(),
+            _ => &mut [],
+        }
+    }
+
+    fn battery_data(&self) -> Option<Vec<u8>> {
+        battery_ram(&self.ines, &self.ram)
+    }
+
+    fn load_battery_data(&mut self, data: &[u8]) {
+        load_battery_ram(&mut self.ram, data);
+    }
+}
+
+impl CartridgeSaveLoad for VRC7 {
+    fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.ram)?;
+        writer.write_all(&self.prg_banks)?;
+        writer.write_all(&self.chr_banks)?;
+        writer.write_u8(self.control)?;
+        self.irq.save(writer)?;
+        self.opll.save(writer)?;
+        writer.write_u8(self.opll_divider)?;
+        if self.ines.is_chr_ram {
+            writer.write_all(&self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+
+    fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.ram)?;
+        reader.read_exact(&mut self.prg_banks)?;
+        reader.read_exact(&mut self.chr_banks)?;
+        self.control = reader.read_u8()?;
+        self.irq.load(reader)?;
+        self.opll.load(reader)?;
+        self.opll_divider = reader.read_u8()?;
+        if self.ines.is_chr_ram {
+            reader.read_exact(&mut self.ines.chr_rom)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use crate::reader_writer::{EasyReader, EasyWriter};
+
+use super::cycle_irq::CpuCycleIrq;
+
+/// The IRQ counter shared by the Konami VRC boards. In scanline mode a prescaler
+/// divides CPU cycles by 113.667 to approximate one clock per scanline.
+///
+/// The hardware counts up from the latch and fires when passing $FF, which is modelled as a
+/// down counter reloaded with `$FF - latch`.
+pub struct VrcIrq {
+    counter: CpuCycleIrq,
+    prescaler: i16,
+    enable_after_ack: bool,
+    cycle_mode: bool,
+}
+
+impl VrcIrq {
+    pub fn new() -> Self {
+        Self {
+            counter: CpuCycleIrq::new(0xFF),
+            prescaler: 341,
+            enable_after_ack: false,
+            cycle_mode: false,
+        }
+    }
+
+    pub fn write_latch(&mut self, value: u8) {
+        self.counter.reload = 0xFF - value as u16;
+    }
+
+    /// VRC4 writes the latch one nibble at a time.
+    pub fn write_latch_nibble(&mut self, high: bool, value: u8) {
+        let latch = (0xFF - self.counter.reload) as u8;
+        let latch = if high {
+            (latch & 0x0F) | (value << 4)
+        } else {
+            (latch & 0xF0) | (value & 0x0F)
+        };
+        self.write_latch(latch);
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        self.enable_after_ack = (value & 1) != 0;
+        self.set_enabled((value & 2) != 0);
+        self.cycle_mode = (value & 4) != 0;
+        self.counter.acknowledge();
+
+        if self.counter.counting {
+            self.counter.counter = self.counter.reload;
+            self.prescaler = 341;
+        }
+    }
+
+    pub fn acknowledge(&mut self) {
+        self.counter.acknowledge();
+        self.set_enabled(self.enable_after_ack);
+    }
+
+    fn set_enabled(&mut self, enabled: bool) {
+        self.counter.counting = enabled;
+        self.counter.irq_enabled = enabled;
+    }
+
+    /// Advances the counter by one CPU cycle and returns the IRQ line state.
+    pub fn tick(&mut self) -> bool {
+        if self.counter.counting {
+            if self.cycle_mode {
+                self.counter.clock();
+            } else {
+                self.prescaler -= 3;
+                if self.prescaler <= 0 {
+                    self.prescaler += 341;
+                    self.counter.clock();
+                }
+            }
+        }
+
+        self.counter.pending()
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        self.counter.save(writer)?;
+        writer.write_i16(self.prescaler)?;
+        writer.write_bool(self.enable_after_ack)?;
+        writer.write_bool(self.cycle_mode)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        self.counter.load(reader)?;
+        self.prescaler = reader.read_i16()?;
+        self.enable_after_ack = reader.read_bool()?;
+        self.cycle_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,129 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    cartridge::{CartMemory, MemoryLocation},
+    reader_writer::{EasyReader, EasyWriter},
+};
+
+/// How the four nametables at $2000-$2FFF map onto the nametable RAM.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mirroring {
+    /// $2000 and $2400 share a page, as do $2800 and $2C00. For vertical scrolling.
+    Horizontal,
+    /// $2000 and $2800 share a page, as do $2400 and $2C00. For horizontal scrolling.
+    Vertical,
+    /// All four nametables show the first page of CIRAM.
+    SingleScreenA,
+    /// All four nametables show the second page of CIRAM.
+    SingleScreenB,
+    /// Four separate nametables, the last two in 2KB of VRAM on the cartridge.
+    FourScreen,
+    /// The board maps each nametable itself, to CIRAM or its own memory.
+    MapperControlled,
+}
+
+impl Mirroring {
+    /// Where a nametable address lands in 4KB of nametable memory: CIRAM, then the cartridge's
+    /// VRAM for four-screen boards. None when the board does its own mapping.
+    pub fn nametable_offset(self, address: u16) -> Option<usize> {
+        let offset = (address & 0x3FF) as usize;
+        let page = match self {
+            Mirroring::Horizontal => (address >> 11) & 1,
+            Mirroring::Vertical => (address >> 10) & 1,
+            Mirroring::SingleScreenA => 0,
+            Mirroring::SingleScreenB => 1,
+            Mirroring::FourScreen => (address >> 10) & 3,
+            Mirroring::MapperControlled => return None,
+        };
+        Some(page as usize * 0x400 + offset)
+    }
+
+    /// Where a nametable address lands, for boards using `Nametables`.
+    pub fn nametable_location(self, address: u16) -> Option<MemoryLocation> {
+        let offset = self.nametable_offset(address)?;
+        Some(if offset < 0x800 {
+            MemoryLocation::new(CartMemory::Ciram, offset)
+        } else {
+            MemoryLocation::new(CartMemory::Vram, offset - 0x800)
+        })
+    }
+}
+
+/// The nametable memory of boards using the standard mirrorings: the console's 2KB of CIRAM,
+/// plus the cartridge's own 2KB of VRAM on four-screen boards.
+#[derive(Default)]
+pub struct Nametables {
+    vram: Vec<u8>,
+}
+
+impl Nametables {
+    pub fn new(mirroring: Mirroring) -> Self {
+        Self {
+            vram: if mirroring == Mirroring::FourScreen {
+                vec![0; 0x800]
+            } else {
+                Vec::new()
+            },
+        }
+    }
+
+    pub fn read(&self, mirroring: Mirroring, address: u16, ciram: &[u8]) -> u8 {
+        match mirroring.nametable_offset(address) {
+            Some(offset) if offset < 0x800 => ciram[offset],
+            Some(offset) => self.vram[offset - 0x800],
+            None => 0,
+        }
+    }
+
+    pub fn write(&mut self, mirroring: Mirroring, address: u16, value: u8, ciram: &mut [u8]) {
+        match mirroring.nametable_offset(address) {
+            Some(offset) if offset < 0x800 => ciram[offset] = value,
+            Some(offset) => self.vram[offset - 0x800] = value,
+            None => {}
+        }
+    }
+
+    /// The board's own nametable RAM, for four-screen boards.
+    pub fn vram(&self) -> &[u8] {
+        &self.vram
+    }
+
+    pub fn vram_mut(&mut self) -> &mut [u8] {
+        &mut self.vram
+    }
+
+    pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
+        writer.write_all(&self.vram)?;
+
+        Ok(())
+    }
+
+    pub fn load(&mut self, reader: &mut dyn EasyReader) -> anyhow::Result<()> {
+        reader.read_exact(&mut self.vram)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Mirroring, Nametables};
+
+    #[test]
+    fn test_four_screen_uses_cartridge_vram() {
+        let mut ciram = [0_u8; 0x800];
+        let mut nametables = Nametables::new(Mirroring::FourScreen);
+        for (i, address) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+            nametables.write(Mirroring::FourScreen, address, i as u8 + 1, &mut ciram);
+        }
+
+        assert_eq!((ciram[0], ciram[0x400]), (1, 2));
+        for (i, address) in [0x2000, 0x2400, 0x2800, 0x2C00].into_iter().enumerate() {
+            assert_eq!(
+                nametables.read(Mirroring::FourScreen, address, &ciram),
+                i as u8 + 1
+            );
+        }
+        assert_eq!(nametables.read(Mirroring::Horizontal, 0x2400, &ciram), 1);
+    }
+}
@@ -1,13 +1,18 @@
-use alloc::boxed::Box;
+use alloc::{boxed::Box, vec::Vec};
 use bitfield_struct::bitfield;
 
 use crate::{
     apu::APU,
     bus::Bus,
-    cartridge::CartridgeWithSaveLoad,
+    cartridge::{CartMemory, CartridgeWithSaveLoad, MemoryLocation},
+    cheats::Cheats,
     cpu,
+    disassembler::{self, Instruction},
+    fds_image::FDSImage,
+    game_db::{GameDatabase, GameInfo},
     ines::INES,
-    mappers,
+    mappers::{self, MapperRegistry},
+    nsf::NSF,
     ppu::PPU,
     reader_writer::{EasyReader, EasyWriter},
 };
@@ -21,6 +26,9 @@ pub struct NesBus {
     pub buttons_down: [u8; 2],
     cpu_timer: u32,
     apu_timer: u32,
+    cheats: Cheats,
+    /// Accesses seen while a debugger is running the console
+    access_log: Option<Vec<Access>>,
 }
 impl NesBus {
     pub fn new(cart: Box<dyn CartridgeWithSaveLoad>) -> Self {
@@ -33,6 +41,61 @@ impl NesBus {
             buttons_down: [0, 0],
             cpu_timer: 0,
             apu_timer: 0,
+            cheats: Cheats::new(),
+            access_log: None,
+        }
+    }
+
+    /// Writes the values of RAM freeze cheats, like a Pro Action Replay does each frame.
+    fn apply_freezes(&mut self) {
+        for cheat in self.cheats.freezes() {
+            let address = cheat.address;
+            let current = match address {
+                0..=0x1FFF => self.cpu_ram[(address & 0x7ff) as usize],
+                // Work RAM on the cartridge, where some boards have registers instead
+                0x6000..=0x7FFF
+                    if self
+                        .cart
+                        .cpu_mapping(address)
+                        .is_some_and(|location| location.memory == CartMemory::PrgRam) =>
+                {
+                    self.cart.cpu_peek(address)
+                }
+                _ => continue,
+            };
+            if cheat.compare.is_some_and(|compare| compare != current) {
+                continue;
+            }
+            if address < 0x2000 {
+                self.cpu_ram[(address & 0x7ff) as usize] = cheat.value;
+            } else {
+                self.cart.cpu_poke(address, cheat.value);
+            }
+        }
+    }
+
+    /// Reads like `cpu_read`, but without side effects like shifting the controllers or
+    /// clearing the vblank flag.
+    fn peek(&self, address: u16) -> u8 {
+        if address == 0x4016 || address == 0x4017 {
+            self.controller_status[(address & 1) as usize] & 1
+        } else if (0x4000..=0x4013).contains(&address) || address == 0x4015 {
+            self.apu.peek_reg(address)
+        } else if address >= 0x4000 {
+            self.cheats.substitute(address, self.cart.cpu_peek(address))
+        } else if address >= 0x2000 {
+            self.ppu.peek_register((address & 7) as u8)
+        } else {
+            self.cpu_ram[(address & 0x7ff) as usize]
+        }
+    }
+
+    /// Writes to the memory behind an address, leaving registers alone.
+    fn poke(&mut self, address: u16, value: u8) {
+        match address {
+            0..=0x1FFF => self.cpu_ram[(address & 0x7ff) as usize] = value,
+            0x4020.. => self.cart.cpu_poke(address, value),
+            _ => {}
         }
     }
 
@@ -61,6 +124,42 @@ impl NesBus {
 
 impl Bus for NesBus {
     fn cpu_read(&mut self, address: u16) -> u8 {
+        if self.access_log.is_none() {
+            return self.read(address);
+        }
+        if is_ppudata(address) {
+            let ppu_address = self.ppu.vram_address();
+            let value = self.ppu.peek(ppu_address, &*self.cart);
+            self.log(Access::PpuRead(ppu_address, value));
+        }
+        let value = self.read(address);
+        self.log(Access::Read(address, value));
+        value
+    }
+
+    fn cpu_write(&mut self, address: u16, value: u8) {
+        if self.access_log.is_some() {
+            self.log(Access::Write(address, value));
+            if is_ppudata(address) {
+                self.log(Access::PpuWrite(self.ppu.vram_address(), value));
+            }
+        }
+        self.write(address, value);
+    }
+}
+
+fn is_ppudata(address: u16) -> bool {
+    (0x2000..0x4000).contains(&address) && address & 7 == 7
+}
+
+impl NesBus {
+    fn log(&mut self, access: Access) {
+        if let Some(log) = &mut self.access_log {
+            log.push(access);
+        }
+    }
+
+    fn read(&mut self, address: u16) -> u8 {
         if address == 0x4016 || address == 0x4017 {
             let controller_id = address & 1;
             let value = self.controller_status[controller_id as usize] & 1;
@@ -71,8 +170,9 @@ impl Bus for NesBus {
             // APU
             self.apu.read_reg(address)
         } else if address >= 0x4000 {
-            // Cart
-            self.cart.cpu_read(address)
+            // Cart, with Game Genie codes patching what's read from ROM
+            let value = self.cart.cpu_read(address);
+            self.cheats.substitute(address, value)
         } else if address >= 0x2000 {
             // PPU
             self.ppu
@@ -83,7 +183,7 @@ impl Bus for NesBus {
         }
     }
 
-    fn cpu_write(&mut self, address: u16, value: u8) {
+    fn write(&mut self, address: u16, value: u8) {
         if address == 0x4014 {
             // DMA
             let page = (value as u16) << 8;
@@ -103,6 +203,7 @@ impl Bus for NesBus {
             self.cart.cpu_write(address, value);
         } else if address >= 0x2000 {
             // PPU
+            self.cart.ppu_register_write((address & 7) as u8, value);
             self.ppu
                 .cpu_ppu_bus_write((address & 7) as u8, value, &mut *self.cart);
         } else {
@@ -124,77 +225,392 @@ pub struct ControllerState {
     pub right: bool,
 }
 
+/// A memory access seen by `NES001::tick_instruction`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Access {
+    Read(u16, u8),
+    Write(u16, u8),
+    /// A read of PPUDATA, at the PPU address it read from.
+    PpuRead(u16, u8),
+    /// A write to PPUDATA, at the PPU address it wrote to.
+    PpuWrite(u16, u8),
+}
+
+/// What happened during `NES001::tick_instruction`.
+#[derive(Clone, Default, Debug)]
+pub struct Trace {
+    /// CPU accesses other than instruction fetches, including interrupt pushes and OAM DMA.
+    pub accesses: Vec<Access>,
+    pub nmi: bool,
+    pub irq: bool,
+    /// The frame finished before the CPU got to another instruction, so it stopped there instead.
+    pub frame_end: bool,
+}
+
+/// The CPU's registers.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Registers {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub sp: u8,
+    pub status: u8,
+}
+
 pub struct NES001 {
     cpu: cpu::MOS6502<NesBus>,
     bus: NesBus,
+    game: Option<GameInfo>,
+    scanline: i16,
+    dot: u16,
+    /// Stopped partway through a dot, just before the CPU starts an instruction
+    at_instruction: bool,
+    trace: Trace,
 }
 
 impl NES001 {
-    pub fn from_rom(rom: &[u8]) -> Self {
-        let cart = mappers::load_cart(INES::new(rom));
-        Self::new(cart)
+    /// Loads an iNES or UNIF ROM on one of the built-in boards.
+    pub fn from_rom(rom: &[u8]) -> anyhow::Result<Self> {
+        Self::from_rom_with_registry(rom, &MapperRegistry::new())
+    }
+
+    /// Loads an iNES or UNIF ROM, looking for its board among the registry's before the built-in
+    /// ones.
+    pub fn from_rom_with_registry(rom: &[u8], registry: &MapperRegistry) -> anyhow::Result<Self> {
+        Self::from_rom_with_database(rom, registry, &GameDatabase::builtin())
     }
 
-    fn new(cart: Box<dyn CartridgeWithSaveLoad>) -> Self {
+    /// Loads a ROM like `from_rom_with_registry`, fixing its header from the database entry for
+    /// the dump if there is one.
+    pub fn from_rom_with_database(
+        rom: &[u8],
+        registry: &MapperRegistry,
+        database: &GameDatabase,
+    ) -> anyhow::Result<Self> {
+        let mut ines = INES::from_file(rom)?;
+        let game = database.find(&ines).cloned();
+        if let Some(game) = &game {
+            game.apply(&mut ines);
+        }
+
+        let mut nes = Self::from_cartridge(registry.load(ines)?);
+        nes.game = game;
+        Ok(nes)
+    }
+
+    /// Boots a Famicom Disk System image with the given BIOS (disksys.rom), with side A of the
+    /// first disk inserted.
+    pub fn from_fds(image: &[u8], bios: &[u8]) -> anyhow::Result<Self> {
+        if bios.len() != 0x2000 {
+            anyhow::bail!("The FDS BIOS should be 8KB");
+        }
+        let cart = mappers::load_fds(FDSImage::new(image)?, bios.to_vec());
+        Ok(Self::from_cartridge(cart))
+    }
+
+    /// Sets up the console to play one track (zero-based) of an NSF rip. See `NSFPlayer` for
+    /// the host side.
+    pub fn from_nsf(nsf: &NSF, track: u8) -> Self {
+        let cart = mappers::load_nsf(nsf, track);
+        Self::from_cartridge(cart)
+    }
+
+    /// Powers on the console with a ready-made cartridge.
+    pub fn from_cartridge(cart: Box<dyn CartridgeWithSaveLoad>) -> Self {
         let mut bus = NesBus::new(cart);
         let mut cpu = cpu::MOS6502::new();
         cpu.reset(&mut bus);
 
-        Self { bus, cpu }
+        Self {
+            bus,
+            cpu,
+            game: None,
+            scanline: -1,
+            dot: 0,
+            at_instruction: false,
+            trace: Trace::default(),
+        }
     }
 
+    /// Runs until the end of the frame. After a debugger stops partway through one, this finishes
+    /// it.
     pub fn tick_frame<T: FnMut(i16)>(&mut self, waveout_callback: &mut T, framebuffer: &mut [u32]) {
-        for scanline in -1..=260 {
-            for dot in 0..=340 {
-                if self
-                    .bus
-                    .ppu
-                    .tick(scanline, dot, framebuffer, &mut *self.bus.cart)
-                {
-                    self.cpu.nmi6502(&mut self.bus);
-                }
+        while !self.tick_dot(false, waveout_callback, framebuffer) {}
+    }
 
-                if self.bus.cpu_timer == 0 {
-                    self.cpu.step(&mut self.bus);
-                    self.bus.cpu_timer = self.cpu.clockticks * 3;
-                } else {
-                    self.bus.cpu_timer -= 1;
-                }
+    /// Runs until the CPU is about to start an instruction, or until the end of the frame if that
+    /// comes first. If it's already about to start one, that instruction runs first.
+    pub fn tick_instruction<T: FnMut(i16)>(
+        &mut self,
+        waveout_callback: &mut T,
+        framebuffer: &mut [u32],
+    ) -> Trace {
+        // Plain frames leave their interrupts behind
+        self.trace = Trace::default();
+        self.bus.access_log = Some(Vec::new());
+        loop {
+            let frame_end = self.tick_dot(true, waveout_callback, framebuffer);
+            if self.at_instruction || frame_end {
+                self.trace.frame_end = frame_end;
+                break;
+            }
+        }
+        self.trace.accesses = self.bus.access_log.take().unwrap_or_default();
+        core::mem::take(&mut self.trace)
+    }
 
-                if self.bus.apu_timer == 2 {
-                    self.bus.apu.tick_triangle(&mut *self.bus.cart);
-                }
+    /// Runs the rest of the current dot, returning whether it was the last of the frame. With
+    /// `hold`, stops before the CPU starts an instruction, and the next call carries on from
+    /// there.
+    fn tick_dot<T: FnMut(i16)>(
+        &mut self,
+        hold: bool,
+        waveout_callback: &mut T,
+        framebuffer: &mut [u32],
+    ) -> bool {
+        let (scanline, dot) = (self.scanline, self.dot);
+        if !self.at_instruction {
+            if scanline == -1 && dot == 0 {
+                self.bus.apply_freezes();
+            }
 
-                if self.bus.apu_timer == 5 {
-                    self.bus.apu.tick_triangle(&mut *self.bus.cart);
-                    self.bus.apu.tick(scanline as i16, waveout_callback);
-                    self.bus.apu_timer = 0;
-                } else {
-                    self.bus.apu_timer += 1;
-                }
+            if self
+                .bus
+                .ppu
+                .tick(scanline as i32, dot, framebuffer, &mut *self.bus.cart)
+            {
+                self.cpu.nmi6502(&mut self.bus);
+                self.trace.nmi = true;
+            }
 
-                if self.bus.apu.frame_interrupt_flag {
-                    self.cpu.irq6502(&mut self.bus);
+            if self.bus.cpu_timer == 0 && hold {
+                self.at_instruction = true;
+                return false;
+            }
+        }
+        self.at_instruction = false;
+
+        if self.bus.cpu_timer == 0 {
+            let fetch = self.bus.access_log.as_ref().map_or(0, Vec::len);
+            self.cpu.step(&mut self.bus);
+            self.bus.cpu_timer = self.cpu.clockticks * 3;
+            // Leave out reading the instruction itself
+            if let Some(log) = &mut self.bus.access_log {
+                if let Some(&Access::Read(_, opcode)) = log.get(fetch) {
+                    let size = 1 + cpu::ADDRESSING_MODES[opcode as usize].operand_len() as usize;
+                    log.drain(fetch..(fetch + size).min(log.len()));
                 }
             }
-            if scanline > -1
-                && scanline <= 239
-                && self.bus.ppu.is_rending_enabled()
-                && self.bus.cart.scanline()
-            {
-                self.cpu.irq6502(&mut self.bus);
+        } else {
+            self.bus.cpu_timer -= 1;
+        }
+
+        if self.bus.apu_timer == 2 || self.bus.apu_timer == 5 {
+            // One CPU cycle
+            self.bus.apu.tick_triangle(&mut *self.bus.cart);
+            if self.bus.cart.cpu_tick() {
+                self.trace.irq |= self.cpu.irq6502(&mut self.bus);
             }
         }
+
+        if self.bus.apu_timer == 5 {
+            self.bus
+                .apu
+                .tick(scanline, &*self.bus.cart, waveout_callback);
+            self.bus.apu_timer = 0;
+        } else {
+            self.bus.apu_timer += 1;
+        }
+
+        if self.bus.apu.frame_interrupt_flag {
+            self.trace.irq |= self.cpu.irq6502(&mut self.bus);
+        }
+
+        if dot < 340 {
+            self.dot += 1;
+            return false;
+        }
+
+        if scanline > -1
+            && scanline <= 239
+            && self.bus.ppu.is_rending_enabled()
+            && self.bus.cart.scanline()
+        {
+            self.trace.irq |= self.cpu.irq6502(&mut self.bus);
+        }
+        self.dot = 0;
+        self.scanline = if scanline == 260 { -1 } else { scanline + 1 };
+        self.scanline == -1
+    }
+
+    /// The scanline and dot the PPU is on, with -1 for the pre-render scanline.
+    pub fn position(&self) -> (i16, u16) {
+        (self.scanline, self.dot)
+    }
+
+    /// Whether the console stopped just before the CPU starts an instruction.
+    pub fn at_instruction(&self) -> bool {
+        self.at_instruction
+    }
+
+    pub fn registers(&self) -> Registers {
+        Registers {
+            pc: self.cpu.pc,
+            a: self.cpu.a,
+            x: self.cpu.x,
+            y: self.cpu.y,
+            sp: self.cpu.sp,
+            status: self.cpu.status.into_bits(),
+        }
     }
 
     pub fn set_buttons_down(&mut self, controller: u8, state: &ControllerState) {
         self.bus.buttons_down[controller as usize] = state.0;
     }
 
+    pub fn set_authentic_audio(&mut self, authentic: bool) {
+        self.bus.cart.set_authentic_audio(authentic);
+    }
+
+    pub fn set_bus_conflicts(&mut self, enabled: bool) {
+        self.bus.cart.set_bus_conflicts(enabled);
+    }
+
+    /// The game database's entry for the loaded ROM, with its title.
+    pub fn game(&self) -> Option<&GameInfo> {
+        self.game.as_ref()
+    }
+
+    /// The console's 2KB of RAM at $0000-$07FF.
+    pub fn ram(&self) -> &[u8] {
+        &self.bus.cpu_ram
+    }
+
+    /// The cartridge's work RAM at $6000-$7FFF, if it has some enabled.
+    pub fn work_ram(&self) -> Option<&[u8]> {
+        self.bus.cart.work_ram()
+    }
+
+    /// Where `work_ram` starts, which is $7000 for the MMC6's 1KB.
+    pub fn work_ram_address(&self) -> u16 {
+        self.bus.cart.work_ram_address()
+    }
+
+    /// Reads a CPU address without the side effects a read by the CPU can have, like
+    /// acknowledging IRQs, clearing the vblank flag or shifting the controllers. Registers that
+    /// can't be read back that way read as 0.
+    pub fn peek(&self, address: u16) -> u8 {
+        self.bus.peek(address)
+    }
+
+    /// Disassembles `count` instructions starting at `address`, as they're currently mapped.
+    pub fn disassemble(&self, address: u16, count: usize) -> Vec<Instruction> {
+        disassembler::disassemble_with(address, count, |address| self.peek(address))
+    }
+
+    /// Writes to the RAM or ROM at a CPU address without going through any registers, so
+    /// writes to ROM stick and writes to registers are dropped.
+    pub fn poke(&mut self, address: u16, value: u8) {
+        self.bus.poke(address, value);
+    }
+
+    /// Reads the PPU's address space: pattern tables at $0000-$1FFF, nametables at
+    /// $2000-$3EFF and the palette at $3F00-$3FFF.
+    pub fn peek_ppu(&self, address: u16) -> u8 {
+        self.bus.ppu.peek(address, &*self.bus.cart)
+    }
+
+    pub fn poke_ppu(&mut self, address: u16, value: u8) {
+        self.bus.ppu.poke(address, value, &mut *self.bus.cart);
+    }
+
+    /// A byte of sprite memory. Each sprite has four: Y, tile, attributes and X.
+    pub fn peek_oam(&self, index: u8) -> u8 {
+        self.bus.ppu.peek_oam(index)
+    }
+
+    pub fn poke_oam(&mut self, index: u8, value: u8) {
+        self.bus.ppu.poke_oam(index, value);
+    }
+
+    /// Where the cartridge maps a CPU address at $4020-$FFFF with its current banking, or None
+    /// for registers and open bus.
+    pub fn cpu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address {
+            0x4020.. => self.bus.cart.cpu_mapping(address),
+            _ => None,
+        }
+    }
+
+    /// Where the cartridge maps a PPU address at $0000-$3EFF with its current banking.
+    pub fn ppu_mapping(&self, address: u16) -> Option<MemoryLocation> {
+        match address & 0x3FFF {
+            address @ 0..=0x3EFF => self.bus.cart.ppu_mapping(address),
+            _ => None,
+        }
+    }
+
+    /// One of the cartridge's memories as a whole, like its PRG-ROM. `CartMemory::Ciram` gives
+    /// the console's nametable RAM.
+    pub fn cart_memory(&self, memory: CartMemory) -> &[u8] {
+        match memory {
+            CartMemory::Ciram => self.bus.ppu.ciram(),
+            _ => self.bus.cart.memory(memory),
+        }
+    }
+
+    pub fn cheats(&self) -> &Cheats {
+        &self.bus.cheats
+    }
+
+    pub fn cheats_mut(&mut self) -> &mut Cheats {
+        &mut self.bus.cheats
+    }
+
+    /// The cartridge's battery-backed save data, if it has any.
+    pub fn battery_data(&self) -> Option<Vec<u8>> {
+        self.bus.cart.battery_data()
+    }
+
+    pub fn load_battery_data(&mut self, data: &[u8]) {
+        self.bus.cart.load_battery_data(data);
+    }
+
+    /// The number of disk sides; zero for cartridges.
+    pub fn disk_sides(&self) -> usize {
+        self.bus.cart.disk_sides()
+    }
+
+    pub fn inserted_disk_side(&self) -> Option<usize> {
+        self.bus.cart.inserted_disk_side()
+    }
+
+    /// Swaps in another side. The drive reads as empty for a moment, so the BIOS notices.
+    pub fn insert_disk_side(&mut self, side: usize) {
+        self.bus.cart.insert_disk_side(Some(side));
+    }
+
+    pub fn eject_disk(&mut self) {
+        self.bus.cart.insert_disk_side(None);
+    }
+
+    /// Turns the inserted disk over, or inserts side A of the first disk if there is none.
+    pub fn flip_disk(&mut self) {
+        let side = self.inserted_disk_side().map_or(0, |side| side ^ 1);
+        if side < self.disk_sides() {
+            self.insert_disk_side(side);
+        }
+    }
+
     pub fn save(&self, writer: &mut dyn EasyWriter) -> anyhow::Result<()> {
         self.cpu.save(writer)?;
         self.bus.save(writer)?;
         self.bus.apu.save(writer)?;
+        // A debugger can stop partway through a frame
+        writer.write_i16(self.scanline)?;
+        writer.write_u16(self.dot)?;
+        writer.write_bool(self.at_instruction)?;
 
         Ok(())
     }
@@ -203,7 +619,121 @@ impl NES001 {
         self.cpu.load(reader)?;
         self.bus.load(reader)?;
         self.bus.apu.load(reader)?;
+        self.scanline = reader.read_i16()?;
+        self.dot = reader.read_u16()?;
+        self.at_instruction = reader.read_bool()?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::NES001;
+    use crate::bus::Bus;
+    use crate::cartridge::{CartMemory, MemoryLocation};
+    use crate::ines::test_helpers::TestRom;
+    use crate::reader_writer::test_helpers::{SliceReader, VecWriter};
+
+    /// UNROM with 4 banks, each filled with its number, that waits for vblank and loops.
+    fn unrom() -> NES001 {
+        #[rustfmt::skip]
+        let program = [
+            0x2C, 0x02, 0x20, // wait: BIT $2002
+            0x10, 0xFB,       // BPL wait
+            0x4C, 0x00, 0xC0, // JMP wait
+            0x40,             // nmi: RTI
+        ];
+        TestRom::new(2, 4, 0)
+            .prg_with(|offset| (offset / 0x4000) as u8)
+            .code(0xC000, &program)
+            .vectors(0xC008, 0xC000, 0)
+            .nes()
+    }
+
+    #[test]
+    fn test_peek_has_no_side_effects() {
+        let mut nes = unrom();
+        let mut framebuffer = vec![0; 256 * 240];
+        nes.tick_frame(&mut |_| {}, &mut framebuffer);
+        // Finish the frame's vblank wait, so the flag is left set by the next one
+        while nes.peek(0x2002) & 0x80 == 0 {
+            nes.bus
+                .ppu
+                .tick(241, 1, &mut framebuffer, &mut *nes.bus.cart);
+        }
+        assert_eq!(nes.peek(0x2002) & 0x80, 0x80);
+        assert_eq!(nes.peek(0x2002) & 0x80, 0x80);
+
+        nes.poke(0x0123, 0x45);
+        nes.poke(0x2000, 0x80);
+        assert_eq!((nes.peek(0x0923), nes.peek(0x2000)), (0x45, 0));
+    }
+
+    #[test]
+    fn test_mid_frame_state() {
+        let mut nes = unrom();
+        let mut framebuffer = vec![0; 256 * 240];
+        // Frames leave an NMI that an instruction trace shouldn't report
+        nes.bus.cpu_write(0x2000, 0x80);
+        nes.tick_frame(&mut |_| {}, &mut framebuffer);
+        nes.tick_frame(&mut |_| {}, &mut framebuffer);
+        assert!(!nes.tick_instruction(&mut |_| {}, &mut framebuffer).nmi);
+        for _ in 0..1000 {
+            nes.tick_instruction(&mut |_| {}, &mut framebuffer);
+        }
+
+        let mut writer = VecWriter(Vec::new());
+        nes.save(&mut writer).unwrap();
+        let mut restored = unrom();
+        restored.load(&mut SliceReader(&writer.0)).unwrap();
+        assert_ne!(nes.position(), (-1, 0));
+        assert_eq!(restored.position(), nes.position());
+        assert_eq!(restored.at_instruction(), nes.at_instruction());
+    }
+
+    #[test]
+    fn test_banking_view() {
+        let mut nes = unrom();
+        assert_eq!(
+            nes.cpu_mapping(0xC000),
+            Some(MemoryLocation::new(CartMemory::PrgRom, 3 * 0x4000))
+        );
+        assert_eq!(nes.cpu_mapping(0x8000).unwrap().bank(0x4000), 0);
+        assert_eq!(nes.cpu_mapping(0x2002), None);
+        let code: Vec<_> = nes
+            .disassemble(0xC000, 3)
+            .iter()
+            .map(|i| i.to_string())
+            .collect();
+        assert_eq!(code, ["BIT $2002", "BPL $C000", "JMP $C000"]);
+
+        // Poking ROM patches it in place
+        nes.poke(0x8010, 0xEA);
+        assert_eq!(nes.peek(0x8010), 0xEA);
+        assert_eq!(nes.cart_memory(CartMemory::PrgRom)[0x10], 0xEA);
+
+        nes.poke_ppu(0x1000, 0x12);
+        nes.poke_ppu(0x2400, 0x34);
+        nes.poke_ppu(0x3F01, 0x21);
+        nes.poke_oam(5, 0x56);
+        assert_eq!(
+            (
+                nes.peek_ppu(0x1000),
+                nes.peek_ppu(0x2400),
+                nes.peek_ppu(0x3F01)
+            ),
+            (0x12, 0x34, 0x21)
+        );
+        assert_eq!(nes.peek_oam(5), 0x56);
+        // Horizontal mirroring puts $2400 in the first page of CIRAM
+        assert_eq!(nes.cart_memory(CartMemory::Ciram)[0], 0x34);
+    }
+
+    #[test]
+    fn test_bad_roms_are_errors() {
+        let error = NES001::from_rom(&TestRom::new(250, 1, 1).build()).err();
+        assert_eq!(error.unwrap().to_string(), "Unsupported mapper 250");
+        assert!(NES001::from_rom(b"NES\x1A").is_err());
+    }
+}
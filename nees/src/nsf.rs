@@ -0,0 +1,287 @@
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use bitfield_struct::bitfield;
+
+const NSF_MAGIC: &[u8] = b"NESM\x1A";
+const NSFE_MAGIC: &[u8] = b"NSFE";
+const NSF_HEADER_SIZE: usize = 0x80;
+
+/// The audio chips a rip was made for, from the header's expansion byte.
+#[bitfield(u8)]
+pub struct ExpansionChips {
+    pub vrc6: bool,
+    pub vrc7: bool,
+    pub fds: bool,
+    pub mmc5: bool,
+    pub n163: bool,
+    pub sunsoft5b: bool,
+    #[bits(2)]
+    __: u8,
+}
+
+/// A music rip in the NSF, NSF2 or NSFe format. NSFe chunks (and NSF2's metadata, which uses
+/// the same chunks) fill in the track names and lengths.
+#[allow(clippy::upper_case_acronyms)]
+pub struct NSF {
+    pub total_songs: u8,
+    /// Zero-based.
+    pub starting_song: u8,
+    pub load_address: u16,
+    pub init_address: u16,
+    pub play_address: u16,
+    pub name: String,
+    pub artist: String,
+    pub copyright: String,
+    /// Microseconds between calls to the play routine on NTSC.
+    pub play_speed_ntsc: u16,
+    /// Initial banks for $8000-$FFFF; all zero for rips that don't bankswitch.
+    pub bankswitch_init: [u8; 8],
+    pub expansion: ExpansionChips,
+    pub data: Vec<u8>,
+
+    pub track_names: Vec<Option<String>>,
+    pub track_lengths_ms: Vec<Option<u32>>,
+    pub track_fades_ms: Vec<Option<u32>>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, pos: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| anyhow::anyhow!("Unexpected end of NSF data"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> anyhow::Result<u16> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// Splits a block of null-terminated strings.
+fn strings(data: &[u8]) -> Vec<String> {
+    data.split(|&b| b == 0)
+        .map(|s| String::from_utf8_lossy(s).to_string())
+        .collect()
+}
+
+fn fixed_string(data: &[u8]) -> String {
+    strings(data).into_iter().next().unwrap_or_default()
+}
+
+/// Track times are signed, with negative values meaning unknown.
+fn times(data: &[u8]) -> Vec<Option<u32>> {
+    data.chunks_exact(4)
+        .map(|t| {
+            let ms = i32::from_le_bytes([t[0], t[1], t[2], t[3]]);
+            (ms >= 0).then_some(ms as u32)
+        })
+        .collect()
+}
+
+impl NSF {
+    pub fn new(data: &[u8]) -> anyhow::Result<Self> {
+        if data.starts_with(NSF_MAGIC) {
+            Self::from_nsf(data)
+        } else if data.starts_with(NSFE_MAGIC) {
+            let mut nsf = Self::empty();
+            nsf.read_chunks(&data[NSFE_MAGIC.len()..], true)?;
+            Ok(nsf)
+        } else {
+            anyhow::bail!("Not an NSF or NSFe file")
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            total_songs: 1,
+            starting_song: 0,
+            load_address: 0x8000,
+            init_address: 0x8000,
+            play_address: 0x8000,
+            name: String::new(),
+            artist: String::new(),
+            copyright: String::new(),
+            play_speed_ntsc: 16639,
+            bankswitch_init: [0; 8],
+            expansion: ExpansionChips::new(),
+            data: Vec::new(),
+            track_names: Vec::new(),
+            track_lengths_ms: Vec::new(),
+            track_fades_ms: Vec::new(),
+        }
+    }
+
+    fn from_nsf(data: &[u8]) -> anyhow::Result<Self> {
+        let mut f = Reader::new(data);
+        f.bytes(NSF_MAGIC.len())?;
+        let version = f.u8()?;
+
+        let mut nsf = Self::empty();
+        nsf.total_songs = f.u8()?;
+        nsf.starting_song = f.u8()?.saturating_sub(1);
+        nsf.load_address = f.u16()?;
+        nsf.init_address = f.u16()?;
+        nsf.play_address = f.u16()?;
+        nsf.name = fixed_string(f.bytes(32)?);
+        nsf.artist = fixed_string(f.bytes(32)?);
+        nsf.copyright = fixed_string(f.bytes(32)?);
+        nsf.play_speed_ntsc = f.u16()?;
+        nsf.bankswitch_init.copy_from_slice(f.bytes(8)?);
+        let _play_speed_pal = f.u16()?;
+        let _region = f.u8()?;
+        nsf.expansion = ExpansionChips::from(f.u8()?);
+        let _nsf2_flags = f.u8()?;
+        let len = f.bytes(3)?;
+        let data_len = u32::from_le_bytes([len[0], len[1], len[2], 0]) as usize;
+
+        // NSF2 metadata follows the program data, when its length is given
+        let data = &data[NSF_HEADER_SIZE..];
+        if version >= 2 && data_len != 0 && data_len <= data.len() {
+            nsf.data = data[..data_len].to_vec();
+            nsf.read_chunks(&data[data_len..], false)?;
+        } else {
+            nsf.data = data.to_vec();
+        }
+
+        Ok(nsf)
+    }
+
+    /// Reads NSFe chunks. NSF2 metadata may only carry the optional ones.
+    fn read_chunks(&mut self, data: &[u8], is_nsfe: bool) -> anyhow::Result<()> {
+        let mut f = Reader::new(data);
+        while !f.is_empty() {
+            let len = f.u32()? as usize;
+            let id = f.bytes(4)?;
+            let chunk = f.bytes(len)?;
+            let mut c = Reader::new(chunk);
+
+            match id {
+                b"INFO" if is_nsfe => {
+                    self.load_address = c.u16()?;
+                    self.init_address = c.u16()?;
+                    self.play_address = c.u16()?;
+                    let _region = c.u8()?;
+                    self.expansion = ExpansionChips::from(c.u8()?);
+                    self.total_songs = c.u8().unwrap_or(1);
+                    self.starting_song = c.u8().unwrap_or(0);
+                }
+                b"DATA" if is_nsfe => self.data = chunk.to_vec(),
+                b"BANK" if is_nsfe => {
+                    let len = chunk.len().min(8);
+                    self.bankswitch_init[..len].copy_from_slice(&chunk[..len]);
+                }
+                b"RATE" => self.play_speed_ntsc = c.u16()?,
+                b"auth" => {
+                    let mut fields = strings(chunk).into_iter();
+                    self.name = fields.next().unwrap_or_default();
+                    self.artist = fields.next().unwrap_or_default();
+                    self.copyright = fields.next().unwrap_or_default();
+                }
+                b"tlbl" => self.track_names = strings(chunk).into_iter().map(Some).collect(),
+                b"time" => self.track_lengths_ms = times(chunk),
+                b"fade" => self.track_fades_ms = times(chunk),
+                b"NEND" => break,
+                // Flags for NSF2 features and the VRC7 variant, neither of which are emulated
+                b"NSF2" | b"VRC7" => {}
+                // Unknown chunks with an uppercase ID are required to play the file
+                _ if id[0].is_ascii_uppercase() => {
+                    anyhow::bail!("Unsupported NSFe chunk {}", String::from_utf8_lossy(id))
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_bankswitched(&self) -> bool {
+        self.bankswitch_init != [0; 8]
+    }
+
+    pub fn track_name(&self, track: u8) -> Option<&str> {
+        self.track_names.get(track as usize)?.as_deref()
+    }
+
+    pub fn track_length_ms(&self, track: u8) -> Option<u32> {
+        *self.track_lengths_ms.get(track as usize)?
+    }
+
+    pub fn track_fade_ms(&self, track: u8) -> Option<u32> {
+        *self.track_fades_ms.get(track as usize)?
+    }
+
+    /// The program data laid out in 4KB banks. Rips that don't bankswitch are padded out to
+    /// start at $8000, bankswitched ones to the 4KB boundary below the load address.
+    pub(crate) fn banked_data(&self) -> Vec<u8> {
+        let padding = if self.is_bankswitched() {
+            (self.load_address & 0x0FFF) as usize
+        } else {
+            self.load_address.saturating_sub(0x8000) as usize
+        };
+        let mut banked = vec![0; padding];
+        banked.extend_from_slice(&self.data);
+        banked.resize(banked.len().next_multiple_of(0x1000).max(0x8000), 0);
+        banked
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::NSF;
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_le_bytes().to_vec();
+        chunk.extend_from_slice(id);
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    #[test]
+    fn test_nsfe_metadata() {
+        let mut file = b"NSFE".to_vec();
+        file.extend(chunk(
+            b"INFO",
+            &[0x00, 0x80, 0x00, 0x80, 0x03, 0x80, 0, 0x01, 2, 1],
+        ));
+        file.extend(chunk(b"DATA", &[0x60, 0x60, 0x60, 0x60]));
+        file.extend(chunk(b"tlbl", b"Title\0Ending\0"));
+        file.extend(chunk(b"time", &[0x10, 0x27, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]));
+        file.extend(chunk(b"NEND", &[]));
+
+        let nsf = NSF::new(&file).unwrap();
+        assert_eq!(nsf.play_address, 0x8003);
+        assert!(nsf.expansion.vrc6());
+        assert_eq!((nsf.total_songs, nsf.starting_song), (2, 1));
+        assert_eq!(nsf.track_name(1), Some("Ending"));
+        assert_eq!(nsf.track_length_ms(0), Some(10000));
+        assert_eq!(nsf.track_length_ms(1), None);
+    }
+}
@@ -0,0 +1,117 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{nes001::NES001, nsf::NSF};
+
+/// Used for tracks without a length in their metadata.
+const DEFAULT_TRACK_LENGTH_MS: u32 = 150_000;
+const DEFAULT_FADE_MS: u32 = 8_000;
+/// An NTSC frame lasts 29780.5 CPU cycles, just under 16.64ms.
+const FRAME_MICROS: u64 = 16_639;
+/// Samples per frame, one per scanline.
+const SAMPLES_PER_FRAME: u32 = 262;
+
+/// Plays the tracks of an NSF rip, one frame of audio at a time. Each track starts from a fresh
+/// console, and fades out once its length has passed.
+#[allow(clippy::upper_case_acronyms)]
+pub struct NSFPlayer {
+    nsf: NSF,
+    nes: NES001,
+    track: u8,
+    playing: bool,
+    frames: u32,
+    framebuffer: Vec<u32>,
+}
+
+impl NSFPlayer {
+    pub fn new(nsf: NSF) -> Self {
+        let track = nsf.starting_song.min(nsf.total_songs.saturating_sub(1));
+        let nes = NES001::from_nsf(&nsf, track);
+
+        Self {
+            nsf,
+            nes,
+            track,
+            playing: true,
+            frames: 0,
+            framebuffer: vec![0; 256 * 240],
+        }
+    }
+
+    pub fn nsf(&self) -> &NSF {
+        &self.nsf
+    }
+
+    pub fn track(&self) -> u8 {
+        self.track
+    }
+
+    /// Starts playing a track (zero-based) from the beginning.
+    pub fn select_track(&mut self, track: u8) {
+        self.track = track.min(self.nsf.total_songs.saturating_sub(1));
+        self.nes = NES001::from_nsf(&self.nsf, self.track);
+        self.frames = 0;
+        self.playing = true;
+    }
+
+    pub fn play(&mut self) {
+        if self.is_finished() {
+            self.select_track(self.track);
+        }
+        self.playing = true;
+    }
+
+    /// Stops and rewinds the current track.
+    pub fn stop(&mut self) {
+        self.select_track(self.track);
+        self.playing = false;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// The current track's length before it fades out.
+    pub fn track_length_ms(&self) -> u32 {
+        self.nsf
+            .track_length_ms(self.track)
+            .unwrap_or(DEFAULT_TRACK_LENGTH_MS)
+    }
+
+    pub fn fade_ms(&self) -> u32 {
+        self.nsf
+            .track_fade_ms(self.track)
+            .unwrap_or(DEFAULT_FADE_MS)
+    }
+
+    pub fn elapsed_ms(&self) -> u32 {
+        (self.frames as u64 * FRAME_MICROS / 1000) as u32
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed_ms() >= self.track_length_ms() + self.fade_ms()
+    }
+
+    /// Runs one frame, producing the same number of samples as `NES001::tick_frame`. Outputs
+    /// silence while stopped.
+    pub fn tick_frame<T: FnMut(i16)>(&mut self, waveout_callback: &mut T) {
+        if !self.playing {
+            for _ in 0..SAMPLES_PER_FRAME {
+                waveout_callback(0);
+            }
+            return;
+        }
+
+        let elapsed = self.elapsed_ms();
+        let length = self.track_length_ms();
+        let fade = self.fade_ms().max(1);
+        let volume = fade.saturating_sub(elapsed.saturating_sub(length)) as i32;
+        let mut faded =
+            |sample: i16| waveout_callback((sample as i32 * volume / fade as i32) as i16);
+        self.nes.tick_frame(&mut faded, &mut self.framebuffer);
+
+        self.frames += 1;
+        if self.is_finished() {
+            self.playing = false;
+        }
+    }
+}
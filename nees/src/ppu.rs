@@ -1,6 +1,6 @@
 use bitfield_struct::bitfield;
 
-use crate::{cartridge::CartridgeWithSaveLoad, reader_writer::{EasyReader, EasyWriter}};
+use crate::{cartridge::{CartridgeWithSaveLoad, PPUFetch}, reader_writer::{EasyReader, EasyWriter}};
 
 #[bitfield(u8)]
 struct PPUCTRL {
@@ -252,6 +252,88 @@ impl PPU {
         self.mask.show_background() || self.mask.show_sprites()
     }
 
+    /// Reads a register like the CPU would, but without clearing the vblank flag, resetting the
+    /// address latch or moving on the $2007 address.
+    pub fn peek_register(&self, address: u8) -> u8 {
+        match address {
+            2 => self.status.0,
+            4 => self.peek_oam(self.oam_addr),
+            // Palette reads aren't delayed by the buffer
+            7 if self.v.0 >= 0x3f00 && self.v.0 <= 0x3fff => self.palette_read(self.v.0),
+            7 => self.ppudata_buffer,
+            _ => 0,
+        }
+    }
+
+    /// The address PPUDATA reads and writes next.
+    pub fn vram_address(&self) -> u16 {
+        self.v.0 & 0x3FFF
+    }
+
+    pub fn ciram(&self) -> &[u8] {
+        &self.ciram
+    }
+
+    /// A byte of OAM, which holds the Y, tile, attributes and X of each of the 64 sprites.
+    pub fn peek_oam(&self, index: u8) -> u8 {
+        let entry = &self.oam_entries[(index >> 2) as usize];
+        match index & 0b11 {
+            0 => entry.y,
+            1 => entry.tile_index,
+            2 => entry.attributes,
+            _ => entry.x,
+        }
+    }
+
+    pub fn poke_oam(&mut self, index: u8, value: u8) {
+        let entry = &mut self.oam_entries[(index >> 2) as usize];
+        match index & 0b11 {
+            0 => entry.y = value,
+            1 => entry.tile_index = value,
+            2 => entry.attributes = value,
+            _ => entry.x = value,
+        }
+    }
+
+    /// Reads the PPU's address space without going through $2007 or moving any of the
+    /// cartridge's latches.
+    pub fn peek(&self, address: u16, cart: &dyn CartridgeWithSaveLoad) -> u8 {
+        let address = address & 0x3FFF;
+        if address >= 0x3F00 {
+            self.palette_read(address)
+        } else {
+            cart.ppu_peek(address, &self.ciram)
+        }
+    }
+
+    /// Writes to the PPU's address space, including pattern tables in CHR-ROM.
+    pub fn poke(&mut self, address: u16, value: u8, cart: &mut dyn CartridgeWithSaveLoad) {
+        let address = address & 0x3FFF;
+        if address >= 0x3F00 {
+            self.palette_write(address, value);
+        } else {
+            cart.ppu_poke(address, value, &mut self.ciram);
+        }
+    }
+
+    fn palette_read(&self, address: u16) -> u8 {
+        let index = address & 0x3;
+        self.palette[if index == 0 {
+            0
+        } else {
+            (address & 0x1F) as usize
+        }]
+    }
+
+    fn palette_write(&mut self, address: u16, value: u8) {
+        let index = address & 0xF;
+        self.palette[if index == 0 {
+            0
+        } else {
+            (address & 0x1F) as usize
+        }] = value;
+    }
+
     pub fn cpu_ppu_bus_read(&mut self, address: u8, cart: &mut dyn CartridgeWithSaveLoad) -> u8 {
         let mut value: u8 = 0;
 
@@ -262,20 +344,11 @@ impl PPU {
                 self.addr_latch = false;
             }
             4 => {
-                let index = self.oam_addr >> 2;
-                let which = self.oam_addr & 0b11;
-                let entry: &mut OAMEntry = &mut self.oam_entries[index as usize];
-                value = match which {
-                    0 => entry.y,
-                    1 => entry.tile_index,
-                    2 => entry.attributes,
-                    3 => entry.x,
-                    _ => panic!("Should not happen!"),
-                }
+                value = self.peek_oam(self.oam_addr);
             }
             7 => {
                 value = self.ppudata_buffer;
-                self.ppudata_buffer = self.internal_bus_read(self.v.0, cart);
+                self.ppudata_buffer = self.internal_bus_read(self.v.0, PPUFetch::Data, cart);
 
                 if self.v.0 >= 0x3f00 && self.v.0 <= 0x3fff {
                     value = self.ppudata_buffer; // Do not delay palette reads
@@ -295,29 +368,22 @@ impl PPU {
 
     fn internal_bus_write(&mut self, address: u16, value: u8, cart: &mut dyn CartridgeWithSaveLoad) {
         if address >= 0x3F00 && address <= 0x3FFF {
-            // Palette control
-            let index = address & 0xF;
-            self.palette[if index == 0 {
-                0
-            } else {
-                (address & 0x1F) as usize
-            }] = value;
+            self.palette_write(address, value);
         } else {
             cart.ppu_write(address, value, &mut self.ciram);
         }
     }
 
-    fn internal_bus_read(&mut self, address: u16, cart: &mut dyn CartridgeWithSaveLoad) -> u8 {
+    fn internal_bus_read(
+        &mut self,
+        address: u16,
+        fetch: PPUFetch,
+        cart: &mut dyn CartridgeWithSaveLoad,
+    ) -> u8 {
         if address >= 0x3F00 && address <= 0x3FFF {
-            // Palette control
-            let index = address & 0x3;
-            self.palette[if index == 0 {
-                0
-            } else {
-                (address & 0x1F) as usize
-            }]
+            self.palette_read(address)
         } else {
-            cart.ppu_read(address, &self.ciram)
+            cart.ppu_read(address, &self.ciram, fetch)
         }
     }
 
@@ -335,16 +401,7 @@ impl PPU {
                 self.oam_addr = value;
             }
             4 => {
-                let index = self.oam_addr >> 2;
-                let which = self.oam_addr & 0b11;
-                let entry: &mut OAMEntry = &mut self.oam_entries[index as usize];
-                match which {
-                    0 => entry.y = value,
-                    1 => entry.tile_index = value,
-                    2 => entry.attributes = value,
-                    3 => entry.x = value,
-                    _ => panic!("Should not happen!"),
-                };
+                self.poke_oam(self.oam_addr, value);
                 self.oam_addr = self.oam_addr.wrapping_add(1);
             }
             5 => {
@@ -382,13 +439,15 @@ impl PPU {
 
     #[inline]
     fn nametable_fetch(&mut self, cart: &mut dyn CartridgeWithSaveLoad) {
-        self.next_tile = self.internal_bus_read(0x2000 | (self.v.0 & 0x0FFF), cart);
+        self.next_tile =
+            self.internal_bus_read(0x2000 | (self.v.0 & 0x0FFF), PPUFetch::Background, cart);
     }
 
     #[inline]
     fn attribute_fetch(&mut self, cart: &mut dyn CartridgeWithSaveLoad) {
         self.next_attribute = self.internal_bus_read(
             0x23C0 | (self.v.0 & 0x0C00) | ((self.v.0 >> 4) & 0x38) | ((self.v.0 >> 2) & 0x07),
+            PPUFetch::Background,
             cart,
         );
         if (self.v.coarse_y_scroll() & 2) != 0 {
@@ -408,13 +467,15 @@ impl PPU {
         self.nametable_address.set_tile_index(self.next_tile);
         self.nametable_address
             .set_upper_patter_table(self.ctrl.upper_background_pattern_table());
-        self.next_pattern_lsb = self.internal_bus_read(self.nametable_address.0, cart);
+        self.next_pattern_lsb =
+            self.internal_bus_read(self.nametable_address.0, PPUFetch::Background, cart);
     }
 
     #[inline]
     fn bg_msb_fetch(&mut self, cart: &mut dyn CartridgeWithSaveLoad) {
         self.nametable_address.set_hi_bit_plane(true);
-        self.next_pattern_msb = self.internal_bus_read(self.nametable_address.0, cart);
+        self.next_pattern_msb =
+            self.internal_bus_read(self.nametable_address.0, PPUFetch::Background, cart);
     }
 
     #[inline]
@@ -497,23 +558,26 @@ impl PPU {
                     self.attrib_1 <<= 1;
                 }
 
-                match (dot - 1) % 8 {
-                    0 => {
-                        self.load_shifters();
-                        self.nametable_fetch(cart);
-                    }
-                    2 => {
-                        self.attribute_fetch(cart);
-                    }
-                    4 => {
-                        self.bg_lsb_fetch(cart);
-                    }
-                    6 => {
-                        self.bg_msb_fetch(cart);
-                    }
-                    7 => self.inc_horiz(),
-                    _ => {}
-                };
+                // The PPU bus is idle while rendering is disabled
+                if self.is_rending_enabled() {
+                    match (dot - 1) % 8 {
+                        0 => {
+                            self.load_shifters();
+                            self.nametable_fetch(cart);
+                        }
+                        2 => {
+                            self.attribute_fetch(cart);
+                        }
+                        4 => {
+                            self.bg_lsb_fetch(cart);
+                        }
+                        6 => {
+                            self.bg_msb_fetch(cart);
+                        }
+                        7 => self.inc_horiz(),
+                        _ => {}
+                    };
+                }
             }
 
             if dot == 256 {
@@ -560,9 +624,9 @@ impl PPU {
                         }
                     }
                 }
-            } else if dot == 338 {
+            } else if dot == 338 && self.is_rending_enabled() {
                 self.nametable_fetch(cart);
-            } else if dot == 340 {
+            } else if dot == 340 && self.is_rending_enabled() {
                 self.nametable_fetch(cart);
 
                 if self.mask.show_sprites() {
@@ -603,9 +667,17 @@ impl PPU {
                                 .set_upper_patter_table(self.ctrl.upper_sprite_pattern_table());
                         }
 
-                        self.sprite_lsb[i] = cart.ppu_read(self.nametable_address.0, &self.ciram);
+                        self.sprite_lsb[i] = cart.ppu_read(
+                            self.nametable_address.0,
+                            &self.ciram,
+                            PPUFetch::Sprite,
+                        );
                         self.nametable_address.set_hi_bit_plane(true);
-                        self.sprite_msb[i] = cart.ppu_read(self.nametable_address.0, &self.ciram);
+                        self.sprite_msb[i] = cart.ppu_read(
+                            self.nametable_address.0,
+                            &self.ciram,
+                            PPUFetch::Sprite,
+                        );
                     }
                 }
             }
@@ -0,0 +1,351 @@
+use alloc::{format, string::ToString, vec::Vec};
+
+use crate::{cartridge::MemoryLocation, cheats::Cheat, nes001::NES001};
+
+/// Where cartridge work RAM follows the console's 2KB in the search's indices.
+const WORK_RAM_START: usize = 0x800;
+
+/// How the bytes at each address are read. 16-bit values are little-endian, like the 6502's.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ValueType {
+    U8,
+    I8,
+    U16,
+    I16,
+}
+
+impl ValueType {
+    pub fn size(self) -> usize {
+        match self {
+            Self::U8 | Self::I8 => 1,
+            Self::U16 | Self::I16 => 2,
+        }
+    }
+
+    fn read(self, memory: &[u8], index: usize) -> Option<i32> {
+        let bytes = memory.get(index..index + self.size())?;
+        Some(match self {
+            Self::U8 => bytes[0] as i32,
+            Self::I8 => bytes[0] as i8 as i32,
+            Self::U16 => u16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+            Self::I16 => i16::from_le_bytes([bytes[0], bytes[1]]) as i32,
+        })
+    }
+}
+
+/// What to keep candidates by, comparing each value with the one at the last search.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Filter {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    /// Holding this value now, whatever it was before.
+    Value(i32),
+}
+
+impl Filter {
+    fn keeps(self, previous: i32, current: i32) -> bool {
+        match self {
+            Self::Equal => current == previous,
+            Self::Changed => current != previous,
+            Self::Increased => current > previous,
+            Self::Decreased => current < previous,
+            Self::Value(value) => current == value,
+        }
+    }
+}
+
+/// A candidate still in the search.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct SearchResult {
+    pub address: u16,
+    pub value: i32,
+    pub previous: i32,
+}
+
+/// The console's RAM and the cartridge's work RAM, as the search sees them.
+struct Snapshot {
+    ram: Vec<u8>,
+    /// None while the cartridge has its work RAM disabled.
+    work_ram: Option<Vec<u8>>,
+    /// Which RAM is in the window, for boards that bank it.
+    work_ram_location: Option<MemoryLocation>,
+}
+
+impl Snapshot {
+    fn new(nes: &NES001) -> Self {
+        Self {
+            ram: nes.ram().to_vec(),
+            work_ram: nes.work_ram().map(<[u8]>::to_vec),
+            work_ram_location: nes.cpu_mapping(nes.work_ram_address()),
+        }
+    }
+
+    /// The value at an index, with 16-bit values not straddling the console's RAM and the
+    /// cartridge's.
+    fn read(&self, value_type: ValueType, index: usize) -> Option<i32> {
+        if index < WORK_RAM_START {
+            value_type.read(&self.ram, index)
+        } else {
+            value_type.read(self.work_ram.as_ref()?, index - WORK_RAM_START)
+        }
+    }
+
+    /// Whether both snapshots hold the same work RAM, so that its values can be compared.
+    fn same_work_ram(&self, other: &Snapshot) -> bool {
+        self.work_ram.is_some()
+            && other.work_ram.is_some()
+            && self.work_ram_location == other.work_ram_location
+    }
+
+    fn indices(&self) -> impl Iterator<Item = usize> {
+        let work_ram_len = self.work_ram.as_ref().map_or(0, Vec::len);
+        (0..self.ram.len()).chain(WORK_RAM_START..WORK_RAM_START + work_ram_len)
+    }
+}
+
+/// Where a searched byte is, with work RAM starting at `work_ram_address`.
+fn address(index: usize, work_ram_address: u16) -> u16 {
+    if index < WORK_RAM_START {
+        index as u16
+    } else {
+        (work_ram_address as usize + index - WORK_RAM_START) as u16
+    }
+}
+
+fn index(address: u16, work_ram_address: u16) -> Option<usize> {
+    match address {
+        0..=0x7FF => Some(address as usize),
+        0x6000..=0x7FFF if address >= work_ram_address => {
+            Some((address - work_ram_address) as usize + WORK_RAM_START)
+        }
+        _ => None,
+    }
+}
+
+/// Narrows down where a game keeps something, like its lives counter, by filtering the RAM
+/// addresses by how their values change between searches.
+pub struct RamSearch {
+    value_type: ValueType,
+    work_ram_address: u16,
+    previous: Snapshot,
+    candidates: Vec<usize>,
+}
+
+impl RamSearch {
+    /// Starts a search with every address as a candidate.
+    pub fn new(nes: &NES001, value_type: ValueType) -> Self {
+        let previous = Snapshot::new(nes);
+        let candidates = previous
+            .indices()
+            .filter(|&i| previous.read(value_type, i).is_some())
+            .collect();
+        Self {
+            value_type,
+            work_ram_address: nes.work_ram_address(),
+            previous,
+            candidates,
+        }
+    }
+
+    pub fn value_type(&self) -> ValueType {
+        self.value_type
+    }
+
+    /// Reads the candidates differently from now on, without restarting the search.
+    pub fn set_value_type(&mut self, value_type: ValueType) {
+        self.value_type = value_type;
+    }
+
+    /// Keeps the candidates that pass the filter, then remembers the values for the next one.
+    /// Work RAM that's disabled or banked out since the last search is left alone until it's
+    /// back. Returns how many are left.
+    pub fn filter(&mut self, nes: &NES001, filter: Filter) -> usize {
+        let mut memory = Snapshot::new(nes);
+        let value_type = self.value_type;
+        let previous = &self.previous;
+        let same_work_ram = memory.same_work_ram(previous);
+        self.candidates.retain(|&i| {
+            if i >= WORK_RAM_START && !same_work_ram {
+                return true;
+            }
+            match (previous.read(value_type, i), memory.read(value_type, i)) {
+                (Some(previous), Some(current)) => filter.keeps(previous, current),
+                _ => false,
+            }
+        });
+        if !same_work_ram && self.previous.work_ram.is_some() {
+            memory.work_ram = self.previous.work_ram.take();
+            memory.work_ram_location = self.previous.work_ram_location;
+        }
+        self.previous = memory;
+        self.candidates.len()
+    }
+
+    pub fn len(&self) -> usize {
+        self.candidates.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.candidates.is_empty()
+    }
+
+    /// The candidates with their values now and at the last search.
+    pub fn results(&self, nes: &NES001) -> Vec<SearchResult> {
+        let memory = Snapshot::new(nes);
+        self.candidates
+            .iter()
+            .filter_map(|&i| {
+                Some(SearchResult {
+                    address: address(i, self.work_ram_address),
+                    value: memory.read(self.value_type, i)?,
+                    previous: self.previous.read(self.value_type, i)?,
+                })
+            })
+            .collect()
+    }
+
+    /// Cheats freezing an address at a value, one per byte of the search's value type.
+    pub fn freeze_cheats(&self, address: u16, value: i32) -> Vec<Cheat> {
+        let bytes = value.to_le_bytes();
+        (0..self.value_type.size())
+            .filter_map(|i| {
+                let byte_address = address.checked_add(i as u16)?;
+                index(byte_address, self.work_ram_address)?;
+                let mut cheat =
+                    Cheat::parse(&format!("{:04X}:{:02X}", byte_address, bytes[i])).ok()?;
+                cheat.description = "RAM search".to_string();
+                Some(cheat)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Filter, RamSearch, ValueType};
+    use crate::{cartridge::CartMemory, ines::test_helpers::TestRom, nes001::NES001};
+
+    /// NROM that counts down $10 and counts up $20-$21 in a loop.
+    fn counting_rom() -> NES001 {
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x64, 0x85, 0x10, // LDA #100, STA $10
+            0xC6, 0x10,             // loop: DEC $10
+            0xE6, 0x20,             // INC $20
+            0xD0, 0x02,             // BNE +2
+            0xE6, 0x21,             // INC $21
+            0x4C, 0x04, 0x80,       // JMP loop
+        ];
+        TestRom::new(0, 1, 1)
+            .code(0x8000, &program)
+            .vectors(0, 0x8000, 0)
+            .nes()
+    }
+
+    /// MMC6 that enables its RAM and counts up $7010 in a loop.
+    fn mmc6_rom() -> NES001 {
+        #[rustfmt::skip]
+        let program = [
+            0xA9, 0x20, 0x8D, 0x00, 0x80, // LDA #$20, STA $8000
+            0xA9, 0xF0, 0x8D, 0x01, 0xA0, // LDA #$F0, STA $A001
+            0xEE, 0x10, 0x70,             // loop: INC $7010
+            0x4C, 0x0A, 0xE0,             // JMP loop
+        ];
+        TestRom::new(4, 2, 1)
+            .submapper(1)
+            .code(0xE000, &program)
+            .vectors(0, 0xE000, 0)
+            .nes()
+    }
+
+    /// MMC3 that counts up $6010 in a loop, protecting its RAM with the value at $0300.
+    fn protecting_rom() -> NES001 {
+        #[rustfmt::skip]
+        let program = [
+            0xEE, 0x10, 0x60, // loop: INC $6010
+            0xAD, 0x00, 0x03, // LDA $0300
+            0x8D, 0x01, 0xA0, // STA $A001
+            0x4C, 0x00, 0xE0, // JMP loop
+        ];
+        TestRom::new(4, 2, 1)
+            .code(0xE000, &program)
+            .vectors(0, 0xE000, 0)
+            .nes()
+    }
+
+    fn run(nes: &mut NES001) {
+        let mut framebuffer = vec![0; 256 * 240];
+        nes.tick_frame(&mut |_| {}, &mut framebuffer);
+    }
+
+    #[test]
+    fn test_finds_counters() {
+        let mut nes = counting_rom();
+        let mut search = RamSearch::new(&nes, ValueType::U8);
+        run(&mut nes);
+        search.filter(&nes, Filter::Changed);
+        run(&mut nes);
+        search.filter(&nes, Filter::Changed);
+        let addresses: Vec<u16> = search.results(&nes).iter().map(|r| r.address).collect();
+        assert!(addresses.contains(&0x10) && addresses.contains(&0x20));
+
+        let mut search = RamSearch::new(&nes, ValueType::U16);
+        run(&mut nes);
+        search.filter(&nes, Filter::Increased);
+        run(&mut nes);
+        search.filter(&nes, Filter::Increased);
+        assert!(search.results(&nes).iter().any(|r| r.address == 0x20));
+
+        let cheats = search.freeze_cheats(0x20, 0x1234);
+        let codes: Vec<&str> = cheats.iter().map(|c| c.code.as_str()).collect();
+        assert_eq!(codes, ["0020:34", "0021:12"]);
+    }
+
+    #[test]
+    fn test_disabled_work_ram_keeps_candidates() {
+        let mut nes = protecting_rom();
+        nes.poke(0x0300, 0x80);
+        run(&mut nes);
+        let mut search = RamSearch::new(&nes, ValueType::U8);
+        run(&mut nes);
+        search.filter(&nes, Filter::Changed);
+
+        // While the RAM is disabled, its candidates are left alone
+        nes.poke(0x0300, 0x00);
+        run(&mut nes);
+        assert!(nes.work_ram().is_none());
+        search.filter(&nes, Filter::Changed);
+        assert!(!search.is_empty());
+        // Without values to show for now
+        assert!(search.results(&nes).is_empty());
+
+        nes.poke(0x0300, 0x80);
+        run(&mut nes);
+        search.filter(&nes, Filter::Changed);
+        let addresses: Vec<u16> = search.results(&nes).iter().map(|r| r.address).collect();
+        assert!(addresses.contains(&0x6010));
+    }
+
+    #[test]
+    fn test_mmc6_ram_address() {
+        let mut nes = mmc6_rom();
+        run(&mut nes);
+        let mut search = RamSearch::new(&nes, ValueType::U8);
+        run(&mut nes);
+        search.filter(&nes, Filter::Changed);
+        let addresses: Vec<u16> = search.results(&nes).iter().map(|r| r.address).collect();
+        assert!(addresses.contains(&0x7010));
+        assert!(addresses
+            .iter()
+            .all(|&a| a < 0x800 || (0x7000..0x7400).contains(&a)));
+
+        // Cheats from the results freeze the RAM behind the address
+        let cheats = search.freeze_cheats(0x7010, 0x55);
+        let codes: Vec<&str> = cheats.iter().map(|c| c.code.as_str()).collect();
+        assert_eq!(codes, ["7010:55"]);
+        assert_eq!(nes.cpu_mapping(0x7010).unwrap().memory, CartMemory::PrgRam);
+        assert!(search.freeze_cheats(0x6010, 0x55).is_empty());
+    }
+}
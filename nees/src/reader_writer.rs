@@ -14,4 +14,81 @@ pub trait EasyReader {
     fn read_u32(&mut self) -> anyhow::Result<u32>;
     fn read_bool(&mut self) -> anyhow::Result<bool>;
     fn read_exact(&mut self, buf: &mut [u8]) -> anyhow::Result<()>;
-}
\ No newline at end of file
+}
+#[cfg(test)]
+pub mod test_helpers {
+    use super::{EasyReader, EasyWriter};
+
+    /// Little-endian in-memory writer for savestate round-trip tests.
+    pub struct VecWriter(pub Vec<u8>);
+
+    impl EasyWriter for VecWriter {
+        fn write_u8(&mut self, value: u8) -> anyhow::Result<()> {
+            self.0.push(value);
+            Ok(())
+        }
+
+        fn write_u16(&mut self, value: u16) -> anyhow::Result<()> {
+            self.write_all(&value.to_le_bytes())
+        }
+
+        fn write_i16(&mut self, value: i16) -> anyhow::Result<()> {
+            self.write_all(&value.to_le_bytes())
+        }
+
+        fn write_u32(&mut self, value: u32) -> anyhow::Result<()> {
+            self.write_all(&value.to_le_bytes())
+        }
+
+        fn write_bool(&mut self, value: bool) -> anyhow::Result<()> {
+            self.write_u8(value as u8)
+        }
+
+        fn write_all(&mut self, buf: &[u8]) -> anyhow::Result<()> {
+            self.0.extend_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    pub struct SliceReader<'a>(pub &'a [u8]);
+
+    impl EasyReader for SliceReader<'_> {
+        fn read_u8(&mut self) -> anyhow::Result<u8> {
+            let mut buf = [0; 1];
+            self.read_exact(&mut buf)?;
+            Ok(buf[0])
+        }
+
+        fn read_u16(&mut self) -> anyhow::Result<u16> {
+            let mut buf = [0; 2];
+            self.read_exact(&mut buf)?;
+            Ok(u16::from_le_bytes(buf))
+        }
+
+        fn read_i16(&mut self) -> anyhow::Result<i16> {
+            let mut buf = [0; 2];
+            self.read_exact(&mut buf)?;
+            Ok(i16::from_le_bytes(buf))
+        }
+
+        fn read_u32(&mut self) -> anyhow::Result<u32> {
+            let mut buf = [0; 4];
+            self.read_exact(&mut buf)?;
+            Ok(u32::from_le_bytes(buf))
+        }
+
+        fn read_bool(&mut self) -> anyhow::Result<bool> {
+            Ok(self.read_u8()? == 1)
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> anyhow::Result<()> {
+            if self.0.len() < buf.len() {
+                anyhow::bail!("Unexpected end of data");
+            }
+            let (head, tail) = self.0.split_at(buf.len());
+            buf.copy_from_slice(head);
+            self.0 = tail;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,74 @@
+/// SHA-1, for matching ROMs against databases that list it alongside the CRC-32.
+pub fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+
+    let bit_len = (data.len() as u64).wrapping_mul(8);
+    let mut tail = [0_u8; 128];
+    let remainder = data.len() % 64;
+    tail[..remainder].copy_from_slice(&data[data.len() - remainder..]);
+    tail[remainder] = 0x80;
+    let tail_len = if remainder < 56 { 64 } else { 128 };
+    tail[tail_len - 8..tail_len].copy_from_slice(&bit_len.to_be_bytes());
+
+    let blocks = data[..data.len() - remainder]
+        .chunks_exact(64)
+        .chain(tail[..tail_len].chunks_exact(64));
+    for block in blocks {
+        let mut w = [0_u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, h) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sha1;
+
+    #[test]
+    fn test_known_digests() {
+        assert_eq!(
+            sha1(b"abc"),
+            [
+                0xA9, 0x99, 0x3E, 0x36, 0x47, 0x06, 0x81, 0x6A, 0xBA, 0x3E, 0x25, 0x71, 0x78, 0x50,
+                0xC2, 0x6C, 0x9C, 0xD0, 0xD8, 0x9D
+            ]
+        );
+        // Two blocks of padding
+        assert_eq!(sha1(&[b'a'; 56])[..4], [0xC2, 0xDB, 0x33, 0x0F]);
+    }
+}
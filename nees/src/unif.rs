@@ -0,0 +1,213 @@
+use alloc::{string::String, vec, vec::Vec};
+
+use crate::{crc32::crc32, ines::INES, mirroring::Mirroring};
+
+pub const UNIF_MAGIC: &[u8] = b"UNIF";
+const UNIF_HEADER_SIZE: usize = 32;
+
+/// Maker and market prefixes, which don't change how a board works.
+const BOARD_PREFIXES: &[&str] = &["NES-", "HVC-", "UNL-", "BTL-", "BMC-"];
+
+/// Board names without their prefix, with the mapper and submapper emulating them.
+#[rustfmt::skip]
+const BOARDS: &[(&str, u16, Option<u8>)] = &[
+    ("NROM", 0, None), ("NROM-128", 0, None), ("NROM-256", 0, None), ("HROM", 0, None),
+    ("RROM", 0, None), ("RROM-128", 0, None), ("SROM", 0, None), ("RTROM", 0, None),
+    ("STROM", 0, None),
+    ("SAROM", 1, None), ("SBROM", 1, None), ("SCROM", 1, None), ("SFROM", 1, None),
+    ("SGROM", 1, None), ("SJROM", 1, None), ("SKROM", 1, None), ("SLROM", 1, None),
+    ("SL1ROM", 1, None), ("SNROM", 1, None), ("SOROM", 1, None), ("SUROM", 1, None),
+    ("SXROM", 1, None), ("SEROM", 1, Some(5)), ("SHROM", 1, Some(5)), ("SH1ROM", 1, Some(5)),
+    ("UNROM", 2, None), ("UOROM", 2, None),
+    ("CNROM", 3, None),
+    ("TBROM", 4, None), ("TEROM", 4, None), ("TFROM", 4, None), ("TGROM", 4, None),
+    ("TKROM", 4, None), ("TLROM", 4, None), ("TL1ROM", 4, None), ("TL2ROM", 4, None),
+    ("TNROM", 4, None), ("TR1ROM", 4, None), ("TSROM", 4, None), ("TVROM", 4, None),
+    ("HKROM", 4, Some(1)),
+    ("EKROM", 5, None), ("ELROM", 5, None), ("ETROM", 5, None), ("EWROM", 5, None),
+    ("AMROM", 7, None), ("ANROM", 7, None), ("AN1ROM", 7, None), ("AOROM", 7, None),
+    ("PNROM", 9, None), ("PEEOROM", 9, None),
+    ("FJROM", 10, None), ("FKROM", 10, None),
+    ("UNROM-512-8", 30, None), ("UNROM-512-16", 30, None), ("UNROM-512-32", 30, None),
+    ("BNROM", 34, None),
+    ("GNROM", 66, None), ("MHROM", 66, None),
+    ("BTR", 69, None), ("JLROM", 69, None), ("JSROM", 69, None),
+    ("TKSROM", 118, None), ("TLSROM", 118, None),
+    ("TQROM", 119, None),
+];
+
+fn find_board(name: &str) -> Option<(u16, Option<u8>)> {
+    let unprefixed = BOARD_PREFIXES
+        .iter()
+        .find_map(|prefix| {
+            let (start, rest) = name.split_at_checked(prefix.len())?;
+            start.eq_ignore_ascii_case(prefix).then_some(rest)
+        })
+        .unwrap_or(name);
+
+    BOARDS
+        .iter()
+        .find(|(board, _, _)| board.eq_ignore_ascii_case(unprefixed))
+        .map(|&(_, mapper_no, submapper)| (mapper_no, submapper))
+}
+
+/// PRG0-PRGF and CHR0-CHRF, with their CRCs from PCK0-PCKF and CCK0-CCKF.
+#[derive(Default)]
+struct Chips {
+    data: [Option<Vec<u8>>; 16],
+    crcs: [Option<u32>; 16],
+}
+
+impl Chips {
+    /// The chips in order, after checking them against their CRCs.
+    fn concat(self, kind: &str) -> anyhow::Result<Vec<u8>> {
+        let mut all = Vec::new();
+        for (i, (data, crc)) in self.data.into_iter().zip(self.crcs).enumerate() {
+            let Some(data) = data else { continue };
+            if let Some(crc) = crc {
+                if crc32(&data) != crc {
+                    anyhow::bail!("{}{:X} doesn't match its CRC", kind, i);
+                }
+            }
+            all.extend_from_slice(&data);
+        }
+        Ok(all)
+    }
+}
+
+fn chunk_u32(chunk: &[u8]) -> anyhow::Result<u32> {
+    let bytes = chunk
+        .get(..4)
+        .ok_or_else(|| anyhow::anyhow!("Truncated UNIF chunk"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Reads a UNIF image into the same form as an iNES one, picking the mapper by board name.
+pub fn parse(data: &[u8]) -> anyhow::Result<INES> {
+    if !data.starts_with(UNIF_MAGIC) || data.len() < UNIF_HEADER_SIZE {
+        anyhow::bail!("Not a UNIF file");
+    }
+
+    let mut board = None;
+    let mut prg = Chips::default();
+    let mut chr = Chips::default();
+    let mut mirroring = Mirroring::Horizontal;
+    let mut four_screen = false;
+    let mut has_battery = false;
+    let mut chr_is_ram = false;
+
+    let mut pos = UNIF_HEADER_SIZE;
+    while pos < data.len() {
+        let header = data
+            .get(pos..pos + 8)
+            .ok_or_else(|| anyhow::anyhow!("Truncated UNIF chunk header"))?;
+        let id = &header[..4];
+        let len = chunk_u32(&header[4..])? as usize;
+        let chunk = data
+            .get(pos + 8..pos + 8 + len)
+            .ok_or_else(|| anyhow::anyhow!("Truncated UNIF chunk"))?;
+        pos += 8 + len;
+
+        // The last character of the numbered chunks is a hex digit
+        let index = (id[3] as char).to_digit(16).unwrap_or(0) as usize;
+        match &id[..3] {
+            b"PRG" => prg.data[index] = Some(chunk.to_vec()),
+            b"CHR" => chr.data[index] = Some(chunk.to_vec()),
+            b"PCK" => prg.crcs[index] = Some(chunk_u32(chunk)?),
+            b"CCK" => chr.crcs[index] = Some(chunk_u32(chunk)?),
+            _ => match id {
+                b"MAPR" => {
+                    let name = chunk.split(|&b| b == 0).next().unwrap_or_default();
+                    board = Some(String::from_utf8_lossy(name).into_owned());
+                }
+                b"MIRR" => match chunk.first() {
+                    Some(0) => mirroring = Mirroring::Horizontal,
+                    Some(1) => mirroring = Mirroring::Vertical,
+                    Some(2) => mirroring = Mirroring::SingleScreenA,
+                    Some(3) => mirroring = Mirroring::SingleScreenB,
+                    Some(4) => four_screen = true,
+                    // Controlled by the mapper, which ignores the header
+                    _ => {}
+                },
+                b"BATR" => has_battery = true,
+                b"VROR" => chr_is_ram = true,
+                // CTRL only lists the controllers the game supports; names and dumper
+                // information don't matter either
+                _ => {}
+            },
+        }
+    }
+
+    let board = board.ok_or_else(|| anyhow::anyhow!("UNIF file without a MAPR chunk"))?;
+    let (mapper_no, submapper) =
+        find_board(&board).ok_or_else(|| anyhow::anyhow!("Unsupported UNIF board {}", board))?;
+
+    let mut prg_rom = prg.concat("PRG")?;
+    if prg_rom.is_empty() {
+        anyhow::bail!("UNIF file without PRG-ROM");
+    }
+    // Boards expect whole 16KB banks, which smaller chips are mirrored into
+    while prg_rom.len() % 0x4000 != 0 {
+        prg_rom.extend_from_within(..);
+    }
+
+    let mut chr_rom = chr.concat("CHR")?;
+    let is_chr_ram = chr_is_ram || chr_rom.is_empty();
+    if chr_rom.is_empty() {
+        chr_rom = vec![0; 0x2000];
+    }
+
+    Ok(INES {
+        mapper_no,
+        submapper,
+        has_battery,
+        prg_ram_size: None,
+        prg_nvram_size: None,
+        prg_rom_size_16k_chunks: (prg_rom.len() / 0x4000) as u8,
+        prg_rom,
+
+        chr_rom,
+        is_chr_ram,
+        mirroring,
+        four_screen,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse;
+    use crate::{crc32::crc32, mirroring::Mirroring};
+
+    fn chunk(id: &[u8], data: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        chunk
+    }
+
+    fn unif(board: &[u8], prg_crc: u32) -> Vec<u8> {
+        let prg = [0xEA; 0x4000];
+        let mut file = b"UNIF".to_vec();
+        file.resize(32, 0);
+        file.extend(chunk(b"MAPR", board));
+        file.extend(chunk(b"PRG0", &prg));
+        file.extend(chunk(b"PCK0", &prg_crc.to_le_bytes()));
+        file.extend(chunk(b"MIRR", &[1]));
+        file.extend(chunk(b"BATR", &[1]));
+        file
+    }
+
+    #[test]
+    fn test_board_names() {
+        let prg_crc = crc32(&[0xEA; 0x4000]);
+        let ines = parse(&unif(b"NES-TLROM\0", prg_crc)).unwrap();
+        assert_eq!((ines.mapper_no, ines.submapper), (4, None));
+        assert_eq!(ines.prg_rom_size_16k_chunks, 1);
+        assert!(ines.is_chr_ram && ines.has_battery);
+        assert_eq!(ines.mirroring, Mirroring::Vertical);
+
+        let error = parse(&unif(b"UNL-Sachen-8259A\0", prg_crc)).err().unwrap();
+        assert_eq!(error.to_string(), "Unsupported UNIF board UNL-Sachen-8259A");
+        assert!(parse(&unif(b"NES-TLROM\0", !prg_crc)).is_err());
+    }
+}